@@ -0,0 +1,46 @@
+mod starter_crate;
+mod wallpaper;
+
+use std::path::PathBuf;
+
+pub use self::{
+    starter_crate::export_starter_crate,
+    wallpaper::export_wallpaper,
+};
+
+#[derive(Debug, thiserror::Error)]
+#[error("export error")]
+pub enum Error {
+    Io(#[from] std::io::Error),
+    Json(#[from] serde_json::Error),
+    Compile(#[from] shade_rs_compile::CompileError),
+    #[error("UI not built: {path}. Run `shade-rs-cli build` first.")]
+    UiNotBuilt {
+        path: PathBuf,
+    },
+    #[error("player not built: {path}. Build the UI with `player = true` in `[package.metadata.shade-rs-build]`.")]
+    PlayerNotBuilt {
+        path: PathBuf,
+    },
+}
+
+/// Turns an arbitrary name (e.g. a file name) into a valid crate name.
+pub(crate) fn crate_name(name: &str) -> String {
+    let mut crate_name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c.to_ascii_lowercase()
+            }
+            else {
+                '-'
+            }
+        })
+        .collect::<String>();
+
+    if !crate_name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        crate_name.insert_str(0, "shader-");
+    }
+
+    crate_name
+}
@@ -0,0 +1,126 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::BufWriter,
+    path::Path,
+};
+
+use askama::Template;
+use shade_rs_compile::{
+    library::Library,
+    preprocess,
+    CompileOptions,
+};
+
+use crate::export::{
+    crate_name,
+    Error,
+};
+
+/// Generates a standalone Rust crate (winit + wgpu) that renders the shader,
+/// with the input uniform already wired up.
+///
+/// `libraries` must contain the libraries that the shader includes. Their code
+/// is inlined into the crate's shader.
+#[tracing::instrument(skip(code, libraries, output_path))]
+pub fn export_starter_crate(
+    code: &str,
+    libraries: &BTreeMap<String, Library>,
+    name: &str,
+    output_path: impl AsRef<Path>,
+) -> Result<(), Error> {
+    let output_path = output_path.as_ref();
+    let src_path = output_path.join("src");
+    std::fs::create_dir_all(&src_path)?;
+
+    let crate_name = crate_name(name);
+    tracing::debug!(%crate_name, path = %output_path.display(), "generating starter crate");
+
+    let mut writer = BufWriter::new(File::create(output_path.join("Cargo.toml"))?);
+    CargoToml { name: &crate_name }.write_into(&mut writer)?;
+
+    let mut writer = BufWriter::new(File::create(output_path.join(".gitignore"))?);
+    Gitignore.write_into(&mut writer)?;
+
+    let mut writer = BufWriter::new(File::create(src_path.join("main.rs"))?);
+    MainRs {
+        title: &format!("{name:?}"),
+    }
+    .write_into(&mut writer)?;
+
+    // the crate doesn't run the shade-rs compiler, so it gets the source with
+    // all declarations injected and the libraries included.
+    let options = CompileOptions {
+        libraries: libraries.clone(),
+        ..Default::default()
+    };
+    let preprocessed = preprocess(code, &options)?;
+    std::fs::write(src_path.join("shader.wgsl"), &preprocessed.source)?;
+
+    Ok(())
+}
+
+#[derive(Debug, Template)]
+#[template(path = "starter-crate/Cargo.toml.in", escape = "none")]
+struct CargoToml<'a> {
+    name: &'a str,
+}
+
+#[derive(Debug, Template)]
+#[template(path = "starter-crate/gitignore.in", escape = "none")]
+struct Gitignore;
+
+#[derive(Debug, Template)]
+#[template(path = "starter-crate/main.rs.in", escape = "none")]
+struct MainRs<'a> {
+    /// Window title as a Rust string literal.
+    title: &'a str,
+}
+
+#[cfg(test)]
+mod tests {
+    use shade_rs_compile::inputs::InputsVersion;
+
+    /// Size of the `InputUniform` that the template declares, in bytes.
+    fn template_uniform_size() -> u32 {
+        let template = include_str!("../../templates/starter-crate/main.rs.in");
+        let start = template
+            .find("struct InputUniform {")
+            .expect("template doesn't declare InputUniform");
+        let body = &template[start..];
+        let body = &body[body.find('{').unwrap() + 1..body.find('}').unwrap()];
+
+        body.lines()
+            .filter_map(|line| line.trim().strip_suffix(','))
+            .map(|field| {
+                let (_, ty) = field.split_once(':').unwrap();
+                match ty.trim() {
+                    "f32" | "u32" => 4,
+                    ty => {
+                        let length = ty
+                            .strip_prefix("[f32;")
+                            .and_then(|ty| ty.strip_suffix(']'))
+                            .unwrap_or_else(|| panic!("unexpected field type: {ty}"));
+                        4 * length.trim().parse::<u32>().unwrap()
+                    }
+                }
+            })
+            .sum()
+    }
+
+    #[test]
+    fn template_uniform_matches_injected_inputs() {
+        let module = naga::front::wgsl::parse_str(InputsVersion::LATEST.declaration()).unwrap();
+        let (_, ty) = module
+            .types
+            .iter()
+            .find(|(_, ty)| ty.name.as_deref() == Some("ShadeRs"))
+            .unwrap();
+        let naga::TypeInner::Struct { span, .. } = ty.inner
+        else {
+            panic!("ShadeRs is not a struct");
+        };
+
+        assert_eq!(template_uniform_size(), span);
+    }
+}
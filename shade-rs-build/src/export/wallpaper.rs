@@ -0,0 +1,255 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::BufWriter,
+    path::Path,
+};
+
+use askama::Template;
+use serde::Serialize;
+use shade_rs_compile::library::{
+    inline_libraries,
+    Library,
+};
+
+use crate::export::Error;
+
+/// Packages a shader as a web wallpaper.
+///
+/// The output directory contains a page that runs the shade-rs player
+/// fullscreen, plus the metadata files expected by Wallpaper Engine
+/// (`project.json`) and Lively Wallpaper (`LivelyInfo.json`).
+///
+/// `ui_dist_path` must contain a build of the UI (i.e. `dist/ui`), including
+/// the player-only bundle in `embed/`. The editor isn't needed to show the
+/// wallpaper, so it's not copied.
+///
+/// `libraries` must contain the libraries that the shader includes. They're
+/// inlined into the shader, so that the wallpaper works offline.
+#[tracing::instrument(skip(code, libraries, ui_dist_path, preview_path, output_path))]
+pub fn export_wallpaper(
+    code: &str,
+    libraries: &BTreeMap<String, Library>,
+    title: &str,
+    ui_dist_path: impl AsRef<Path>,
+    preview_path: Option<&Path>,
+    output_path: impl AsRef<Path>,
+) -> Result<(), Error> {
+    let ui_dist_path = ui_dist_path.as_ref();
+    let output_path = output_path.as_ref();
+    std::fs::create_dir_all(output_path)?;
+
+    // the player would fetch included libraries from the server.
+    let code = inline_libraries(code, libraries)?;
+
+    let ui_files = UiFiles::find(ui_dist_path)?;
+    let embed_path = ui_dist_path.join(EMBED_DIR);
+    for (directory, filename) in [
+        (embed_path.as_path(), &ui_files.js),
+        (embed_path.as_path(), &ui_files.wasm),
+        (ui_dist_path, &ui_files.css),
+    ] {
+        tracing::debug!(%filename, "copying UI file");
+        std::fs::copy(directory.join(filename), output_path.join(filename))?;
+    }
+
+    let preview = if let Some(preview_path) = preview_path {
+        let extension = preview_path
+            .extension()
+            .map(|extension| extension.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "png".to_owned());
+        let filename = format!("preview.{extension}");
+        std::fs::copy(preview_path, output_path.join(&filename))?;
+        Some(filename)
+    }
+    else {
+        tracing::warn!("no preview image given. the wallpaper will have no thumbnail.");
+        None
+    };
+
+    let mut writer = BufWriter::new(File::create(output_path.join("index.html"))?);
+    IndexHtml {
+        title,
+        css: &ui_files.css,
+    }
+    .write_into(&mut writer)?;
+
+    let mut writer = BufWriter::new(File::create(output_path.join("loader.js"))?);
+    LoaderJs {
+        js: &ui_files.js,
+        code_json: &serde_json::to_string(&code)?,
+    }
+    .write_into(&mut writer)?;
+
+    let writer = BufWriter::new(File::create(output_path.join("project.json"))?);
+    serde_json::to_writer_pretty(
+        writer,
+        &WallpaperEngineProject {
+            file: "index.html",
+            preview: preview.as_deref(),
+            title,
+            ty: "web",
+        },
+    )?;
+
+    let writer = BufWriter::new(File::create(output_path.join("LivelyInfo.json"))?);
+    serde_json::to_writer_pretty(
+        writer,
+        &LivelyInfo {
+            app_version: "1.0.0.0",
+            title,
+            desc: "Generated by shade-rs",
+            thumbnail: preview.as_deref(),
+            preview: preview.as_deref(),
+            ty: LIVELY_TYPE_WEB,
+            file_name: "index.html",
+        },
+    )?;
+
+    Ok(())
+}
+
+/// Directory of the player-only bundle in the UI's dist directory.
+const EMBED_DIR: &str = "embed";
+
+/// The files produced by `compile_ui` that the wallpaper needs. The JS and
+/// wasm are the player-only bundle in [`EMBED_DIR`], and the CSS is shared by
+/// all bundles.
+#[derive(Debug)]
+struct UiFiles {
+    js: String,
+    wasm: String,
+    css: String,
+}
+
+impl UiFiles {
+    fn find(ui_dist_path: &Path) -> Result<Self, Error> {
+        if !ui_dist_path.exists() {
+            return Err(Error::UiNotBuilt {
+                path: ui_dist_path.to_owned(),
+            });
+        }
+
+        let embed_path = ui_dist_path.join(EMBED_DIR);
+        let not_built = || {
+            Error::PlayerNotBuilt {
+                path: embed_path.clone(),
+            }
+        };
+
+        if !embed_path.exists() {
+            return Err(not_built());
+        }
+
+        for result in std::fs::read_dir(&embed_path)? {
+            let entry = result?;
+            let filename = entry.file_name();
+            let Some(target_name) = filename
+                .to_str()
+                .and_then(|filename| filename.strip_suffix("_bg.wasm"))
+            else {
+                continue;
+            };
+
+            let ui_files = Self {
+                js: format!("{target_name}.js"),
+                wasm: format!("{target_name}_bg.wasm"),
+                css: format!("{target_name}.css"),
+            };
+
+            if !embed_path.join(&ui_files.js).exists() {
+                continue;
+            }
+            if !ui_dist_path.join(&ui_files.css).exists() {
+                return Err(Error::UiNotBuilt {
+                    path: ui_dist_path.to_owned(),
+                });
+            }
+            return Ok(ui_files);
+        }
+
+        Err(not_built())
+    }
+}
+
+#[derive(Debug, Template)]
+#[template(path = "wallpaper/index.html")]
+struct IndexHtml<'a> {
+    title: &'a str,
+    css: &'a str,
+}
+
+#[derive(Debug, Template)]
+#[template(path = "wallpaper/loader.js", escape = "none")]
+struct LoaderJs<'a> {
+    js: &'a str,
+    /// The shader code as a JSON (and thus JS) string literal.
+    code_json: &'a str,
+}
+
+/// `project.json` for Wallpaper Engine web wallpapers.
+#[derive(Debug, Serialize)]
+struct WallpaperEngineProject<'a> {
+    file: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preview: Option<&'a str>,
+    title: &'a str,
+    #[serde(rename = "type")]
+    ty: &'a str,
+}
+
+/// `LivelyInfo.json` for Lively Wallpaper.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct LivelyInfo<'a> {
+    app_version: &'a str,
+    title: &'a str,
+    desc: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thumbnail: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preview: Option<&'a str>,
+    #[serde(rename = "Type")]
+    ty: u32,
+    file_name: &'a str,
+}
+
+/// Wallpaper type for web pages in Lively.
+const LIVELY_TYPE_WEB: u32 = 1;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(path: &Path) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(path).unwrap();
+    }
+
+    #[test]
+    fn uses_the_player_bundle() {
+        let dist = tempfile::tempdir().unwrap();
+        let ui_dist_path = dist.path().join("ui");
+        assert!(matches!(
+            UiFiles::find(&ui_dist_path),
+            Err(Error::UiNotBuilt { .. })
+        ));
+
+        // only the editor is built.
+        for filename in ["shade-rs-ui.js", "shade-rs-ui_bg.wasm", "shade-rs-ui.css"] {
+            touch(&ui_dist_path.join(filename));
+        }
+        assert!(matches!(
+            UiFiles::find(&ui_dist_path),
+            Err(Error::PlayerNotBuilt { path }) if path == ui_dist_path.join(EMBED_DIR)
+        ));
+
+        for filename in ["shade-rs-ui.js", "shade-rs-ui_bg.wasm"] {
+            touch(&ui_dist_path.join(EMBED_DIR).join(filename));
+        }
+        let ui_files = UiFiles::find(&ui_dist_path).unwrap();
+        assert_eq!(ui_files.js, "shade-rs-ui.js");
+        assert_eq!(ui_files.wasm, "shade-rs-ui_bg.wasm");
+        assert_eq!(ui_files.css, "shade-rs-ui.css");
+    }
+}
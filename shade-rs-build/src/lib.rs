@@ -1,4 +1,7 @@
 #![allow(dead_code)]
 
+pub mod export;
+pub mod library;
+pub mod project;
 pub mod ui;
 pub mod util;
@@ -0,0 +1,193 @@
+use std::{
+    io::Write,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use shade_rs_compile::library::{
+    Library,
+    LibraryName,
+    Version,
+    VersionRequirement,
+};
+
+/// A directory with libraries, stored at `{user}/{name}/{version}.wgsl`.
+///
+/// This is where the server keeps published libraries, and where the CLI
+/// vendors the libraries a project includes (see
+/// [`LIBRARIES_DIRECTORY`](shade_rs_compile::library::LIBRARIES_DIRECTORY)).
+#[derive(Clone, Debug)]
+pub struct LibraryDirectory {
+    pub path: PathBuf,
+}
+
+impl LibraryDirectory {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The published versions of a library, oldest first.
+    pub fn versions(&self, name: &LibraryName) -> Result<Vec<Version>, LibraryError> {
+        let path = self.path.join(&name.user).join(&name.name);
+        let entries = match std::fs::read_dir(&path) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(source) => return Err(LibraryError::Io { path, source }),
+        };
+
+        let mut versions = vec![];
+        for entry in entries {
+            let entry = entry.map_err(|source| {
+                LibraryError::Io {
+                    path: path.clone(),
+                    source,
+                }
+            })?;
+            // ignore anything that isn't a library, e.g. editor backups.
+            let file_name = entry.file_name();
+            if let Some(version) = file_name
+                .to_str()
+                .and_then(|file_name| file_name.strip_suffix(".wgsl"))
+                .and_then(|version| version.parse().ok())
+            {
+                versions.push(version);
+            }
+        }
+        versions.sort();
+
+        Ok(versions)
+    }
+
+    /// The latest version of the library that matches `requirement`.
+    pub fn resolve(
+        &self,
+        name: &LibraryName,
+        requirement: &VersionRequirement,
+    ) -> Result<Option<Library>, LibraryError> {
+        let Some(version) = requirement.resolve(&self.versions(name)?)
+        else {
+            return Ok(None);
+        };
+        self.get(name, version)
+    }
+
+    pub fn get(&self, name: &LibraryName, version: Version) -> Result<Option<Library>, LibraryError> {
+        let library = Library {
+            name: name.clone(),
+            version,
+            source: String::new(),
+        };
+        let path = self.path.join(library.path());
+
+        match std::fs::read_to_string(&path) {
+            Ok(source) => Ok(Some(Library { source, ..library })),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(source) => Err(LibraryError::Io { path, source }),
+        }
+    }
+
+    /// Stores a library. Published versions never change, so this fails if
+    /// the version already exists.
+    ///
+    /// The library is written to a temporary file that is then linked into
+    /// place, so that it's never read half-written, and nothing is left behind
+    /// if writing fails.
+    pub fn insert(&self, library: &Library) -> Result<(), LibraryError> {
+        let path = self.path.join(library.path());
+        let directory = path.parent().unwrap_or(&self.path);
+        create_dir_all(directory)?;
+
+        let io_error = |source| {
+            LibraryError::Io {
+                path: path.clone(),
+                source,
+            }
+        };
+        let mut file = tempfile::NamedTempFile::new_in(directory).map_err(io_error)?;
+        file.write_all(library.source.as_bytes())
+            .map_err(io_error)?;
+
+        // the temporary file is removed if it can't be persisted.
+        match file.persist_noclobber(&path) {
+            Ok(_) => Ok(()),
+            Err(error) if error.error.kind() == std::io::ErrorKind::AlreadyExists => {
+                Err(LibraryError::AlreadyExists {
+                    name: library.name.clone(),
+                    version: library.version,
+                })
+            }
+            Err(error) => Err(io_error(error.error)),
+        }
+    }
+
+    /// Stores a library, replacing it if the version already exists. This is
+    /// for vendoring, where the server is the source of truth.
+    pub fn vendor(&self, library: &Library) -> Result<(), LibraryError> {
+        let path = self.path.join(library.path());
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        std::fs::write(&path, &library.source).map_err(|source| LibraryError::Io { path, source })
+    }
+}
+
+fn create_dir_all(path: &Path) -> Result<(), LibraryError> {
+    std::fs::create_dir_all(path).map_err(|source| {
+        LibraryError::Io {
+            path: path.to_owned(),
+            source,
+        }
+    })
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LibraryError {
+    #[error("could not access {path}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("{name}@{version} was already published")]
+    AlreadyExists { name: LibraryName, version: Version },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn published_versions_are_kept() {
+        let directory = tempfile::tempdir().unwrap();
+        let libraries = LibraryDirectory::new(directory.path());
+        let library = Library {
+            name: LibraryName::new("jgraef", "noise").unwrap(),
+            version: "1.2.3".parse().unwrap(),
+            source: "fn noise() -> f32 { return 0.5; }".to_owned(),
+        };
+
+        libraries.insert(&library).unwrap();
+        let replacement = Library {
+            source: "fn noise() -> f32 { return 1.0; }".to_owned(),
+            ..library.clone()
+        };
+        assert!(matches!(
+            libraries.insert(&replacement),
+            Err(LibraryError::AlreadyExists { .. })
+        ));
+
+        let stored = libraries
+            .get(&library.name, library.version)
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.source, library.source);
+
+        // no temporary files are left behind.
+        let files = std::fs::read_dir(directory.path().join("jgraef/noise"))
+            .unwrap()
+            .count();
+        assert_eq!(files, 1);
+    }
+}
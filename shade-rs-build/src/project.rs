@@ -0,0 +1,234 @@
+use std::{
+    collections::BTreeMap,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use shade_rs_compile::{
+    library::{
+        parse_includes,
+        Library,
+        LibraryName,
+        Version,
+        LIBRARIES_DIRECTORY,
+    },
+    project::{
+        write_lock,
+        LockedLibrary,
+        ManifestError,
+        PassManifest,
+        ProjectManifest,
+        MANIFEST_FILE_NAME,
+    },
+    CompileOptions,
+};
+
+use crate::library::{
+    LibraryDirectory,
+    LibraryError,
+};
+
+/// A project directory with its manifest and the sources of its passes.
+///
+/// See [`shade_rs_compile::project`] for the format.
+#[derive(Clone, Debug)]
+pub struct Project {
+    /// The project directory. Paths in the manifest are relative to this.
+    pub path: PathBuf,
+
+    /// Path of the `shader.toml`.
+    pub manifest_path: PathBuf,
+
+    pub manifest: ProjectManifest,
+
+    /// The code of each pass, in the same order as in the manifest.
+    pub sources: Vec<String>,
+
+    /// The vendored libraries that the passes include, by
+    /// [`Include::key`](shade_rs_compile::library::Include::key). Includes
+    /// that aren't vendored are missing, and fail to compile. Includes that are
+    /// in the manifest's lock use the locked version.
+    pub libraries: BTreeMap<String, Library>,
+}
+
+impl Project {
+    /// Opens a project from its directory, or from its `shader.toml`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ProjectError> {
+        let mut project = Self::open_without_libraries(path)?;
+        project.libraries = project.load_libraries()?;
+        Ok(project)
+    }
+
+    /// Opens a project without loading its vendored libraries, e.g. to vendor
+    /// them again.
+    pub fn open_without_libraries(path: impl AsRef<Path>) -> Result<Self, ProjectError> {
+        let path = path.as_ref();
+        let (path, manifest_path) = if path.is_dir() {
+            (path.to_owned(), path.join(MANIFEST_FILE_NAME))
+        }
+        else {
+            (
+                path.parent().unwrap_or(Path::new(".")).to_owned(),
+                path.to_owned(),
+            )
+        };
+
+        let toml = read_to_string(&manifest_path)?;
+        let manifest = ProjectManifest::from_toml(&toml)
+            .map_err(|errors| manifest_error(&manifest_path, &toml, errors))?;
+
+        let sources = manifest
+            .passes
+            .iter()
+            .map(|pass| read_to_string(&path.join(&pass.source)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            path,
+            manifest_path,
+            manifest,
+            sources,
+            libraries: BTreeMap::new(),
+        })
+    }
+
+    /// Loads the vendored libraries that the passes include.
+    fn load_libraries(&self) -> Result<BTreeMap<String, Library>, ProjectError> {
+        // invalid includes are reported when the passes are compiled.
+        let vendored = LibraryDirectory::new(self.path.join(LIBRARIES_DIRECTORY));
+        let mut libraries = BTreeMap::new();
+        for include in self
+            .sources
+            .iter()
+            .flat_map(|source| parse_includes(source).unwrap_or_default())
+        {
+            let key = include.key();
+            let library = if let Some(locked) = self.manifest.lock.get(&key) {
+                let library = vendored.get(&include.library, locked.version)?;
+                if let Some(library) = &library {
+                    if !locked.matches(library) {
+                        return Err(ProjectError::LockMismatch {
+                            name: include.library,
+                            version: locked.version,
+                        });
+                    }
+                }
+                library
+            }
+            else {
+                vendored.resolve(&include.library, &include.requirement)?
+            };
+
+            if let Some(library) = library {
+                libraries.insert(key, library);
+            }
+        }
+
+        Ok(libraries)
+    }
+
+    /// Replaces the lock in the manifest and writes it to the `shader.toml`.
+    pub fn write_lock(
+        &mut self,
+        lock: BTreeMap<String, LockedLibrary>,
+    ) -> Result<(), ProjectError> {
+        let toml = read_to_string(&self.manifest_path)?;
+        let toml = write_lock(&toml, &lock)
+            .map_err(|error| manifest_error(&self.manifest_path, &toml, vec![error]))?;
+        std::fs::write(&self.manifest_path, toml).map_err(|source| {
+            ProjectError::Io {
+                path: self.manifest_path.clone(),
+                source,
+            }
+        })?;
+
+        self.manifest.lock = lock;
+        Ok(())
+    }
+
+    /// Options that the project's passes are compiled with, including the
+    /// vendored libraries.
+    pub fn compile_options(&self) -> CompileOptions {
+        CompileOptions {
+            libraries: self.libraries.clone(),
+            ..self.manifest.compile_options()
+        }
+    }
+
+    /// Whether `path` is a project directory or manifest, as opposed to a
+    /// single WGSL file.
+    pub fn is_project(path: impl AsRef<Path>) -> bool {
+        let path = path.as_ref();
+        path.join(MANIFEST_FILE_NAME).is_file()
+            || path
+                .file_name()
+                .is_some_and(|file_name| file_name == MANIFEST_FILE_NAME)
+    }
+
+    /// The passes with their code.
+    pub fn passes(&self) -> impl Iterator<Item = (&PassManifest, &str)> {
+        self.manifest
+            .passes
+            .iter()
+            .zip(self.sources.iter().map(|source| source.as_str()))
+    }
+
+    /// Title from the manifest, or the name of the project directory.
+    pub fn title(&self) -> String {
+        self.manifest.shader.title.clone().unwrap_or_else(|| {
+            self.path
+                .canonicalize()
+                .ok()
+                .and_then(|path| {
+                    path.file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                })
+                .unwrap_or_else(|| "shader".to_owned())
+        })
+    }
+}
+
+fn manifest_error(path: &Path, toml: &str, errors: Vec<ManifestError>) -> ProjectError {
+    let display_path = path.display().to_string();
+    ProjectError::Manifest {
+        rendered: errors
+            .iter()
+            .map(|error| error.render(toml, &display_path))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        path: path.to_owned(),
+        errors,
+    }
+}
+
+fn read_to_string(path: &Path) -> Result<String, ProjectError> {
+    std::fs::read_to_string(path).map_err(|source| {
+        ProjectError::Io {
+            path: path.to_owned(),
+            source,
+        }
+    })
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProjectError {
+    #[error("could not access {path}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("invalid project manifest {path}\n\n{rendered}")]
+    Manifest {
+        path: PathBuf,
+        errors: Vec<ManifestError>,
+        /// The errors formatted with the lines they're in.
+        rendered: String,
+    },
+    #[error("could not load vendored library")]
+    Library(#[from] LibraryError),
+    #[error("vendored library {name}@{version} doesn't match the manifest's lock. vendor it again")]
+    LockMismatch { name: LibraryName, version: Version },
+}
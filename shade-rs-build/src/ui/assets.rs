@@ -0,0 +1,87 @@
+//! The asset manifest, which lists the generated files with their
+//! subresource-integrity hashes.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{
+        BufReader,
+        BufWriter,
+    },
+    path::Path,
+};
+
+use base64::{
+    engine::general_purpose::STANDARD,
+    Engine,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sha2::{
+    Digest,
+    Sha384,
+};
+
+/// File name of the asset manifest in the dist directory.
+pub const ASSET_MANIFEST: &str = "assets.json";
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AssetManifest {
+    /// The assets by their path relative to the dist directory, e.g.
+    /// `embed/shade-rs-ui.js`.
+    pub assets: BTreeMap<String, Asset>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Asset {
+    pub size: u64,
+
+    /// Subresource-integrity hash, e.g. `sha384-...`.
+    pub integrity: String,
+}
+
+impl AssetManifest {
+    /// Reads the asset manifest from the dist directory. If there is none,
+    /// an empty one is returned.
+    pub fn read(output_path: &Path) -> Result<Self, std::io::Error> {
+        let path = output_path.join(ASSET_MANIFEST);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let reader = BufReader::new(File::open(&path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    pub fn write(&self, output_path: &Path) -> Result<(), std::io::Error> {
+        let writer = BufWriter::new(File::create(output_path.join(ASSET_MANIFEST))?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    /// Hashes the file at `path` relative to the dist directory and adds it.
+    pub fn add(&mut self, output_path: &Path, path: &str) -> Result<(), std::io::Error> {
+        let data = std::fs::read(output_path.join(path))?;
+        self.assets.insert(
+            path.to_owned(),
+            Asset {
+                size: data.len() as u64,
+                integrity: integrity(&data),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn integrity(&self, path: &str) -> Option<&str> {
+        self.assets
+            .get(path)
+            .map(|asset| asset.integrity.as_str())
+    }
+}
+
+/// Computes the subresource-integrity hash of a file, as it's used in the
+/// `integrity` attribute.
+pub fn integrity(data: &[u8]) -> String {
+    format!("sha384-{}", STANDARD.encode(Sha384::digest(data)))
+}
@@ -90,7 +90,13 @@ impl Cargo {
             .into_json_result()?)
     }
 
-    pub async fn build(&self, target: Option<&str>, release: bool) -> Result<(), Error> {
+    pub async fn build(
+        &self,
+        target: Option<&str>,
+        release: bool,
+        target_dir: Option<&Path>,
+        features: &Features,
+    ) -> Result<(), Error> {
         let mut command = self.command();
         command.arg("build");
         if let Some(target) = target {
@@ -100,11 +106,29 @@ impl Cargo {
         if release {
             command.arg("--release");
         }
+        if let Some(target_dir) = target_dir {
+            command.arg("--target-dir");
+            command.arg(target_dir);
+        }
+        if features.no_default_features {
+            command.arg("--no-default-features");
+        }
+        if !features.features.is_empty() {
+            command.arg("--features");
+            command.arg(features.features.join(","));
+        }
         command.spawn()?.wait().await?.into_result()?;
         Ok(())
     }
 }
 
+/// Feature selection for `cargo build`.
+#[derive(Clone, Debug, Default)]
+pub struct Features {
+    pub no_default_features: bool,
+    pub features: Vec<String>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Manifest {
     pub name: String,
@@ -116,9 +140,10 @@ pub struct Manifest {
     pub source: Option<String>,
     pub dependencies: Vec<Dependency>,
     pub targets: Vec<Target>,
-    pub features: HashMap<String, String>,
+    pub features: HashMap<String, Vec<String>>,
     pub manifest_path: PathBuf,
-    pub metadata: HashMap<String, serde_json::Value>,
+    /// `[package.metadata]`, which is `null` if the crate has none.
+    pub metadata: Option<HashMap<String, serde_json::Value>>,
     pub publish: Option<bool>,
     pub authors: Vec<String>,
     pub categories: Vec<String>,
@@ -0,0 +1,190 @@
+use std::{
+    collections::BTreeMap,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use serde::Deserialize;
+
+use crate::ui::cargo::{
+    Manifest,
+    Target,
+};
+
+/// Key of the build settings in the crate's `[package.metadata]`.
+pub const METADATA_KEY: &str = "shade-rs-build";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("invalid [package.metadata.{METADATA_KEY}]")]
+    Invalid(#[from] serde_json::Error),
+
+    #[error("the crate has no binary or cdylib target that can be built to wasm")]
+    NoTarget,
+
+    #[error(
+        "the crate has several targets ({}). select one with `target` in [package.metadata.{METADATA_KEY}]",
+        .0.join(", ")
+    )]
+    AmbiguousTarget(Vec<String>),
+
+    #[error("the crate has no target named `{0}`")]
+    TargetNotFound(String),
+}
+
+/// How a crate is built into a UI.
+///
+/// This is read from `[package.metadata.shade-rs-build]` in the crate's
+/// `Cargo.toml`. All settings are optional:
+///
+/// ```toml
+/// [package.metadata.shade-rs-build]
+/// # The target that is built, if the crate has several.
+/// target = "my-app"
+/// # Templates, relative to the crate. Every file in it, e.g. a page or
+/// # `robots.txt`, is rendered into a file of the same name. `{{ js }}`,
+/// # `{{ wasm }}` and `{{ css }}` are replaced with the generated files,
+/// # `{{ js_integrity }}` etc. with their integrity hashes, and `{{ KEY }}`
+/// # with the variable `KEY`.
+/// templates = "templates"
+/// # Files that are copied into the dist directory as they are.
+/// static = "static"
+/// # Optimize the wasm module with `wasm-opt -Oz` in release builds.
+/// wasm-opt = "z"
+///
+/// # Commands that run as extra build steps, in the crate's directory. They
+/// # run last, unless they name the step they come after.
+/// [[package.metadata.shade-rs-build.steps]]
+/// name = "tailwind"
+/// command = ["npx", "tailwindcss", "-o", "static/tailwind.css"]
+/// after = "wasm-bindgen"
+///
+/// # Variables for the templates. The built-in pages use `title`,
+/// # `description` and `head`, which is inserted into the head unescaped.
+/// [package.metadata.shade-rs-build.vars]
+/// title = "My shaders"
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct UiConfig {
+    pub target: Option<String>,
+
+    pub templates: PathBuf,
+
+    #[serde(rename = "static")]
+    pub static_files: PathBuf,
+
+    /// Whether this is the shade-rs UI. It then also gets the player-only
+    /// bundle and the pages for the embed and dev endpoints.
+    pub player: bool,
+
+    pub wasm_opt: Option<String>,
+
+    pub steps: Vec<CommandStepConfig>,
+
+    pub vars: BTreeMap<String, String>,
+}
+
+/// A command that runs as a build step, see [`UiConfig`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct CommandStepConfig {
+    pub name: String,
+
+    /// The program and its arguments.
+    pub command: Vec<String>,
+
+    /// Name of the step after which this runs.
+    pub after: Option<String>,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            target: None,
+            templates: "templates".into(),
+            static_files: "static".into(),
+            player: false,
+            wasm_opt: None,
+            steps: vec![],
+            vars: BTreeMap::new(),
+        }
+    }
+}
+
+impl UiConfig {
+    pub fn from_manifest(manifest: &Manifest) -> Result<Self, ConfigError> {
+        Ok(manifest
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(METADATA_KEY))
+            .map(|value| serde_json::from_value(value.clone()))
+            .transpose()?
+            .unwrap_or_default())
+    }
+
+    /// Picks the target that is built.
+    ///
+    /// Without an explicit `target`, the crate must have exactly one binary or
+    /// cdylib target.
+    pub fn select_target<'a>(&self, manifest: &'a Manifest) -> Result<&'a Target, ConfigError> {
+        if let Some(name) = &self.target {
+            return manifest
+                .targets
+                .iter()
+                .find(|target| &target.name == name)
+                .ok_or_else(|| ConfigError::TargetNotFound(name.clone()));
+        }
+
+        let mut candidates = manifest.targets.iter().filter(|target| target.is_wasm_output());
+        match (candidates.next(), candidates.next()) {
+            (None, _) => Err(ConfigError::NoTarget),
+            (Some(target), None) => Ok(target),
+            (Some(first), Some(second)) => {
+                Err(ConfigError::AmbiguousTarget(
+                    [first, second]
+                        .into_iter()
+                        .chain(candidates)
+                        .map(|target| target.name.clone())
+                        .collect(),
+                ))
+            }
+        }
+    }
+
+    /// The directory with the crate's CSS, if it uses `kardashev-style`.
+    ///
+    /// The path is relative to the workspace.
+    pub fn css_path(manifest: &Manifest) -> Option<&Path> {
+        manifest
+            .metadata
+            .as_ref()?
+            .get("kardashev")?
+            .get("style")?
+            .get("output")?
+            .as_str()
+            .map(Path::new)
+    }
+}
+
+impl Target {
+    /// Whether building this target produces a `.wasm` file that wasm-bindgen
+    /// can process.
+    pub fn is_wasm_output(&self) -> bool {
+        self.kind
+            .iter()
+            .any(|kind| kind == "bin" || kind == "cdylib")
+    }
+
+    /// File name of the `.wasm` file that cargo produces for this target.
+    pub fn wasm_filename(&self) -> String {
+        if self.kind.iter().any(|kind| kind == "bin") {
+            format!("{}.wasm", self.name)
+        }
+        else {
+            // library artifacts have their dashes replaced.
+            format!("{}.wasm", self.name.replace('-', "_"))
+        }
+    }
+}
@@ -1,18 +1,21 @@
-mod cargo;
+pub mod assets;
+pub mod cargo;
+pub mod config;
 mod git;
+pub mod pipeline;
+pub mod steps;
 mod wasm_bindgen;
 
 use std::{
+    collections::BTreeMap,
     fs::File,
     io::{
         BufReader,
         BufWriter,
-        Read,
     },
     path::Path,
 };
 
-use askama::Template;
 use chrono::{
     DateTime,
     Utc,
@@ -24,11 +27,17 @@ use serde::{
 
 use crate::{
     ui::{
-        cargo::Cargo,
+        assets::ASSET_MANIFEST,
         git::Git,
-        wasm_bindgen::wasm_bindgen,
+        pipeline::{
+            BuildContext,
+            Pipeline,
+        },
+    },
+    util::{
+        path_modified_timestamp,
+        process::ExitStatusError,
     },
-    util::path_modified_timestamp,
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -38,8 +47,22 @@ pub enum Error {
     Cargo(#[from] crate::ui::cargo::Error),
     WasmBindgen(#[from] crate::ui::wasm_bindgen::WasmBindgenError),
     Json(#[from] serde_json::Error),
+    Config(#[from] crate::ui::config::ConfigError),
+    ExitStatus(#[from] ExitStatusError),
+    /// An error from a custom [`BuildStep`](pipeline::BuildStep).
+    Step(#[source] Box<dyn std::error::Error + Send + Sync>),
 }
 
+/// Builds a wasm-bindgen crate into a directory that can be served as it is.
+///
+/// The crate doesn't need to be shade-rs-ui. Its target is detected, and its
+/// own templates and static files are used, see [`UiConfig`].
+///
+/// This runs the [`Pipeline`] that the crate's config describes. To add custom
+/// steps from Rust, assemble the pipeline and pass it to [`run_pipeline`]
+/// instead.
+///
+/// [`UiConfig`]: config::UiConfig
 #[tracing::instrument(skip_all)]
 pub async fn compile_ui(
     input_path: impl AsRef<Path>,
@@ -47,18 +70,23 @@ pub async fn compile_ui(
     clean: bool,
     release: bool,
 ) -> Result<(), Error> {
-    let input_path = input_path.as_ref();
-    let output_path = output_path.as_ref();
-
-    std::fs::create_dir_all(&output_path)?;
+    let context = BuildContext::new(input_path, output_path, release).await?;
+    let pipeline = Pipeline::from_config(&context);
+    run_pipeline(&context, &pipeline, clean).await
+}
 
-    let cargo = Cargo::new(&input_path);
+/// Runs the steps of a UI build, unless the crate didn't change since the last
+/// build.
+#[tracing::instrument(skip_all)]
+pub async fn run_pipeline(
+    context: &BuildContext,
+    pipeline: &Pipeline,
+    clean: bool,
+) -> Result<(), Error> {
+    let input_path = &context.input_path;
+    let output_path = &context.output_path;
 
-    let manifest = cargo.manifest().await?;
-    if manifest.targets.len() != 1 {
-        // todo: don't panic
-        panic!("Unexpected number of targets: {}", manifest.targets.len());
-    }
+    std::fs::create_dir_all(output_path)?;
 
     let build_time = Utc::now();
     let build_info_path = output_path.join("build_info.json");
@@ -73,32 +101,11 @@ pub async fn compile_ui(
 
     let commit = Git.head().await.ok();
 
-    let target_name = &manifest.targets[0].name;
+    let target_name = &context.target.name;
     tracing::debug!(%target_name);
 
-    let workspace_path = cargo.locate_workspace().await?;
-    let workspace_path = workspace_path.parent().unwrap();
-    tracing::debug!(workspace_path = %workspace_path.display());
-
-    let target_wasm_path = workspace_path
-        .join("target")
-        .join("wasm32-unknown-unknown")
-        .join(if release { "release" } else { "debug" })
-        .join(format!("{target_name}.wasm"));
-    tracing::debug!(target_wasm_path = %target_wasm_path.display());
-
-    let wasm_filename = format!("{target_name}_bg.wasm");
-    let js_filename = format!("{target_name}.js");
-    let css_filename = format!("{target_name}.css");
-    let index_filename = "index.html";
-    let embed_filename = "embed.html";
-
     // check if all files exist
-    if !output_path.join(&wasm_filename).exists()
-        || !output_path.join(&js_filename).exists()
-        || !output_path.join(&css_filename).exists()
-        || !output_path.join(&index_filename).exists()
-    {
+    if pipeline.outputs(context).iter().any(|path| !path.exists()) {
         tracing::warn!("input file missing. rebuilding.");
     }
     else {
@@ -118,55 +125,32 @@ pub async fn compile_ui(
             _ => false,
         };
 
-        if is_fresh {
+        // the variables might have been changed on the command line.
+        let vars_changed = build_info
+            .as_ref()
+            .is_none_or(|build_info| build_info.vars != context.vars);
+
+        if is_fresh && !vars_changed {
             tracing::debug!("not modified since last build. skipping.");
             return Ok(());
         }
     }
 
-    tracing::info!(target = %target_name, "running `cargo build`");
-    cargo.build(Some("wasm32-unknown-unknown"), release).await?;
-
-    tracing::info!(target = %target_name, "running `wasm-bindgen`");
-    wasm_bindgen(&target_wasm_path, output_path, &target_name).await?;
-
-    tracing::info!("collecting CSS");
-    let css_path = workspace_path
-        .join("target")
-        .join("css")
-        .join("shade-rs-ui");
-    let mut css_buf = vec![];
-    for result in std::fs::read_dir(&css_path)? {
-        let entry = result?;
-        let mut reader = BufReader::new(File::open(&entry.path())?);
-        reader.read_to_end(&mut css_buf)?;
-    }
-    let css_output_path = output_path.join(&css_filename);
-    tracing::debug!(path = %css_output_path.display(), "writing CSS file");
-    std::fs::write(&css_output_path, &css_buf)?;
-
-    tracing::debug!(target = %target_name, "generating `index.html`");
-    let mut writer = BufWriter::new(File::create(output_path.join(&index_filename))?);
-    IndexHtml {
-        js: &js_filename,
-        wasm: &wasm_filename,
-        css: &css_filename,
-    }
-    .write_into(&mut writer)?;
-
-    tracing::debug!(target = %target_name, "generating `embed.html`");
-    let mut writer = BufWriter::new(File::create(output_path.join(&embed_filename))?);
-    EmbedHtml {
-        js: &js_filename,
-        wasm: &wasm_filename,
-        css: &css_filename,
+    // the integrity step writes a new one. if it's skipped, the pages must not
+    // use stale hashes.
+    let asset_manifest_path = output_path.join(ASSET_MANIFEST);
+    if asset_manifest_path.exists() {
+        std::fs::remove_file(&asset_manifest_path)?;
     }
-    .write_into(&mut writer)?;
+
+    tracing::info!(target = %target_name, "building");
+    pipeline.run(context).await?;
 
     let build_info = BuildInfo {
         build_time,
-        version: manifest.version,
+        version: context.manifest.version.clone(),
         commit,
+        vars: context.vars.clone(),
     };
 
     let writer = BufWriter::new(File::create(&build_info_path)?);
@@ -177,26 +161,11 @@ pub async fn compile_ui(
     Ok(())
 }
 
-#[derive(Debug, Template)]
-#[template(path = "index.html")]
-struct IndexHtml<'a> {
-    js: &'a str,
-    wasm: &'a str,
-    css: &'a str,
-}
-
-
-#[derive(Debug, Template)]
-#[template(path = "embed.html")]
-struct EmbedHtml<'a> {
-    js: &'a str,
-    wasm: &'a str,
-    css: &'a str,
-}
-
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct BuildInfo {
     build_time: DateTime<Utc>,
     version: String,
     commit: Option<String>,
+    #[serde(default)]
+    vars: BTreeMap<String, String>,
 }
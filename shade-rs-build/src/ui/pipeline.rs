@@ -0,0 +1,275 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use async_trait::async_trait;
+
+use crate::ui::{
+    cargo::{
+        Cargo,
+        Features,
+        Manifest,
+        Target,
+    },
+    config::UiConfig,
+    steps::{
+        CargoBuild,
+        CommandStep,
+        Css,
+        Integrity,
+        StaticFiles,
+        Templates,
+        WasmBindgen,
+        WasmOpt,
+    },
+    Error,
+};
+
+/// A step of a UI build, e.g. running `cargo build` or generating the pages.
+///
+/// Steps run in the order of the [`Pipeline`], and each one sees the files
+/// the previous ones wrote to [`BuildContext::output_path`].
+#[async_trait]
+pub trait BuildStep: Debug + Send + Sync {
+    /// Name with which the step is referred to, e.g. to insert another step
+    /// after it.
+    fn name(&self) -> &str;
+
+    /// Files this step writes. If any of them is missing, the UI is rebuilt
+    /// even if the crate didn't change.
+    fn outputs(&self, _context: &BuildContext) -> Vec<PathBuf> {
+        vec![]
+    }
+
+    async fn run(&self, context: &BuildContext) -> Result<(), Error>;
+}
+
+/// What the steps of a build need to know about the crate that is built.
+#[derive(Debug)]
+pub struct BuildContext {
+    pub cargo: Cargo,
+    pub manifest: Manifest,
+    pub config: UiConfig,
+
+    /// The target that is built, see [`UiConfig::select_target`].
+    pub target: Target,
+
+    /// Path to the crate.
+    pub input_path: PathBuf,
+
+    /// The dist directory.
+    pub output_path: PathBuf,
+
+    pub workspace_path: PathBuf,
+
+    /// The workspace's target directory.
+    pub target_dir: PathBuf,
+
+    pub release: bool,
+
+    /// Variables for the templates. These are the ones from the
+    /// [`UiConfig::vars`], but can be changed before the build runs, e.g. from
+    /// the command line.
+    pub vars: BTreeMap<String, String>,
+}
+
+impl BuildContext {
+    pub async fn new(
+        input_path: impl AsRef<Path>,
+        output_path: impl AsRef<Path>,
+        release: bool,
+    ) -> Result<Self, Error> {
+        let input_path = input_path.as_ref().to_owned();
+        let output_path = output_path.as_ref().to_owned();
+
+        let cargo = Cargo::new(&input_path);
+        let manifest = cargo.manifest().await?;
+        let config = UiConfig::from_manifest(&manifest)?;
+        let target = config.select_target(&manifest)?.clone();
+
+        let workspace_path = cargo.locate_workspace().await?;
+        let workspace_path = workspace_path.parent().unwrap().to_owned();
+        tracing::debug!(workspace_path = %workspace_path.display());
+        let target_dir = workspace_path.join("target");
+        let vars = config.vars.clone();
+
+        Ok(Self {
+            cargo,
+            manifest,
+            config,
+            target,
+            input_path,
+            output_path,
+            workspace_path,
+            target_dir,
+            release,
+            vars,
+        })
+    }
+
+    /// Name of the JS module that wasm-bindgen generates.
+    pub fn js_filename(&self) -> String {
+        format!("{}.js", self.target.name)
+    }
+
+    /// Name of the wasm module that wasm-bindgen generates.
+    pub fn wasm_filename(&self) -> String {
+        format!("{}_bg.wasm", self.target.name)
+    }
+
+    pub fn css_filename(&self) -> String {
+        format!("{}.css", self.target.name)
+    }
+
+    /// Path of the `.wasm` file that cargo produces in the given target
+    /// directory.
+    pub fn target_wasm_path(&self, target_dir: &Path) -> PathBuf {
+        target_dir
+            .join("wasm32-unknown-unknown")
+            .join(if self.release { "release" } else { "debug" })
+            .join(self.target.wasm_filename())
+    }
+}
+
+/// The steps of a UI build, in order.
+#[derive(Debug, Default)]
+pub struct Pipeline {
+    steps: Vec<Box<dyn BuildStep>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The steps that build the crate as its [`UiConfig`] says.
+    ///
+    /// This runs `cargo build` and wasm-bindgen, then `wasm-opt` if
+    /// configured, and then collects the CSS, hashes the generated files,
+    /// copies the static files and generates the pages. The commands from [`UiConfig::steps`] are inserted
+    /// where they ask to be.
+    pub fn from_config(context: &BuildContext) -> Self {
+        let config = &context.config;
+        let mut pipeline = Self::new();
+
+        pipeline.push(CargoBuild::default());
+        pipeline.push(WasmBindgen::default());
+
+        if config.player {
+            // the player-only bundle gets its own target directory, so that the
+            // two builds don't keep invalidating each other.
+            pipeline.push(CargoBuild {
+                name: "cargo-build-embed".to_owned(),
+                target_dir: Some("embed".into()),
+                features: Features {
+                    no_default_features: true,
+                    features: vec![],
+                },
+            });
+            pipeline.push(WasmBindgen {
+                name: "wasm-bindgen-embed".to_owned(),
+                target_dir: Some("embed".into()),
+                output_dir: Some("embed".into()),
+            });
+        }
+
+        if let Some(level) = config.wasm_opt.as_ref().filter(|_| context.release) {
+            pipeline.push(WasmOpt {
+                level: level.clone(),
+            });
+        }
+
+        pipeline.push(Css);
+        pipeline.push(Integrity);
+        pipeline.push(StaticFiles);
+        pipeline.push(Templates);
+
+        for step in &config.steps {
+            let command = CommandStep {
+                name: step.name.clone(),
+                command: step.command.clone(),
+            };
+            let index = step.after.as_deref().and_then(|after| {
+                let index = pipeline.position(after);
+                if index.is_none() {
+                    tracing::warn!(step = %step.name, %after, "no such step. running it last.");
+                }
+                index
+            });
+            match index {
+                Some(index) => pipeline.steps.insert(index + 1, Box::new(command)),
+                None => {
+                    pipeline.push(command);
+                }
+            }
+        }
+
+        pipeline
+    }
+
+    pub fn push(&mut self, step: impl BuildStep + 'static) -> &mut Self {
+        self.steps.push(Box::new(step));
+        self
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.steps.iter().position(|step| step.name() == name)
+    }
+
+    /// Inserts a step before the step with the given name. Returns `false` if
+    /// there is no such step.
+    pub fn insert_before(&mut self, name: &str, step: impl BuildStep + 'static) -> bool {
+        let Some(index) = self.position(name)
+        else {
+            return false;
+        };
+        self.steps.insert(index, Box::new(step));
+        true
+    }
+
+    /// Inserts a step after the step with the given name. Returns `false` if
+    /// there is no such step.
+    pub fn insert_after(&mut self, name: &str, step: impl BuildStep + 'static) -> bool {
+        let Some(index) = self.position(name)
+        else {
+            return false;
+        };
+        self.steps.insert(index + 1, Box::new(step));
+        true
+    }
+
+    /// Removes the step with the given name. Returns `false` if there is no
+    /// such step.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let Some(index) = self.position(name)
+        else {
+            return false;
+        };
+        self.steps.remove(index);
+        true
+    }
+
+    pub fn step_names(&self) -> impl Iterator<Item = &str> {
+        self.steps.iter().map(|step| step.name())
+    }
+
+    pub fn outputs(&self, context: &BuildContext) -> Vec<PathBuf> {
+        self.steps
+            .iter()
+            .flat_map(|step| step.outputs(context))
+            .collect()
+    }
+
+    pub async fn run(&self, context: &BuildContext) -> Result<(), Error> {
+        for step in &self.steps {
+            tracing::info!(step = %step.name(), "running build step");
+            step.run(context).await?;
+        }
+        Ok(())
+    }
+}
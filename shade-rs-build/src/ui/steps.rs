@@ -0,0 +1,509 @@
+//! The build steps that [`Pipeline::from_config`] assembles.
+//!
+//! [`Pipeline::from_config`]: crate::ui::pipeline::Pipeline::from_config
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{
+        BufReader,
+        BufWriter,
+        Read,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use askama::Template;
+use async_trait::async_trait;
+use tokio::process::Command;
+
+use crate::{
+    ui::{
+        assets::{
+            AssetManifest,
+            ASSET_MANIFEST,
+        },
+        cargo::Features,
+        config::UiConfig,
+        pipeline::{
+            BuildContext,
+            BuildStep,
+        },
+        wasm_bindgen::wasm_bindgen,
+        Error,
+    },
+    util::{
+        copy_dir,
+        process::ExitStatusExt,
+    },
+};
+
+/// Runs `cargo build` for `wasm32-unknown-unknown`.
+#[derive(Clone, Debug)]
+pub struct CargoBuild {
+    pub name: String,
+
+    /// Target directory, relative to the workspace's target directory.
+    pub target_dir: Option<PathBuf>,
+
+    pub features: Features,
+}
+
+impl Default for CargoBuild {
+    fn default() -> Self {
+        Self {
+            name: "cargo-build".to_owned(),
+            target_dir: None,
+            features: Features::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl BuildStep for CargoBuild {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn run(&self, context: &BuildContext) -> Result<(), Error> {
+        let target_dir = self
+            .target_dir
+            .as_ref()
+            .map(|target_dir| context.target_dir.join(target_dir));
+        context
+            .cargo
+            .build(
+                Some("wasm32-unknown-unknown"),
+                context.release,
+                target_dir.as_deref(),
+                &self.features,
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// Runs wasm-bindgen on the module that [`CargoBuild`] produced.
+#[derive(Clone, Debug)]
+pub struct WasmBindgen {
+    pub name: String,
+
+    /// Target directory of the [`CargoBuild`], relative to the workspace's
+    /// target directory.
+    pub target_dir: Option<PathBuf>,
+
+    /// Output directory, relative to the dist directory.
+    pub output_dir: Option<PathBuf>,
+}
+
+impl Default for WasmBindgen {
+    fn default() -> Self {
+        Self {
+            name: "wasm-bindgen".to_owned(),
+            target_dir: None,
+            output_dir: None,
+        }
+    }
+}
+
+impl WasmBindgen {
+    fn output_path(&self, context: &BuildContext) -> PathBuf {
+        match &self.output_dir {
+            Some(output_dir) => context.output_path.join(output_dir),
+            None => context.output_path.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl BuildStep for WasmBindgen {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn outputs(&self, context: &BuildContext) -> Vec<PathBuf> {
+        let output_path = self.output_path(context);
+        vec![
+            output_path.join(context.js_filename()),
+            output_path.join(context.wasm_filename()),
+        ]
+    }
+
+    async fn run(&self, context: &BuildContext) -> Result<(), Error> {
+        let target_dir = match &self.target_dir {
+            Some(target_dir) => context.target_dir.join(target_dir),
+            None => context.target_dir.clone(),
+        };
+        let output_path = self.output_path(context);
+        std::fs::create_dir_all(&output_path)?;
+        wasm_bindgen(
+            &context.target_wasm_path(&target_dir),
+            &output_path,
+            &context.target.name,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// Optimizes the wasm module with binaryen's `wasm-opt`, which needs to be
+/// installed.
+#[derive(Clone, Debug)]
+pub struct WasmOpt {
+    /// Optimization level, e.g. `z` for `-Oz`.
+    pub level: String,
+}
+
+#[async_trait]
+impl BuildStep for WasmOpt {
+    fn name(&self) -> &str {
+        "wasm-opt"
+    }
+
+    async fn run(&self, context: &BuildContext) -> Result<(), Error> {
+        let wasm_path = context.output_path.join(context.wasm_filename());
+        Command::new("wasm-opt")
+            .arg(format!("-O{}", self.level))
+            .arg(&wasm_path)
+            .arg("-o")
+            .arg(&wasm_path)
+            .spawn()?
+            .wait()
+            .await?
+            .into_result()?;
+        Ok(())
+    }
+}
+
+/// Collects the CSS that `kardashev-style` generated into a single file. If
+/// the crate doesn't use it, the file is empty.
+#[derive(Clone, Copy, Debug)]
+pub struct Css;
+
+#[async_trait]
+impl BuildStep for Css {
+    fn name(&self) -> &str {
+        "css"
+    }
+
+    fn outputs(&self, context: &BuildContext) -> Vec<PathBuf> {
+        vec![context.output_path.join(context.css_filename())]
+    }
+
+    async fn run(&self, context: &BuildContext) -> Result<(), Error> {
+        let mut css_buf = vec![];
+        if let Some(css_path) = UiConfig::css_path(&context.manifest) {
+            for result in std::fs::read_dir(context.workspace_path.join(css_path))? {
+                let entry = result?;
+                let mut reader = BufReader::new(File::open(entry.path())?);
+                reader.read_to_end(&mut css_buf)?;
+            }
+        }
+        let css_output_path = context.output_path.join(context.css_filename());
+        tracing::debug!(path = %css_output_path.display(), "writing CSS file");
+        std::fs::write(&css_output_path, &css_buf)?;
+        Ok(())
+    }
+}
+
+/// Computes subresource-integrity hashes of the generated JS, wasm and CSS,
+/// and writes them to the asset manifest.
+///
+/// The [`Templates`] put them into the `integrity` attributes of the pages,
+/// so that the files can be served from a CDN that isn't trusted.
+#[derive(Clone, Copy, Debug)]
+pub struct Integrity;
+
+#[async_trait]
+impl BuildStep for Integrity {
+    fn name(&self) -> &str {
+        "integrity"
+    }
+
+    fn outputs(&self, context: &BuildContext) -> Vec<PathBuf> {
+        vec![context.output_path.join(ASSET_MANIFEST)]
+    }
+
+    async fn run(&self, context: &BuildContext) -> Result<(), Error> {
+        let mut assets = AssetManifest::default();
+        let js = context.js_filename();
+        let wasm = context.wasm_filename();
+        let css = context.css_filename();
+
+        let mut paths = vec![js.clone(), wasm.clone(), css];
+        if context.config.player {
+            paths.push(format!("embed/{js}"));
+            paths.push(format!("embed/{wasm}"));
+        }
+
+        for path in &paths {
+            // e.g. the CSS step might have been skipped.
+            if context.output_path.join(path).exists() {
+                assets.add(&context.output_path, path)?;
+            }
+        }
+
+        assets.write(&context.output_path)?;
+        Ok(())
+    }
+}
+
+/// Copies the crate's static files into the dist directory.
+#[derive(Clone, Copy, Debug)]
+pub struct StaticFiles;
+
+#[async_trait]
+impl BuildStep for StaticFiles {
+    fn name(&self) -> &str {
+        "static"
+    }
+
+    async fn run(&self, context: &BuildContext) -> Result<(), Error> {
+        let static_path = context.input_path.join(&context.config.static_files);
+        if static_path.is_dir() {
+            tracing::debug!(path = %static_path.display(), "copying static files");
+            copy_dir(&static_path, &context.output_path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Generates the pages and other templated files.
+///
+/// Player crates get the built-in `index.html`, `embed.html` and `dev.html`.
+/// Other crates get a minimal `index.html`, unless they have their own. The
+/// crate's own templates are rendered last, so that they can replace the
+/// built-in pages.
+#[derive(Clone, Copy, Debug)]
+pub struct Templates;
+
+impl Templates {
+    /// The crate's templates, with the file names they're rendered to.
+    fn crate_templates(context: &BuildContext) -> Result<Vec<(PathBuf, PathBuf)>, Error> {
+        let templates_path = context.input_path.join(&context.config.templates);
+        let mut templates = vec![];
+        if templates_path.is_dir() {
+            for result in std::fs::read_dir(&templates_path)? {
+                let path = result?.path();
+                if path.is_file() {
+                    templates.push((path.file_name().unwrap().into(), path));
+                }
+            }
+        }
+        Ok(templates)
+    }
+}
+
+#[async_trait]
+impl BuildStep for Templates {
+    fn name(&self) -> &str {
+        "templates"
+    }
+
+    fn outputs(&self, context: &BuildContext) -> Vec<PathBuf> {
+        let mut filenames = Self::crate_templates(context)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(filename, _)| filename)
+            .collect::<Vec<_>>();
+        if context.config.player {
+            filenames.extend(["index.html", "embed.html", "dev.html"].map(PathBuf::from));
+        }
+        else if !filenames.iter().any(|filename| filename == Path::new("index.html")) {
+            filenames.push("index.html".into());
+        }
+        filenames
+            .into_iter()
+            .map(|filename| context.output_path.join(filename))
+            .collect()
+    }
+
+    async fn run(&self, context: &BuildContext) -> Result<(), Error> {
+        let js = context.js_filename();
+        let wasm = context.wasm_filename();
+        let css = context.css_filename();
+        let output_path = &context.output_path;
+        let templates = Self::crate_templates(context)?;
+        let assets = AssetManifest::read(output_path)?;
+        let integrity = PageIntegrity::new(&assets, "", context);
+        let empty = String::new();
+
+        if context.config.player {
+            tracing::debug!("generating `index.html`");
+            let mut writer = BufWriter::new(File::create(output_path.join("index.html"))?);
+            IndexHtml {
+                js: &js,
+                wasm: &wasm,
+                css: &css,
+                vars: &context.vars,
+                integrity: &integrity,
+            }
+            .write_into(&mut writer)?;
+
+            tracing::debug!("generating `embed.html`");
+            let mut writer = BufWriter::new(File::create(output_path.join("embed.html"))?);
+            EmbedHtml {
+                js: &js,
+                wasm: &wasm,
+                css: &css,
+                integrity: &PageIntegrity::new(&assets, "embed/", context),
+            }
+            .write_into(&mut writer)?;
+
+            tracing::debug!("generating `dev.html`");
+            let mut writer = BufWriter::new(File::create(output_path.join("dev.html"))?);
+            DevHtml {
+                js: &js,
+                wasm: &wasm,
+                css: &css,
+                vars: &context.vars,
+                integrity: &integrity,
+            }
+            .write_into(&mut writer)?;
+        }
+        else if !templates
+            .iter()
+            .any(|(filename, _)| filename == Path::new("index.html"))
+        {
+            tracing::debug!("generating default `index.html`");
+            let mut writer = BufWriter::new(File::create(output_path.join("index.html"))?);
+            DefaultHtml {
+                title: context
+                    .vars
+                    .get("title")
+                    .unwrap_or(&context.manifest.name),
+                js: &js,
+                wasm: &wasm,
+                css: &css,
+                vars: &context.vars,
+                integrity: &integrity,
+            }
+            .write_into(&mut writer)?;
+        }
+
+        // these templates are only known at runtime, so they can't be askama
+        // templates, but they use the same syntax for the placeholders.
+        for (filename, path) in &templates {
+            tracing::debug!(template = %path.display(), "rendering template");
+            let mut contents = std::fs::read_to_string(path)?;
+            let integrity_vars = [
+                ("js_integrity", &integrity.js),
+                ("wasm_integrity", &integrity.wasm),
+                ("css_integrity", &integrity.css),
+            ]
+            .into_iter()
+            .map(|(key, value)| (key, value.as_ref().unwrap_or(&empty)));
+            for (key, value) in [("js", &js), ("wasm", &wasm), ("css", &css)]
+                .into_iter()
+                .chain(integrity_vars)
+                .chain(context.vars.iter().map(|(key, value)| (key.as_str(), value)))
+            {
+                contents = contents.replace(&format!("{{{{ {key} }}}}"), value);
+            }
+            std::fs::write(output_path.join(filename), contents)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs a command in the crate's directory, e.g. a CSS framework's CLI.
+///
+/// The path of the dist directory is passed in `SHADE_RS_DIST`.
+#[derive(Clone, Debug)]
+pub struct CommandStep {
+    pub name: String,
+
+    /// The program and its arguments.
+    pub command: Vec<String>,
+}
+
+#[async_trait]
+impl BuildStep for CommandStep {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn run(&self, context: &BuildContext) -> Result<(), Error> {
+        let Some((program, args)) = self.command.split_first()
+        else {
+            return Ok(());
+        };
+        Command::new(program)
+            .args(args)
+            .current_dir(&context.input_path)
+            .env("SHADE_RS_DIST", &context.output_path)
+            .spawn()?
+            .wait()
+            .await?
+            .into_result()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Template)]
+#[template(path = "index.html")]
+struct IndexHtml<'a> {
+    js: &'a str,
+    wasm: &'a str,
+    css: &'a str,
+    vars: &'a BTreeMap<String, String>,
+    integrity: &'a PageIntegrity,
+}
+
+#[derive(Debug, Template)]
+#[template(path = "embed.html")]
+struct EmbedHtml<'a> {
+    js: &'a str,
+    wasm: &'a str,
+    css: &'a str,
+    integrity: &'a PageIntegrity,
+}
+
+/// Page of the `dev` command, which previews a project from disk.
+#[derive(Debug, Template)]
+#[template(path = "dev.html")]
+struct DevHtml<'a> {
+    js: &'a str,
+    wasm: &'a str,
+    css: &'a str,
+    vars: &'a BTreeMap<String, String>,
+    integrity: &'a PageIntegrity,
+}
+
+/// Page for crates that don't bring their own templates. It only loads the
+/// wasm module, which then runs its start function.
+#[derive(Debug, Template)]
+#[template(path = "default.html")]
+struct DefaultHtml<'a> {
+    title: &'a str,
+    js: &'a str,
+    wasm: &'a str,
+    css: &'a str,
+    vars: &'a BTreeMap<String, String>,
+    integrity: &'a PageIntegrity,
+}
+
+/// The integrity hashes of the files a page loads, if they were computed.
+#[derive(Debug)]
+struct PageIntegrity {
+    js: Option<String>,
+    wasm: Option<String>,
+    css: Option<String>,
+}
+
+impl PageIntegrity {
+    /// The hashes of a bundle, e.g. the embed player in `embed/`. The CSS is
+    /// the same for all bundles.
+    fn new(assets: &AssetManifest, prefix: &str, context: &BuildContext) -> Self {
+        let integrity = |path: String| assets.integrity(&path).map(ToOwned::to_owned);
+        Self {
+            js: integrity(format!("{prefix}{}", context.js_filename())),
+            wasm: integrity(format!("{prefix}{}", context.wasm_filename())),
+            css: integrity(context.css_filename()),
+        }
+    }
+}
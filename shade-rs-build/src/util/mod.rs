@@ -34,3 +34,26 @@ pub fn path_modified_timestamp(
 
     Ok(modified_time)
 }
+
+/// Copies the files in `from` into `to`, keeping their relative paths.
+pub fn copy_dir(from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<(), std::io::Error> {
+    let from = from.as_ref();
+    let to = to.as_ref();
+
+    for result in WalkDir::new(from) {
+        let entry = result?;
+        let relative_path = entry
+            .path()
+            .strip_prefix(from)
+            .expect("walkdir returned a path outside of the directory");
+        let target_path = to.join(relative_path);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target_path)?;
+        }
+        else {
+            std::fs::copy(entry.path(), &target_path)?;
+        }
+    }
+
+    Ok(())
+}
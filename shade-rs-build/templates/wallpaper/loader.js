@@ -0,0 +1,9 @@
+// Generated by shade-rs.
+import init, { mount_player } from './{{ js }}';
+
+// The shader is inlined, because wallpaper tools load this page from the file
+// system, where `fetch` doesn't work.
+const SHADER = {{ code_json }};
+
+await init({});
+mount_player("shade-rs-root", SHADER);
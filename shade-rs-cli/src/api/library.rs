@@ -0,0 +1,363 @@
+use std::{
+    fmt::Debug,
+    sync::Arc,
+};
+
+use axum::{
+    extract::{
+        Path,
+        State,
+    },
+    http::{
+        header::{
+            AUTHORIZATION,
+            WWW_AUTHENTICATE,
+        },
+        HeaderMap,
+        StatusCode,
+    },
+    response::{
+        IntoResponse,
+        Response,
+    },
+    Json,
+};
+use serde::Serialize;
+use shade_rs_build::library::{
+    LibraryDirectory,
+    LibraryError,
+};
+use shade_rs_compile::{
+    diagnostic::Diagnostic,
+    library::{
+        validate_library,
+        Library,
+        LibraryName,
+        Version,
+        VersionRequirement,
+    },
+};
+
+use crate::{
+    api::validate::Validation,
+    util::shutdown::Workers,
+};
+
+/// `GET /api/libraries/:user/:name`
+///
+/// Returns the published versions of a library, oldest first.
+pub async fn versions(
+    State(libraries): State<LibraryDirectory>,
+    Path((user, name)): Path<(String, String)>,
+) -> Result<Json<Versions>, ApiError> {
+    let name = LibraryName::new(&user, &name).ok_or(ApiError::InvalidName)?;
+    let versions = libraries.versions(&name)?;
+    if versions.is_empty() {
+        return Err(ApiError::NotFound);
+    }
+    Ok(Json(Versions { versions }))
+}
+
+/// `GET /api/libraries/:user/:name/:requirement`
+///
+/// Returns the latest version of a library that matches the requirement, e.g.
+/// `1.2`, with its code.
+pub async fn resolve(
+    State(libraries): State<LibraryDirectory>,
+    Path((user, name, requirement)): Path<(String, String, String)>,
+) -> Result<Json<Library>, ApiError> {
+    let name = LibraryName::new(&user, &name).ok_or(ApiError::InvalidName)?;
+    let requirement = requirement
+        .parse::<VersionRequirement>()
+        .map_err(|()| ApiError::InvalidVersion)?;
+    let library = libraries
+        .resolve(&name, &requirement)?
+        .ok_or(ApiError::NotFound)?;
+    Ok(Json(library))
+}
+
+/// `PUT /api/libraries/:user/:name/:version`
+///
+/// Publishes a version of a library, with the WGSL code in the request body.
+/// The code must parse and validate by itself. Published versions can't be
+/// changed.
+///
+/// Clients must send the server's [`PublishToken`] as `Authorization: Bearer
+/// <token>`.
+pub async fn publish(
+    State(libraries): State<LibraryDirectory>,
+    State(publish_token): State<PublishToken>,
+    State(workers): State<Workers>,
+    Path((user, name, version)): Path<(String, String, String)>,
+    headers: HeaderMap,
+    source: String,
+) -> Result<(StatusCode, Json<Validation>), ApiError> {
+    publish_token.authorize(&headers)?;
+
+    let name = LibraryName::new(&user, &name).ok_or(ApiError::InvalidName)?;
+    let version = version
+        .parse::<Version>()
+        .map_err(|()| ApiError::InvalidVersion)?;
+
+    if let Err(error) = validate_library(&source) {
+        return Ok((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(Validation {
+                valid: false,
+                diagnostics: vec![Diagnostic::from(&error)],
+            }),
+        ));
+    }
+
+    if workers.is_draining() {
+        return Err(ApiError::ShuttingDown);
+    }
+
+    // the library is written by a worker, so that it's not left half-written if
+    // the request is cancelled or the server shuts down.
+    let library = Library {
+        name,
+        version,
+        source,
+    };
+    let written = workers
+        .spawn(async move { libraries.insert(&library).map(|()| library) })
+        .await;
+    let library = match written {
+        Ok(Some(result)) => result?,
+        // the worker didn't finish before the drain timeout.
+        Ok(None) => return Err(ApiError::ShuttingDown),
+        Err(error) => std::panic::resume_unwind(error.into_panic()),
+    };
+    tracing::info!(name = %library.name, %version, "published library");
+
+    Ok((
+        StatusCode::CREATED,
+        Json(Validation {
+            valid: true,
+            diagnostics: vec![],
+        }),
+    ))
+}
+
+/// The token that clients need to publish libraries. Publishing is disabled
+/// without one.
+#[derive(Clone, Default)]
+pub struct PublishToken(Option<Arc<str>>);
+
+impl PublishToken {
+    /// An empty token disables publishing too.
+    pub fn new(token: Option<String>) -> Self {
+        Self(token.filter(|token| !token.is_empty()).map(Into::into))
+    }
+
+    fn authorize(&self, headers: &HeaderMap) -> Result<(), ApiError> {
+        let Some(expected) = &self.0
+        else {
+            return Err(ApiError::PublishingDisabled);
+        };
+        let token = headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(ApiError::Unauthorized)?;
+
+        // compare all bytes, so that the time doesn't tell how much of the token
+        // was right.
+        let matches = token.len() == expected.len()
+            && token
+                .bytes()
+                .zip(expected.bytes())
+                .fold(0, |difference, (a, b)| difference | (a ^ b))
+                == 0;
+        if matches {
+            Ok(())
+        }
+        else {
+            Err(ApiError::Unauthorized)
+        }
+    }
+}
+
+impl Debug for PublishToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // never log the token.
+        let token = self.0.as_ref().map(|_| "...");
+        f.debug_tuple("PublishToken").field(&token).finish()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Versions {
+    pub versions: Vec<Version>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("invalid library name. names may only contain lowercase letters, digits, `-` and `_`")]
+    InvalidName,
+    #[error("invalid version. versions look like `1.2.3`, requirements like `1.2`")]
+    InvalidVersion,
+    #[error("library not found")]
+    NotFound,
+    #[error("publishing libraries is disabled on this server")]
+    PublishingDisabled,
+    #[error("publishing libraries requires a valid token")]
+    Unauthorized,
+    #[error("the server is shutting down")]
+    ShuttingDown,
+    #[error("{0}")]
+    Library(#[from] LibraryError),
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            Self::InvalidName | Self::InvalidVersion => StatusCode::BAD_REQUEST,
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::PublishingDisabled => StatusCode::FORBIDDEN,
+            Self::ShuttingDown => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Unauthorized => {
+                return (
+                    StatusCode::UNAUTHORIZED,
+                    [(WWW_AUTHENTICATE, "Bearer")],
+                    self.to_string(),
+                )
+                    .into_response();
+            }
+            Self::Library(LibraryError::AlreadyExists { .. }) => StatusCode::CONFLICT,
+            Self::Library(error) => {
+                // don't tell clients about the server's file system.
+                tracing::error!(?error);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{
+            Method,
+            Request,
+        },
+        Router,
+    };
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        api,
+        util::shutdown::GracefulShutdown,
+    };
+
+    const TOKEN: &str = "secret";
+    const LIBRARY: &str = "fn double(x: f32) -> f32 { return 2.0 * x; }";
+
+    async fn publish(router: &Router, token: Option<&str>, source: &str) -> StatusCode {
+        let mut request = Request::builder()
+            .method(Method::PUT)
+            .uri("/libraries/alice/math/1.0.0");
+        if let Some(token) = token {
+            request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let request = request.body(Body::from(source.to_owned())).unwrap();
+        router.clone().oneshot(request).await.unwrap().status()
+    }
+
+    fn router(directory: &tempfile::TempDir, token: Option<&str>) -> Router {
+        api::router(
+            LibraryDirectory::new(directory.path()),
+            PublishToken::new(token.map(ToOwned::to_owned)),
+            GracefulShutdown::new().workers(),
+        )
+    }
+
+    #[tokio::test]
+    async fn publishing_is_disabled_without_token() {
+        let directory = tempfile::tempdir().unwrap();
+        let router = router(&directory, None);
+        assert_eq!(publish(&router, None, LIBRARY).await, StatusCode::FORBIDDEN);
+        assert_eq!(
+            publish(&router, Some(""), LIBRARY).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[tokio::test]
+    async fn publishing_requires_the_token() {
+        let directory = tempfile::tempdir().unwrap();
+        let router = router(&directory, Some(TOKEN));
+        assert_eq!(
+            publish(&router, None, LIBRARY).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            publish(&router, Some("wrong"), LIBRARY).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            publish(&router, Some("secret2"), LIBRARY).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            publish(&router, Some(TOKEN), LIBRARY).await,
+            StatusCode::CREATED
+        );
+    }
+
+    #[tokio::test]
+    async fn published_versions_cant_be_replaced() {
+        let directory = tempfile::tempdir().unwrap();
+        let router = router(&directory, Some(TOKEN));
+        assert_eq!(
+            publish(&router, Some(TOKEN), LIBRARY).await,
+            StatusCode::CREATED
+        );
+        assert_eq!(
+            publish(
+                &router,
+                Some(TOKEN),
+                "fn double(x: f32) -> f32 { return x; }"
+            )
+            .await,
+            StatusCode::CONFLICT
+        );
+
+        let name = LibraryName::new("alice", "math").unwrap();
+        let library = LibraryDirectory::new(directory.path())
+            .get(&name, "1.0.0".parse().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(library.source, LIBRARY);
+    }
+
+    #[tokio::test]
+    async fn publishing_stops_on_shutdown() {
+        let directory = tempfile::tempdir().unwrap();
+        let shutdown = GracefulShutdown::new();
+        let router = api::router(
+            LibraryDirectory::new(directory.path()),
+            PublishToken::new(Some(TOKEN.to_owned())),
+            shutdown.workers(),
+        );
+        shutdown.shutdown();
+        assert_eq!(
+            publish(&router, Some(TOKEN), LIBRARY).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_libraries_are_rejected() {
+        let directory = tempfile::tempdir().unwrap();
+        let router = router(&directory, Some(TOKEN));
+        assert_eq!(
+            publish(&router, Some(TOKEN), "fn double(").await,
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+    }
+}
@@ -0,0 +1,63 @@
+mod library;
+mod validate;
+
+use axum::{
+    extract::FromRef,
+    routing::{
+        get,
+        post,
+    },
+    Router,
+};
+use shade_rs_build::library::LibraryDirectory;
+
+pub use crate::api::library::PublishToken;
+use crate::util::shutdown::Workers;
+
+/// The API routes. Published libraries are stored in `libraries`, and clients
+/// can only publish them with the `publish_token`. Writes run on `workers`, so
+/// that they finish on shutdown.
+pub fn router(
+    libraries: LibraryDirectory,
+    publish_token: PublishToken,
+    workers: Workers,
+) -> Router {
+    Router::new()
+        .route("/validate", post(validate::validate))
+        .route("/validate-project", post(validate::validate_project))
+        .route("/libraries/:user/:name", get(library::versions))
+        .route(
+            "/libraries/:user/:name/:version",
+            get(library::resolve).put(library::publish),
+        )
+        .with_state(ApiState {
+            libraries,
+            publish_token,
+            workers,
+        })
+}
+
+#[derive(Clone, Debug)]
+struct ApiState {
+    libraries: LibraryDirectory,
+    publish_token: PublishToken,
+    workers: Workers,
+}
+
+impl FromRef<ApiState> for LibraryDirectory {
+    fn from_ref(state: &ApiState) -> Self {
+        state.libraries.clone()
+    }
+}
+
+impl FromRef<ApiState> for PublishToken {
+    fn from_ref(state: &ApiState) -> Self {
+        state.publish_token.clone()
+    }
+}
+
+impl FromRef<ApiState> for Workers {
+    fn from_ref(state: &ApiState) -> Self {
+        state.workers.clone()
+    }
+}
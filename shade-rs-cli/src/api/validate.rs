@@ -0,0 +1,60 @@
+use axum::Json;
+use serde::Serialize;
+use shade_rs_compile::{
+    compile_shader,
+    diagnostic::Diagnostic,
+    project::ProjectManifest,
+};
+
+/// `POST /api/validate`
+///
+/// Parses and validates the WGSL code in the request body and returns the
+/// diagnostics.
+pub async fn validate(code: String) -> Json<Validation> {
+    let validation = match compile_shader(&code, &Default::default()) {
+        Ok(_) => {
+            Validation {
+                valid: true,
+                diagnostics: vec![],
+            }
+        }
+        Err(error) => {
+            Validation {
+                valid: false,
+                diagnostics: vec![Diagnostic::from(&error)],
+            }
+        }
+    };
+    Json(validation)
+}
+
+/// `POST /api/validate-project`
+///
+/// Parses and validates the project manifest (`shader.toml`) in the request
+/// body and returns the diagnostics. The sources of the passes aren't checked.
+pub async fn validate_project(toml: String) -> Json<Validation> {
+    let validation = match ProjectManifest::from_toml(&toml) {
+        Ok(_) => {
+            Validation {
+                valid: true,
+                diagnostics: vec![],
+            }
+        }
+        Err(errors) => {
+            Validation {
+                valid: false,
+                diagnostics: errors
+                    .iter()
+                    .map(|error| Diagnostic::from_manifest_error(error, &toml))
+                    .collect(),
+            }
+        }
+    };
+    Json(validation)
+}
+
+#[derive(Debug, Serialize)]
+pub struct Validation {
+    pub valid: bool,
+    pub diagnostics: Vec<Diagnostic>,
+}
@@ -1,10 +1,19 @@
 use std::{
-    path::PathBuf,
+    path::{
+        Path,
+        PathBuf,
+    },
     time::Duration,
 };
 
 use shade_rs_build::{
-    ui::compile_ui,
+    ui::{
+        pipeline::{
+            BuildContext,
+            Pipeline,
+        },
+        run_pipeline,
+    },
     util::watch::WatchFiles,
 };
 
@@ -37,7 +46,8 @@ pub struct BuildOptions {
     #[arg(long = "dist", env = "DIST", default_value = "./dist/")]
     pub dist_path: PathBuf,
 
-    /// Path to the UI crate.
+    /// Path to the UI crate. This can be any crate that builds with
+    /// wasm-bindgen, configured with `[package.metadata.shade-rs-build]`.
     #[arg(long, env = "UI", default_value = "./shade-rs-ui/")]
     pub ui_path: PathBuf,
 
@@ -60,6 +70,17 @@ pub struct BuildOptions {
     /// Start with a clean build.
     #[arg(long)]
     pub clean: bool,
+
+    /// Skip a step of the UI build, e.g. `wasm-opt`. Can be given several
+    /// times.
+    #[arg(long = "skip", value_name = "STEP")]
+    pub skip_steps: Vec<String>,
+
+    /// Set a variable for the UI's templates, e.g. `--var title=Shaders`.
+    /// Overrides the variables from the UI crate's config. Can be given
+    /// several times.
+    #[arg(long = "var", value_name = "KEY=VALUE", value_parser = parse_var)]
+    pub vars: Vec<(String, String)>,
 }
 
 impl BuildOptions {
@@ -68,7 +89,15 @@ impl BuildOptions {
 
         let dist_ui = self.dist_path.join("ui");
         let clean = self.clean || self.release;
-        compile_ui(&self.ui_path, &dist_ui, clean, self.release).await?;
+        build_ui(
+            &self.ui_path,
+            &dist_ui,
+            clean,
+            self.release,
+            &self.skip_steps,
+            &self.vars,
+        )
+        .await?;
 
         if self.watch {
             tracing::info!("Watching for file changes...");
@@ -79,13 +108,15 @@ impl BuildOptions {
 
             let token = shutdown.token();
             let release = self.release;
+            let skip_steps = self.skip_steps.clone();
+            let vars = self.vars.clone();
             shutdown.spawn(async move {
                 loop {
                     tokio::select! {
                         _ = token.cancelled() => break,
                         changes_option = watch_files.next(debounce) => {
                             let Some(_changes) = changes_option else { break; };
-                            if let Err(error) = compile_ui(&ui_path, &dist_ui, false, release).await {
+                            if let Err(error) = build_ui(&ui_path, &dist_ui, false, release, &skip_steps, &vars).await {
                                 tracing::error!(%error);
                             }
                         }
@@ -99,3 +130,32 @@ impl BuildOptions {
         Ok(())
     }
 }
+
+/// Builds the UI with the pipeline its crate describes, without the skipped
+/// steps and with the extra template variables.
+async fn build_ui(
+    ui_path: &Path,
+    dist_ui: &Path,
+    clean: bool,
+    release: bool,
+    skip_steps: &[String],
+    vars: &[(String, String)],
+) -> Result<(), shade_rs_build::ui::Error> {
+    let mut context = BuildContext::new(ui_path, dist_ui, release).await?;
+    context.vars.extend(vars.iter().cloned());
+    let mut pipeline = Pipeline::from_config(&context);
+    for step in skip_steps {
+        if !pipeline.remove(step) {
+            let steps = pipeline.step_names().collect::<Vec<_>>().join(", ");
+            tracing::warn!(%step, %steps, "no such build step");
+        }
+    }
+    run_pipeline(&context, &pipeline, clean).await
+}
+
+fn parse_var(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, but got `{s}`"))?;
+    Ok((key.to_owned(), value.to_owned()))
+}
@@ -0,0 +1,100 @@
+use std::path::PathBuf;
+
+use color_eyre::eyre::bail;
+use shade_rs_build::project::Project;
+use shade_rs_compile::{
+    compile_shader,
+    graph::PassGraph,
+    lint::lint_webgl_compat,
+    CompileOptions,
+};
+
+use crate::Error;
+
+/// Check shaders for errors, without running them.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// Paths to the WGSL shaders, or to project directories or manifests
+    /// (`shader.toml`), in which case all passes are checked.
+    #[arg(required = true)]
+    shaders: Vec<PathBuf>,
+
+    /// Warn about constructs that don't work on the WebGL backend.
+    #[arg(long)]
+    webgl: bool,
+
+    /// Don't inject the input declarations into shaders that don't declare
+    /// them.
+    #[arg(long)]
+    no_inject_inputs: bool,
+}
+
+impl Args {
+    pub async fn run(self) -> Result<(), Error> {
+        let mut num_checked = 0;
+        let mut num_failed = 0;
+        for path in &self.shaders {
+            let project = Project::is_project(path)
+                .then(|| Project::open(path))
+                .transpose()?;
+            let options = CompileOptions {
+                inject_inputs: !self.no_inject_inputs,
+                ..project
+                    .as_ref()
+                    .map(Project::compile_options)
+                    .unwrap_or_default()
+            };
+            let shaders = if let Some(project) = &project {
+                project
+                    .passes()
+                    .map(|(pass, code)| {
+                        (
+                            format!("{} ({})", path.display(), pass.name),
+                            code.to_owned(),
+                        )
+                    })
+                    .collect()
+            }
+            else {
+                vec![(path.display().to_string(), std::fs::read_to_string(path)?)]
+            };
+
+            let mut modules = vec![];
+            for (name, code) in shaders {
+                num_checked += 1;
+                match compile_shader(&code, &options) {
+                    Ok(compiled) => {
+                        if self.webgl {
+                            for lint in lint_webgl_compat(&compiled.module, &compiled.source) {
+                                eprintln!("{name}: {lint}");
+                            }
+                        }
+                        modules.push(compiled.module);
+                    }
+                    Err(error) => {
+                        eprintln!("{name}:\n{error}");
+                        num_failed += 1;
+                    }
+                }
+            }
+
+            // the passes can only be ordered if all of them compiled.
+            if let Some(project) = &project {
+                if modules.len() == project.sources.len() {
+                    let graph =
+                        PassGraph::new(&project.manifest, &modules.iter().collect::<Vec<_>>());
+                    if let Err(error) = graph.execution_order() {
+                        eprintln!("{}: {error}", path.display());
+                        num_failed += 1;
+                    }
+                }
+            }
+        }
+
+        if num_failed > 0 {
+            bail!("{num_failed} of {num_checked} shaders failed to compile");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    error::Error as _,
+    fmt::Write,
+    net::SocketAddr,
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::Duration,
+};
+
+use axum::{
+    extract::{
+        ws::{
+            Message,
+            WebSocket,
+        },
+        State,
+        WebSocketUpgrade,
+    },
+    response::Response,
+    routing::get,
+    Router,
+};
+use color_eyre::eyre::bail;
+use shade_rs_build::{
+    library::LibraryDirectory,
+    project::Project,
+    util::watch::WatchFiles,
+};
+use shade_rs_compile::{
+    dev::{
+        DevMessage,
+        DevPass,
+    },
+    library::LIBRARIES_DIRECTORY,
+};
+use tokio::{
+    net::TcpListener,
+    sync::watch,
+};
+
+use crate::{
+    api::{
+        self,
+        PublishToken,
+    },
+    util::{
+        shutdown::GracefulShutdown,
+        ui::UiFiles,
+    },
+    Error,
+};
+
+/// Preview a project in the browser while editing it.
+///
+/// The preview reloads the project whenever a file in the project directory
+/// changes, so that it can be edited with any editor. This needs a build of
+/// the UI (see `shade-rs-cli build`), but doesn't rebuild it. Without one, the
+/// UI embedded with the `embed-ui` feature is used.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// Path to the project directory or its `shader.toml`.
+    project: PathBuf,
+
+    /// The address on which to listen for HTTP connections.
+    #[arg(long, env = "ADDRESS", default_value = "127.0.0.1:3333")]
+    address: SocketAddr,
+
+    /// Path to the dist directory containing the UI build.
+    #[arg(long = "dist", env = "DIST", default_value = "./dist/")]
+    dist_path: PathBuf,
+
+    /// After a file change, wait N seconds for more changes before reloading
+    /// the project, e.g. while an editor saves several files.
+    #[arg(long, default_value = "0.1")]
+    debounce: f32,
+}
+
+impl Args {
+    pub async fn run(self) -> Result<(), Error> {
+        let mut shutdown = GracefulShutdown::new();
+
+        let dist_ui = UiFiles::Directory(self.dist_path.join("ui"));
+        let ui_files = if dist_ui.contains(DEV_PAGE) {
+            dist_ui
+        }
+        else if let Some(embedded) = UiFiles::embedded().filter(|ui| ui.contains(DEV_PAGE)) {
+            embedded
+        }
+        else {
+            bail!(
+                "{dist_ui} doesn't contain {DEV_PAGE}. build the UI with `shade-rs-cli build` first"
+            );
+        };
+        tracing::info!("Serving UI from {ui_files}");
+
+        // the project might not load yet, but we still need its directory to
+        // watch it.
+        let project_path = if self.project.is_dir() {
+            self.project.clone()
+        }
+        else {
+            self.project.parent().unwrap_or(Path::new(".")).to_owned()
+        };
+
+        let (tx_message, rx_message) = watch::channel(load_project(&self.project));
+
+        let mut watch_files = WatchFiles::new()?;
+        watch_files.watch(&project_path)?;
+        let debounce = Duration::from_secs_f32(self.debounce);
+        shutdown.spawn({
+            let token = shutdown.token();
+            let path = self.project.clone();
+            async move {
+                loop {
+                    tokio::select! {
+                        _ = token.cancelled() => break,
+                        changes_option = watch_files.next(Some(debounce)) => {
+                            let Some(_changes) = changes_option else { break; };
+                            tracing::info!("reloading project");
+                            tx_message.send_replace(load_project(&path));
+                        }
+                    }
+                }
+
+                Ok(())
+            }
+        });
+
+        // the player fetches included libraries that aren't in the message
+        // from the project's vendored libraries. they're vendored with the CLI,
+        // so publishing is disabled.
+        let libraries = LibraryDirectory::new(project_path.join(LIBRARIES_DIRECTORY));
+        let router = Router::new().nest(
+            "/api",
+            api::router(libraries, PublishToken::default(), shutdown.workers())
+                .route("/dev", get(dev_socket).with_state(rx_message)),
+        );
+        let router = ui_files.serve(router, DEV_PAGE);
+
+        shutdown.spawn({
+            let token = shutdown.token();
+            async move {
+                tracing::info!("Previewing at http://{}/{DEV_PAGE}", self.address);
+                let listener = TcpListener::bind(&self.address).await?;
+                axum::serve(listener, router)
+                    .with_graceful_shutdown(async move { token.cancelled().await })
+                    .await?;
+                Ok::<(), Error>(())
+            }
+        });
+
+        shutdown.join().await
+    }
+}
+
+/// The page of the UI build that runs the preview.
+const DEV_PAGE: &str = "dev.html";
+
+/// Opens the project and serializes it as a [`DevMessage`].
+fn load_project(path: &Path) -> String {
+    let message = match Project::open(path) {
+        Ok(project) => {
+            DevMessage::Project {
+                title: project.title(),
+                options: project.compile_options(),
+                passes: project
+                    .manifest
+                    .passes
+                    .into_iter()
+                    .zip(project.sources)
+                    .map(|(pass, code)| DevPass { pass, code })
+                    .collect(),
+            }
+        }
+        Err(error) => {
+            tracing::error!(%error, "could not load project");
+            let mut message = error.to_string();
+            let mut source = error.source();
+            while let Some(error) = source {
+                write!(message, ": {error}").unwrap();
+                source = error.source();
+            }
+            DevMessage::Error { message }
+        }
+    };
+    serde_json::to_string(&message).expect("failed to serialize dev message")
+}
+
+/// `GET /api/dev`
+///
+/// Sends the project to the client, and again whenever it changes.
+async fn dev_socket(
+    State(rx_message): State<watch::Receiver<String>>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    upgrade.on_upgrade(move |socket| send_messages(socket, rx_message))
+}
+
+async fn send_messages(mut socket: WebSocket, mut rx_message: watch::Receiver<String>) {
+    loop {
+        let message = rx_message.borrow_and_update().clone();
+        if socket.send(Message::Text(message)).await.is_err() {
+            // the client disconnected.
+            break;
+        }
+        if rx_message.changed().await.is_err() {
+            // the server is shutting down.
+            break;
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    collections::BTreeMap,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use color_eyre::eyre::bail;
+use shade_rs_build::{
+    export::{
+        export_starter_crate,
+        export_wallpaper,
+    },
+    project::Project,
+};
+use shade_rs_compile::library::{
+    parse_includes,
+    Library,
+};
+use url::Url;
+
+use crate::{
+    vendor::fetch_library,
+    Error,
+};
+
+/// Export a shader to other formats.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    #[command(subcommand)]
+    target: Target,
+}
+
+impl Args {
+    pub async fn run(self) -> Result<(), Error> {
+        match self.target {
+            Target::StarterCrate(args) => args.run().await,
+            Target::Wallpaper(args) => args.run().await,
+        }
+    }
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum Target {
+    StarterCrate(StarterCrateArgs),
+    Wallpaper(WallpaperArgs),
+}
+
+/// Generate a standalone Rust crate (winit + wgpu) that renders the shader.
+#[derive(Debug, clap::Args)]
+pub struct StarterCrateArgs {
+    /// Path to the WGSL shader, or to a project. Only the bottom pass of a
+    /// project is exported.
+    shader: PathBuf,
+
+    /// Directory to which the crate will be written.
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Name of the generated crate. Defaults to the file name of the shader.
+    #[arg(long)]
+    name: Option<String>,
+
+    #[command(flatten)]
+    server: ServerArgs,
+}
+
+impl StarterCrateArgs {
+    pub async fn run(self) -> Result<(), Error> {
+        let shader = load_shader(&self.shader, &self.server.server).await?;
+        let name = self.name.unwrap_or(shader.name);
+
+        export_starter_crate(&shader.code, &shader.libraries, &name, &self.output)?;
+
+        tracing::info!(
+            "Exported starter crate to {}. Run it with `cargo run` in that directory.",
+            self.output.display()
+        );
+
+        Ok(())
+    }
+}
+
+/// Package the shader as a web wallpaper for Wallpaper Engine or Lively.
+///
+/// This needs a build of the UI (see `shade-rs-cli build`).
+#[derive(Debug, clap::Args)]
+pub struct WallpaperArgs {
+    /// Path to the WGSL shader, or to a project. Only the bottom pass of a
+    /// project is exported.
+    shader: PathBuf,
+
+    /// Directory to which the wallpaper will be written.
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Title of the wallpaper. Defaults to the file name of the shader.
+    #[arg(long)]
+    title: Option<String>,
+
+    /// Preview image to include in the bundle.
+    #[arg(long)]
+    preview: Option<PathBuf>,
+
+    /// Path to the dist directory containing the UI build.
+    #[arg(long = "dist", env = "DIST", default_value = "./dist/")]
+    dist_path: PathBuf,
+
+    #[command(flatten)]
+    server: ServerArgs,
+}
+
+impl WallpaperArgs {
+    pub async fn run(self) -> Result<(), Error> {
+        let shader = load_shader(&self.shader, &self.server.server).await?;
+        let title = self.title.unwrap_or(shader.name);
+
+        export_wallpaper(
+            &shader.code,
+            &shader.libraries,
+            &title,
+            self.dist_path.join("ui"),
+            self.preview.as_deref(),
+            &self.output,
+        )?;
+
+        tracing::info!("Exported wallpaper to {}", self.output.display());
+
+        Ok(())
+    }
+}
+
+/// Exports include the libraries that the shader includes, so that they work
+/// offline.
+#[derive(Debug, clap::Args)]
+struct ServerArgs {
+    /// URL of the server from which included libraries are fetched, if the
+    /// project doesn't vendor them.
+    #[arg(long, env = "SERVER", default_value = "http://127.0.0.1:3333/")]
+    server: Url,
+}
+
+/// A shader that is exported.
+struct Shader {
+    code: String,
+    name: String,
+
+    /// The libraries that the shader includes, by
+    /// [`Include::key`](shade_rs_compile::library::Include::key).
+    libraries: BTreeMap<String, Library>,
+}
+
+/// Reads a shader or the bottom pass of a project, together with its name and
+/// the libraries it includes.
+///
+/// Projects use their vendored libraries. Other libraries are fetched from the
+/// server, in the version that the project's lock pins, if any.
+async fn load_shader(path: &Path, server: &Url) -> Result<Shader, Error> {
+    let (code, name, mut libraries, lock) = if Project::is_project(path) {
+        let project = Project::open(path)?;
+        let Some(code) = project.sources.first()
+        else {
+            bail!("project {} has no passes", path.display());
+        };
+        (
+            code.clone(),
+            project.title(),
+            project.libraries,
+            project.manifest.lock,
+        )
+    }
+    else {
+        (
+            std::fs::read_to_string(path)?,
+            shader_name(path),
+            BTreeMap::new(),
+            BTreeMap::new(),
+        )
+    };
+
+    let client = reqwest::Client::new();
+    for include in parse_includes(&code)? {
+        let key = include.key();
+        if libraries.contains_key(&key) {
+            continue;
+        }
+
+        let library = if let Some(locked) = lock.get(&key) {
+            let library = fetch_library(&client, server, &include.library, locked.version).await?;
+            if !locked.matches(&library) {
+                bail!(
+                    "`{key}` is locked to {}@{}, but the server has different code for it",
+                    library.name,
+                    locked.version
+                );
+            }
+            library
+        }
+        else {
+            fetch_library(&client, server, &include.library, include.requirement).await?
+        };
+        tracing::info!("fetched {key} as {}@{}", library.name, library.version);
+        libraries.insert(key, library);
+    }
+
+    Ok(Shader {
+        code,
+        name,
+        libraries,
+    })
+}
+
+fn shader_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "shader".to_owned())
+}
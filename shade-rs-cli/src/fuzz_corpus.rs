@@ -0,0 +1,74 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{
+        Hash,
+        Hasher,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use crate::Error;
+
+/// Seed the corpus of the compiler's fuzz target with WGSL shaders.
+///
+/// Directories are searched recursively for `.wgsl` files. Files are named by
+/// the hash of their content, so running this again doesn't add duplicates.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// Shaders, or directories containing shaders.
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+
+    /// Corpus directory of the fuzz target.
+    #[arg(short, long, default_value = "./shade-rs-compile/fuzz/corpus/compile_shader/")]
+    output: PathBuf,
+}
+
+impl Args {
+    pub async fn run(self) -> Result<(), Error> {
+        let mut shaders = vec![];
+        for path in &self.paths {
+            collect_shaders(path, &mut shaders)?;
+        }
+
+        std::fs::create_dir_all(&self.output)?;
+
+        let mut num_added = 0;
+        for path in &shaders {
+            let code = std::fs::read(path)?;
+            let mut hasher = DefaultHasher::new();
+            code.hash(&mut hasher);
+            let corpus_path = self.output.join(format!("{:016x}", hasher.finish()));
+            if !corpus_path.exists() {
+                std::fs::write(&corpus_path, &code)?;
+                num_added += 1;
+            }
+        }
+
+        tracing::info!(
+            "Added {num_added} of {} shaders to {}",
+            shaders.len(),
+            self.output.display()
+        );
+
+        Ok(())
+    }
+}
+
+fn collect_shaders(path: &Path, shaders: &mut Vec<PathBuf>) -> Result<(), Error> {
+    if path.is_dir() {
+        for entry in std::fs::read_dir(path)? {
+            let entry_path = entry?.path();
+            if entry_path.is_dir() || entry_path.extension().is_some_and(|ext| ext == "wgsl") {
+                collect_shaders(&entry_path, shaders)?;
+            }
+        }
+    }
+    else {
+        shaders.push(path.to_owned());
+    }
+    Ok(())
+}
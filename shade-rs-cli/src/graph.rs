@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+
+use color_eyre::eyre::bail;
+use shade_rs_build::project::Project;
+use shade_rs_compile::{
+    compile_shader,
+    graph::PassGraph,
+};
+
+use crate::Error;
+
+/// Show the passes of a project in the order they're rendered, and what each
+/// one reads.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// Path to the project directory or its `shader.toml`.
+    project: PathBuf,
+
+    /// Print the graph in Graphviz's DOT language, e.g. to pipe it into
+    /// `dot -Tsvg`.
+    #[arg(long)]
+    dot: bool,
+}
+
+impl Args {
+    pub async fn run(self) -> Result<(), Error> {
+        let project = Project::open(&self.project)?;
+
+        let options = project.compile_options();
+        let mut modules = Vec::with_capacity(project.sources.len());
+        for (pass, code) in project.passes() {
+            match compile_shader(code, &options) {
+                Ok(compiled) => modules.push(compiled.module),
+                Err(error) => bail!("pass `{}` failed to compile:\n{error}", pass.name),
+            }
+        }
+
+        let graph = PassGraph::new(&project.manifest, &modules.iter().collect::<Vec<_>>());
+
+        if self.dot {
+            print!("{}", graph.to_dot());
+        }
+        else {
+            print!("{}", graph.render());
+        }
+
+        graph.execution_order()?;
+
+        Ok(())
+    }
+}
@@ -1,8 +1,15 @@
 #![allow(dead_code)]
 
+mod api;
 mod build;
+mod check;
+mod dev;
+mod export;
+mod fuzz_corpus;
+mod graph;
 mod serve;
 mod util;
+mod vendor;
 
 use clap::{
     builder::styling,
@@ -25,14 +32,26 @@ const STYLES: styling::Styles = styling::Styles::styled()
 #[command(version = clap::crate_version!(), styles = STYLES)]
 pub enum Args {
     Build(crate::build::Args),
+    Check(crate::check::Args),
+    Dev(crate::dev::Args),
     Serve(crate::serve::Args),
+    Export(crate::export::Args),
+    FuzzCorpus(crate::fuzz_corpus::Args),
+    Graph(crate::graph::Args),
+    Vendor(crate::vendor::Args),
 }
 
 impl Args {
     pub async fn run(self) -> Result<(), Error> {
         match self {
             Self::Build(args) => args.run().await?,
+            Self::Check(args) => args.run().await?,
+            Self::Dev(args) => args.run().await?,
             Self::Serve(args) => args.run().await?,
+            Self::Export(args) => args.run().await?,
+            Self::FuzzCorpus(args) => args.run().await?,
+            Self::Graph(args) => args.run().await?,
+            Self::Vendor(args) => args.run().await?,
         }
 
         Ok(())
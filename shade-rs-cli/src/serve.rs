@@ -1,4 +1,8 @@
-use std::net::SocketAddr;
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    time::Duration,
+};
 
 use axum::{
     extract::{
@@ -7,27 +11,35 @@ use axum::{
     },
     Router,
 };
+use shade_rs_build::library::LibraryDirectory;
 use tokio::net::TcpListener;
 use tower::ServiceBuilder;
-use tower_http::{
-    services::{
-        ServeDir,
-        ServeFile,
-    },
-    trace::{
-        DefaultOnRequest,
-        DefaultOnResponse,
-        TraceLayer,
-    },
+use tower_http::trace::{
+    DefaultOnRequest,
+    DefaultOnResponse,
+    TraceLayer,
 };
 
 use crate::{
+    api::{
+        self,
+        PublishToken,
+    },
     build::BuildOptions,
-    util::shutdown::GracefulShutdown,
+    util::{
+        headers::HeaderOptions,
+        limits::LimitOptions,
+        parse_seconds,
+        shutdown::GracefulShutdown,
+        ui::UiFiles,
+    },
     Error,
 };
 
 /// Serve API, and optionally assets and UI.
+///
+/// If the UI crate doesn't exist and the binary was built with the `embed-ui`
+/// feature, the embedded UI is served instead of building it.
 #[derive(Debug, clap::Args)]
 pub struct Args {
     #[command(flatten)]
@@ -36,20 +48,50 @@ pub struct Args {
     /// The address on which to listen for HTTP connections.
     #[arg(long, env = "ADDRESS", default_value = "127.0.0.1:3333")]
     address: SocketAddr,
+
+    /// Path to the directory in which published libraries are stored.
+    #[arg(long = "libraries", env = "LIBRARIES", default_value = "./data/libraries/")]
+    libraries_path: PathBuf,
+
+    /// Token that clients must send as `Authorization: Bearer <token>` to
+    /// publish libraries. Publishing is disabled without it.
+    #[arg(long, env = "PUBLISH_TOKEN", hide_env_values = true)]
+    publish_token: Option<String>,
+
+    #[command(flatten)]
+    headers: HeaderOptions,
+
+    #[command(flatten)]
+    limits: LimitOptions,
+
+    /// On shutdown, wait up to N seconds for running exports and other
+    /// long-running tasks to finish before aborting them.
+    #[arg(long, env = "DRAIN_TIMEOUT", default_value = "30", value_parser = parse_seconds)]
+    drain_timeout: Duration,
 }
 
 impl Args {
     pub async fn run(self) -> Result<(), Error> {
-        let mut shutdown = GracefulShutdown::new();
+        let mut shutdown = GracefulShutdown::new().with_drain_timeout(self.drain_timeout);
 
-        self.build_options.spawn(&mut shutdown).await?;
-
-        let mut router = Router::new();
+        let ui_files = match UiFiles::embedded() {
+            Some(embedded) if !self.build_options.ui_path.exists() => embedded,
+            _ => {
+                self.build_options.spawn(&mut shutdown).await?;
+                UiFiles::Directory(self.build_options.dist_path.join("ui"))
+            }
+        };
+        tracing::info!("Serving UI from {ui_files}");
 
-        let dist_ui = self.build_options.dist_path.join("ui");
-        router = router.fallback_service(ServeDir::new(&dist_ui).fallback(
-            ServeFile::new_with_mime(dist_ui.join("index.html"), &mime::TEXT_HTML_UTF_8),
-        ));
+        let libraries = LibraryDirectory::new(&self.libraries_path);
+        let publish_token = PublishToken::new(self.publish_token.clone());
+        let mut router = Router::new().nest(
+            "/api",
+            api::router(libraries, publish_token, shutdown.workers()),
+        );
+        router = ui_files.serve(router, "index.html");
+        router = self.headers.apply(router)?;
+        router = self.limits.apply(router);
 
         router = router.layer(
             ServiceBuilder::new().layer(
@@ -0,0 +1,175 @@
+//! Response headers that restrict how browsers may use the served pages.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{
+        Request,
+        State,
+    },
+    http::{
+        header::{
+            CONTENT_SECURITY_POLICY,
+            REFERRER_POLICY,
+            X_FRAME_OPTIONS,
+        },
+        HeaderMap,
+        HeaderName,
+        HeaderValue,
+    },
+    middleware::{
+        self,
+        Next,
+    },
+    response::Response,
+    Router,
+};
+use color_eyre::eyre::Error;
+
+/// The page with the player that other sites embed.
+const EMBED_PAGE: &str = "/embed.html";
+
+/// The UI runs WebAssembly, loads the audio worklet from a blob URL, and
+/// loads CodeMirror and the icons from CDNs. The pages' scripts are inline.
+const DEFAULT_CONTENT_SECURITY_POLICY: &str = "default-src 'self'; script-src 'self' 'unsafe-inline' 'wasm-unsafe-eval' blob: https://unpkg.com; style-src 'self' 'unsafe-inline' https://cdn.jsdelivr.net https://unpkg.com; font-src 'self' https://cdn.jsdelivr.net; img-src 'self' data: blob:; media-src 'self' blob:; connect-src 'self'; object-src 'none'; base-uri 'self'";
+
+#[derive(Debug, clap::Args)]
+pub struct HeaderOptions {
+    /// Sources that may embed the player from `embed.html` in an iframe, e.g.
+    /// `https://example.com`. Separate several with spaces, or pass `'none'`
+    /// to forbid embedding. By default any site may embed the player.
+    #[arg(long, env = "FRAME_ANCESTORS", value_delimiter = ' ')]
+    frame_ancestors: Vec<String>,
+
+    /// Don't send the `Content-Security-Policy`, `Referrer-Policy` and
+    /// `Permissions-Policy` headers, e.g. because a reverse proxy sets them.
+    #[arg(long)]
+    no_security_headers: bool,
+
+    /// The `Content-Security-Policy` of the served pages.
+    #[arg(
+        long,
+        env = "CONTENT_SECURITY_POLICY",
+        default_value = DEFAULT_CONTENT_SECURITY_POLICY
+    )]
+    content_security_policy: String,
+
+    /// The `Referrer-Policy` of the served pages.
+    #[arg(long, env = "REFERRER_POLICY", default_value = "strict-origin-when-cross-origin")]
+    referrer_policy: String,
+
+    /// Don't allow the pages to use the webcam, which can be bound to
+    /// channels.
+    #[arg(long)]
+    no_camera: bool,
+
+    /// Don't allow the pages to use the microphone, whose spectrum and
+    /// waveform can be bound to channels.
+    #[arg(long)]
+    no_microphone: bool,
+}
+
+impl HeaderOptions {
+    pub fn apply(&self, router: Router) -> Result<Router, Error> {
+        let headers = Headers::new(self)?;
+        Ok(router.layer(middleware::from_fn_with_state(
+            Arc::new(headers),
+            set_headers,
+        )))
+    }
+
+    fn permissions_policy(&self) -> String {
+        let allow = |forbidden: bool| if forbidden { "()" } else { "(self)" };
+        format!(
+            "camera={}, microphone={}",
+            allow(self.no_camera),
+            allow(self.no_microphone)
+        )
+    }
+}
+
+/// The headers that are added to responses.
+#[derive(Debug, Default)]
+struct Headers {
+    all: HeaderMap,
+    embed: HeaderMap,
+}
+
+impl Headers {
+    fn new(options: &HeaderOptions) -> Result<Self, Error> {
+        let mut headers = Self::default();
+        let mut content_security_policy = None;
+
+        if !options.no_security_headers {
+            content_security_policy = Some(options.content_security_policy.trim().to_owned())
+                .filter(|policy| !policy.is_empty());
+            if let Some(policy) = &content_security_policy {
+                headers
+                    .all
+                    .insert(CONTENT_SECURITY_POLICY, HeaderValue::from_str(policy)?);
+            }
+            headers.all.insert(
+                REFERRER_POLICY,
+                HeaderValue::from_str(&options.referrer_policy)?,
+            );
+            headers.all.insert(
+                HeaderName::from_static("permissions-policy"),
+                HeaderValue::from_str(&options.permissions_policy())?,
+            );
+        }
+
+        let sources = options
+            .frame_ancestors
+            .iter()
+            .map(|source| source.trim())
+            .filter(|source| !source.is_empty())
+            .collect::<Vec<_>>();
+        if !sources.is_empty() {
+            // the embed page gets the same policy, restricted to the frame
+            // ancestors.
+            let frame_ancestors = format!("frame-ancestors {}", sources.join(" "));
+            let policy = match content_security_policy {
+                Some(policy) => format!("{}; {frame_ancestors}", policy.trim_end_matches(';')),
+                None => frame_ancestors,
+            };
+            headers
+                .embed
+                .insert(CONTENT_SECURITY_POLICY, HeaderValue::from_str(&policy)?);
+
+            // `X-Frame-Options` is only understood by browsers that don't know
+            // `frame-ancestors`, and it can't list other sites. So it's only sent
+            // if it means the same, otherwise it would block allowed sites.
+            match sources.as_slice() {
+                ["'none'"] => {
+                    headers
+                        .embed
+                        .insert(X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+                }
+                ["'self'"] => {
+                    headers
+                        .embed
+                        .insert(X_FRAME_OPTIONS, HeaderValue::from_static("SAMEORIGIN"));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(headers)
+    }
+}
+
+async fn set_headers(
+    State(headers): State<Arc<Headers>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let is_embed = request.uri().path() == EMBED_PAGE;
+    let mut response = next.run(request).await;
+
+    response.headers_mut().extend(headers.all.clone());
+    if is_embed {
+        response.headers_mut().extend(headers.embed.clone());
+    }
+
+    response
+}
@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use axum::{
+    extract::DefaultBodyLimit,
+    Router,
+};
+use tower::{
+    limit::ConcurrencyLimitLayer,
+    ServiceBuilder,
+};
+use tower_http::{
+    limit::RequestBodyLimitLayer,
+    timeout::TimeoutLayer,
+};
+
+use crate::util::parse_seconds;
+
+/// Limits that keep a single client from tying up the server.
+#[derive(Debug, clap::Args)]
+pub struct LimitOptions {
+    /// Abort requests that take longer than N seconds, with `408 Request
+    /// Timeout`. 0 disables the timeout.
+    #[arg(long, env = "REQUEST_TIMEOUT", default_value = "30", value_parser = parse_seconds)]
+    request_timeout: Duration,
+
+    /// How many requests are handled at once. Further requests wait until one
+    /// of them is done.
+    #[arg(long, env = "MAX_CONCURRENT_REQUESTS", default_value = "256")]
+    max_concurrent_requests: usize,
+
+    /// Largest request body that is accepted, in bytes. Larger requests are
+    /// refused with `413 Payload Too Large`.
+    #[arg(long, env = "MAX_BODY_SIZE", default_value = "1048576")]
+    max_body_size: usize,
+}
+
+impl LimitOptions {
+    pub fn apply(&self, router: Router) -> Router {
+        // the body limit is checked by the extractors, e.g. for the shader code, and
+        // for bodies that are read as a stream.
+        let router = router.layer(
+            ServiceBuilder::new()
+                .layer(DefaultBodyLimit::max(self.max_body_size))
+                .layer(RequestBodyLimitLayer::new(self.max_body_size))
+                .layer(ConcurrencyLimitLayer::new(self.max_concurrent_requests)),
+        );
+
+        if !self.request_timeout.is_zero() {
+            router.layer(TimeoutLayer::new(self.request_timeout))
+        }
+        else {
+            router
+        }
+    }
+}
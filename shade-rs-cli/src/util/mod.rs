@@ -1 +1,31 @@
+pub mod headers;
+pub mod limits;
 pub mod shutdown;
+pub mod ui;
+
+use std::time::Duration;
+
+/// Parses a duration given in (fractional) seconds, e.g. for timeouts.
+pub fn parse_seconds(s: &str) -> Result<Duration, String> {
+    let seconds = s
+        .parse::<f32>()
+        .map_err(|_| format!("expected a number of seconds, but got `{s}`"))?;
+    Duration::try_from_secs_f32(seconds)
+        .map_err(|_| format!("expected a finite, positive number of seconds, but got `{s}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seconds_must_be_finite_and_positive() {
+        assert_eq!(parse_seconds("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_seconds("0.5"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_seconds("0"), Ok(Duration::ZERO));
+
+        for invalid in ["-1", "NaN", "inf", "1e40", "", "soon"] {
+            assert!(parse_seconds(invalid).is_err(), "{invalid}");
+        }
+    }
+}
@@ -1,17 +1,29 @@
-use std::future::Future;
+use std::{
+    future::Future,
+    time::Duration,
+};
 
 use tokio::task::{
     AbortHandle,
+    JoinHandle,
     JoinSet,
 };
-use tokio_util::sync::CancellationToken;
+use tokio_util::{
+    sync::CancellationToken,
+    task::TaskTracker,
+};
 
 use crate::Error;
 
+/// How long workers get to finish after a shutdown signal by default.
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[derive(Debug)]
 pub struct GracefulShutdown {
     token: CancellationToken,
     join_set: JoinSet<Result<(), Error>>,
+    workers: Workers,
+    drain_timeout: Duration,
 }
 
 impl GracefulShutdown {
@@ -36,11 +48,24 @@ impl GracefulShutdown {
         });
 
         Self {
-            token,
+            token: token.clone(),
             join_set: JoinSet::new(),
+            workers: Workers {
+                token,
+                abort: CancellationToken::new(),
+                tracker: TaskTracker::new(),
+            },
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
         }
     }
 
+    /// Sets how long [`Workers`] get to finish after the shutdown signal,
+    /// before they're aborted.
+    pub fn with_drain_timeout(mut self, drain_timeout: Duration) -> Self {
+        self.drain_timeout = drain_timeout;
+        self
+    }
+
     pub fn token(&self) -> CancellationToken {
         self.token.clone()
     }
@@ -56,6 +81,12 @@ impl GracefulShutdown {
         self.join_set.spawn(future)
     }
 
+    /// Handle to spawn long-running tasks from anywhere, e.g. from request
+    /// handlers.
+    pub fn workers(&self) -> Workers {
+        self.workers.clone()
+    }
+
     pub async fn join(mut self) -> Result<(), Error> {
         let mut errors = vec![];
 
@@ -82,9 +113,22 @@ impl GracefulShutdown {
             }
         }
 
-        // wait for all remaining tasks to finish. but if a second Ctrl-C/SIGTERM
-        // arrives, we abort.
-        loop {
+        // wait for all remaining tasks and workers to finish. but if a second
+        // Ctrl-C/SIGTERM arrives, or they take longer than the drain timeout, we
+        // abort.
+        self.workers.tracker.close();
+        if !self.workers.tracker.is_empty() {
+            tracing::info!(
+                "Waiting up to {:?} for {} workers to finish.",
+                self.drain_timeout,
+                self.workers.tracker.len()
+            );
+        }
+        let drain_timeout = tokio::time::sleep(self.drain_timeout);
+        tokio::pin!(drain_timeout);
+        let mut tasks_done = false;
+        let mut workers_done = false;
+        while !(tasks_done && workers_done) {
             tokio::select! {
                 _ = tokio::signal::ctrl_c() => {
                     tracing::info!("Received second Ctrl-C. Aborting.");
@@ -94,23 +138,31 @@ impl GracefulShutdown {
                     tracing::info!("Received second SIGTERM. Aborting.");
                     break;
                 }
-                opt = self.join_set.join_next() => {
+                _ = &mut drain_timeout => {
+                    tracing::warn!("Drain timeout elapsed. Aborting.");
+                    break;
+                }
+                _ = self.workers.tracker.wait(), if !workers_done => {
+                    workers_done = true;
+                }
+                opt = self.join_set.join_next(), if !tasks_done => {
                     match opt {
-                        None => break,
+                        None => tasks_done = true,
                         Some(Err(join_error)) => {
                             errors.push(Error::from(join_error));
                         }
                         Some(Ok(Err(task_error))) => {
                             errors.push(task_error);
                         }
-                        Some(Ok(Ok(()))) => break,
+                        Some(Ok(Ok(()))) => {}
                     }
                 }
             }
         }
 
-        // abort all remaining tasks
+        // abort all remaining tasks and workers
         self.join_set.abort_all();
+        self.workers.abort.cancel();
 
         // log/return errors
         match errors.len() {
@@ -127,6 +179,47 @@ impl GracefulShutdown {
     }
 }
 
+/// Spawns long-running tasks, e.g. exports or database writes, that are given
+/// time to finish when the server shuts down, instead of being cut off.
+///
+/// Workers should check [`Self::is_draining`] or [`Self::token`] to not start
+/// new work once the shutdown signal was received.
+#[derive(Clone, Debug)]
+pub struct Workers {
+    token: CancellationToken,
+    abort: CancellationToken,
+    tracker: TaskTracker,
+}
+
+impl Workers {
+    /// Spawns a worker. Its output is `None` if it was aborted, because it
+    /// didn't finish in time.
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<Option<F::Output>>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let abort = self.abort.clone();
+        self.tracker.spawn(async move {
+            tokio::select! {
+                _ = abort.cancelled() => None,
+                output = future => Some(output),
+            }
+        })
+    }
+
+    /// Cancelled when the shutdown signal is received.
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// Whether the shutdown signal was received and workers are being waited
+    /// for.
+    pub fn is_draining(&self) -> bool {
+        self.token.is_cancelled()
+    }
+}
+
 async fn sigterm() {
     #[cfg(unix)]
     tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
@@ -137,3 +230,82 @@ async fn sigterm() {
     #[cfg(not(unix))]
     std::future::pending::<()>().await;
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        Arc,
+    };
+
+    use color_eyre::eyre::eyre;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn join_waits_for_all_tasks() {
+        let mut shutdown = GracefulShutdown::new();
+        let finished = Arc::new(AtomicBool::new(false));
+
+        // the first task finishing must not abort the other one.
+        shutdown.spawn(async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Ok(())
+        });
+        shutdown.spawn({
+            let finished = finished.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                finished.store(true, Ordering::SeqCst);
+                Ok(())
+            }
+        });
+        shutdown.shutdown();
+
+        shutdown.join().await.unwrap();
+        assert!(finished.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn join_shuts_down_when_a_task_fails() {
+        let mut shutdown = GracefulShutdown::new();
+        let token = shutdown.token();
+
+        shutdown.spawn(async { Err(eyre!("failed")) });
+        shutdown.spawn(async move {
+            token.cancelled().await;
+            Ok(())
+        });
+
+        assert!(shutdown.join().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn workers_are_drained_on_shutdown() {
+        let shutdown = GracefulShutdown::new();
+        let workers = shutdown.workers();
+
+        let worker = workers.spawn(async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            42
+        });
+        shutdown.shutdown();
+        assert!(workers.is_draining());
+
+        shutdown.join().await.unwrap();
+        assert_eq!(worker.await.unwrap(), Some(42));
+    }
+
+    #[tokio::test]
+    async fn workers_are_aborted_after_drain_timeout() {
+        let shutdown = GracefulShutdown::new().with_drain_timeout(Duration::from_millis(10));
+        let worker = shutdown.workers().spawn(std::future::pending::<()>());
+        shutdown.shutdown();
+
+        shutdown.join().await.unwrap();
+        assert_eq!(worker.await.unwrap(), None);
+    }
+}
@@ -0,0 +1,123 @@
+//! Serving the UI, either from a build in the dist directory, or from the
+//! build that is embedded into the binary.
+
+use std::{
+    fmt::Display,
+    path::PathBuf,
+};
+
+use axum::Router;
+use tower_http::services::{
+    ServeDir,
+    ServeFile,
+};
+
+/// Where the UI files are served from.
+#[derive(Clone, Debug)]
+pub enum UiFiles {
+    /// A build of the UI, i.e. `dist/ui`.
+    Directory(PathBuf),
+
+    /// The build that was embedded into the binary with the `embed-ui`
+    /// feature.
+    #[cfg(feature = "embed-ui")]
+    Embedded,
+}
+
+impl UiFiles {
+    /// The UI that is embedded into the binary, if it was built with the
+    /// `embed-ui` feature.
+    #[cfg(feature = "embed-ui")]
+    pub fn embedded() -> Option<Self> {
+        Some(Self::Embedded)
+    }
+
+    /// The UI that is embedded into the binary, if it was built with the
+    /// `embed-ui` feature.
+    #[cfg(not(feature = "embed-ui"))]
+    pub fn embedded() -> Option<Self> {
+        None
+    }
+
+    /// Whether the UI has a file, e.g. a page.
+    pub fn contains(&self, path: &str) -> bool {
+        match self {
+            Self::Directory(directory) => directory.join(path).is_file(),
+            #[cfg(feature = "embed-ui")]
+            Self::Embedded => embedded::EmbeddedUi::get(path).is_some(),
+        }
+    }
+
+    /// Serves the UI files as the fallback of the router. Paths that aren't
+    /// files get `page`, e.g. `index.html`.
+    pub fn serve(&self, router: Router, page: &'static str) -> Router {
+        match self {
+            Self::Directory(directory) => {
+                router.fallback_service(ServeDir::new(directory).fallback(
+                    ServeFile::new_with_mime(directory.join(page), &mime::TEXT_HTML_UTF_8),
+                ))
+            }
+            #[cfg(feature = "embed-ui")]
+            Self::Embedded => {
+                router.fallback(move |uri: axum::http::Uri| {
+                    async move { embedded::serve(uri.path(), page) }
+                })
+            }
+        }
+    }
+}
+
+impl Display for UiFiles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Directory(directory) => write!(f, "{}", directory.display()),
+            #[cfg(feature = "embed-ui")]
+            Self::Embedded => write!(f, "the embedded UI"),
+        }
+    }
+}
+
+#[cfg(feature = "embed-ui")]
+mod embedded {
+    use axum::{
+        http::{
+            header::CONTENT_TYPE,
+            StatusCode,
+        },
+        response::{
+            IntoResponse,
+            Response,
+        },
+    };
+    use rust_embed::RustEmbed;
+
+    /// The UI build, i.e. `dist/ui`.
+    ///
+    /// This is allowed to be missing, so that the CLI builds before the UI was
+    /// built. It then only serves an error.
+    #[derive(RustEmbed)]
+    #[folder = "../dist/ui/"]
+    #[allow_missing = true]
+    pub struct EmbeddedUi;
+
+    const UI_NOT_EMBEDDED: &str = "The UI wasn't embedded. Run `shade-rs-cli build` and rebuild shade-rs-cli with the `embed-ui` feature.";
+
+    pub fn serve(path: &str, page: &str) -> Response {
+        let path = path.trim_start_matches('/');
+
+        if let Some(file) = EmbeddedUi::get(path).filter(|_| !path.is_empty()) {
+            let mime = mime_guess::from_path(path).first_or_octet_stream();
+            ([(CONTENT_TYPE, mime.to_string())], file.data).into_response()
+        }
+        else if let Some(file) = EmbeddedUi::get(page) {
+            (
+                [(CONTENT_TYPE, mime::TEXT_HTML_UTF_8.to_string())],
+                file.data,
+            )
+                .into_response()
+        }
+        else {
+            (StatusCode::NOT_FOUND, UI_NOT_EMBEDDED).into_response()
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::PathBuf,
+};
+
+use color_eyre::eyre::{
+    bail,
+    eyre,
+};
+use shade_rs_build::{
+    library::LibraryDirectory,
+    project::Project,
+};
+use shade_rs_compile::{
+    library::{
+        parse_includes,
+        Library,
+        LibraryName,
+        LIBRARIES_DIRECTORY,
+    },
+    project::LockedLibrary,
+};
+use url::Url;
+
+use crate::Error;
+
+/// Download the libraries that a project includes, so that it can be built
+/// offline.
+///
+/// The libraries are written to the project's `libraries` directory, from
+/// where `check` and `graph` pick them up. The versions they resolved to are
+/// pinned in the `[lock]` table of the manifest, so that the project builds
+/// with the same code even after newer versions are published.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// Path to the project directory or its `shader.toml`.
+    project: PathBuf,
+
+    /// URL of the server from which the libraries are fetched.
+    #[arg(long, env = "SERVER", default_value = "http://127.0.0.1:3333/")]
+    server: Url,
+
+    /// Update the libraries to the latest matching versions, instead of the
+    /// ones in the lock.
+    #[arg(long)]
+    update: bool,
+}
+
+impl Args {
+    pub async fn run(self) -> Result<(), Error> {
+        // vendored libraries that don't match the lock are replaced.
+        let mut project = Project::open_without_libraries(&self.project)?;
+        let vendored = LibraryDirectory::new(project.path.join(LIBRARIES_DIRECTORY));
+        let client = reqwest::Client::new();
+
+        let mut lock = BTreeMap::new();
+        for (pass, code) in project.passes() {
+            let includes = parse_includes(code).map_err(|error| eyre!("{}:\n{error}", pass.name))?;
+            for include in includes {
+                let key = include.key();
+                if lock.contains_key(&key) {
+                    continue;
+                }
+
+                let locked = project
+                    .manifest
+                    .lock
+                    .get(&key)
+                    .filter(|_| !self.update);
+                // a full version only matches itself.
+                let library = if let Some(locked) = locked {
+                    fetch_library(&client, &self.server, &include.library, locked.version).await?
+                }
+                else {
+                    fetch_library(&client, &self.server, &include.library, include.requirement)
+                        .await?
+                };
+
+                if let Some(locked) = locked {
+                    if !locked.matches(&library) {
+                        bail!(
+                            "`{key}` is locked to {}@{} with hash {}, but the server has {}. run with `--update` to lock the server's version",
+                            library.name,
+                            locked.version,
+                            locked.hash,
+                            library.hash()
+                        );
+                    }
+                }
+
+                vendored.vendor(&library)?;
+                tracing::info!("vendored {key} as {}@{}", library.name, library.version);
+                lock.insert(key, LockedLibrary::new(&library));
+            }
+        }
+
+        if lock.is_empty() {
+            tracing::info!("{} doesn't include any libraries", project.title());
+        }
+
+        if lock != project.manifest.lock {
+            project.write_lock(lock)?;
+            tracing::info!("updated the lock in {}", project.manifest_path.display());
+        }
+
+        Ok(())
+    }
+}
+
+/// Fetches the latest version of a library that matches `requirement` from
+/// the server.
+pub async fn fetch_library(
+    client: &reqwest::Client,
+    server: &Url,
+    name: &LibraryName,
+    requirement: impl Display,
+) -> Result<Library, Error> {
+    let url = server.join(&format!(
+        "api/libraries/{}/{}/{requirement}",
+        name.user, name.name
+    ))?;
+    let response = client.get(url).send().await?;
+    if !response.status().is_success() {
+        bail!(
+            "could not fetch `{name}@{requirement}`: {}",
+            response.text().await?
+        );
+    }
+    Ok(response.json::<Library>().await?)
+}
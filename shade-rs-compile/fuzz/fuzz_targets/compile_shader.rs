@@ -0,0 +1,50 @@
+//! Feeds arbitrary source into the compile path, including the preprocessor
+//! and everything that maps naga's output back to the source.
+//!
+//! Run with `cargo fuzz run compile_shader` in `shade-rs-compile`. A corpus
+//! can be seeded with `shade-rs-cli fuzz-corpus`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use shade_rs_compile::{
+    compile_shader,
+    diagnostic::Diagnostic,
+    lint::lint_webgl_compat,
+    probe::compile_probe_shader,
+    reflect::{
+        describe_bind_groups,
+        reads_time,
+        reflect_params,
+    },
+    sound::compile_sound_shader,
+    CompileOptions,
+};
+
+fuzz_target!(|source: &str| {
+    for inject_inputs in [true, false] {
+        let options = CompileOptions {
+            inject_inputs,
+            ..Default::default()
+        };
+        match compile_shader(source, &options) {
+            Ok(compiled) => {
+                for lint in lint_webgl_compat(&compiled.module, &compiled.source) {
+                    let _ = Diagnostic::from(&lint);
+                }
+                let _ = describe_bind_groups(&compiled.module);
+                let _ = reads_time(&compiled.module);
+                let _ = reflect_params(&compiled.module, &compiled.source);
+                if let Err(error) = compile_sound_shader(&compiled) {
+                    let _ = Diagnostic::from(&error);
+                }
+                if let Err(error) = compile_probe_shader(&compiled) {
+                    let _ = Diagnostic::from(&error);
+                }
+            }
+            Err(error) => {
+                let _ = Diagnostic::from(&error);
+            }
+        }
+    }
+});
@@ -0,0 +1,609 @@
+//! Conditional compilation with `#if`, `#ifdef`, `#ifndef`, `#elif`, `#else`
+//! and `#endif`, evaluated over the shader's [defines](crate::defines).
+//!
+//! This is mostly for porting GLSL code, which often picks code paths with the
+//! C preprocessor:
+//!
+//! ```wgsl
+//! // @define QUALITY 2
+//!
+//! #if QUALITY >= 2 && defined(SHADOWS)
+//! let shadow = soft_shadow(p);
+//! #else
+//! let shadow = 1.0;
+//! #endif
+//! ```
+//!
+//! Unlike in C, the names in a condition must be defines the shader declared.
+//! Boolean defines are 1 if they're `true` and 0 otherwise. `#ifdef` only
+//! checks whether a define is declared, so use `#if` to check a boolean's
+//! value.
+//!
+//! Other directives, e.g. `// @requires`, only apply if they're in a branch
+//! that is taken. Defines can't be declared inside `#if` blocks, since the
+//! conditions are evaluated with them.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+};
+
+use crate::{
+    defines::{
+        is_define,
+        is_identifier,
+        DefineValue,
+    },
+    CompileError,
+};
+
+/// Comments out the conditional directives and the lines in branches that
+/// aren't taken.
+///
+/// Lines are never added or removed, so locations in diagnostics still match
+/// the user's code.
+pub fn evaluate_conditionals(
+    source: &str,
+    values: &BTreeMap<String, DefineValue>,
+) -> Result<String, CompileError> {
+    let mut blocks: Vec<Block> = vec![];
+    let mut output = String::with_capacity(source.len());
+
+    for (line_index, text) in source.split('\n').enumerate() {
+        let line = line_index + 1;
+        let is_active = blocks.last().is_none_or(|block| block.is_active);
+
+        if line_index > 0 {
+            output.push('\n');
+        }
+
+        let Some((directive, argument)) = parse_directive(text)
+        else {
+            if !blocks.is_empty() && is_define(text) {
+                return Err(CompileError::ConditionalDefine { line });
+            }
+            if is_active {
+                output.push_str(text);
+            }
+            else {
+                write!(output, "// {text}").unwrap();
+            }
+            continue;
+        };
+
+        let invalid_condition = |message: &str| {
+            CompileError::InvalidCondition {
+                condition: argument.to_owned(),
+                message: message.to_owned(),
+                line,
+            }
+        };
+        let unbalanced = || {
+            CompileError::UnbalancedConditional {
+                directive: format!("#{directive}"),
+                line,
+            }
+        };
+
+        match directive {
+            "if" | "ifdef" | "ifndef" => {
+                // conditions in branches that aren't taken are never looked at, so that
+                // they can use defines from another configuration.
+                let condition = if !is_active {
+                    false
+                }
+                else if directive == "if" {
+                    evaluate(argument, values).map_err(|message| invalid_condition(&message))?
+                }
+                else {
+                    if !is_identifier(argument) {
+                        return Err(invalid_condition("expected the name of a define"));
+                    }
+                    values.contains_key(argument) == (directive == "ifdef")
+                };
+
+                blocks.push(Block {
+                    directive,
+                    line,
+                    parent_is_active: is_active,
+                    is_active: condition,
+                    was_taken: condition,
+                    has_else: false,
+                });
+            }
+            "elif" => {
+                let block = blocks
+                    .last_mut()
+                    .filter(|block| !block.has_else)
+                    .ok_or_else(unbalanced)?;
+                let condition = if !block.parent_is_active || block.was_taken {
+                    false
+                }
+                else {
+                    evaluate(argument, values).map_err(|message| invalid_condition(&message))?
+                };
+                block.is_active = condition;
+                block.was_taken |= condition;
+            }
+            "else" => {
+                let block = blocks
+                    .last_mut()
+                    .filter(|block| !block.has_else)
+                    .ok_or_else(unbalanced)?;
+                block.is_active = block.parent_is_active && !block.was_taken;
+                block.was_taken = true;
+                block.has_else = true;
+            }
+            "endif" => {
+                blocks.pop().ok_or_else(unbalanced)?;
+            }
+            _ => unreachable!(),
+        }
+
+        write!(output, "// {text}").unwrap();
+    }
+
+    if let Some(block) = blocks.pop() {
+        return Err(CompileError::UnbalancedConditional {
+            directive: format!("#{}", block.directive),
+            line: block.line,
+        });
+    }
+
+    Ok(output)
+}
+
+/// An `#if` and its branches, up to the `#endif`.
+struct Block {
+    directive: &'static str,
+
+    /// Line of the `#if`.
+    line: usize,
+
+    parent_is_active: bool,
+
+    /// Whether the lines of the current branch are kept.
+    is_active: bool,
+
+    /// Whether any branch so far was taken, so that the following ones aren't.
+    was_taken: bool,
+
+    has_else: bool,
+}
+
+/// Splits a line like `#if A > 1 // comment` into `("if", "A > 1")`.
+fn parse_directive(line: &str) -> Option<(&'static str, &str)> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start();
+    let end = rest
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    let (name, argument) = rest.split_at(end);
+
+    let directive = ["if", "ifdef", "ifndef", "elif", "else", "endif"]
+        .into_iter()
+        .find(|directive| *directive == name)?;
+
+    let argument = argument
+        .find("//")
+        .map_or(argument, |start| &argument[..start]);
+    Some((directive, argument.trim()))
+}
+
+/// How deeply parentheses and unary operators can be nested in a condition.
+/// The parser recurses for each level.
+const MAX_NESTING: usize = 64;
+
+/// Evaluates the condition of an `#if` or `#elif`. A value other than 0 is
+/// true.
+fn evaluate(condition: &str, values: &BTreeMap<String, DefineValue>) -> Result<bool, String> {
+    let tokens = tokenize(condition)?;
+    if tokens.is_empty() {
+        return Err("expected a condition".to_owned());
+    }
+
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+        depth: 0,
+        values,
+    };
+    let value = parser.or()?;
+
+    if let Some(token) = parser.peek() {
+        return Err(format!("unexpected `{token}`"));
+    }
+
+    Ok(value != 0)
+}
+
+fn tokenize(condition: &str) -> Result<Vec<&str>, String> {
+    // longer operators first, so that e.g. `<=` isn't read as `<`.
+    const OPERATORS: [&str; 16] = [
+        "||", "&&", "==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%", "!", "(", ")",
+    ];
+
+    let mut tokens = vec![];
+    let mut rest = condition.trim_start();
+
+    while !rest.is_empty() {
+        let length = if let Some(operator) = OPERATORS
+            .iter()
+            .find(|operator| rest.starts_with(**operator))
+        {
+            operator.len()
+        }
+        else if rest.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+            rest.find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len())
+        }
+        else {
+            let c = rest.chars().next().unwrap();
+            return Err(format!("unexpected `{c}`"));
+        };
+
+        let (token, remaining) = rest.split_at(length);
+        tokens.push(token);
+        rest = remaining.trim_start();
+    }
+
+    Ok(tokens)
+}
+
+/// A recursive descent parser for conditions, with C's operator precedence.
+struct Parser<'a> {
+    tokens: &'a [&'a str],
+    position: usize,
+
+    /// Current nesting of parentheses and unary operators.
+    depth: usize,
+
+    values: &'a BTreeMap<String, DefineValue>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.peek()?;
+        self.position += 1;
+        Some(token)
+    }
+
+    fn eat(&mut self, expected: &str) -> bool {
+        let found = self.peek() == Some(expected);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        if self.eat(expected) {
+            Ok(())
+        }
+        else {
+            Err(format!("expected `{expected}`"))
+        }
+    }
+
+    /// Parses a nested expression with `parse`, failing if it's nested too
+    /// deeply.
+    fn nested(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<i64, String>,
+    ) -> Result<i64, String> {
+        if self.depth == MAX_NESTING {
+            return Err(format!(
+                "conditions can't be nested more than {MAX_NESTING} levels deep"
+            ));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn or(&mut self) -> Result<i64, String> {
+        let mut value = self.and()?;
+        while self.eat("||") {
+            let right = self.and()?;
+            value = i64::from(value != 0 || right != 0);
+        }
+        Ok(value)
+    }
+
+    fn and(&mut self) -> Result<i64, String> {
+        let mut value = self.equality()?;
+        while self.eat("&&") {
+            let right = self.equality()?;
+            value = i64::from(value != 0 && right != 0);
+        }
+        Ok(value)
+    }
+
+    fn equality(&mut self) -> Result<i64, String> {
+        let mut value = self.relational()?;
+        loop {
+            if self.eat("==") {
+                value = i64::from(value == self.relational()?);
+            }
+            else if self.eat("!=") {
+                value = i64::from(value != self.relational()?);
+            }
+            else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn relational(&mut self) -> Result<i64, String> {
+        let mut value = self.additive()?;
+        loop {
+            if self.eat("<") {
+                value = i64::from(value < self.additive()?);
+            }
+            else if self.eat("<=") {
+                value = i64::from(value <= self.additive()?);
+            }
+            else if self.eat(">") {
+                value = i64::from(value > self.additive()?);
+            }
+            else if self.eat(">=") {
+                value = i64::from(value >= self.additive()?);
+            }
+            else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn additive(&mut self) -> Result<i64, String> {
+        let mut value = self.multiplicative()?;
+        loop {
+            if self.eat("+") {
+                value = value.wrapping_add(self.multiplicative()?);
+            }
+            else if self.eat("-") {
+                value = value.wrapping_sub(self.multiplicative()?);
+            }
+            else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<i64, String> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat("*") {
+                value = value.wrapping_mul(self.unary()?);
+            }
+            else if self.eat("/") {
+                value = value.wrapping_div(divisor(self.unary()?)?);
+            }
+            else if self.eat("%") {
+                value = value.wrapping_rem(divisor(self.unary()?)?);
+            }
+            else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<i64, String> {
+        if self.eat("!") {
+            Ok(i64::from(self.nested(Self::unary)? == 0))
+        }
+        else if self.eat("-") {
+            Ok(self.nested(Self::unary)?.wrapping_neg())
+        }
+        else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<i64, String> {
+        let Some(token) = self.next()
+        else {
+            return Err("unexpected end of condition".to_owned());
+        };
+
+        if token == "(" {
+            let value = self.nested(Self::or)?;
+            self.expect(")")?;
+            return Ok(value);
+        }
+
+        if token == "defined" {
+            let parenthesized = self.eat("(");
+            let name = self
+                .next()
+                .filter(|name| is_identifier(name))
+                .ok_or_else(|| "expected the name of a define after `defined`".to_owned())?;
+            if parenthesized {
+                self.expect(")")?;
+            }
+            return Ok(i64::from(self.values.contains_key(name)));
+        }
+
+        if token.starts_with(|c: char| c.is_ascii_digit()) {
+            return token
+                .parse()
+                .map_err(|_| format!("invalid number `{token}`"));
+        }
+
+        match token {
+            "true" => Ok(1),
+            "false" => Ok(0),
+            _ if is_identifier(token) => {
+                match self.values.get(token) {
+                    Some(DefineValue::Bool(value)) => Ok(i64::from(*value)),
+                    Some(DefineValue::Int(value)) => Ok(*value),
+                    None => Err(format!("unknown define `{token}`")),
+                }
+            }
+            _ => Err(format!("unexpected `{token}`")),
+        }
+    }
+}
+
+/// Like all arithmetic in conditions, dividing wraps around, e.g. for
+/// `i64::MIN / -1`. Only dividing by zero is an error.
+fn divisor(value: i64) -> Result<i64, String> {
+    if value == 0 {
+        Err("division by zero".to_owned())
+    }
+    else {
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate_with(condition: &str, defines: &[(&str, DefineValue)]) -> Result<bool, String> {
+        let values = defines
+            .iter()
+            .map(|(name, value)| ((*name).to_owned(), *value))
+            .collect();
+        evaluate(condition, &values)
+    }
+
+    fn evaluate_source(
+        source: &str,
+        defines: &[(&str, DefineValue)],
+    ) -> Result<String, CompileError> {
+        let values = defines
+            .iter()
+            .map(|(name, value)| ((*name).to_owned(), *value))
+            .collect();
+        evaluate_conditionals(source, &values)
+    }
+
+    #[test]
+    fn operators_have_c_precedence() {
+        assert_eq!(evaluate_with("1 + 2 * 3 == 7", &[]), Ok(true));
+        assert_eq!(evaluate_with("(1 + 2) * 3 == 9", &[]), Ok(true));
+        assert_eq!(evaluate_with("!0 && 1 || 0", &[]), Ok(true));
+        assert_eq!(evaluate_with("0 && 1 || 1", &[]), Ok(true));
+        assert_eq!(evaluate_with("1 || 0 && 0", &[]), Ok(true));
+        assert_eq!(evaluate_with("-2 + 3 > 0 == 1", &[]), Ok(true));
+        assert_eq!(evaluate_with("7 - 2 - 1 == 4", &[]), Ok(true));
+    }
+
+    #[test]
+    fn defined_checks_declared_defines() {
+        let defines = [("SHADOWS", DefineValue::Bool(false))];
+        assert_eq!(evaluate_with("defined(SHADOWS)", &defines), Ok(true));
+        assert_eq!(evaluate_with("defined SHADOWS", &defines), Ok(true));
+        assert_eq!(evaluate_with("defined(FOG)", &defines), Ok(false));
+        assert_eq!(evaluate_with("SHADOWS", &defines), Ok(false));
+        assert!(evaluate_with("defined(1)", &[]).is_err());
+    }
+
+    #[test]
+    fn unknown_defines_are_rejected() {
+        let error = evaluate_with("QUALITY > 1", &[]).unwrap_err();
+        assert!(error.contains("unknown define `QUALITY`"));
+
+        let error = evaluate_source("#if QUALITY > 1\n#endif", &[]).unwrap_err();
+        assert!(matches!(
+            error,
+            CompileError::InvalidCondition { line: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn only_the_first_taken_branch_is_kept() {
+        let source = "#if QUALITY >= 2\nhigh\n#elif QUALITY == 1\nmedium\n#else\nlow\n#endif";
+        let evaluate_quality =
+            |quality| evaluate_source(source, &[("QUALITY", DefineValue::Int(quality))]).unwrap();
+
+        assert_eq!(
+            evaluate_quality(3),
+            "// #if QUALITY >= 2\nhigh\n// #elif QUALITY == 1\n// medium\n// #else\n// low\n// #endif"
+        );
+        assert_eq!(
+            evaluate_quality(1),
+            "// #if QUALITY >= 2\n// high\n// #elif QUALITY == 1\nmedium\n// #else\n// low\n// #endif"
+        );
+        assert_eq!(
+            evaluate_quality(0),
+            "// #if QUALITY >= 2\n// high\n// #elif QUALITY == 1\n// medium\n// #else\nlow\n// #endif"
+        );
+    }
+
+    #[test]
+    fn branches_inside_untaken_branches_are_not_taken() {
+        let source = "#ifdef FOG\n#if FOG_DENSITY > 1\nfog\n#else\nno fog\n#endif\n#endif";
+        let output = evaluate_source(source, &[]).unwrap();
+        assert!(output.lines().all(|line| line.starts_with("// ")));
+    }
+
+    #[test]
+    fn unbalanced_conditionals_are_rejected() {
+        for (source, unbalanced, unbalanced_line) in [
+            ("#endif", "#endif", 1),
+            ("#else", "#else", 1),
+            ("#elif 1", "#elif", 1),
+            ("#if 1\n#else\n#else\n#endif", "#else", 3),
+            ("#if 1\n#else\n#elif 1\n#endif", "#elif", 3),
+            ("#if 1\n#ifdef A\n#endif", "#if", 1),
+        ] {
+            let error = evaluate_source(source, &[]).unwrap_err();
+            assert!(
+                matches!(
+                    &error,
+                    CompileError::UnbalancedConditional { directive, line }
+                        if directive == unbalanced && *line == unbalanced_line
+                ),
+                "{source:?}: {error:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn defines_inside_conditionals_are_rejected() {
+        let error = evaluate_source("#if 1\n// @define FOG true\n#endif", &[]).unwrap_err();
+        assert!(matches!(error, CompileError::ConditionalDefine { line: 2 }));
+
+        assert!(evaluate_source("// @define FOG true\n#ifdef FOG\n#endif", &[]).is_ok());
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected() {
+        let nots = format!("{}1", "!".repeat(100_000));
+        assert!(evaluate_with(&nots, &[]).unwrap_err().contains("nested"));
+
+        let negations = format!("{}1", "-".repeat(100_000));
+        assert!(evaluate_with(&negations, &[])
+            .unwrap_err()
+            .contains("nested"));
+
+        let parentheses = format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert!(evaluate_with(&parentheses, &[])
+            .unwrap_err()
+            .contains("nested"));
+
+        let allowed = format!("{}1{}", "(".repeat(32), ")".repeat(32));
+        assert_eq!(evaluate_with(&allowed, &[]), Ok(true));
+    }
+
+    #[test]
+    fn division_wraps_around() {
+        let min = [("MIN", DefineValue::Int(i64::MIN))];
+        assert_eq!(evaluate_with("MIN / -1 == MIN", &min), Ok(true));
+        assert_eq!(evaluate_with("MIN % -1 == 0", &min), Ok(true));
+        assert_eq!(
+            evaluate_with("1 / 0", &[]),
+            Err("division by zero".to_owned())
+        );
+        assert_eq!(
+            evaluate_with("1 % (2 - 2)", &[]),
+            Err("division by zero".to_owned())
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{
+        Display,
+        Write,
+    },
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    directive::find_directives,
+    CompileError,
+};
+
+/// Value of a define, which replaces its name in the code.
+///
+/// Shaders declare defines with their default value, e.g.:
+///
+/// ```wgsl
+/// // @define QUALITY 2
+/// // @define SHADOWS true
+/// ```
+///
+/// The values can be changed without editing the code (see
+/// [`CompileOptions::defines`](crate::CompileOptions::defines)), so one shader
+/// can ship e.g. low and high quality variants:
+///
+/// ```wgsl
+/// for (var i = 0; i < QUALITY * 16; i++) { ... }
+/// if SHADOWS { ... }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DefineValue {
+    Bool(bool),
+    Int(i64),
+}
+
+impl DefineValue {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "true" => Some(Self::Bool(true)),
+            "false" => Some(Self::Bool(false)),
+            _ => value.parse().ok().map(Self::Int),
+        }
+    }
+
+    /// Whether both values are booleans or both are integers.
+    pub fn is_same_kind(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::Bool(_), Self::Bool(_)) | (Self::Int(_), Self::Int(_))
+        )
+    }
+}
+
+impl Display for DefineValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Int(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// A define that the shader declared.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Define {
+    pub name: String,
+
+    /// The value from the directive.
+    pub default: DefineValue,
+
+    /// Line of the directive.
+    pub line: usize,
+}
+
+const DIRECTIVE: &str = "@define";
+
+/// Parses all `@define` directives in the shader source.
+pub fn parse_defines(source: &str) -> Result<Vec<Define>, CompileError> {
+    let mut defines: Vec<Define> = vec![];
+
+    for (line, arguments) in find_directives(source, DIRECTIVE) {
+        let [name, value] = arguments.as_slice()
+        else {
+            return Err(CompileError::InvalidDefine {
+                text: arguments.join(" "),
+                line,
+            });
+        };
+        let default = DefineValue::parse(value)
+            .filter(|_| is_identifier(name))
+            .ok_or_else(|| {
+                CompileError::InvalidDefine {
+                    text: arguments.join(" "),
+                    line,
+                }
+            })?;
+
+        if defines.iter().any(|define| define.name == *name) {
+            return Err(CompileError::DuplicateDefine {
+                name: (*name).to_owned(),
+                line,
+            });
+        }
+
+        defines.push(Define {
+            name: (*name).to_owned(),
+            default,
+            line,
+        });
+    }
+
+    Ok(defines)
+}
+
+/// Whether a line of the shader source is a `@define` directive.
+pub(crate) fn is_define(line: &str) -> bool {
+    find_directives(line, DIRECTIVE).next().is_some()
+}
+
+/// The value of each define: the one from `values` if it's given and of the
+/// same kind as the default, otherwise the default.
+///
+/// Values for defines the shader doesn't declare are ignored.
+pub fn resolve_defines(
+    defines: &[Define],
+    values: &BTreeMap<String, DefineValue>,
+) -> BTreeMap<String, DefineValue> {
+    defines
+        .iter()
+        .map(|define| {
+            let value = values
+                .get(&define.name)
+                .filter(|value| value.is_same_kind(&define.default))
+                .copied()
+                .unwrap_or(define.default);
+            (define.name.clone(), value)
+        })
+        .collect()
+}
+
+/// Replaces the names of the defines in the code with their values.
+///
+/// Comments are left alone, so that the directives still read as they were
+/// written. Lines are never added or removed, so locations in diagnostics
+/// still match the user's code.
+pub fn substitute_defines(source: &str, values: &BTreeMap<String, DefineValue>) -> String {
+    if values.is_empty() {
+        return source.to_owned();
+    }
+
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    let mut output = String::with_capacity(source.len());
+
+    for (index, line) in source.split('\n').enumerate() {
+        if index > 0 {
+            output.push('\n');
+        }
+
+        let (code, comment) = line
+            .find("//")
+            .map_or((line, ""), |start| line.split_at(start));
+
+        let mut rest = code;
+        while let Some(start) = rest.find(is_word_char) {
+            output.push_str(&rest[..start]);
+            let word = &rest[start..];
+            let end = word.find(|c: char| !is_word_char(c)).unwrap_or(word.len());
+            let (word, remaining) = word.split_at(end);
+
+            // words starting with a digit are literals, e.g. `1e5`.
+            match values.get(word) {
+                _ if word.starts_with(|c: char| c.is_ascii_digit()) => output.push_str(word),
+                // parenthesized, so that e.g. `x -N` doesn't become `x --1`.
+                Some(DefineValue::Int(value)) if *value < 0 => {
+                    write!(output, "({value})").unwrap();
+                }
+                Some(value) => write!(output, "{value}").unwrap(),
+                None => output.push_str(word),
+            }
+            rest = remaining;
+        }
+
+        output.push_str(rest);
+        output.push_str(comment);
+    }
+
+    output
+}
+
+/// Whether `name` can be used as the name of a define.
+pub(crate) fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
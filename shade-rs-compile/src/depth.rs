@@ -0,0 +1,95 @@
+use std::fmt::Display;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    directive::find_directives,
+    CompileError,
+};
+
+/// Format of the depth buffer a shader is drawn with.
+///
+/// Shaders that are drawn with a mesh always get a depth buffer. Others can
+/// opt into one with a directive, e.g. to depth test geometry they generate
+/// from the vertex index, optionally naming its format:
+///
+/// ```wgsl
+/// // @depth
+/// // @depth depth16unorm
+/// ```
+///
+/// The depth buffer is cleared every time the layer is drawn. It can be bound
+/// to a channel, so that later layers can sample it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DepthFormat {
+    Depth16Unorm,
+    Depth24Plus,
+    #[default]
+    Depth32Float,
+}
+
+impl DepthFormat {
+    pub const ALL: [Self; 3] = [Self::Depth16Unorm, Self::Depth24Plus, Self::Depth32Float];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Depth16Unorm => "depth16unorm",
+            Self::Depth24Plus => "depth24plus",
+            Self::Depth32Float => "depth32float",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|format| format.name() == name)
+    }
+}
+
+impl Display for DepthFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+const DIRECTIVE: &str = "@depth";
+
+/// Parses the `@depth` directive, if the shader has one.
+pub fn parse_depth(source: &str) -> Result<Option<DepthFormat>, CompileError> {
+    let mut depth = None;
+
+    for (line, arguments) in find_directives(source, DIRECTIVE) {
+        let format = match arguments.as_slice() {
+            [] => DepthFormat::default(),
+            [name] => {
+                DepthFormat::from_name(name).ok_or_else(|| {
+                    CompileError::UnknownDepthFormat {
+                        name: (*name).to_owned(),
+                        line,
+                    }
+                })?
+            }
+            _ => {
+                return Err(CompileError::UnknownDepthFormat {
+                    name: arguments.join(" "),
+                    line,
+                });
+            }
+        };
+
+        match depth {
+            Some(previous) if previous != format => {
+                return Err(CompileError::ConflictingDepthFormats {
+                    first: previous,
+                    second: format,
+                    line,
+                });
+            }
+            _ => depth = Some(format),
+        }
+    }
+
+    Ok(depth)
+}
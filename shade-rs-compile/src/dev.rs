@@ -0,0 +1,41 @@
+//! Messages that the `dev` command of the CLI sends to the player over the
+//! WebSocket at `/api/dev`, to preview a project while it's edited on disk.
+//!
+//! The server sends the whole project when a client connects, and again
+//! whenever a file in the project directory changes.
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    project::PassManifest,
+    CompileOptions,
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum DevMessage {
+    /// The project was loaded, or changed on disk.
+    Project {
+        title: String,
+
+        /// Passes from bottom to top.
+        passes: Vec<DevPass>,
+
+        /// Options that all passes are compiled with, including the vendored
+        /// libraries.
+        options: CompileOptions,
+    },
+
+    /// The project couldn't be loaded, e.g. because the manifest is invalid.
+    /// The player keeps showing the last version that loaded.
+    Error { message: String },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DevPass {
+    pub pass: PassManifest,
+    pub code: String,
+}
@@ -0,0 +1,238 @@
+use naga::Span;
+use serde::Serialize;
+
+use crate::{
+    lint::Lint,
+    project::{
+        ManifestError,
+        MANIFEST_FILE_NAME,
+    },
+    reflect::{
+        stage_name,
+        EntryPointError,
+    },
+    CompileError,
+};
+
+/// A machine-readable compiler error or warning.
+#[derive(Debug, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub stage: Stage,
+    pub message: String,
+    pub location: Option<Location>,
+    pub labels: Vec<Label>,
+    /// The diagnostic formatted like the naga CLI would print it.
+    pub rendered: String,
+}
+
+impl Diagnostic {
+    /// An error in a directive comment.
+    fn directive(message: String, line: usize, error: &CompileError) -> Self {
+        Self {
+            severity: Severity::Error,
+            stage: Stage::Directives,
+            message,
+            location: Some(Location {
+                line: line.try_into().unwrap_or(u32::MAX),
+                column: 1,
+                offset: 0,
+                length: 0,
+            }),
+            labels: vec![],
+            rendered: error.to_string(),
+        }
+    }
+
+    /// An error in a project manifest. `toml` is the manifest's source.
+    pub fn from_manifest_error(error: &ManifestError, toml: &str) -> Self {
+        Self {
+            severity: Severity::Error,
+            stage: Stage::Manifest,
+            message: error.to_string(),
+            location: error.location(toml).map(|(line, column)| {
+                let span = error.span.clone().unwrap_or_default();
+                Location {
+                    line: line.try_into().unwrap_or(u32::MAX),
+                    column: column.try_into().unwrap_or(u32::MAX),
+                    offset: span.start.try_into().unwrap_or(u32::MAX),
+                    length: span.len().try_into().unwrap_or(u32::MAX),
+                }
+            }),
+            labels: vec![],
+            rendered: error.render(toml, MANIFEST_FILE_NAME),
+        }
+    }
+}
+
+impl From<&CompileError> for Diagnostic {
+    fn from(error: &CompileError) -> Self {
+        match error {
+            CompileError::Parse { parse_error, code } => {
+                Self {
+                    severity: Severity::Error,
+                    stage: Stage::Parse,
+                    message: parse_error.message().to_owned(),
+                    location: parse_error.location(code).map(Into::into),
+                    labels: parse_error
+                        .labels()
+                        .map(|(span, message)| Label::new(span, message.to_owned(), code))
+                        .collect(),
+                    rendered: error.to_string(),
+                }
+            }
+            CompileError::Validate {
+                validation_error,
+                code,
+            } => {
+                Self {
+                    severity: Severity::Error,
+                    stage: Stage::Validate,
+                    message: validation_error.as_inner().to_string(),
+                    location: validation_error.location(code).map(Into::into),
+                    labels: validation_error
+                        .spans()
+                        .map(|(span, message)| Label::new(*span, message.clone(), code))
+                        .collect(),
+                    rendered: error.to_string(),
+                }
+            }
+            CompileError::UnknownRequirement { name, line } => {
+                Self::directive(format!("unknown requirement `{name}`"), *line, error)
+            }
+            CompileError::UnknownInputsVersion { name, line } => {
+                Self::directive(format!("unknown inputs version `{name}`"), *line, error)
+            }
+            CompileError::ConflictingInputsVersions { second, line, .. } => {
+                Self::directive(
+                    format!("conflicting inputs version `{second}`"),
+                    *line,
+                    error,
+                )
+            }
+            CompileError::UnknownDepthFormat { name, line } => {
+                Self::directive(format!("unknown depth format `{name}`"), *line, error)
+            }
+            CompileError::ConflictingDepthFormats { second, line, .. } => {
+                Self::directive(format!("conflicting depth format `{second}`"), *line, error)
+            }
+            CompileError::EmptyProbe { line } => {
+                Self::directive("probe has no expression".to_owned(), *line, error)
+            }
+            CompileError::UnsupportedProbe { expression, line } => {
+                Self::directive(format!("can't probe `{expression}`"), *line, error)
+            }
+            CompileError::InvalidDefine { text, line } => {
+                Self::directive(format!("invalid define `{text}`"), *line, error)
+            }
+            CompileError::DuplicateDefine { name, line } => {
+                Self::directive(format!("`{name}` is already defined"), *line, error)
+            }
+            CompileError::InvalidCondition { message, line, .. } => {
+                Self::directive(format!("invalid condition: {message}"), *line, error)
+            }
+            CompileError::UnbalancedConditional { directive, line } => {
+                Self::directive(format!("unbalanced `{directive}`"), *line, error)
+            }
+            CompileError::ConditionalDefine { line } => {
+                Self::directive("define inside `#if`".to_owned(), *line, error)
+            }
+            CompileError::InvalidInclude { text, line } => {
+                Self::directive(format!("invalid include `{text}`"), *line, error)
+            }
+            CompileError::MissingLibrary { include, line } => {
+                Self::directive(format!("library `{include}` isn't available"), *line, error)
+            }
+            CompileError::NestedInclude { include, line } => {
+                Self::directive(format!("library includes `{include}`"), *line, error)
+            }
+            CompileError::EntryPoint(entry_point_error) => {
+                let message = match entry_point_error {
+                    EntryPointError::Missing { stage } => {
+                        format!("no {} entry point", stage_name(*stage))
+                    }
+                    EntryPointError::Ambiguous { stage, .. } => {
+                        format!("several {} entry points", stage_name(*stage))
+                    }
+                    EntryPointError::Unknown { stage, name, .. } => {
+                        format!("no {} entry point `{name}`", stage_name(*stage))
+                    }
+                };
+                Self {
+                    severity: Severity::Error,
+                    stage: Stage::Validate,
+                    message,
+                    location: None,
+                    labels: vec![],
+                    rendered: error.to_string(),
+                }
+            }
+        }
+    }
+}
+
+impl From<&Lint> for Diagnostic {
+    fn from(lint: &Lint) -> Self {
+        Self {
+            severity: Severity::Warning,
+            stage: Stage::Lint,
+            message: lint.message.clone(),
+            location: lint.location.map(Into::into),
+            labels: vec![],
+            rendered: lint.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Stage {
+    Manifest,
+    Directives,
+    Parse,
+    Validate,
+    Lint,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Label {
+    pub message: String,
+    pub location: Option<Location>,
+}
+
+impl Label {
+    fn new(span: Span, message: String, code: &str) -> Self {
+        Self {
+            message,
+            location: span.is_defined().then(|| span.location(code).into()),
+        }
+    }
+}
+
+/// Source location of a diagnostic. Lines and columns are 1-based, offset and
+/// length are in bytes.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Location {
+    pub line: u32,
+    pub column: u32,
+    pub offset: u32,
+    pub length: u32,
+}
+
+impl From<naga::SourceLocation> for Location {
+    fn from(location: naga::SourceLocation) -> Self {
+        Self {
+            line: location.line_number,
+            column: location.line_position,
+            offset: location.offset,
+            length: location.length,
+        }
+    }
+}
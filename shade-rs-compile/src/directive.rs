@@ -0,0 +1,40 @@
+/// Finds all directives with the given name, e.g. `// @requires webgpu`.
+///
+/// Yields the (1-based) line number and the arguments of each directive.
+pub(crate) fn find_directives<'a>(
+    source: &'a str,
+    name: &'a str,
+) -> impl Iterator<Item = (usize, Vec<&'a str>)> + 'a {
+    find_directive_lines(source, name).map(|(line, arguments)| {
+        let arguments = arguments
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|argument| !argument.is_empty())
+            .collect();
+        (line, arguments)
+    })
+}
+
+/// Like [`find_directives`], but yields the text after the directive's name
+/// as is, for directives whose argument isn't a list, e.g. `// @probe a + b`.
+pub(crate) fn find_directive_lines<'a>(
+    source: &'a str,
+    name: &'a str,
+) -> impl Iterator<Item = (usize, &'a str)> + 'a {
+    source
+        .lines()
+        .enumerate()
+        .filter_map(move |(line_index, line)| {
+            let arguments = line
+                .trim_start()
+                .strip_prefix("//")?
+                .trim_start()
+                .strip_prefix(name)?;
+
+            // don't match e.g. `@requiresfoo`
+            if !arguments.is_empty() && !arguments.starts_with(char::is_whitespace) {
+                return None;
+            }
+
+            Some((line_index + 1, arguments.trim()))
+        })
+}
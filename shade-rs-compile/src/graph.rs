@@ -0,0 +1,268 @@
+//! Dependencies between the passes of a project.
+//!
+//! A pass depends on another one if it reads that pass's output through a
+//! channel (see [`ChannelSource::Pass`]). Passes are rendered in an order in
+//! which every pass comes after the passes it depends on, but are still
+//! composited from bottom to top, in the order of the manifest.
+
+use std::fmt::{
+    Display,
+    Write,
+};
+
+use naga::Module;
+
+use crate::{
+    project::{
+        ChannelSource,
+        ProjectManifest,
+    },
+    reflect::{
+        declared_channels,
+        reads_previous_frame,
+    },
+};
+
+/// The passes of a project with their inputs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassGraph {
+    /// In the order of the manifest, i.e. from bottom to top.
+    pub passes: Vec<PassNode>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassNode {
+    pub name: String,
+
+    /// The channels the pass's shader declares, with what's bound to them.
+    /// Unbound channels are transparent black.
+    pub channels: Vec<(usize, Option<ChannelSource>)>,
+
+    /// Indices of the passes whose output this pass reads.
+    pub dependencies: Vec<usize>,
+
+    pub reads_previous_frame: bool,
+}
+
+impl PassGraph {
+    /// Builds the graph from a validated manifest and the compiled modules of
+    /// its passes, in the same order as the passes.
+    pub fn new(manifest: &ProjectManifest, modules: &[&Module]) -> Self {
+        let passes = manifest
+            .passes
+            .iter()
+            .zip(modules)
+            .map(|(pass, module)| {
+                let channels = declared_channels(module)
+                    .into_iter()
+                    .map(|slot| {
+                        let source = manifest
+                            .channels
+                            .iter()
+                            .find(|channel| channel.slot == slot)
+                            .map(|channel| channel.source.clone());
+                        (slot, source)
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut dependencies = channels
+                    .iter()
+                    .filter_map(|(_, source)| {
+                        match source {
+                            Some(ChannelSource::Pass(name)) => {
+                                manifest.passes.iter().position(|pass| pass.name == *name)
+                            }
+                            _ => None,
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                dependencies.sort_unstable();
+                dependencies.dedup();
+
+                PassNode {
+                    name: pass.name.clone(),
+                    channels,
+                    dependencies,
+                    reads_previous_frame: reads_previous_frame(module),
+                }
+            })
+            .collect();
+
+        Self { passes }
+    }
+
+    /// Indices of the passes in the order they're rendered.
+    ///
+    /// Passes that don't depend on each other keep their order from the
+    /// manifest.
+    pub fn execution_order(&self) -> Result<Vec<usize>, CycleError> {
+        let mut order = Vec::with_capacity(self.passes.len());
+        let mut done = vec![false; self.passes.len()];
+
+        while order.len() < self.passes.len() {
+            let next = (0..self.passes.len()).find(|&index| {
+                !done[index]
+                    && self.passes[index]
+                        .dependencies
+                        .iter()
+                        .all(|&dependency| done[dependency])
+            });
+
+            let Some(next) = next
+            else {
+                return Err(self.find_cycle(&done));
+            };
+
+            done[next] = true;
+            order.push(next);
+        }
+
+        Ok(order)
+    }
+
+    /// Finds a cycle among the passes that are not `done`. Every one of them
+    /// depends on another one that is not done, or it could have been
+    /// rendered, so following those dependencies must run into a cycle.
+    fn find_cycle(&self, done: &[bool]) -> CycleError {
+        let mut path: Vec<usize> = vec![];
+        let mut current = done
+            .iter()
+            .position(|done| !done)
+            .expect("no remaining passes");
+
+        loop {
+            if let Some(start) = path.iter().position(|&index| index == current) {
+                return CycleError {
+                    passes: path[start..]
+                        .iter()
+                        .map(|&index| self.passes[index].name.clone())
+                        .collect(),
+                };
+            }
+
+            path.push(current);
+            current = *self.passes[current]
+                .dependencies
+                .iter()
+                .find(|&&dependency| !done[dependency])
+                .expect("pass without remaining dependencies");
+        }
+    }
+
+    /// Describes the passes in the order they're rendered, with their inputs.
+    /// If the passes form a cycle, they're listed in the order of the manifest.
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        let order = self
+            .execution_order()
+            .unwrap_or_else(|_| (0..self.passes.len()).collect());
+
+        for (step, index) in order.into_iter().enumerate() {
+            let pass = &self.passes[index];
+            writeln!(output, "{}. {}", step + 1, pass.name).unwrap();
+
+            for (slot, source) in &pass.channels {
+                let source = source
+                    .as_ref()
+                    .map_or_else(|| "nothing".to_owned(), describe_source);
+                writeln!(output, "     channel{slot} <- {source}").unwrap();
+            }
+            if pass.reads_previous_frame {
+                writeln!(output, "     previous frame").unwrap();
+            }
+        }
+
+        output
+    }
+
+    /// The graph in Graphviz's DOT language, e.g. for `dot -Tsvg`.
+    pub fn to_dot(&self) -> String {
+        let mut output = "digraph passes {\n    rankdir=LR;\n".to_owned();
+        let mut previous_frame = false;
+
+        for (index, pass) in self.passes.iter().enumerate() {
+            writeln!(
+                output,
+                "    pass{index} [shape=box, label={:?}];",
+                pass.name
+            )
+            .unwrap();
+
+            for (slot, source) in &pass.channels {
+                match source {
+                    Some(ChannelSource::Pass(name)) => {
+                        if let Some(dependency) =
+                            self.passes.iter().position(|pass| pass.name == *name)
+                        {
+                            writeln!(
+                                output,
+                                "    pass{dependency} -> pass{index} [label=\"channel{slot}\"];"
+                            )
+                            .unwrap();
+                        }
+                    }
+                    source => {
+                        let label = format!(
+                            "channel{slot}\n{}",
+                            source
+                                .as_ref()
+                                .map_or_else(|| "nothing".to_owned(), describe_source)
+                        );
+                        writeln!(
+                            output,
+                            "    channel{slot}_{index} [label={label:?}];\n    channel{slot}_{index} -> pass{index};"
+                        )
+                        .unwrap();
+                    }
+                }
+            }
+
+            if pass.reads_previous_frame {
+                previous_frame = true;
+                writeln!(output, "    previous_frame -> pass{index} [style=dashed];").unwrap();
+            }
+        }
+
+        if previous_frame {
+            output.push_str("    previous_frame [shape=box, style=dashed, label=\"previous frame\"];\n");
+        }
+
+        output.push_str("}\n");
+        output
+    }
+}
+
+fn describe_source(source: &ChannelSource) -> String {
+    match source {
+        ChannelSource::Image(path) => format!("image `{}`", path.display()),
+        ChannelSource::Text(text) => format!("text {text:?}"),
+        ChannelSource::Pass(name) => format!("pass `{name}`"),
+    }
+}
+
+/// Passes that read each other's output in the same frame.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub struct CycleError {
+    /// Names of the passes in the cycle. Each one reads the output of the next
+    /// one, and the last one reads the output of the first one.
+    pub passes: Vec<String>,
+}
+
+impl Display for CycleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let [pass] = self.passes.as_slice() {
+            write!(f, "pass `{pass}` reads its own output")?;
+        }
+        else {
+            write!(f, "passes depend on each other in a cycle: ")?;
+            for pass in &self.passes {
+                write!(f, "`{pass}` <- ")?;
+            }
+            write!(f, "`{}`", self.passes[0])?;
+        }
+        write!(
+            f,
+            ". passes can't read each other's output in the same frame. read the previous frame for feedback effects instead"
+        )
+    }
+}
@@ -0,0 +1,201 @@
+use std::fmt::Display;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    directive::find_directives,
+    CompileError,
+};
+
+/// Layout version of the input uniform, i.e. the `ShadeRs` struct bound at
+/// `@group(0) @binding(0)`.
+///
+/// New versions only ever append fields and the player always provides the
+/// latest layout, so shaders written against an older version keep working.
+///
+/// Unless disabled, the compiler injects the declarations of the latest version
+/// into shaders that don't declare them. A shader can also pin a version with a
+/// directive:
+///
+/// ```wgsl
+/// // @inputs v2
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InputsVersion {
+    /// `time`, `aspect`, `mouse`
+    V1,
+
+    /// Adds `mouse_click`, `resolution`, `frame`, `delta_time`
+    V2,
+
+    /// Adds `scroll`, `zoom`
+    V3,
+
+    /// Adds `rotation`
+    V4,
+
+    /// Adds `pixel_aspect`
+    V5,
+}
+
+impl InputsVersion {
+    pub const ALL: [Self; 5] = [Self::V1, Self::V2, Self::V3, Self::V4, Self::V5];
+    pub const LATEST: Self = Self::V5;
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::V1 => "v1",
+            Self::V2 => "v2",
+            Self::V3 => "v3",
+            Self::V4 => "v4",
+            Self::V5 => "v5",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|version| version.name() == name)
+    }
+
+    /// WGSL declarations of the input struct and its binding.
+    pub fn declaration(&self) -> &'static str {
+        match self {
+            Self::V1 => include_str!("inputs/v1.wgsl"),
+            Self::V2 => include_str!("inputs/v2.wgsl"),
+            Self::V3 => include_str!("inputs/v3.wgsl"),
+            Self::V4 => include_str!("inputs/v4.wgsl"),
+            Self::V5 => include_str!("inputs/v5.wgsl"),
+        }
+    }
+}
+
+impl Display for InputsVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+const DIRECTIVE: &str = "@inputs";
+
+/// Parses the `@inputs` directive, if the shader has one.
+pub fn parse_inputs_version(source: &str) -> Result<Option<InputsVersion>, CompileError> {
+    let mut inputs_version = None;
+
+    for (line, arguments) in find_directives(source, DIRECTIVE) {
+        let [name] = arguments.as_slice()
+        else {
+            return Err(CompileError::UnknownInputsVersion {
+                name: arguments.join(" "),
+                line,
+            });
+        };
+        let version = InputsVersion::from_name(name).ok_or_else(|| {
+            CompileError::UnknownInputsVersion {
+                name: (*name).to_owned(),
+                line,
+            }
+        })?;
+
+        match inputs_version {
+            Some(previous) if previous != version => {
+                return Err(CompileError::ConflictingInputsVersions {
+                    first: previous,
+                    second: version,
+                    line,
+                });
+            }
+            _ => inputs_version = Some(version),
+        }
+    }
+
+    Ok(inputs_version)
+}
+
+/// Appends the declarations of the input struct to the source.
+///
+/// They're appended rather than prepended, so that locations in diagnostics
+/// still match the user's code.
+pub fn inject_inputs(source: &str, version: InputsVersion) -> String {
+    format!(
+        "{source}\n\n// injected by shade-rs (@inputs {version})\n{}",
+        version.declaration()
+    )
+}
+
+/// Checks whether the shader declares the input struct or its binding itself.
+/// Declarations that are commented out don't count.
+pub fn declares_inputs(source: &str) -> bool {
+    let source = strip_comments(source);
+    let tokens = source
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|token| !token.is_empty())
+        .collect::<Vec<_>>();
+
+    tokens
+        .windows(2)
+        .any(|window| window == ["struct", "ShadeRs"])
+        || tokens
+            .windows(3)
+            .any(|window| window == ["var", "uniform", "input"])
+}
+
+/// Replaces line comments and (nested) block comments with a space.
+fn strip_comments(source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    let mut block_depth = 0usize;
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('/', Some('*')) => {
+                chars.next();
+                block_depth += 1;
+            }
+            ('*', Some('/')) if block_depth > 0 => {
+                chars.next();
+                block_depth -= 1;
+                if block_depth == 0 {
+                    output.push(' ');
+                }
+            }
+            ('/', Some('/')) if block_depth == 0 => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+                output.push(' ');
+            }
+            _ if block_depth == 0 => output.push(c),
+            _ => {}
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commented_out_declarations_dont_count() {
+        assert!(declares_inputs("struct ShadeRs { time: f32 }"));
+        assert!(declares_inputs(
+            "@group(0) @binding(0) var<uniform> input: ShadeRs;"
+        ));
+
+        assert!(!declares_inputs("// struct ShadeRs { time: f32 }"));
+        assert!(!declares_inputs(
+            "// @group(0) @binding(0) var<uniform> input: ShadeRs;"
+        ));
+        assert!(!declares_inputs(
+            "/* struct ShadeRs { time: f32 } */\nfn main() {}"
+        ));
+        assert!(!declares_inputs(
+            "/* outer /* inner */ struct ShadeRs {} */"
+        ));
+        assert!(declares_inputs(
+            "/* struct */ struct/**/ShadeRs { time: f32 }"
+        ));
+    }
+}
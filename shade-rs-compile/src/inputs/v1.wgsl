@@ -0,0 +1,8 @@
+struct ShadeRs {
+    time: f32,
+    aspect: f32,
+    mouse: vec2f,
+}
+
+@group(0) @binding(0)
+var<uniform> input: ShadeRs;
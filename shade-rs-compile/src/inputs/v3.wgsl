@@ -0,0 +1,17 @@
+struct ShadeRs {
+    time: f32,
+    aspect: f32,
+    mouse: vec2f,
+    // like Shadertoy's iMouse, in pixels
+    mouse_click: vec4f,
+    resolution: vec2f,
+    frame: u32,
+    delta_time: f32,
+    // accumulated mouse wheel scroll, in pixels
+    scroll: vec2f,
+    // starts at 1, and doubles for every 500 pixels scrolled up
+    zoom: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> input: ShadeRs;
@@ -0,0 +1,24 @@
+struct ShadeRs {
+    time: f32,
+    aspect: f32,
+    mouse: vec2f,
+    // like Shadertoy's iMouse, in pixels
+    mouse_click: vec4f,
+    // size of the rendered frame, in pixels
+    resolution: vec2f,
+    frame: u32,
+    delta_time: f32,
+    // accumulated mouse wheel scroll, in pixels
+    scroll: vec2f,
+    // starts at 1, and doubles for every 500 pixels scrolled up
+    zoom: f32,
+    // orientation of the device as a quaternion (x, y, z, w), relative to the
+    // earth. the identity on devices without an orientation sensor
+    rotation: vec4f,
+    // width of a rendered pixel on the screen, relative to its height. like
+    // the z component of Shadertoy's iResolution
+    pixel_aspect: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> input: ShadeRs;
@@ -0,0 +1,503 @@
+//! Compiling WGSL shaders for shade-rs.
+//!
+//! This is shared by the UI, the CLI and the server, so that shaders are
+//! checked the same way and produce the same diagnostics everywhere.
+
+pub mod conditional;
+pub mod defines;
+pub mod depth;
+pub mod dev;
+pub mod diagnostic;
+mod directive;
+pub mod graph;
+pub mod inputs;
+pub mod library;
+pub mod lint;
+pub mod probe;
+pub mod project;
+pub mod reflect;
+pub mod requirements;
+pub mod sound;
+
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    time::Duration,
+};
+
+use naga::{
+    front::wgsl::ParseError,
+    valid::{
+        Capabilities,
+        ModuleInfo,
+        ValidationError,
+        ValidationFlags,
+        Validator,
+    },
+    Module,
+    ShaderStage,
+    WithSpan,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use web_time::Instant;
+
+use crate::{
+    conditional::evaluate_conditionals,
+    defines::{
+        parse_defines,
+        resolve_defines,
+        substitute_defines,
+        Define,
+        DefineValue,
+    },
+    depth::{
+        parse_depth,
+        DepthFormat,
+    },
+    inputs::{
+        declares_inputs,
+        inject_inputs,
+        parse_inputs_version,
+        InputsVersion,
+    },
+    library::{
+        include_libraries,
+        parse_includes,
+        Include,
+        Library,
+    },
+    reflect::{
+        find_entry_points,
+        reflect_bindings,
+        reflect_params,
+        stage_name,
+        EntryPointError,
+        EntryPoints,
+        ParamsLayout,
+        ResourceBinding,
+    },
+    requirements::{
+        parse_requirements,
+        Requirement,
+    },
+};
+
+/// Options for compiling shaders.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompileOptions {
+    /// Inject the declarations of the latest input struct, if the shader
+    /// doesn't declare them itself or pin a version with `@inputs`.
+    pub inject_inputs: bool,
+
+    /// Values of the shader's [defines](defines::DefineValue), by name.
+    /// Defines that aren't in here keep the value from their directive.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub defines: BTreeMap<String, DefineValue>,
+
+    /// The [libraries](library) that the shader includes, by
+    /// [`Include::key`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub libraries: BTreeMap<String, Library>,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            inject_inputs: true,
+            defines: BTreeMap::new(),
+            libraries: BTreeMap::new(),
+        }
+    }
+}
+
+/// A parsed and validated shader.
+#[derive(Clone, Debug)]
+pub struct CompiledShader {
+    /// The source after preprocessing. Spans in `module` refer to this.
+    pub source: String,
+
+    pub module: Module,
+    pub module_info: ModuleInfo,
+
+    /// Capabilities the shader declared with `@requires` directives.
+    pub requirements: Vec<Requirement>,
+
+    /// The input layout that was injected, either because the shader pinned it
+    /// with an `@inputs` directive, or because of
+    /// [`CompileOptions::inject_inputs`].
+    pub inputs_version: Option<InputsVersion>,
+
+    /// Layout of the user-defined parameters, if the shader declares them.
+    pub params: Option<ParamsLayout>,
+
+    /// The depth buffer the shader asked for with a `@depth` directive.
+    pub depth: Option<DepthFormat>,
+
+    /// Defines the shader declared with `@define` directives.
+    pub defines: Vec<Define>,
+
+    /// The libraries the shader includes.
+    pub includes: Vec<Include>,
+
+    /// The entry points that the shader is drawn with.
+    pub entry_points: EntryPoints,
+
+    /// The resource bindings that the entry points use.
+    pub bindings: Vec<ResourceBinding>,
+
+    pub timings: FrontendTimings,
+}
+
+/// Shader source after directives have been processed.
+#[derive(Clone, Debug)]
+pub struct Preprocessed {
+    /// The source with evaluated conditionals, included libraries, substituted
+    /// defines and injected declarations.
+    pub source: String,
+    pub requirements: Vec<Requirement>,
+    pub inputs_version: Option<InputsVersion>,
+    pub depth: Option<DepthFormat>,
+    pub defines: Vec<Define>,
+    pub includes: Vec<Include>,
+}
+
+/// Processes the directives in the shader source.
+///
+/// Conditionals are evaluated first, so that the other directives in branches
+/// that aren't taken are ignored. Only defines are parsed before, since the
+/// conditions use them, so they can't be declared inside `#if` blocks.
+pub fn preprocess(source: &str, options: &CompileOptions) -> Result<Preprocessed, CompileError> {
+    let defines = parse_defines(source)?;
+    let values = resolve_defines(&defines, &options.defines);
+
+    let source = evaluate_conditionals(source, &values)?;
+    let requirements = parse_requirements(&source)?;
+    let depth = parse_depth(&source)?;
+    let includes = parse_includes(&source)?;
+    let source = include_libraries(&source, &includes, &options.libraries)?;
+    let inputs_version = parse_inputs_version(&source)?.or_else(|| {
+        (options.inject_inputs && !declares_inputs(&source)).then_some(InputsVersion::LATEST)
+    });
+
+    let source = substitute_defines(&source, &values);
+    let source = if let Some(inputs_version) = inputs_version {
+        inject_inputs(&source, inputs_version)
+    }
+    else {
+        source
+    };
+
+    Ok(Preprocessed {
+        source,
+        requirements,
+        inputs_version,
+        depth,
+        defines,
+        includes,
+    })
+}
+
+/// How long the individual compilation steps took.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrontendTimings {
+    pub parse: Duration,
+    pub validate: Duration,
+}
+
+/// Parses and validates a WGSL shader.
+pub fn compile_shader(
+    source: &str,
+    options: &CompileOptions,
+) -> Result<CompiledShader, CompileError> {
+    compile_shader_with_fragment(source, options, None)
+}
+
+/// Like [`compile_shader`], but the shader is drawn with the fragment entry
+/// point named `fragment_entry_point`, if it's given. This picks one of
+/// several variants in the same file.
+pub fn compile_shader_with_fragment(
+    source: &str,
+    options: &CompileOptions,
+    fragment_entry_point: Option<&str>,
+) -> Result<CompiledShader, CompileError> {
+    let Preprocessed {
+        source,
+        requirements,
+        inputs_version,
+        depth,
+        defines,
+        includes,
+    } = preprocess(source, options)?;
+
+    let start_time = Instant::now();
+    let module = naga::front::wgsl::parse_str(&source).map_err(|parse_error| {
+        CompileError::Parse {
+            parse_error: Box::new(parse_error),
+            code: source.clone(),
+        }
+    })?;
+    let parse = start_time.elapsed();
+
+    let start_time = Instant::now();
+    let mut validator = Validator::new(ValidationFlags::all(), Capabilities::all());
+    let module_info = validator.validate(&module).map_err(|validation_error| {
+        CompileError::Validate {
+            validation_error: Box::new(validation_error),
+            code: source.clone(),
+        }
+    })?;
+    let validate = start_time.elapsed();
+
+    let entry_points =
+        find_entry_points(&module, fragment_entry_point).map_err(CompileError::EntryPoint)?;
+    let params = reflect_params(&module, &source);
+    let bindings = reflect_bindings(&module, &module_info, &entry_points);
+
+    Ok(CompiledShader {
+        source,
+        module,
+        module_info,
+        requirements,
+        inputs_version,
+        params,
+        depth,
+        defines,
+        includes,
+        entry_points,
+        bindings,
+        timings: FrontendTimings { parse, validate },
+    })
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CompileError {
+    Parse {
+        #[source]
+        parse_error: Box<ParseError>,
+        code: String,
+    },
+    Validate {
+        #[source]
+        validation_error: Box<WithSpan<ValidationError>>,
+        code: String,
+    },
+    UnknownRequirement {
+        name: String,
+        line: usize,
+    },
+    UnknownInputsVersion {
+        name: String,
+        line: usize,
+    },
+    ConflictingInputsVersions {
+        first: InputsVersion,
+        second: InputsVersion,
+        line: usize,
+    },
+    UnknownDepthFormat {
+        name: String,
+        line: usize,
+    },
+    ConflictingDepthFormats {
+        first: DepthFormat,
+        second: DepthFormat,
+        line: usize,
+    },
+    EmptyProbe {
+        line: usize,
+    },
+    UnsupportedProbe {
+        expression: String,
+        line: usize,
+    },
+    InvalidDefine {
+        text: String,
+        line: usize,
+    },
+    DuplicateDefine {
+        name: String,
+        line: usize,
+    },
+    InvalidCondition {
+        condition: String,
+        message: String,
+        line: usize,
+    },
+    UnbalancedConditional {
+        directive: String,
+        line: usize,
+    },
+    ConditionalDefine {
+        line: usize,
+    },
+    InvalidInclude {
+        text: String,
+        line: usize,
+    },
+    MissingLibrary {
+        include: String,
+        line: usize,
+    },
+    NestedInclude {
+        include: String,
+        line: usize,
+    },
+    EntryPoint(EntryPointError),
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let error_string = match self {
+            CompileError::Parse { parse_error, code } => parse_error.emit_to_string(code),
+            CompileError::Validate {
+                validation_error,
+                code,
+            } => validation_error.emit_to_string(code),
+            CompileError::UnknownRequirement { name, line } => {
+                let known = Requirement::ALL.map(|requirement| requirement.name());
+                format!(
+                    "error: unknown requirement `{name}` on line {line}\n\nknown requirements are: {}",
+                    known.join(", ")
+                )
+            }
+            CompileError::UnknownInputsVersion { name, line } => {
+                let known = InputsVersion::ALL.map(|version| version.name());
+                format!(
+                    "error: unknown inputs version `{name}` on line {line}\n\nknown versions are: {}",
+                    known.join(", ")
+                )
+            }
+            CompileError::ConflictingInputsVersions {
+                first,
+                second,
+                line,
+            } => {
+                format!(
+                    "error: inputs version `{second}` on line {line} conflicts with previously declared `{first}`"
+                )
+            }
+            CompileError::UnknownDepthFormat { name, line } => {
+                let known = DepthFormat::ALL.map(|format| format.name());
+                format!(
+                    "error: unknown depth format `{name}` on line {line}\n\nknown formats are: {}",
+                    known.join(", ")
+                )
+            }
+            CompileError::ConflictingDepthFormats {
+                first,
+                second,
+                line,
+            } => {
+                format!(
+                    "error: depth format `{second}` on line {line} conflicts with previously declared `{first}`"
+                )
+            }
+            CompileError::EmptyProbe { line } => {
+                format!("error: probe on line {line} has no expression")
+            }
+            CompileError::UnsupportedProbe { expression, line } => {
+                format!(
+                    "error: can't probe `{expression}` on line {line}\n\nonly scalars and vectors can be probed"
+                )
+            }
+            CompileError::InvalidDefine { text, line } => {
+                format!(
+                    "error: invalid define `{text}` on line {line}\n\ndefines have a name and a default value, e.g. `// @define QUALITY 2` or `// @define SHADOWS true`"
+                )
+            }
+            CompileError::DuplicateDefine { name, line } => {
+                format!("error: `{name}` on line {line} is already defined")
+            }
+            CompileError::InvalidCondition {
+                condition,
+                message,
+                line,
+            } => {
+                format!("error: invalid condition `{condition}` on line {line}: {message}")
+            }
+            CompileError::UnbalancedConditional { directive, line }
+                if directive.starts_with("#if") =>
+            {
+                format!(
+                    "error: `{directive}` on line {line} is never closed\n\nclose it with `#endif`"
+                )
+            }
+            CompileError::UnbalancedConditional { directive, line } => {
+                format!("error: `{directive}` on line {line} has no matching `#if`")
+            }
+            CompileError::ConditionalDefine { line } => {
+                format!(
+                    "error: define on line {line} is inside an `#if`\n\nconditions are evaluated with the defines, so declare them outside of `#if` blocks"
+                )
+            }
+            CompileError::InvalidInclude { text, line } => {
+                format!(
+                    "error: invalid include `{text}` on line {line}\n\ninclude a library with its version, e.g. `#include \"jgraef/noise@1.2\"`"
+                )
+            }
+            CompileError::MissingLibrary { include, line } => {
+                format!(
+                    "error: library `{include}` on line {line} isn't available\n\nit wasn't found on the server, or isn't vendored into the project"
+                )
+            }
+            CompileError::NestedInclude { include, line } => {
+                format!(
+                    "error: `{include}` is included on line {line}, but libraries can't include other libraries"
+                )
+            }
+            CompileError::EntryPoint(EntryPointError::Missing { stage }) => {
+                let example = match stage {
+                    ShaderStage::Vertex => "@vertex\nfn vs_main(...) -> ...",
+                    _ => "@fragment\nfn fs_main(...) -> @location(0) vec4f",
+                };
+                format!(
+                    "error: the shader has no {} entry point\n\ndeclare one like this:\n\n{example}",
+                    stage_name(*stage)
+                )
+            }
+            CompileError::EntryPoint(EntryPointError::Ambiguous { stage, names }) => {
+                let default = match stage {
+                    ShaderStage::Vertex => EntryPoints::default().vertex,
+                    _ => EntryPoints::default().fragment,
+                };
+                format!(
+                    "error: the shader has several {} entry points: {}\n\nname the one to draw with `{default}`",
+                    stage_name(*stage),
+                    names.join(", ")
+                )
+            }
+            CompileError::EntryPoint(EntryPointError::Unknown { stage, name, names }) => {
+                format!(
+                    "error: the shader has no {} entry point `{name}`\n\nits {0} entry points are: {}",
+                    stage_name(*stage),
+                    names.join(", ")
+                )
+            }
+        };
+        write!(f, "{error_string}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directives_in_untaken_branches_are_ignored() {
+        let source = "// @define MSAA false\n#if MSAA\n// @requires float32-filterable\n// @depth depth16unorm\n#endif";
+        let preprocessed = preprocess(source, &CompileOptions::default()).unwrap();
+        assert!(preprocessed.requirements.is_empty());
+        assert_eq!(preprocessed.depth, None);
+
+        let options = CompileOptions {
+            defines: [("MSAA".to_owned(), DefineValue::Bool(true))].into(),
+            ..Default::default()
+        };
+        let preprocessed = preprocess(source, &options).unwrap();
+        assert_eq!(preprocessed.requirements.len(), 1);
+        assert_eq!(preprocessed.depth, Some(DepthFormat::Depth16Unorm));
+    }
+}
@@ -0,0 +1,539 @@
+//! Libraries are reusable WGSL modules that are published to the server and
+//! included by shaders with a specific version:
+//!
+//! ```wgsl
+//! #include "jgraef/noise@1.2"
+//! ```
+//!
+//! The version requirement is a prefix of the version, so `1.2` picks the
+//! latest published `1.2.x`, and `1` the latest `1.x.y`.
+//!
+//! Compiling doesn't fetch anything. The included libraries are resolved
+//! beforehand and passed in [`CompileOptions::libraries`], by the player from
+//! the server, and by the CLI from the project's vendored
+//! [`LIBRARIES_DIRECTORY`]. Their code is appended to the shader, so that
+//! locations in diagnostics still match the user's code.
+//!
+//! Projects pin the versions their includes resolved to, with a
+//! [content hash](Library::hash), in the `[lock]` table of their manifest (see
+//! [`ProjectManifest::lock`]). Newer versions are only used when the libraries
+//! are vendored again with `--update`.
+//!
+//! Libraries can't include other libraries.
+//!
+//! [`CompileOptions::libraries`]: crate::CompileOptions::libraries
+//! [`ProjectManifest::lock`]: crate::project::ProjectManifest::lock
+
+use std::{
+    collections::BTreeMap,
+    fmt::{
+        Display,
+        Write,
+    },
+    str::FromStr,
+};
+
+use naga::{
+    valid::{
+        Capabilities,
+        ValidationFlags,
+        Validator,
+    },
+    Module,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+
+use crate::CompileError;
+
+/// Directory in a project to which the CLI vendors the libraries its passes
+/// include. Libraries are stored at `{user}/{name}/{version}.wgsl` in it. The
+/// server stores published libraries the same way.
+pub const LIBRARIES_DIRECTORY: &str = "libraries";
+
+/// Maximum length of user and library names.
+pub const MAX_NAME_LENGTH: usize = 64;
+
+/// Name of a library, e.g. `jgraef/noise`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct LibraryName {
+    pub user: String,
+    pub name: String,
+}
+
+impl LibraryName {
+    pub fn new(user: &str, name: &str) -> Option<Self> {
+        (is_valid_name(user) && is_valid_name(name)).then(|| {
+            Self {
+                user: user.to_owned(),
+                name: name.to_owned(),
+            }
+        })
+    }
+}
+
+impl FromStr for LibraryName {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let (user, name) = s.split_once('/').ok_or(())?;
+        Self::new(user, name).ok_or(())
+    }
+}
+
+impl Display for LibraryName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.user, self.name)
+    }
+}
+
+/// Whether `name` can be used as a user or library name. These are used as
+/// paths, so only lowercase letters, digits, `-` and `_` are allowed.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+/// Version of a published library, e.g. `1.2.3`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl FromStr for Version {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let requirement = s.parse::<VersionRequirement>()?;
+        match requirement {
+            VersionRequirement {
+                major,
+                minor: Some(minor),
+                patch: Some(patch),
+            } => {
+                Ok(Self {
+                    major,
+                    minor,
+                    patch,
+                })
+            }
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl Serialize for Version {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse()
+            .map_err(|()| serde::de::Error::custom(format!("invalid version `{s}`")))
+    }
+}
+
+/// The versions an include accepts, e.g. `1.2` for any `1.2.x`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VersionRequirement {
+    pub major: u32,
+    pub minor: Option<u32>,
+
+    /// Only set if `minor` is.
+    pub patch: Option<u32>,
+}
+
+impl VersionRequirement {
+    pub fn matches(&self, version: &Version) -> bool {
+        self.major == version.major
+            && self.minor.is_none_or(|minor| minor == version.minor)
+            && self.patch.is_none_or(|patch| patch == version.patch)
+    }
+
+    /// The latest of the `versions` that matches.
+    pub fn resolve<'a>(&self, versions: impl IntoIterator<Item = &'a Version>) -> Option<Version> {
+        versions
+            .into_iter()
+            .filter(|version| self.matches(version))
+            .max()
+            .copied()
+    }
+}
+
+impl FromStr for VersionRequirement {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        // `u32::from_str` accepts a leading `+`.
+        let number = |part: &str| {
+            part.chars()
+                .all(|c| c.is_ascii_digit())
+                .then(|| part.parse::<u32>().ok())
+                .flatten()
+                .ok_or(())
+        };
+
+        let mut parts = s.split('.');
+        let major = number(parts.next().ok_or(())?)?;
+        let minor = parts.next().map(number).transpose()?;
+        let patch = parts.next().map(number).transpose()?;
+        if parts.next().is_some() {
+            return Err(());
+        }
+
+        Ok(Self {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
+impl Display for VersionRequirement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.major)?;
+        if let Some(minor) = self.minor {
+            write!(f, ".{minor}")?;
+        }
+        if let Some(patch) = self.patch {
+            write!(f, ".{patch}")?;
+        }
+        Ok(())
+    }
+}
+
+/// An `#include` in a shader.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Include {
+    pub library: LibraryName,
+    pub requirement: VersionRequirement,
+
+    /// Line of the `#include`.
+    pub line: usize,
+}
+
+impl Include {
+    /// The key of the resolved library in
+    /// [`CompileOptions::libraries`](crate::CompileOptions::libraries), e.g.
+    /// `jgraef/noise@1.2`.
+    pub fn key(&self) -> String {
+        format!("{}@{}", self.library, self.requirement)
+    }
+}
+
+/// A published version of a library, with its code.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Library {
+    #[serde(flatten)]
+    pub name: LibraryName,
+    pub version: Version,
+    pub source: String,
+}
+
+impl Library {
+    /// Path of the library relative to a [`LIBRARIES_DIRECTORY`].
+    pub fn path(&self) -> String {
+        format!(
+            "{}/{}/{}.wgsl",
+            self.name.user, self.name.name, self.version
+        )
+    }
+
+    /// Content hash of the library's code, e.g. `sha256:9f86d0...`.
+    pub fn hash(&self) -> String {
+        let mut hash = "sha256:".to_owned();
+        for byte in Sha256::digest(self.source.as_bytes()) {
+            write!(hash, "{byte:02x}").unwrap();
+        }
+        hash
+    }
+}
+
+/// Whether `key` is an [`Include::key`], e.g. `jgraef/noise@1.2`.
+pub fn is_include_key(key: &str) -> bool {
+    key.split_once('@').is_some_and(|(library, requirement)| {
+        library.parse::<LibraryName>().is_ok()
+            && requirement.parse::<VersionRequirement>().is_ok()
+    })
+}
+
+/// Finds the `#include` lines in the shader source.
+pub fn parse_includes(source: &str) -> Result<Vec<Include>, CompileError> {
+    let mut includes = vec![];
+
+    for (line_index, text) in source.lines().enumerate() {
+        let Some(argument) = include_argument(text)
+        else {
+            continue;
+        };
+        let line = line_index + 1;
+
+        let include = argument
+            .strip_prefix('"')
+            .and_then(|argument| argument.strip_suffix('"'))
+            .and_then(|argument| argument.split_once('@'))
+            .and_then(|(library, requirement)| {
+                Some(Include {
+                    library: library.parse().ok()?,
+                    requirement: requirement.parse().ok()?,
+                    line,
+                })
+            })
+            .ok_or_else(|| {
+                CompileError::InvalidInclude {
+                    text: argument.to_owned(),
+                    line,
+                }
+            })?;
+        includes.push(include);
+    }
+
+    Ok(includes)
+}
+
+/// The text after `#include`, if the line is one.
+fn include_argument(line: &str) -> Option<&str> {
+    let argument = line
+        .trim_start()
+        .strip_prefix('#')?
+        .trim_start()
+        .strip_prefix("include")?;
+
+    // don't match e.g. `#includes`
+    if !argument.is_empty() && !argument.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let argument = argument
+        .find("//")
+        .map_or(argument, |start| &argument[..start]);
+    Some(argument.trim())
+}
+
+/// Comments out the `#include` lines and appends the code of the included
+/// libraries, which must be in `libraries`. Each library is included once,
+/// even if several lines include it.
+pub(crate) fn include_libraries(
+    source: &str,
+    includes: &[Include],
+    libraries: &BTreeMap<String, Library>,
+) -> Result<String, CompileError> {
+    if includes.is_empty() {
+        return Ok(source.to_owned());
+    }
+
+    let mut output = source
+        .split('\n')
+        .map(|text| {
+            if include_argument(text).is_some() {
+                format!("// {text}")
+            }
+            else {
+                text.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut included = vec![];
+    for include in includes {
+        let library = libraries.get(&include.key()).ok_or_else(|| {
+            CompileError::MissingLibrary {
+                include: include.key(),
+                line: include.line,
+            }
+        })?;
+
+        if included.contains(&(&library.name, library.version)) {
+            continue;
+        }
+        included.push((&library.name, library.version));
+
+        write!(
+            output,
+            "\n\n// included from {}@{}\n{}",
+            library.name, library.version, library.source
+        )
+        .unwrap();
+    }
+
+    Ok(output)
+}
+
+/// Inlines the libraries that the source includes, so that it compiles
+/// without fetching them, e.g. in exports that must work offline. Like when
+/// compiling, lines of the source stay where they are.
+pub fn inline_libraries(
+    source: &str,
+    libraries: &BTreeMap<String, Library>,
+) -> Result<String, CompileError> {
+    let includes = parse_includes(source)?;
+    include_libraries(source, &includes, libraries)
+}
+
+/// Checks a library before it's published. Libraries must be valid WGSL
+/// modules by themselves, and can't include other libraries.
+pub fn validate_library(source: &str) -> Result<Module, CompileError> {
+    if let Some(include) = parse_includes(source)?.first() {
+        return Err(CompileError::NestedInclude {
+            include: include.key(),
+            line: include.line,
+        });
+    }
+
+    let module = naga::front::wgsl::parse_str(source).map_err(|parse_error| {
+        CompileError::Parse {
+            parse_error: Box::new(parse_error),
+            code: source.to_owned(),
+        }
+    })?;
+
+    Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|validation_error| {
+            CompileError::Validate {
+                validation_error: Box::new(validation_error),
+                code: source.to_owned(),
+            }
+        })?;
+
+    Ok(module)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    fn library(name: &str, version: &str, source: &str) -> Library {
+        Library {
+            name: name.parse().unwrap(),
+            version: version.parse().unwrap(),
+            source: source.to_owned(),
+        }
+    }
+
+    #[test]
+    fn invalid_version_requirements_are_rejected() {
+        for invalid in ["", "+1", "1.+2", "1..2", "1.", ".1", "1.2.3.4", "-1", "1.x"] {
+            assert_eq!(
+                invalid.parse::<VersionRequirement>(),
+                Err(()),
+                "{invalid:?}"
+            );
+        }
+
+        assert_eq!(
+            "1.2".parse(),
+            Ok(VersionRequirement {
+                major: 1,
+                minor: Some(2),
+                patch: None,
+            })
+        );
+        assert_eq!("1.2".parse::<Version>(), Err(()));
+    }
+
+    #[test]
+    fn latest_matching_patch_wins() {
+        let versions = ["1.2.0", "1.2.9", "1.2.10", "1.3.0", "2.0.0"].map(version);
+        let resolve = |requirement: &str| {
+            requirement
+                .parse::<VersionRequirement>()
+                .unwrap()
+                .resolve(&versions)
+        };
+
+        assert_eq!(resolve("1.2"), Some(version("1.2.10")));
+        assert_eq!(resolve("1"), Some(version("1.3.0")));
+        assert_eq!(resolve("1.2.9"), Some(version("1.2.9")));
+        assert_eq!(resolve("1.4"), None);
+        assert_eq!(resolve("3"), None);
+
+        let requirement = "1.2".parse::<VersionRequirement>().unwrap();
+        assert!(requirement.matches(&version("1.2.0")));
+        assert!(!requirement.matches(&version("1.20.0")));
+        assert!(!requirement.matches(&version("2.2.0")));
+    }
+
+    #[test]
+    fn include_lines_are_recognized() {
+        assert_eq!(
+            include_argument("#include \"jgraef/noise@1.2\""),
+            Some("\"jgraef/noise@1.2\"")
+        );
+        assert_eq!(
+            include_argument("  # include \"jgraef/noise@1.2\" // for fbm"),
+            Some("\"jgraef/noise@1.2\"")
+        );
+        assert_eq!(include_argument("#includes \"jgraef/noise@1.2\""), None);
+        assert_eq!(include_argument("// #include \"jgraef/noise@1.2\""), None);
+    }
+
+    #[test]
+    fn libraries_are_included_once() {
+        let source = "#include \"jgraef/noise@1.2\"\nfn a() {}\n#include \"jgraef/noise@1.2.3\"";
+        let includes = parse_includes(source).unwrap();
+        assert_eq!(includes.len(), 2);
+        assert_eq!(includes[1].line, 3);
+
+        let noise = library("jgraef/noise", "1.2.3", "fn noise() -> f32 { return 0.5; }");
+        let libraries = includes
+            .iter()
+            .map(|include| (include.key(), noise.clone()))
+            .collect();
+        let output = include_libraries(source, &includes, &libraries).unwrap();
+
+        // the lines of the source stay where they are.
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "// #include \"jgraef/noise@1.2\"");
+        assert_eq!(lines[1], "fn a() {}");
+        assert_eq!(lines[2], "// #include \"jgraef/noise@1.2.3\"");
+        assert_eq!(output.matches(&noise.source).count(), 1);
+    }
+
+    #[test]
+    fn missing_libraries_are_reported() {
+        let source = "fn a() {}\n#include \"jgraef/noise@1.2\"";
+        let includes = parse_includes(source).unwrap();
+        let error = include_libraries(source, &includes, &BTreeMap::new()).unwrap_err();
+        assert!(matches!(
+            error,
+            CompileError::MissingLibrary { include, line: 2 } if include == "jgraef/noise@1.2"
+        ));
+    }
+
+    #[test]
+    fn libraries_cant_include_libraries() {
+        let error = validate_library("#include \"jgraef/noise@1\"\nfn a() {}").unwrap_err();
+        assert!(matches!(
+            error,
+            CompileError::NestedInclude { include, line: 1 } if include == "jgraef/noise@1"
+        ));
+
+        assert!(validate_library("fn a() -> f32 { return 1.0; }").is_ok());
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt::Display;
+
+use naga::{
+    AddressSpace,
+    Binding,
+    BuiltIn,
+    ImageClass,
+    ImageDimension,
+    Module,
+    ShaderStage,
+    SourceLocation,
+    Span,
+    TypeInner,
+};
+
+/// A construct that is valid WGSL, but will fail on some backend.
+#[derive(Clone, Debug)]
+pub struct Lint {
+    pub message: String,
+    pub location: Option<SourceLocation>,
+}
+
+impl Lint {
+    fn new(message: impl ToString, span: Span, code: &str) -> Self {
+        Self {
+            message: message.to_string(),
+            location: span.is_defined().then(|| span.location(code)),
+        }
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "warning: {}", self.message)?;
+        if let Some(location) = &self.location {
+            write!(
+                f,
+                "\n  --> {}:{}",
+                location.line_number, location.line_position
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Flags constructs that are known to fail on the WebGL/GLES backend.
+///
+/// This is meant to run while on WebGPU, so that shaders that are shared don't
+/// surprise users that fall back to WebGL.
+pub fn lint_webgl_compat(module: &Module, code: &str) -> Vec<Lint> {
+    let mut lints = vec![];
+
+    for (handle, global) in module.global_variables.iter() {
+        if let AddressSpace::Storage { .. } = global.space {
+            lints.push(Lint::new(
+                format!(
+                    "storage buffer `{}` is not supported on WebGL",
+                    global.name.as_deref().unwrap_or("_")
+                ),
+                module.global_variables.get_span(handle),
+                code,
+            ));
+        }
+    }
+
+    for (handle, ty) in module.types.iter() {
+        let message = match &ty.inner {
+            TypeInner::Image {
+                class: ImageClass::Storage { format, .. },
+                ..
+            } => format!("storage texture with format `{format:?}` is not supported on WebGL"),
+            TypeInner::Image {
+                dim: ImageDimension::Cube,
+                arrayed: true,
+                ..
+            } => "cube map arrays are not supported on WebGL".to_owned(),
+            TypeInner::Image {
+                class: ImageClass::Depth { multi: true },
+                ..
+            } => "multisampled depth textures are not supported on WebGL".to_owned(),
+            TypeInner::BindingArray { .. } => {
+                "binding arrays are not supported on WebGL".to_owned()
+            }
+            _ => continue,
+        };
+        lints.push(Lint::new(message, module.types.get_span(handle), code));
+    }
+
+    for entry_point in &module.entry_points {
+        if entry_point.stage == ShaderStage::Compute {
+            lints.push(Lint::new(
+                format!(
+                    "compute entry point `{}` is not supported on WebGL",
+                    entry_point.name
+                ),
+                Span::UNDEFINED,
+                code,
+            ));
+        }
+
+        let mut bindings = vec![];
+        for argument in &entry_point.function.arguments {
+            collect_bindings(
+                module,
+                argument.binding.as_ref(),
+                argument.ty,
+                &mut bindings,
+            );
+        }
+        if let Some(result) = &entry_point.function.result {
+            collect_bindings(module, result.binding.as_ref(), result.ty, &mut bindings);
+        }
+        for binding in bindings {
+            if let Binding::BuiltIn(builtin @ (BuiltIn::SampleIndex | BuiltIn::SampleMask)) =
+                binding
+            {
+                lints.push(Lint::new(
+                    format!(
+                        "builtin `{builtin:?}` in entry point `{}` is not supported on WebGL",
+                        entry_point.name
+                    ),
+                    Span::UNDEFINED,
+                    code,
+                ));
+            }
+        }
+    }
+
+    lints
+}
+
+/// Collects the binding of an entry point argument or result, or the bindings
+/// of its members, if it's a struct.
+fn collect_bindings<'a>(
+    module: &'a Module,
+    binding: Option<&'a Binding>,
+    ty: naga::Handle<naga::Type>,
+    bindings: &mut Vec<&'a Binding>,
+) {
+    if let Some(binding) = binding {
+        bindings.push(binding);
+    }
+    else if let TypeInner::Struct { members, .. } = &module.types[ty].inner {
+        bindings.extend(members.iter().filter_map(|member| member.binding.as_ref()));
+    }
+}
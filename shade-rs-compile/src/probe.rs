@@ -0,0 +1,277 @@
+use naga::{
+    front::wgsl::parse_str,
+    valid::{
+        Capabilities,
+        ModuleInfo,
+        ValidationFlags,
+        Validator,
+    },
+    Module,
+    ScalarKind,
+    TypeInner,
+    VectorSize,
+};
+
+use crate::{
+    directive::find_directive_lines,
+    CompileError,
+    CompiledShader,
+};
+
+/// Binding of the storage buffer the probed values are written to, with one
+/// element of [`PROBE_VALUE_SIZE`] bytes per probe:
+///
+/// ```wgsl
+/// struct ShadeRsProbeValue {
+///     // the value's components, bitcast to `u32`.
+///     bits: vec4u,
+///     // set if the probe was reached.
+///     written: u32,
+/// }
+/// ```
+///
+/// A shader can probe the value of an expression with a directive:
+///
+/// ```wgsl
+/// let d = scene(p);
+/// // @probe d
+/// ```
+///
+/// The directive is replaced with code that writes the value for the pixel
+/// under the mouse into the buffer. It has to be inside a function that is
+/// only called from the fragment shader, and the expression can use anything
+/// that is in scope there. Inside a loop, the value of the last iteration is
+/// probed.
+///
+/// Only scalars and vectors can be probed.
+pub const PROBE_BINDING: u32 = 5;
+
+/// Size of a probe's value in the buffer at [`PROBE_BINDING`], including
+/// padding.
+pub const PROBE_VALUE_SIZE: u64 = 32;
+
+const DIRECTIVE: &str = "@probe";
+
+/// Prefix of the names that probed expressions are bound to, to find out
+/// their types.
+const PROBE_PREFIX: &str = "shade_rs_probe_";
+
+const PROBE_PASS: &str = include_str!("probe/pass.wgsl");
+
+/// A shader that was instrumented to write the values of its probes to the
+/// buffer at [`PROBE_BINDING`].
+#[derive(Clone, Debug)]
+pub struct ProbeShader {
+    pub module: Module,
+
+    /// The probes, in the order of their values in the buffer.
+    pub probes: Vec<Probe>,
+}
+
+/// An expression that is probed with a `@probe` directive.
+#[derive(Clone, Debug)]
+pub struct Probe {
+    /// 1-based line of the directive.
+    pub line: usize,
+    pub expression: String,
+    pub ty: ProbeType,
+}
+
+impl Probe {
+    /// Formats the value that was read from the buffer.
+    pub fn format_value(&self, bits: [u32; 4]) -> String {
+        let components = bits[..usize::from(self.ty.components)]
+            .iter()
+            .map(|&bits| {
+                match self.ty.kind {
+                    ProbeKind::Float => f32::from_bits(bits).to_string(),
+                    ProbeKind::Sint => (bits as i32).to_string(),
+                    ProbeKind::Uint => bits.to_string(),
+                    ProbeKind::Bool => (bits != 0).to_string(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if components.len() == 1 {
+            components.into_iter().next().unwrap()
+        }
+        else {
+            format!("({})", components.join(", "))
+        }
+    }
+}
+
+/// Type of a probed expression.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProbeType {
+    pub kind: ProbeKind,
+
+    /// 1 for scalars.
+    pub components: u8,
+}
+
+impl ProbeType {
+    fn from_inner(inner: &TypeInner) -> Option<Self> {
+        let (scalar, components) = match inner {
+            TypeInner::Scalar(scalar) => (scalar, 1),
+            TypeInner::Vector { size, scalar } => {
+                let components = match size {
+                    VectorSize::Bi => 2,
+                    VectorSize::Tri => 3,
+                    VectorSize::Quad => 4,
+                };
+                (scalar, components)
+            }
+            _ => return None,
+        };
+
+        let kind = match scalar.kind {
+            ScalarKind::Float if scalar.width == 4 => ProbeKind::Float,
+            ScalarKind::Sint if scalar.width == 4 => ProbeKind::Sint,
+            ScalarKind::Uint if scalar.width == 4 => ProbeKind::Uint,
+            ScalarKind::Bool => ProbeKind::Bool,
+            _ => return None,
+        };
+
+        Some(Self { kind, components })
+    }
+
+    /// WGSL that converts `expression` to the `vec4u` that is written to the
+    /// buffer.
+    fn to_bits(self, expression: &str) -> String {
+        let bits = match (self.kind, self.components) {
+            (ProbeKind::Uint, _) => expression.to_owned(),
+            (ProbeKind::Bool, 1) => format!("u32({expression})"),
+            (ProbeKind::Bool, n) => format!("vec{n}u({expression})"),
+            (_, 1) => format!("bitcast<u32>({expression})"),
+            (_, n) => format!("bitcast<vec{n}u>({expression})"),
+        };
+        let padding = ", 0u".repeat(4 - usize::from(self.components));
+        format!("vec4u({bits}{padding})")
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProbeKind {
+    Float,
+    Sint,
+    Uint,
+    Bool,
+}
+
+/// Builds the instrumented shader for the `@probe` directives in the shader,
+/// or returns `None` if it has none.
+pub fn compile_probe_shader(shader: &CompiledShader) -> Result<Option<ProbeShader>, CompileError> {
+    let directives = find_directive_lines(&shader.source, DIRECTIVE).collect::<Vec<_>>();
+    if directives.is_empty() {
+        return Ok(None);
+    }
+    if let Some((line, _)) = directives
+        .iter()
+        .find(|(_, expression)| expression.is_empty())
+    {
+        return Err(CompileError::EmptyProbe { line: *line });
+    }
+
+    // first bind each expression to a name, to find out its type.
+    let code = replace_directives(&shader.source, &directives, |index, expression| {
+        format!("let {PROBE_PREFIX}{index} = ({expression});")
+    });
+    let (module, module_info) = parse_and_validate(code)?;
+
+    let probes = directives
+        .iter()
+        .enumerate()
+        .map(|(index, (line, expression))| {
+            let ty = find_named_type(&module, &module_info, &format!("{PROBE_PREFIX}{index}"))
+                .ok_or_else(|| {
+                    CompileError::UnsupportedProbe {
+                        expression: (*expression).to_owned(),
+                        line: *line,
+                    }
+                })?;
+            Ok(Probe {
+                line: *line,
+                expression: (*expression).to_owned(),
+                ty,
+            })
+        })
+        .collect::<Result<Vec<_>, CompileError>>()?;
+
+    // then write the values to the buffer.
+    let code = replace_directives(&shader.source, &directives, |index, expression| {
+        let bits = probes[index].ty.to_bits(&format!("({expression})"));
+        format!("shade_rs_probe({index}u, {bits});")
+    });
+    let (module, _) = parse_and_validate(format!("{code}\n{PROBE_PASS}"))?;
+
+    Ok(Some(ProbeShader { module, probes }))
+}
+
+/// Replaces the lines of the directives with code. Lines stay where they are,
+/// so that diagnostics still match the user's code.
+fn replace_directives(
+    source: &str,
+    directives: &[(usize, &str)],
+    mut replacement: impl FnMut(usize, &str) -> String,
+) -> String {
+    let mut directives = directives.iter().enumerate().peekable();
+    let mut lines = vec![];
+
+    for (line_index, line) in source.lines().enumerate() {
+        match directives.next_if(|(_, (line, _))| *line == line_index + 1) {
+            Some((index, (_, expression))) => lines.push(replacement(index, expression)),
+            None => lines.push(line.to_owned()),
+        }
+    }
+
+    lines.join("\n")
+}
+
+fn parse_and_validate(code: String) -> Result<(Module, ModuleInfo), CompileError> {
+    let module = parse_str(&code).map_err(|parse_error| {
+        CompileError::Parse {
+            parse_error: Box::new(parse_error),
+            code: code.clone(),
+        }
+    })?;
+    let module_info = Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|validation_error| {
+            CompileError::Validate {
+                validation_error: Box::new(validation_error),
+                code,
+            }
+        })?;
+    Ok((module, module_info))
+}
+
+/// Finds the type of a `let` binding with the given name in any function.
+fn find_named_type(module: &Module, module_info: &ModuleInfo, name: &str) -> Option<ProbeType> {
+    let functions = module
+        .functions
+        .iter()
+        .map(|(handle, function)| (function, &module_info[handle]))
+        .chain(
+            module
+                .entry_points
+                .iter()
+                .enumerate()
+                .map(|(index, entry_point)| {
+                    (&entry_point.function, module_info.get_entry_point(index))
+                }),
+        );
+
+    for (function, function_info) in functions {
+        let named = function
+            .named_expressions
+            .iter()
+            .find(|(_, expression_name)| *expression_name == name);
+        if let Some((expression, _)) = named {
+            let inner = function_info[*expression].ty.inner_with(&module.types);
+            return ProbeType::from_inner(inner);
+        }
+    }
+
+    None
+}
@@ -0,0 +1,16 @@
+// Probe pass. This is appended to shaders with `@probe` directives, whose lines
+// are replaced with calls to `shade_rs_probe`. The pass is only drawn for the
+// pixel under the mouse, so that fragment's values end up in the buffer.
+
+struct ShadeRsProbeValue {
+    bits: vec4u,
+    // set if the probe was reached. The buffer is cleared before each pass.
+    written: u32,
+}
+
+@group(0) @binding(5)
+var<storage, read_write> shade_rs_probes: array<ShadeRsProbeValue>;
+
+fn shade_rs_probe(index: u32, bits: vec4u) {
+    shade_rs_probes[index] = ShadeRsProbeValue(bits, 1u);
+}
@@ -0,0 +1,709 @@
+//! The on-disk project format.
+//!
+//! A project is a directory with a `shader.toml` manifest, the WGSL sources of
+//! its passes and any assets they use. Paths in the manifest are relative to
+//! the project directory. For example:
+//!
+//! ```toml
+//! [shader]
+//! title = "Plasma"
+//! author = "jgraef"
+//! tags = ["2d", "animated"]
+//!
+//! [[pass]]
+//! name = "background"
+//! source = "background.wgsl"
+//!
+//! [[pass]]
+//! name = "plasma"
+//! source = "plasma.wgsl"
+//! blend = "add"
+//! opacity = 0.5
+//!
+//! [[channel]]
+//! slot = 0
+//! image = "assets/noise.png"
+//!
+//! [[channel]]
+//! slot = 1
+//! pass = "background"
+//!
+//! [params]
+//! speed = 2.0
+//! tint = [1.0, 0.5, 0.0]
+//!
+//! [defines]
+//! QUALITY = 2
+//! SHADOWS = false
+//!
+//! [lock]
+//! "jgraef/noise@1.2" = { version = "1.2.3", hash = "sha256:9f86d0..." }
+//! ```
+
+use std::{
+    collections::{
+        BTreeMap,
+        HashSet,
+    },
+    fmt::Display,
+    ops::Range,
+    path::{
+        Component,
+        Path,
+        PathBuf,
+    },
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    defines::{
+        is_identifier,
+        DefineValue,
+    },
+    library::{
+        is_include_key,
+        Library,
+        Version,
+    },
+    CompileOptions,
+};
+
+/// File name of the manifest in a project directory.
+pub const MANIFEST_FILE_NAME: &str = "shader.toml";
+
+/// Number of texture channels that shaders can bind.
+pub const NUM_CHANNELS: usize = 4;
+
+/// Contents of a `shader.toml`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectManifest {
+    #[serde(default)]
+    pub shader: ShaderMetadata,
+
+    /// Passes from bottom to top. Each one is drawn as a layer on top of the
+    /// previous ones.
+    #[serde(default, rename = "pass")]
+    pub passes: Vec<PassManifest>,
+
+    /// Inputs bound to the texture channels.
+    #[serde(default, rename = "channel", skip_serializing_if = "Vec::is_empty")]
+    pub channels: Vec<ChannelManifest>,
+
+    /// Mesh that is drawn by shaders that take vertex attributes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<PathBuf>,
+
+    /// Initial values of the bottom pass's parameters, by field name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, ParamValue>,
+
+    /// Values of the passes' [defines](DefineValue), by name. They replace the
+    /// defaults from the passes' `@define` directives.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub defines: BTreeMap<String, DefineValue>,
+
+    /// The libraries that the passes' includes resolved to when they were
+    /// vendored, by [`Include::key`](crate::library::Include::key).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub lock: BTreeMap<String, LockedLibrary>,
+}
+
+impl ProjectManifest {
+    /// Parses and [validates](Self::validate) a manifest.
+    pub fn from_toml(toml: &str) -> Result<Self, Vec<ManifestError>> {
+        let manifest: Self =
+            toml::from_str(toml).map_err(|error| vec![ManifestError::from_toml(&error, toml)])?;
+
+        let errors = manifest.validate(toml);
+        if errors.is_empty() {
+            Ok(manifest)
+        }
+        else {
+            Err(errors)
+        }
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("failed to serialize project manifest")
+    }
+
+    /// Options that the project's passes are compiled with.
+    pub fn compile_options(&self) -> CompileOptions {
+        CompileOptions {
+            defines: self.defines.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Checks what the types of the manifest can't express, e.g. that pass
+    /// names are unique and that paths stay inside the project directory.
+    ///
+    /// `toml` is the source the manifest was parsed from. It's only used to
+    /// locate the errors.
+    pub fn validate(&self, toml: &str) -> Vec<ManifestError> {
+        let mut errors = vec![];
+        let mut error = |key: String, message: String| {
+            errors.push(ManifestError::at_key(key, message, toml));
+        };
+
+        if self.passes.is_empty() {
+            error(
+                "pass".to_owned(),
+                "the project has no passes. add one with a `[[pass]]` table".to_owned(),
+            );
+        }
+
+        let mut pass_names = HashSet::new();
+        for (index, pass) in self.passes.iter().enumerate() {
+            if pass.name.is_empty() {
+                error(
+                    format!("pass[{index}].name"),
+                    format!("pass {index} has an empty name"),
+                );
+            }
+            else if !pass_names.insert(&pass.name) {
+                error(
+                    format!("pass[{index}].name"),
+                    format!("there is more than one pass named `{}`", pass.name),
+                );
+            }
+
+            if let Err(message) = check_relative_path(&pass.source) {
+                error(
+                    format!("pass[{index}].source"),
+                    format!("source of pass `{}` {message}", pass.name),
+                );
+            }
+
+            if !(0.0..=1.0).contains(&pass.opacity) {
+                error(
+                    format!("pass[{index}].opacity"),
+                    format!(
+                        "opacity of pass `{}` must be between 0 and 1, but is {}",
+                        pass.name, pass.opacity
+                    ),
+                );
+            }
+        }
+
+        let mut slots = HashSet::new();
+        let mut unknown_passes = vec![];
+        for (index, channel) in self.channels.iter().enumerate() {
+            if channel.slot >= NUM_CHANNELS {
+                error(
+                    format!("channel[{index}].slot"),
+                    format!(
+                        "channel slot {} doesn't exist. slots are 0 to {}",
+                        channel.slot,
+                        NUM_CHANNELS - 1
+                    ),
+                );
+            }
+            else if !slots.insert(channel.slot) {
+                error(
+                    format!("channel[{index}].slot"),
+                    format!("channel slot {} is bound more than once", channel.slot),
+                );
+            }
+
+            match &channel.source {
+                ChannelSource::Image(path) => {
+                    if let Err(message) = check_relative_path(path) {
+                        error(
+                            format!("channel[{index}].image"),
+                            format!("image of channel {} {message}", channel.slot),
+                        );
+                    }
+                }
+                ChannelSource::Pass(name) if !pass_names.contains(name) => {
+                    unknown_passes.push((index, channel.slot, name));
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(mesh) = &self.mesh {
+            if let Err(message) = check_relative_path(mesh) {
+                error("mesh".to_owned(), format!("mesh {message}"));
+            }
+        }
+
+        for (name, value) in &self.params {
+            if let ParamValue::Vector(components) = value {
+                if !(2..=4).contains(&components.len()) {
+                    error(
+                        format!("params.{name}"),
+                        format!(
+                            "parameter `{name}` has {} components, but vectors have 2 to 4",
+                            components.len()
+                        ),
+                    );
+                }
+            }
+        }
+
+        for name in self.defines.keys() {
+            if !is_identifier(name) {
+                error(
+                    format!("defines.{name}"),
+                    format!("define `{name}` isn't a valid name"),
+                );
+            }
+        }
+
+        for key in self.lock.keys() {
+            if !is_include_key(key) {
+                error(
+                    format!("lock.{key}"),
+                    format!("`{key}` isn't an include like `user/library@1.2`"),
+                );
+            }
+        }
+
+        // the closure above borrows `errors` until its last use.
+        for (index, slot, name) in unknown_passes {
+            let mut unknown = ManifestError::at_key(
+                format!("channel[{index}].pass"),
+                format!("channel {slot} reads pass `{name}`, but there is no pass with that name"),
+                toml,
+            );
+            unknown.suggestion =
+                closest_name(name, pass_names.iter().map(|pass_name| pass_name.as_str()));
+            errors.push(unknown);
+        }
+
+        errors
+    }
+}
+
+/// Replaces the `[lock]` table of a manifest, keeping the formatting and
+/// comments of the rest of it.
+pub fn write_lock(
+    toml: &str,
+    lock: &BTreeMap<String, LockedLibrary>,
+) -> Result<String, ManifestError> {
+    let mut document = toml
+        .parse::<toml_edit::DocumentMut>()
+        .map_err(|error| ManifestError::from_toml_edit(&error))?;
+
+    if lock.is_empty() {
+        document.remove("lock");
+    }
+    else {
+        let mut table = toml_edit::Table::new();
+        for (key, locked) in lock {
+            let mut entry = toml_edit::InlineTable::new();
+            entry.insert("version", locked.version.to_string().into());
+            entry.insert("hash", locked.hash.clone().into());
+            table.insert(key, toml_edit::value(entry));
+        }
+        document.insert("lock", toml_edit::Item::Table(table));
+    }
+
+    Ok(document.to_string())
+}
+
+/// Paths in the manifest must point into the project directory.
+fn check_relative_path(path: &Path) -> Result<(), &'static str> {
+    if path.as_os_str().is_empty() {
+        Err("is empty")
+    }
+    else if !path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        Err("must be a path relative to the project directory, without `..`")
+    }
+    else {
+        Ok(())
+    }
+}
+
+/// An error in a project manifest.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub struct ManifestError {
+    pub message: String,
+
+    /// Path of the key the error is about, e.g. `pass[1].opacity`.
+    pub key: Option<String>,
+
+    /// Byte range in the manifest.
+    pub span: Option<Range<usize>>,
+
+    /// A known name that is similar to a misspelled one.
+    pub suggestion: Option<String>,
+}
+
+impl ManifestError {
+    fn from_toml(error: &toml::de::Error, toml: &str) -> Self {
+        let message = error.message().trim_end().to_owned();
+        let span = error.span();
+        Self {
+            key: span
+                .as_ref()
+                .and_then(|span| key_at(toml, span.start)),
+            suggestion: suggest_name(&message),
+            message,
+            span,
+        }
+    }
+
+    fn from_toml_edit(error: &toml_edit::TomlError) -> Self {
+        Self {
+            message: error.message().trim_end().to_owned(),
+            key: None,
+            span: error.span(),
+            suggestion: None,
+        }
+    }
+
+    fn at_key(key: String, message: String, toml: &str) -> Self {
+        Self {
+            span: span_of_key(toml, &key),
+            key: Some(key),
+            message,
+            suggestion: None,
+        }
+    }
+
+    /// 1-based line and column of the start of the error.
+    pub fn location(&self, toml: &str) -> Option<(usize, usize)> {
+        let offset = self.span.as_ref()?.start.min(toml.len());
+        let before = &toml[..offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |index| index + 1) + 1;
+        Some((line, column))
+    }
+
+    /// Formats the error with the line of the manifest it's in.
+    pub fn render(&self, toml: &str, path: &str) -> String {
+        let mut rendered = format!("error: {}\n", self.message);
+
+        if let Some((line, column)) = self.location(toml) {
+            let source_line = toml.lines().nth(line - 1).unwrap_or_default();
+            let length = self
+                .span
+                .as_ref()
+                .map_or(1, |span| span.len())
+                .clamp(1, source_line.len().saturating_sub(column - 1).max(1));
+            let gutter = " ".repeat(line.to_string().len());
+            rendered.push_str(&format!(
+                "{gutter}--> {path}:{line}:{column}\n{gutter} |\n{line} | {source_line}\n{gutter} | {}{}\n",
+                " ".repeat(column - 1),
+                "^".repeat(length),
+            ));
+        }
+        else if let Some(key) = &self.key {
+            rendered.push_str(&format!("  --> {path}: `{key}`\n"));
+        }
+
+        if let Some(suggestion) = &self.suggestion {
+            rendered.push_str(&format!("\nhelp: did you mean `{suggestion}`?\n"));
+        }
+
+        rendered
+    }
+}
+
+impl Display for ManifestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(key) = &self.key {
+            write!(f, "`{key}`: ")?;
+        }
+        write!(f, "{}", self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean `{suggestion}`?)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Finds the span of a key path like `pass[1].opacity`.
+fn span_of_key(toml: &str, key: &str) -> Option<Range<usize>> {
+    let document = toml_edit::ImDocument::parse(toml).ok()?;
+    let mut item = document.as_item();
+    let mut span = None;
+
+    for segment in key.split('.') {
+        let (name, index) = match segment.split_once('[') {
+            Some((name, index)) => (name, index.trim_end_matches(']').parse::<usize>().ok()),
+            None => (segment, None),
+        };
+        item = item.get(name)?;
+        span = item.span().or(span);
+        if let Some(index) = index {
+            item = item.get(index)?;
+            span = item.span().or(span);
+        }
+    }
+
+    span
+}
+
+/// Finds the key path at `offset`, from the table headers and the key on the
+/// line.
+fn key_at(toml: &str, offset: usize) -> Option<String> {
+    let before = toml.get(..offset)?;
+    let mut table = None;
+    let mut counts = BTreeMap::<&str, usize>::new();
+
+    for line in before.lines() {
+        let line = line.trim();
+        if let Some(name) = line
+            .strip_prefix("[[")
+            .and_then(|line| line.split_once("]]"))
+            .map(|(name, _)| name.trim())
+        {
+            let count = counts.entry(name).or_default();
+            table = Some(format!("{name}[{count}]"));
+            *count += 1;
+        }
+        else if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.split_once(']'))
+            .map(|(name, _)| name.trim())
+        {
+            table = Some(name.to_owned());
+        }
+    }
+
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    let line = toml[line_start..].lines().next().unwrap_or_default();
+    let key = line
+        .split_once('=')
+        .map(|(key, _)| key.trim())
+        .filter(|key| !key.is_empty() && !key.starts_with('['));
+
+    match (table, key) {
+        (Some(table), Some(key)) => Some(format!("{table}.{key}")),
+        (table, key) => table.or(key.map(ToOwned::to_owned)),
+    }
+}
+
+/// Suggests a name for serde's "unknown field" and "unknown variant" errors,
+/// which list the expected names.
+fn suggest_name(message: &str) -> Option<String> {
+    let (_, rest) = message
+        .split_once("unknown field `")
+        .or_else(|| message.split_once("unknown variant `"))?;
+    let (unknown, rest) = rest.split_once('`')?;
+    let (_, expected) = rest.split_once("expected ")?;
+
+    closest_name(unknown, expected.split('`').skip(1).step_by(2))
+}
+
+/// The name that is closest to a misspelled one, if any is close enough.
+fn closest_name<'a>(unknown: &str, names: impl Iterator<Item = &'a str>) -> Option<String> {
+    names
+        .map(|name| (edit_distance(unknown, name), name))
+        .filter(|(distance, name)| *distance <= (name.len() / 3).max(1))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name.to_owned())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a != *b);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Describes the shader, e.g. for listings.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShaderMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PassManifest {
+    pub name: String,
+
+    /// Path to the WGSL source.
+    pub source: PathBuf,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub blend: PassBlendMode,
+
+    #[serde(default = "default_opacity", skip_serializing_if = "is_default_opacity")]
+    pub opacity: f32,
+}
+
+/// How a pass is combined with the passes below it. These are the blend modes
+/// of the UI's layers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PassBlendMode {
+    #[default]
+    Normal,
+    Add,
+    Multiply,
+    Screen,
+}
+
+// `deny_unknown_fields` doesn't work together with `flatten`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChannelManifest {
+    /// Index of the channel, i.e. `channel0` to `channel3`.
+    pub slot: usize,
+
+    #[serde(flatten)]
+    pub source: ChannelSource,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChannelSource {
+    /// Path to an image file.
+    Image(PathBuf),
+
+    /// Text that is rendered into the channel.
+    Text(String),
+
+    /// Output of another pass in the current frame, by name. The pass is
+    /// rendered before the passes that read it, so passes must not depend on
+    /// each other in a cycle. Feedback effects read the previous frame
+    /// instead.
+    Pass(String),
+}
+
+/// The exact version of a library that an include is pinned to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockedLibrary {
+    pub version: Version,
+
+    /// [Content hash](Library::hash) of the library's code.
+    pub hash: String,
+}
+
+impl LockedLibrary {
+    pub fn new(library: &Library) -> Self {
+        Self {
+            version: library.version,
+            hash: library.hash(),
+        }
+    }
+
+    /// Whether `library` is the locked version, with the same code.
+    pub fn matches(&self, library: &Library) -> bool {
+        self.version == library.version && self.hash == library.hash()
+    }
+}
+
+/// Value of a parameter field. Vectors are given as arrays of their
+/// components.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ParamValue {
+    Scalar(f64),
+    Vector(Vec<f64>),
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+fn is_default_opacity(opacity: &f32) -> bool {
+    *opacity == default_opacity()
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(toml: &str) -> Vec<ManifestError> {
+        ProjectManifest::from_toml(toml).unwrap_err()
+    }
+
+    #[test]
+    fn unknown_keys_get_a_suggestion() {
+        let toml = "[[pass]]\nname = \"main\"\nsoruce = \"main.wgsl\"\n";
+        let [error] = errors(toml).try_into().unwrap();
+        assert!(error.message.contains("unknown field `soruce`"));
+        assert_eq!(error.key.as_deref(), Some("pass[0].soruce"));
+        assert_eq!(error.suggestion.as_deref(), Some("source"));
+        assert_eq!(error.location(toml), Some((3, 1)));
+
+        let toml = "[[pass]]\nname = \"main\"\nsource = \"main.wgsl\"\nblend = \"ad\"\n";
+        let [error] = errors(toml).try_into().unwrap();
+        assert_eq!(error.suggestion.as_deref(), Some("add"));
+    }
+
+    #[test]
+    fn unknown_passes_are_located() {
+        let toml = r#"[[pass]]
+name = "background"
+source = "background.wgsl"
+
+[[pass]]
+name = "main"
+source = "main.wgsl"
+
+[[channel]]
+slot = 0
+image = "noise.png"
+
+[[channel]]
+slot = 1
+pass = "backgroud"
+"#;
+        let [error] = errors(toml).try_into().unwrap();
+        assert_eq!(error.key.as_deref(), Some("channel[1].pass"));
+        assert_eq!(&toml[error.span.clone().unwrap()], "\"backgroud\"");
+        assert_eq!(error.location(toml), Some((15, 8)));
+        assert_eq!(error.suggestion.as_deref(), Some("background"));
+        assert!(error
+            .render(toml, "shader.toml")
+            .contains("--> shader.toml:15:8"));
+    }
+
+    #[test]
+    fn paths_must_stay_in_the_project() {
+        for path in [
+            "../other/main.wgsl",
+            "passes/../../main.wgsl",
+            "/main.wgsl",
+            "",
+        ] {
+            let toml = format!("[[pass]]\nname = \"main\"\nsource = \"{path}\"\n");
+            let [error] = errors(&toml).try_into().unwrap();
+            assert_eq!(error.key.as_deref(), Some("pass[0].source"), "{path:?}");
+            assert_eq!(error.location(&toml), Some((3, 10)), "{path:?}");
+        }
+
+        let toml = "mesh = \"../mesh.glb\"\n[[pass]]\nname = \"main\"\nsource = \"./main.wgsl\"\n";
+        let [error] = errors(toml).try_into().unwrap();
+        assert_eq!(error.key.as_deref(), Some("mesh"));
+    }
+}
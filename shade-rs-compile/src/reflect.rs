@@ -0,0 +1,734 @@
+use std::{
+    collections::HashMap,
+    fmt::{
+        Display,
+        Write,
+    },
+};
+
+use naga::{
+    valid::ModuleInfo,
+    AddressSpace,
+    Expression,
+    ImageClass,
+    ImageDimension,
+    Module,
+    Scalar,
+    ScalarKind,
+    ShaderStage,
+    StorageAccess,
+    TypeInner,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::project::NUM_CHANNELS;
+
+/// Describes the resource bindings of a shader, one per line, ordered by group
+/// and binding.
+pub fn describe_bind_groups(module: &Module) -> String {
+    let mut bindings = module
+        .global_variables
+        .iter()
+        .filter_map(|(_, global)| global.binding.as_ref().map(|binding| (binding, global)))
+        .collect::<Vec<_>>();
+    bindings.sort_by_key(|(binding, _)| (binding.group, binding.binding));
+
+    if bindings.is_empty() {
+        return "no resource bindings".to_owned();
+    }
+
+    let mut output = String::new();
+    for (binding, global) in bindings {
+        let ty = &module.types[global.ty];
+        let type_name = ty.name.clone().unwrap_or_else(|| format!("{:?}", ty.inner));
+        writeln!(
+            output,
+            "@group({}) @binding({}) {} {:?}: {} ({} bytes)",
+            binding.group,
+            binding.binding,
+            global.name.as_deref().unwrap_or("_"),
+            global.space,
+            type_name,
+            ty.inner.size(module.to_ctx()),
+        )
+        .unwrap();
+    }
+
+    output
+}
+
+/// A resource binding that the entry points a shader is drawn with use.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceBinding {
+    pub group: u32,
+    pub binding: u32,
+
+    /// Name of the global variable.
+    pub name: Option<String>,
+
+    pub kind: BindingKind,
+
+    /// The stages whose entry points use the binding.
+    pub stages: Vec<ShaderStage>,
+}
+
+impl Display for ResourceBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "@group({}) @binding({}) {}: {}",
+            self.group,
+            self.binding,
+            self.name.as_deref().unwrap_or("_"),
+            self.kind
+        )
+    }
+}
+
+/// What kind of resource a binding is.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BindingKind {
+    Uniform,
+    Storage {
+        read_only: bool,
+    },
+    Texture {
+        dimension: ImageDimension,
+        arrayed: bool,
+        class: ImageClass,
+    },
+    Sampler {
+        comparison: bool,
+    },
+    /// Anything else, e.g. binding arrays. Described like in WGSL.
+    Other(String),
+}
+
+impl Display for BindingKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Uniform => write!(f, "var<uniform>"),
+            Self::Storage { read_only: true } => write!(f, "var<storage, read>"),
+            Self::Storage { read_only: false } => write!(f, "var<storage, read_write>"),
+            Self::Texture {
+                dimension,
+                arrayed,
+                class,
+            } => {
+                let dimension = match dimension {
+                    ImageDimension::D1 => "1d",
+                    ImageDimension::D2 => "2d",
+                    ImageDimension::D3 => "3d",
+                    ImageDimension::Cube => "cube",
+                };
+                let array = if *arrayed { "_array" } else { "" };
+                match class {
+                    ImageClass::Sampled { kind, multi } => {
+                        let multisampled = if *multi { "multisampled_" } else { "" };
+                        let scalar = match kind {
+                            ScalarKind::Sint => "i32",
+                            ScalarKind::Uint => "u32",
+                            _ => "f32",
+                        };
+                        write!(f, "texture_{multisampled}{dimension}{array}<{scalar}>")
+                    }
+                    ImageClass::Depth { multi } => {
+                        let multisampled = if *multi { "multisampled_" } else { "" };
+                        write!(f, "texture_depth_{multisampled}{dimension}{array}")
+                    }
+                    ImageClass::Storage { format, access } => {
+                        let format = format!("{format:?}").to_lowercase();
+                        let access = match (
+                            access.contains(StorageAccess::LOAD),
+                            access.contains(StorageAccess::STORE),
+                        ) {
+                            (true, true) => "read_write",
+                            (false, true) => "write",
+                            _ => "read",
+                        };
+                        write!(f, "texture_storage_{dimension}{array}<{format}, {access}>")
+                    }
+                }
+            }
+            Self::Sampler { comparison: false } => write!(f, "sampler"),
+            Self::Sampler { comparison: true } => write!(f, "sampler_comparison"),
+            Self::Other(description) => write!(f, "{description}"),
+        }
+    }
+}
+
+/// The resource bindings that the shader's `entry_points` use, ordered by
+/// group and binding. Bindings that only other entry points use, e.g. the
+/// sound's, are left out, since they don't need to be in the pipeline's
+/// layout.
+pub fn reflect_bindings(
+    module: &Module,
+    module_info: &ModuleInfo,
+    entry_points: &EntryPoints,
+) -> Vec<ResourceBinding> {
+    let drawn = module
+        .entry_points
+        .iter()
+        .enumerate()
+        .filter(|(_, entry_point)| {
+            match entry_point.stage {
+                ShaderStage::Vertex => entry_point.name == entry_points.vertex,
+                ShaderStage::Fragment => entry_point.name == entry_points.fragment,
+                ShaderStage::Compute => false,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut bindings = module
+        .global_variables
+        .iter()
+        .filter_map(|(handle, global)| {
+            let binding = global.binding.as_ref()?;
+
+            let stages = drawn
+                .iter()
+                .filter(|(index, _)| !module_info.get_entry_point(*index)[handle].is_empty())
+                .map(|(_, entry_point)| entry_point.stage)
+                .collect::<Vec<_>>();
+            if stages.is_empty() {
+                return None;
+            }
+
+            let kind = match (global.space, &module.types[global.ty].inner) {
+                (AddressSpace::Uniform, _) => BindingKind::Uniform,
+                (AddressSpace::Storage { access }, _) => {
+                    BindingKind::Storage {
+                        read_only: !access.contains(StorageAccess::STORE),
+                    }
+                }
+                (
+                    AddressSpace::Handle,
+                    TypeInner::Image {
+                        dim,
+                        arrayed,
+                        class,
+                    },
+                ) => {
+                    BindingKind::Texture {
+                        dimension: *dim,
+                        arrayed: *arrayed,
+                        class: *class,
+                    }
+                }
+                (AddressSpace::Handle, TypeInner::Sampler { comparison }) => {
+                    BindingKind::Sampler {
+                        comparison: *comparison,
+                    }
+                }
+                (_, TypeInner::BindingArray { .. }) => {
+                    BindingKind::Other("binding_array".to_owned())
+                }
+                (space, _) => BindingKind::Other(format!("{space:?}")),
+            };
+
+            Some(ResourceBinding {
+                group: binding.group,
+                binding: binding.binding,
+                name: global.name.clone(),
+                kind,
+                stages,
+            })
+        })
+        .collect::<Vec<_>>();
+    bindings.sort_by_key(|binding| (binding.group, binding.binding));
+
+    bindings
+}
+
+/// Names of the entry points that a shader is drawn with.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryPoints {
+    pub vertex: String,
+    pub fragment: String,
+}
+
+impl Default for EntryPoints {
+    fn default() -> Self {
+        Self {
+            vertex: "vs_main".to_owned(),
+            fragment: "fs_main".to_owned(),
+        }
+    }
+}
+
+/// Why an entry point for a stage couldn't be picked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EntryPointError {
+    /// The shader has no entry point for the stage.
+    Missing { stage: ShaderStage },
+
+    /// The shader has several entry points for the stage, and none of them
+    /// has the default name.
+    Ambiguous {
+        stage: ShaderStage,
+        names: Vec<String>,
+    },
+
+    /// The entry point that was asked for doesn't exist.
+    Unknown {
+        stage: ShaderStage,
+        name: String,
+        names: Vec<String>,
+    },
+}
+
+impl EntryPointError {
+    /// Names of the stage's entry points, to pick one from.
+    pub fn candidates(&self) -> &[String] {
+        match self {
+            Self::Missing { .. } => &[],
+            Self::Ambiguous { names, .. } | Self::Unknown { names, .. } => names,
+        }
+    }
+}
+
+/// Name of a shader stage, like in its attribute.
+pub fn stage_name(stage: ShaderStage) -> &'static str {
+    match stage {
+        ShaderStage::Vertex => "vertex",
+        ShaderStage::Fragment => "fragment",
+        ShaderStage::Compute => "compute",
+    }
+}
+
+/// Names of the shader's entry points for a stage, in the order they're
+/// declared.
+pub fn entry_point_names(module: &Module, stage: ShaderStage) -> Vec<String> {
+    module
+        .entry_points
+        .iter()
+        .filter(|entry_point| entry_point.stage == stage)
+        .map(|entry_point| entry_point.name.clone())
+        .collect()
+}
+
+/// Picks the vertex and fragment entry points of a shader.
+///
+/// If `fragment` is given, the fragment entry point with that name is used.
+/// Otherwise, if a stage has several entry points, the one named like in
+/// [`EntryPoints::default`] is used.
+pub fn find_entry_points(
+    module: &Module,
+    fragment: Option<&str>,
+) -> Result<EntryPoints, EntryPointError> {
+    let defaults = EntryPoints::default();
+    let find = |stage: ShaderStage, default: &str, selected: Option<&str>| {
+        let names = entry_point_names(module, stage);
+
+        if let Some(selected) = selected {
+            return if names.iter().any(|name| name == selected) {
+                Ok(selected.to_owned())
+            }
+            else {
+                Err(EntryPointError::Unknown {
+                    stage,
+                    name: selected.to_owned(),
+                    names,
+                })
+            };
+        }
+
+        match names.as_slice() {
+            [] => Err(EntryPointError::Missing { stage }),
+            [name] => Ok(name.clone()),
+            _ if names.iter().any(|name| name == default) => Ok(default.to_owned()),
+            _ => Err(EntryPointError::Ambiguous { stage, names }),
+        }
+    };
+
+    Ok(EntryPoints {
+        vertex: find(ShaderStage::Vertex, &defaults.vertex, None)?,
+        fragment: find(ShaderStage::Fragment, &defaults.fragment, fragment)?,
+    })
+}
+
+/// Bind group of the user-defined parameters.
+pub const PARAMS_GROUP: u32 = 0;
+
+/// Binding of the user-defined parameters.
+///
+/// A shader can declare a uniform with any struct type at this binding, e.g.:
+///
+/// ```wgsl
+/// struct Params {
+///     speed: f32,
+///     offset: vec2f,
+/// }
+///
+/// @group(0) @binding(1)
+/// var<uniform> params: Params;
+/// ```
+///
+/// The UI then shows controls for the fields of that struct. Fields can be
+/// annotated with a comment to change how they're edited:
+///
+/// ```wgsl
+/// struct Params {
+///     // @color
+///     tint: vec3f,
+///     background: vec4f, // @color
+/// }
+/// ```
+///
+/// See [`ParamHint`] for the supported annotations.
+pub const PARAMS_BINDING: u32 = 1;
+
+/// Binding of the orbit camera.
+///
+/// A shader that declares a uniform at this binding gets a camera that orbits
+/// around the origin when the mouse is dragged over the canvas, and zooms when
+/// scrolling:
+///
+/// ```wgsl
+/// struct Camera {
+///     view_projection: mat4x4f,
+///     inverse_view_projection: mat4x4f,
+///     position: vec3f,
+/// }
+///
+/// @group(0) @binding(4)
+/// var<uniform> camera: Camera;
+/// ```
+///
+/// `view_projection` transforms world space into clip space, e.g. for meshes.
+/// For ray marching, `inverse_view_projection` turns a point in normalized
+/// device coordinates back into world space.
+pub const CAMERA_BINDING: u32 = 4;
+
+/// Whether the shader declares the camera uniform at [`CAMERA_BINDING`].
+pub fn declares_camera(module: &Module) -> bool {
+    module.global_variables.iter().any(|(_, global)| {
+        global.space == AddressSpace::Uniform
+            && global.binding.as_ref().is_some_and(|binding| {
+                binding.group == PARAMS_GROUP && binding.binding == CAMERA_BINDING
+            })
+    })
+}
+
+/// Members of the input uniform that change every frame.
+const ANIMATED_INPUTS: [&str; 3] = ["time", "frame", "delta_time"];
+
+/// Members of the input uniform that change with the mouse wheel.
+const SCROLL_INPUTS: [&str; 2] = ["scroll", "zoom"];
+
+/// Members of the input uniform that change with the device orientation.
+const ORIENTATION_INPUTS: [&str; 1] = ["rotation"];
+
+/// Whether the shader reads the time or the frame number from the input
+/// uniform at `@group(0) @binding(0)`.
+///
+/// Shaders that don't, and don't read the previous frame, render the same
+/// frame until their inputs change. Reading the whole uniform at once counts
+/// as reading the time.
+pub fn reads_time(module: &Module) -> bool {
+    reads_inputs(module, &ANIMATED_INPUTS)
+}
+
+/// Whether the shader reads the scroll or zoom from the input uniform. Only
+/// then the mouse wheel is taken over, instead of scrolling the page.
+///
+/// Like with [`reads_time`], reading the whole uniform counts.
+pub fn reads_scroll(module: &Module) -> bool {
+    reads_inputs(module, &SCROLL_INPUTS)
+}
+
+/// Whether the shader reads the device's rotation from the input uniform.
+/// Only then the UI listens to the device orientation, which on some devices
+/// needs a permission.
+///
+/// Like with [`reads_time`], reading the whole uniform counts.
+pub fn reads_orientation(module: &Module) -> bool {
+    reads_inputs(module, &ORIENTATION_INPUTS)
+}
+
+/// Whether the shader reads any of the `names` members of the input uniform,
+/// or the whole uniform at once.
+fn reads_inputs(module: &Module, names: &[&str]) -> bool {
+    let Some((input, global)) = module.global_variables.iter().find(|(_, global)| {
+        global.space == AddressSpace::Uniform
+            && global
+                .binding
+                .as_ref()
+                .is_some_and(|binding| binding.group == PARAMS_GROUP && binding.binding == 0)
+    })
+    else {
+        return false;
+    };
+
+    let TypeInner::Struct { members, .. } = &module.types[global.ty].inner
+    else {
+        return false;
+    };
+    let is_named = |index: u32| {
+        members
+            .get(index as usize)
+            .and_then(|member| member.name.as_deref())
+            .is_some_and(|name| names.contains(&name))
+    };
+
+    let functions = module
+        .functions
+        .iter()
+        .map(|(_, function)| function)
+        .chain(module.entry_points.iter().map(|entry_point| &entry_point.function));
+
+    for function in functions {
+        let is_input = |expression| {
+            matches!(
+                function.expressions[expression],
+                Expression::GlobalVariable(handle) if handle == input
+            )
+        };
+
+        let reads = function.expressions.iter().any(|(_, expression)| {
+            match *expression {
+                Expression::AccessIndex { base, index } => is_input(base) && is_named(index),
+                Expression::Load { pointer } => is_input(pointer),
+                _ => false,
+            }
+        });
+        if reads {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Bind group of the texture channels. Channel `i` is bound at binding `2 * i`,
+/// and its sampler at `2 * i + 1`. Its cubemap, if it was converted to one, is
+/// bound at [`CHANNEL_CUBE_BINDING`] `+ i`.
+pub const CHANNEL_GROUP: u32 = 1;
+
+/// Binding of channel 0's cubemap in [`CHANNEL_GROUP`]. The other channels'
+/// cubemaps follow it.
+pub const CHANNEL_CUBE_BINDING: u32 = 2 * NUM_CHANNELS as u32;
+
+/// Bind group of the previous frame.
+pub const PREVIOUS_FRAME_GROUP: u32 = 3;
+
+/// Slots of the texture channels that the shader declares, in ascending order.
+pub fn declared_channels(module: &Module) -> Vec<usize> {
+    let mut slots = module
+        .global_variables
+        .iter()
+        .filter_map(|(_, global)| global.binding.as_ref())
+        .filter(|binding| binding.group == CHANNEL_GROUP)
+        .map(|binding| {
+            if binding.binding >= CHANNEL_CUBE_BINDING {
+                (binding.binding - CHANNEL_CUBE_BINDING) as usize
+            }
+            else {
+                binding.binding as usize / 2
+            }
+        })
+        .collect::<Vec<_>>();
+    slots.sort_unstable();
+    slots.dedup();
+    slots
+}
+
+/// Whether the shader declares the previous frame in
+/// [`PREVIOUS_FRAME_GROUP`].
+pub fn reads_previous_frame(module: &Module) -> bool {
+    module.global_variables.iter().any(|(_, global)| {
+        global
+            .binding
+            .as_ref()
+            .is_some_and(|binding| binding.group == PREVIOUS_FRAME_GROUP)
+    })
+}
+
+/// Layout of the user-defined parameters uniform.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ParamsLayout {
+    /// Name of the struct type.
+    pub struct_name: Option<String>,
+
+    /// Size of the struct in bytes.
+    pub size: u32,
+
+    /// Fields that can be edited. Fields with unsupported types are omitted.
+    pub fields: Vec<ParamField>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ParamField {
+    pub name: String,
+
+    /// Offset in bytes from the start of the struct.
+    pub offset: u32,
+
+    pub ty: ParamType,
+
+    /// How the field should be edited, if it was annotated.
+    pub hint: Option<ParamHint>,
+}
+
+/// Annotation on a parameter field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParamHint {
+    /// `// @color`: Edit a `vec3f` or `vec4f` with a color picker. The
+    /// components are RGB(A) in the range `0..=1`.
+    Color,
+}
+
+impl ParamHint {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "color" => Some(Self::Color),
+            _ => None,
+        }
+    }
+
+    /// Whether the hint can be used for a field of this type.
+    pub fn applies_to(&self, ty: ParamType) -> bool {
+        match self {
+            Self::Color => ty.scalar == ParamScalar::F32 && matches!(ty.components, 3 | 4),
+        }
+    }
+}
+
+/// Type of a parameter field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParamType {
+    pub scalar: ParamScalar,
+
+    /// Number of components, 1 for scalars.
+    pub components: u8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParamScalar {
+    F32,
+    I32,
+    U32,
+}
+
+impl ParamScalar {
+    fn from_naga(scalar: Scalar) -> Option<Self> {
+        match (scalar.kind, scalar.width) {
+            (ScalarKind::Float, 4) => Some(Self::F32),
+            (ScalarKind::Sint, 4) => Some(Self::I32),
+            (ScalarKind::Uint, 4) => Some(Self::U32),
+            _ => None,
+        }
+    }
+}
+
+/// Finds the parameters uniform and computes its layout.
+///
+/// `source` must be the code the module was parsed from. It's used to read the
+/// annotations of the fields.
+pub fn reflect_params(module: &Module, source: &str) -> Option<ParamsLayout> {
+    let global = module.global_variables.iter().find_map(|(_, global)| {
+        let binding = global.binding.as_ref()?;
+        (global.space == AddressSpace::Uniform
+            && binding.group == PARAMS_GROUP
+            && binding.binding == PARAMS_BINDING)
+            .then_some(global)
+    })?;
+
+    let ty = &module.types[global.ty];
+    let TypeInner::Struct { members, span } = &ty.inner
+    else {
+        return None;
+    };
+
+    let annotations = module
+        .types
+        .get_span(global.ty)
+        .to_range()
+        .and_then(|range| source.get(range))
+        .map(field_annotations)
+        .unwrap_or_default();
+
+    let fields = members
+        .iter()
+        .filter_map(|member| {
+            let ty = match module.types[member.ty].inner {
+                TypeInner::Scalar(scalar) => {
+                    ParamType {
+                        scalar: ParamScalar::from_naga(scalar)?,
+                        components: 1,
+                    }
+                }
+                TypeInner::Vector { size, scalar } => {
+                    ParamType {
+                        scalar: ParamScalar::from_naga(scalar)?,
+                        components: size as u8,
+                    }
+                }
+                _ => return None,
+            };
+            let name = member.name.clone()?;
+            let hint = annotations
+                .get(name.as_str())
+                .into_iter()
+                .flatten()
+                .filter_map(|annotation| ParamHint::from_name(annotation))
+                .find(|hint| hint.applies_to(ty));
+            Some(ParamField {
+                name,
+                offset: member.offset,
+                ty,
+                hint,
+            })
+        })
+        .collect();
+
+    Some(ParamsLayout {
+        struct_name: ty.name.clone(),
+        size: *span,
+        fields,
+    })
+}
+
+/// Collects the annotations of the fields in a struct declaration.
+///
+/// Annotations are words starting with `@` in a comment, either on the line of
+/// the field, or on comment lines directly above it.
+fn field_annotations(struct_source: &str) -> HashMap<&str, Vec<&str>> {
+    let mut annotations = HashMap::<&str, Vec<&str>>::new();
+    let mut pending = vec![];
+
+    for line in struct_source.lines() {
+        let (code, comment) = line.split_once("//").unwrap_or((line, ""));
+        let line_annotations = comment
+            .split_whitespace()
+            .filter_map(|word| word.strip_prefix('@'))
+            .filter(|word| !word.is_empty());
+
+        if code.trim().is_empty() {
+            pending.extend(line_annotations);
+            continue;
+        }
+
+        // skip attributes like `@align(16)` and take the last word before the
+        // colon.
+        let Some(name) = code
+            .split_once(':')
+            .and_then(|(name, _)| name.split_whitespace().last())
+        else {
+            pending.clear();
+            continue;
+        };
+
+        annotations
+            .entry(name)
+            .or_default()
+            .extend(pending.drain(..).chain(line_annotations));
+    }
+
+    annotations
+}
@@ -0,0 +1,79 @@
+use std::fmt::Display;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    directive::find_directives,
+    CompileError,
+};
+
+/// A capability a shader needs from the backend.
+///
+/// Shaders declare these with a comment, e.g.:
+///
+/// ```wgsl
+/// // @requires webgpu, float32-filterable
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Requirement {
+    WebGpu,
+    Float32Filterable,
+    StorageTextures,
+}
+
+impl Requirement {
+    pub const ALL: [Self; 3] = [Self::WebGpu, Self::Float32Filterable, Self::StorageTextures];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::WebGpu => "webgpu",
+            Self::Float32Filterable => "float32-filterable",
+            Self::StorageTextures => "storage-textures",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|requirement| requirement.name() == name)
+    }
+
+    /// Whether this requirement can be satisfied by switching to the WebGPU
+    /// backend.
+    pub fn needs_webgpu(&self) -> bool {
+        matches!(self, Self::WebGpu | Self::StorageTextures)
+    }
+}
+
+impl Display for Requirement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+const DIRECTIVE: &str = "@requires";
+
+/// Parses all `@requires` directives in the shader source.
+pub fn parse_requirements(source: &str) -> Result<Vec<Requirement>, CompileError> {
+    let mut requirements = vec![];
+
+    for (line, names) in find_directives(source, DIRECTIVE) {
+        for name in names {
+            let requirement = Requirement::from_name(name).ok_or_else(|| {
+                CompileError::UnknownRequirement {
+                    name: name.to_owned(),
+                    line,
+                }
+            })?;
+            if !requirements.contains(&requirement) {
+                requirements.push(requirement);
+            }
+        }
+    }
+
+    Ok(requirements)
+}
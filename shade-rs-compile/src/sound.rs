@@ -0,0 +1,79 @@
+use naga::{
+    front::wgsl::parse_str,
+    valid::{
+        Capabilities,
+        ValidationFlags,
+        Validator,
+    },
+    Module,
+};
+
+use crate::{
+    CompileError,
+    CompiledShader,
+};
+
+/// Name of the function that generates sound.
+///
+/// Like Shadertoy's `mainSound`, a shader can declare a function that returns
+/// the stereo sample at a given sample index and time (in seconds):
+///
+/// ```wgsl
+/// fn main_sound(sample: u32, time: f32) -> vec2f {
+///     return vec2f(sin(6.2831 * 440.0 * time) * exp(-3.0 * fract(time)));
+/// }
+/// ```
+///
+/// Samples are clamped to `-1..=1`. The function runs in a compute shader, so
+/// it can use the inputs and parameters in `@group(0)`, but no textures.
+pub const SOUND_FUNCTION: &str = "main_sound";
+
+/// Entry point of the compute shader produced by [`compile_sound_shader`].
+pub const SOUND_ENTRY_POINT: &str = "shade_rs_sound_main";
+
+/// Workgroup size of [`SOUND_ENTRY_POINT`].
+pub const SOUND_WORKGROUP_SIZE: u32 = 64;
+
+/// Binding of the uniform with the first sample index and the sample rate, both
+/// `u32`.
+pub const SOUND_BLOCK_BINDING: u32 = 2;
+
+/// Binding of the storage buffer that the samples are written to, as
+/// `array<vec2f>`.
+pub const SOUND_SAMPLES_BINDING: u32 = 3;
+
+const SOUND_PASS: &str = include_str!("sound/pass.wgsl");
+
+/// Whether the shader declares [`SOUND_FUNCTION`].
+pub fn declares_sound(module: &Module) -> bool {
+    module
+        .functions
+        .iter()
+        .any(|(_, function)| function.name.as_deref() == Some(SOUND_FUNCTION))
+}
+
+/// Builds the compute shader that runs the shader's [`SOUND_FUNCTION`], or
+/// returns `None` if it doesn't declare one.
+pub fn compile_sound_shader(shader: &CompiledShader) -> Result<Option<Module>, CompileError> {
+    if !declares_sound(&shader.module) {
+        return Ok(None);
+    }
+
+    let code = format!("{}\n{SOUND_PASS}", shader.source);
+    let module = parse_str(&code).map_err(|parse_error| {
+        CompileError::Parse {
+            parse_error: Box::new(parse_error),
+            code: code.clone(),
+        }
+    })?;
+    Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|validation_error| {
+            CompileError::Validate {
+                validation_error: Box::new(validation_error),
+                code,
+            }
+        })?;
+
+    Ok(Some(module))
+}
@@ -0,0 +1,27 @@
+// Sound pass. This is appended to shaders that declare `main_sound`, and fills
+// a block of stereo samples.
+
+struct ShadeRsSoundBlock {
+    first_sample: u32,
+    sample_rate: u32,
+}
+
+@group(0) @binding(2)
+var<uniform> shade_rs_sound_block: ShadeRsSoundBlock;
+
+@group(0) @binding(3)
+var<storage, read_write> shade_rs_sound_samples: array<vec2f>;
+
+@compute @workgroup_size(64)
+fn shade_rs_sound_main(@builtin(global_invocation_id) id: vec3u) {
+    if id.x >= arrayLength(&shade_rs_sound_samples) {
+        return;
+    }
+
+    let sample = shade_rs_sound_block.first_sample + id.x;
+    let rate = shade_rs_sound_block.sample_rate;
+    // split off whole seconds, so that the fraction doesn't lose precision.
+    let time = f32(sample / rate) + f32(sample % rate) / f32(rate);
+
+    shade_rs_sound_samples[id.x] = clamp(main_sound(sample, time), vec2f(-1.0), vec2f(1.0));
+}
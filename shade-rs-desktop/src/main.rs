@@ -0,0 +1,151 @@
+//! Desktop app that runs the shade-rs UI in a webview.
+//!
+//! The UI is embedded into the binary at compile time, so build it first:
+//!
+//! ```sh
+//! shade-rs-cli build
+//! cargo build --release -p shade-rs-desktop
+//! ```
+
+use std::{
+    borrow::Cow,
+    path::PathBuf,
+};
+
+use color_eyre::eyre::Error;
+use rust_embed::RustEmbed;
+use tao::{
+    event::{
+        Event,
+        WindowEvent,
+    },
+    event_loop::{
+        ControlFlow,
+        EventLoop,
+    },
+    window::WindowBuilder,
+};
+use tracing_subscriber::EnvFilter;
+use wry::{
+    http::{
+        header::CONTENT_TYPE,
+        Request,
+        Response,
+        StatusCode,
+    },
+    WebViewBuilder,
+    WebViewId,
+};
+
+/// The UI build, i.e. `dist/ui`.
+///
+/// This is allowed to be missing, so that the workspace builds without a UI
+/// build. The app then only shows an error.
+#[derive(RustEmbed)]
+#[folder = "../dist/ui/"]
+#[allow_missing = true]
+struct Ui;
+
+/// Scheme under which the embedded UI is served.
+const PROTOCOL: &str = "shade-rs";
+
+/// URL of the UI's index page. Windows and Android don't support custom schemes
+/// in URLs, so wry maps them to `http://<scheme>.localhost`.
+#[cfg(any(windows, target_os = "android"))]
+const INDEX_URL: &str = "http://shade-rs.localhost/";
+#[cfg(not(any(windows, target_os = "android")))]
+const INDEX_URL: &str = "shade-rs://localhost/";
+
+const UI_NOT_BUILT: &str = "<!DOCTYPE html>
+<html>
+    <body>
+        <p>The UI wasn't built. Run <code>shade-rs-cli build</code> and rebuild shade-rs-desktop.</p>
+    </body>
+</html>";
+
+fn main() -> Result<(), Error> {
+    color_eyre::install()?;
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .pretty()
+        .init();
+
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_title("shade-rs")
+        .build(&event_loop)?;
+
+    #[cfg(not(any(target_os = "linux", target_os = "freebsd", target_os = "openbsd")))]
+    let builder = WebViewBuilder::new(&window);
+    #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "openbsd"))]
+    let builder = {
+        use tao::platform::unix::WindowExtUnix;
+        use wry::WebViewBuilderExtUnix;
+        WebViewBuilder::new_gtk(window.default_vbox().unwrap())
+    };
+
+    let _webview = builder
+        .with_custom_protocol(PROTOCOL.to_owned(), serve_ui)
+        .with_download_started_handler(choose_download_path)
+        .with_url(INDEX_URL)
+        .build()?;
+
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Wait;
+
+        if let Event::WindowEvent {
+            event: WindowEvent::CloseRequested,
+            ..
+        } = event
+        {
+            *control_flow = ControlFlow::Exit;
+        }
+    });
+}
+
+/// Serves the embedded UI files. Unknown paths get the index page, like
+/// `shade-rs-cli serve` does.
+fn serve_ui(_webview_id: WebViewId, request: Request<Vec<u8>>) -> Response<Cow<'static, [u8]>> {
+    let path = request.uri().path().trim_start_matches('/');
+    tracing::debug!(%path, "request");
+
+    let (file, mime) = match Ui::get(path).filter(|_| !path.is_empty()) {
+        Some(file) => (file, mime_guess::from_path(path).first_or_octet_stream()),
+        None => {
+            let Some(index) = Ui::get("index.html")
+            else {
+                return Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .header(CONTENT_TYPE, "text/html")
+                    .body(Cow::Borrowed(UI_NOT_BUILT.as_bytes()))
+                    .unwrap();
+            };
+            (index, mime_guess::mime::TEXT_HTML_UTF_8)
+        }
+    };
+
+    Response::builder()
+        .header(CONTENT_TYPE, mime.as_ref())
+        .body(file.data)
+        .unwrap()
+}
+
+/// Asks the user where to save a download, i.e. a shader saved in the editor.
+///
+/// Returns `false` if the dialog was cancelled, which cancels the download.
+fn choose_download_path(url: String, destination: &mut PathBuf) -> bool {
+    tracing::debug!(%url, destination = %destination.display(), "download started");
+
+    let mut dialog = rfd::FileDialog::new().add_filter("WGSL shader", &["wgsl"]);
+    if let Some(file_name) = destination.file_name() {
+        dialog = dialog.set_file_name(file_name.to_string_lossy());
+    }
+
+    match dialog.save_file() {
+        Some(path) => {
+            *destination = path;
+            true
+        }
+        None => false,
+    }
+}
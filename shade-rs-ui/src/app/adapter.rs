@@ -0,0 +1,104 @@
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_effect,
+    create_rw_signal,
+    spawn_local,
+    view,
+    CollectView,
+    IntoView,
+    Signal,
+    SignalGet,
+    SignalSet,
+    StoredValue,
+};
+
+use crate::graphics::{
+    adapter::AdapterInfo,
+    WindowHandle,
+};
+
+#[style(path = "src/app/adapter.scss")]
+struct Style;
+
+/// Shows the backend and adapter that render the preview, with its features
+/// and limits.
+///
+/// It's queried whenever the panel is shown, since switching to WebGPU
+/// replaces the adapter.
+#[component]
+pub fn Adapter(
+    #[prop(into)] show: Signal<bool>,
+    window_handle: StoredValue<Option<WindowHandle>>,
+) -> impl IntoView {
+    let adapter_info = create_rw_signal::<Option<AdapterInfo>>(None);
+
+    create_effect(move |_| {
+        if !show.get() {
+            return;
+        }
+        if let Some(window_handle) = window_handle.get_value() {
+            spawn_local(async move {
+                adapter_info.set(window_handle.adapter_info().await);
+            });
+        }
+    });
+
+    view! {
+        <div class=Style::adapter>
+            {move || {
+                let Some(adapter_info) = adapter_info.get()
+                else {
+                    return view! { <div>"No adapter"</div> }.into_view();
+                };
+                view! {
+                    <div>
+                        {format!(
+                            "{} on {} ({:?})",
+                            adapter_info.backend_type,
+                            adapter_info.name,
+                            adapter_info.device_type,
+                        )}
+                    </div>
+                    <div data-hidden=adapter_info.driver.is_empty()>
+                        {format!("Driver: {}", adapter_info.driver)}
+                    </div>
+                    <h4>"Features"</h4>
+                    <ul class=Style::names>
+                        {adapter_info
+                            .features
+                            .iter()
+                            .map(|name| view! { <li>{*name}</li> })
+                            .collect_view()}
+                    </ul>
+                    <h4 data-hidden=adapter_info.missing_capabilities.is_empty()>
+                        "Missing capabilities"
+                    </h4>
+                    <ul class=Style::names data-missing=true>
+                        {adapter_info
+                            .missing_capabilities
+                            .iter()
+                            .map(|name| view! { <li>{*name}</li> })
+                            .collect_view()}
+                    </ul>
+                    <h4>"Limits"</h4>
+                    <table>
+                        {adapter_info
+                            .limits
+                            .iter()
+                            .map(|(name, value)| {
+                                view! {
+                                    <tr>
+                                        <td>{*name}</td>
+                                        <td>{*value}</td>
+                                    </tr>
+                                }
+                            })
+                            .collect_view()}
+                    </table>
+                }
+                    .into_view()
+            }}
+        </div>
+    }
+}
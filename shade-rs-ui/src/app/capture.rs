@@ -0,0 +1,162 @@
+use leptos::{
+    component,
+    create_rw_signal,
+    store_value,
+    view,
+    IntoView,
+    SignalGet,
+    SignalSet,
+    StoredValue,
+};
+use wasm_bindgen::{
+    JsCast,
+    JsValue,
+};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    HtmlVideoElement,
+    MediaStream,
+    MediaStreamTrack,
+};
+
+use crate::{
+    app::icon::BootstrapIcon,
+    graphics::{
+        self,
+        WindowHandle,
+    },
+    utils::futures::spawn_local_and_handle_error,
+};
+
+/// Toolbar button that toggles a picture-in-picture capture of the window.
+#[component]
+pub fn CaptureButton(window_handle: StoredValue<Option<WindowHandle>>) -> impl IntoView {
+    let capture_stream = store_value::<Option<CaptureStream>>(None);
+    let capturing = create_rw_signal(false);
+
+    view! {
+        <button
+            on:click=move |_| {
+                if let Some(capture) = capture_stream.try_update_value(|capture| capture.take()).flatten() {
+                    capture.stop();
+                    capturing.set(false);
+                }
+                else if let Some(window_handle) = window_handle.get_value() {
+                    spawn_local_and_handle_error(async move {
+                        let capture = CaptureStream::start(&window_handle, true).await?;
+                        capture_stream.set_value(Some(capture));
+                        capturing.set(true);
+                        Ok::<(), CaptureError>(())
+                    });
+                }
+            }
+            data-toggled=move || capturing.get()
+            title="Capture stream"
+        >
+            <BootstrapIcon icon="pip" />
+        </button>
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CaptureError {
+    #[error("graphics error")]
+    Graphics(#[from] graphics::Error),
+
+    #[error("javascript error: {message}")]
+    Js { message: String },
+}
+
+impl From<JsValue> for CaptureError {
+    fn from(value: JsValue) -> Self {
+        Self::Js {
+            message: format!("{value:?}"),
+        }
+    }
+}
+
+/// A running capture of a window's canvas.
+///
+/// The stream can be used directly (e.g. by a browser source in OBS), or piped
+/// into a picture-in-picture video element, which can then be shared in video
+/// calls.
+#[derive(Debug)]
+pub struct CaptureStream {
+    stream: MediaStream,
+    video: Option<HtmlVideoElement>,
+}
+
+impl CaptureStream {
+    pub async fn start(
+        window_handle: &WindowHandle,
+        picture_in_picture: bool,
+    ) -> Result<Self, CaptureError> {
+        let stream = window_handle.capture_stream(None)?;
+
+        let video = if picture_in_picture {
+            let video: HtmlVideoElement = gloo_utils::document()
+                .create_element("video")?
+                .unchecked_into();
+            video.set_muted(true);
+            video.set_src_object(Some(&stream));
+
+            JsFuture::from(video.play()?).await?;
+            JsFuture::from(
+                video
+                    .unchecked_ref::<picture_in_picture_sys::VideoElement>()
+                    .request_picture_in_picture(),
+            )
+            .await?;
+
+            Some(video)
+        }
+        else {
+            None
+        };
+
+        Ok(Self { stream, video })
+    }
+
+    pub fn stream(&self) -> &MediaStream {
+        &self.stream
+    }
+
+    pub fn stop(self) {
+        if let Some(video) = self.video {
+            let document =
+                gloo_utils::document().unchecked_into::<picture_in_picture_sys::Document>();
+            if document.picture_in_picture_element().is_some() {
+                let _ = document.exit_picture_in_picture();
+            }
+            video.set_src_object(None);
+        }
+
+        for track in self.stream.get_tracks().iter() {
+            track.unchecked_into::<MediaStreamTrack>().stop();
+        }
+    }
+}
+
+mod picture_in_picture_sys {
+    use wasm_bindgen::prelude::wasm_bindgen;
+
+    #[wasm_bindgen]
+    extern "C" {
+        #[derive(Debug)]
+        #[wasm_bindgen(extends = web_sys::HtmlVideoElement)]
+        pub type VideoElement;
+
+        #[wasm_bindgen(method, js_name = requestPictureInPicture)]
+        pub fn request_picture_in_picture(this: &VideoElement) -> js_sys::Promise;
+
+        #[derive(Debug)]
+        #[wasm_bindgen(extends = web_sys::Document)]
+        pub type Document;
+
+        #[wasm_bindgen(method, js_name = exitPictureInPicture)]
+        pub fn exit_picture_in_picture(this: &Document) -> js_sys::Promise;
+
+        #[wasm_bindgen(method, getter, js_name = pictureInPictureElement)]
+        pub fn picture_in_picture_element(this: &Document) -> Option<web_sys::Element>;
+    }
+}
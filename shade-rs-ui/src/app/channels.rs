@@ -0,0 +1,947 @@
+use js_sys::Uint8Array;
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_node_ref,
+    create_rw_signal,
+    event_target,
+    event_target_value,
+    html,
+    on_cleanup,
+    store_value,
+    view,
+    CollectView,
+    IntoView,
+    RwSignal,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+    SignalWith,
+    StoredValue,
+};
+use wasm_bindgen::{
+    Clamped,
+    JsCast,
+    JsValue,
+};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    AudioBuffer,
+    AudioContext,
+    AudioNode,
+    CanvasRenderingContext2d,
+    File,
+    HtmlCanvasElement,
+    HtmlImageElement,
+    HtmlInputElement,
+    HtmlVideoElement,
+    ImageData,
+    MediaStream,
+    MediaStreamConstraints,
+    MediaStreamTrack,
+    Url,
+};
+
+use crate::{
+    app::{
+        icon::BootstrapIcon,
+        text::render_text,
+    },
+    graphics::{
+        self,
+        channel::{
+            ChannelFilter,
+            ChannelImage,
+            ChannelSampler,
+            ChannelWrap,
+            AUDIO_SAMPLES,
+            NUM_CHANNELS,
+        },
+        compressed::{
+            CompressedImage,
+            CompressedImageError,
+        },
+        hdr::{
+            HdrImage,
+            HdrImageError,
+        },
+        mesh::{
+            MeshData,
+            MeshError,
+        },
+        WindowHandle,
+    },
+    utils::futures::spawn_local_and_handle_error,
+};
+
+#[style(path = "src/app/channels.scss")]
+struct Style;
+
+/// Width of the channel previews, in pixels.
+const THUMBNAIL_WIDTH: u32 = 64;
+
+/// Where the contents of a channel come from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum ChannelKind {
+    #[default]
+    None,
+    Image,
+    Text,
+    Webcam,
+    Audio,
+    Microphone,
+    Depth,
+}
+
+impl ChannelKind {
+    const ALL: [Self; 7] = [
+        Self::None,
+        Self::Image,
+        Self::Text,
+        Self::Webcam,
+        Self::Audio,
+        Self::Microphone,
+        Self::Depth,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Image => "image",
+            Self::Text => "text",
+            Self::Webcam => "webcam",
+            Self::Audio => "audio",
+            Self::Microphone => "microphone",
+            Self::Depth => "depth",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+/// Lets the user pick what is bound to each of the shader's texture channels:
+/// an image, text that is rendered into it, the webcam, the spectrum and
+/// waveform of an audio file or the microphone, or a layer's depth buffer. Each
+/// channel shows a preview of its contents and how it's sampled. A mesh can be
+/// loaded for shaders that take vertex attributes.
+#[component]
+pub fn Channels(window_handle: StoredValue<Option<WindowHandle>>) -> impl IntoView {
+    let channels = (0..NUM_CHANNELS)
+        .map(|index| view! { <Channel window_handle index /> })
+        .collect_view();
+
+    view! {
+        {channels}
+        <MeshChannel window_handle />
+    }
+}
+
+#[component]
+fn Channel(window_handle: StoredValue<Option<WindowHandle>>, index: usize) -> impl IntoView {
+    let kind = create_rw_signal(ChannelKind::None);
+    let file_name = create_rw_signal::<Option<String>>(None);
+    let thumbnail = create_rw_signal::<Option<String>>(None);
+    let sampler = create_rw_signal(ChannelSampler::default());
+    let webcam = store_value::<Option<MediaStream>>(None);
+    let audio = store_value::<Option<AudioInput>>(None);
+    let video_ref = create_node_ref::<html::Video>();
+    // kept to bind it again when the exposure changes.
+    let hdr_image = create_rw_signal::<Option<HdrImage>>(None);
+    let exposure = create_rw_signal(0.0f32);
+    // whether the image is a panorama that is also bound as a cubemap.
+    let is_cubemap = create_rw_signal(false);
+    // the layer whose depth buffer is bound.
+    let depth_layer = create_rw_signal(0usize);
+
+    let clear = move || {
+        hdr_image.set(None);
+        if let Some(stream) = webcam.try_update_value(|webcam| webcam.take()).flatten() {
+            stop_media_stream(&stream);
+        }
+        if let Some(input) = audio.try_update_value(|audio| audio.take()).flatten() {
+            input.stop();
+        }
+        if let Some(video) = video_ref.get_untracked() {
+            video.set_src_object(None);
+        }
+        set_channel(window_handle, index, None, file_name, thumbnail);
+    };
+
+    let set_kind = move |new_kind: ChannelKind| {
+        clear();
+        kind.set(new_kind);
+        match new_kind {
+            ChannelKind::Webcam => {
+                if let Some(video) = video_ref.get_untracked() {
+                    set_webcam_channel(window_handle, index, (*video).clone(), webcam, kind);
+                }
+            }
+            ChannelKind::Microphone => {
+                set_microphone_channel(window_handle, index, audio, kind, file_name);
+            }
+            ChannelKind::Depth => {
+                set_depth_channel(window_handle, index, depth_layer.get_untracked(), file_name);
+            }
+            _ => {}
+        }
+    };
+
+    let set_sampler = move |new_sampler: ChannelSampler| {
+        sampler.set(new_sampler);
+        if let Some(window_handle) = window_handle.get_value() {
+            window_handle.set_channel_sampler(index, new_sampler);
+        }
+    };
+
+    on_cleanup(move || {
+        if let Some(stream) = webcam.try_update_value(|webcam| webcam.take()).flatten() {
+            stop_media_stream(&stream);
+        }
+        if let Some(input) = audio.try_update_value(|audio| audio.take()).flatten() {
+            input.stop();
+        }
+    });
+
+    view! {
+        <div class=Style::channel>
+            <span class=Style::label>{format!("channel{index}")}</span>
+            <select
+                title="Source"
+                on:change=move |event| {
+                    if let Some(new_kind) = ChannelKind::from_name(&event_target_value(&event)) {
+                        set_kind(new_kind);
+                    }
+                }
+            >
+                {ChannelKind::ALL
+                    .into_iter()
+                    .map(|option| {
+                        view! {
+                            // a property, since the kind is also reset when the webcam can't be
+                            // used.
+                            <option value=option.name() prop:selected=move || kind.get() == option>
+                                {option.name()}
+                            </option>
+                        }
+                    })
+                    .collect_view()}
+            </select>
+            <label
+                class=Style::file
+                title="Upload image, KTX2 texture or HDR image"
+                data-hidden=move || kind.get() != ChannelKind::Image
+            >
+                <BootstrapIcon icon="image" />
+                <input
+                    type="file"
+                    accept="image/*,.ktx2,.hdr,.exr"
+                    on:change=move |event| {
+                        let input = event_target::<HtmlInputElement>(&event);
+                        let Some(file) = input.files().and_then(|files| files.get(0))
+                        else {
+                            return;
+                        };
+                        let name = file.name().to_lowercase();
+                        if name.ends_with(".hdr") || name.ends_with(".exr") {
+                            load_hdr_channel(
+                                window_handle,
+                                index,
+                                file,
+                                hdr_image,
+                                exposure,
+                                file_name,
+                                thumbnail,
+                            );
+                        }
+                        else {
+                            hdr_image.set(None);
+                            set_image_channel(window_handle, index, file, file_name, thumbnail);
+                        }
+                        input.set_value("");
+                    }
+                />
+            </label>
+            <label
+                class=Style::file
+                title="Upload audio file"
+                data-hidden=move || kind.get() != ChannelKind::Audio
+            >
+                <BootstrapIcon icon="music-note-beamed" />
+                <input
+                    type="file"
+                    accept="audio/*"
+                    on:change=move |event| {
+                        let input = event_target::<HtmlInputElement>(&event);
+                        let Some(file) = input.files().and_then(|files| files.get(0))
+                        else {
+                            return;
+                        };
+                        set_audio_file_channel(window_handle, index, file, audio, file_name);
+                        input.set_value("");
+                    }
+                />
+            </label>
+            <input
+                type="number"
+                class=Style::depth_layer
+                title="Layer whose depth buffer is bound"
+                min="0"
+                prop:value=move || depth_layer.get()
+                data-hidden=move || kind.get() != ChannelKind::Depth
+                on:change=move |event| {
+                    let Ok(layer) = event_target_value(&event).parse::<usize>()
+                    else {
+                        return;
+                    };
+                    depth_layer.set(layer);
+                    set_depth_channel(window_handle, index, layer, file_name);
+                }
+            />
+            <span class=Style::file_name>
+                {move || file_name.get().unwrap_or_else(|| "none".to_owned())}
+            </span>
+            <select
+                title="Filter"
+                on:change=move |event| {
+                    if let Some(filter) = ChannelFilter::from_name(&event_target_value(&event)) {
+                        set_sampler(ChannelSampler { filter, ..sampler.get_untracked() });
+                    }
+                }
+            >
+                {ChannelFilter::ALL
+                    .into_iter()
+                    .map(|option| {
+                        view! {
+                            <option value=option.name() selected=move || sampler.get().filter == option>
+                                {option.name()}
+                            </option>
+                        }
+                    })
+                    .collect_view()}
+            </select>
+            <select
+                title="Wrap"
+                on:change=move |event| {
+                    if let Some(wrap) = ChannelWrap::from_name(&event_target_value(&event)) {
+                        set_sampler(ChannelSampler { wrap, ..sampler.get_untracked() });
+                    }
+                }
+            >
+                {ChannelWrap::ALL
+                    .into_iter()
+                    .map(|option| {
+                        view! {
+                            <option value=option.name() selected=move || sampler.get().wrap == option>
+                                {option.name()}
+                            </option>
+                        }
+                    })
+                    .collect_view()}
+            </select>
+            <button
+                on:click=move |_| {
+                    set_cubemap(window_handle, index, !is_cubemap.get_untracked(), is_cubemap);
+                }
+                data-toggled=move || is_cubemap.get()
+                data-hidden=move || kind.get() != ChannelKind::Image
+                title=format!("Convert panorama to a cubemap, bound as channel{index}_cube")
+            >
+                <BootstrapIcon icon="globe" />
+            </button>
+            <button on:click=move |_| set_kind(ChannelKind::None) title="Clear channel">
+                <BootstrapIcon icon="x" />
+            </button>
+        </div>
+        <div class=Style::channel data-hidden=move || kind.get() != ChannelKind::Text>
+            <textarea
+                class=Style::text
+                placeholder="Text to render into the channel"
+                rows="2"
+                on:change=move |event| {
+                    set_text_channel(window_handle, index, event_target_value(&event), file_name, thumbnail);
+                }
+            />
+        </div>
+        <div class=Style::channel data-hidden=move || hdr_image.with(Option::is_none)>
+            <span class=Style::label>"exposure"</span>
+            <input
+                type="range"
+                class=Style::exposure
+                min="-8"
+                max="8"
+                step="0.1"
+                prop:value=move || exposure.get()
+                on:change=move |event| {
+                    let Ok(value) = event_target_value(&event).parse::<f32>()
+                    else {
+                        return;
+                    };
+                    exposure.set(value);
+                    let (Some(window_handle), Some(image)) = (
+                        window_handle.get_value(),
+                        hdr_image.get_untracked(),
+                    )
+                    else {
+                        return;
+                    };
+                    spawn_local_and_handle_error(
+                        set_hdr_channel(window_handle, index, image, value, thumbnail),
+                    );
+                }
+            />
+            <span>{move || format!("{:+.1} EV", exposure.get())}</span>
+        </div>
+        <div class=Style::preview>
+            <img
+                class=Style::thumbnail
+                src=move || thumbnail.get()
+                data-hidden=move || thumbnail.with(|thumbnail| thumbnail.is_none())
+            />
+            // the webcam's video is copied into the channel, and doubles as its
+            // preview.
+            <video
+                class=Style::thumbnail
+                node_ref=video_ref
+                prop:muted=true
+                playsinline=true
+                data-hidden=move || kind.get() != ChannelKind::Webcam
+            />
+        </div>
+    }
+}
+
+#[component]
+fn MeshChannel(window_handle: StoredValue<Option<WindowHandle>>) -> impl IntoView {
+    let file_name = create_rw_signal::<Option<String>>(None);
+
+    view! {
+        <div class=Style::channel>
+            <span class=Style::label>"mesh"</span>
+            <label class=Style::file title="Load mesh (.obj or .glb)">
+                <BootstrapIcon icon="box" />
+                <input
+                    type="file"
+                    accept=".obj,.glb"
+                    on:change=move |event| {
+                        let input = event_target::<HtmlInputElement>(&event);
+                        let Some(file) = input.files().and_then(|files| files.get(0))
+                        else {
+                            return;
+                        };
+                        set_mesh(window_handle, Some(file), file_name);
+                        input.set_value("");
+                    }
+                />
+            </label>
+            <span class=Style::file_name>
+                {move || file_name.get().unwrap_or_else(|| "none".to_owned())}
+            </span>
+            <button
+                on:click=move |_| set_mesh(window_handle, None, file_name)
+                title="Clear mesh"
+            >
+                <BootstrapIcon icon="x" />
+            </button>
+        </div>
+    }
+}
+
+fn set_channel(
+    window_handle: StoredValue<Option<WindowHandle>>,
+    index: usize,
+    image: Option<(ChannelImage, String)>,
+    file_name: RwSignal<Option<String>>,
+    thumbnail: RwSignal<Option<String>>,
+) {
+    let Some(window_handle) = window_handle.get_value()
+    else {
+        return;
+    };
+
+    spawn_local_and_handle_error(async move {
+        let (image, name) = image.unzip();
+        let preview = image.as_ref().map(make_thumbnail).transpose()?;
+        window_handle.set_channel(index, image).await?;
+        file_name.set(name);
+        thumbnail.set(preview);
+        Ok::<(), ChannelError>(())
+    });
+}
+
+fn set_image_channel(
+    window_handle: StoredValue<Option<WindowHandle>>,
+    index: usize,
+    file: File,
+    file_name: RwSignal<Option<String>>,
+    thumbnail: RwSignal<Option<String>>,
+) {
+    if file.name().to_lowercase().ends_with(".ktx2") {
+        set_compressed_channel(window_handle, index, file, file_name, thumbnail);
+        return;
+    }
+
+    spawn_local_and_handle_error(async move {
+        let image = decode_image(&file).await?;
+        set_channel(
+            window_handle,
+            index,
+            Some((image, file.name())),
+            file_name,
+            thumbnail,
+        );
+        Ok::<(), ChannelError>(())
+    });
+}
+
+/// Binds a KTX2 texture to a channel. Compressed textures can't be drawn onto a
+/// canvas, so it has no preview.
+fn set_compressed_channel(
+    window_handle: StoredValue<Option<WindowHandle>>,
+    index: usize,
+    file: File,
+    file_name: RwSignal<Option<String>>,
+    thumbnail: RwSignal<Option<String>>,
+) {
+    let Some(window_handle) = window_handle.get_value()
+    else {
+        return;
+    };
+
+    spawn_local_and_handle_error(async move {
+        let buffer = JsFuture::from(file.array_buffer()).await?;
+        let image = CompressedImage::from_ktx2(&Uint8Array::new(&buffer).to_vec())?;
+        let name = format!("{} ({:?})", file.name(), image.format);
+        window_handle.set_channel_compressed(index, image).await?;
+        file_name.set(Some(name));
+        thumbnail.set(None);
+        Ok::<(), ChannelError>(())
+    });
+}
+
+/// Decodes a `.hdr` or `.exr` file and binds it to a channel.
+fn load_hdr_channel(
+    window_handle: StoredValue<Option<WindowHandle>>,
+    index: usize,
+    file: File,
+    hdr_image: RwSignal<Option<HdrImage>>,
+    exposure: RwSignal<f32>,
+    file_name: RwSignal<Option<String>>,
+    thumbnail: RwSignal<Option<String>>,
+) {
+    let Some(window_handle) = window_handle.get_value()
+    else {
+        return;
+    };
+
+    spawn_local_and_handle_error(async move {
+        let buffer = JsFuture::from(file.array_buffer()).await?;
+        let image = HdrImage::decode(&Uint8Array::new(&buffer).to_vec())?;
+        set_hdr_channel(
+            window_handle,
+            index,
+            image.clone(),
+            exposure.get_untracked(),
+            thumbnail,
+        )
+        .await?;
+        hdr_image.set(Some(image));
+        file_name.set(Some(file.name()));
+        Ok::<(), ChannelError>(())
+    });
+}
+
+/// Binds an HDR image with an exposure. The preview shows it tonemapped.
+async fn set_hdr_channel(
+    window_handle: WindowHandle,
+    index: usize,
+    image: HdrImage,
+    exposure: f32,
+    thumbnail: RwSignal<Option<String>>,
+) -> Result<(), ChannelError> {
+    let preview = make_thumbnail(&image.tonemap(exposure))?;
+    window_handle.set_channel_hdr(index, image, exposure).await?;
+    thumbnail.set(Some(preview));
+    Ok(())
+}
+
+/// Converts the channel's image to a cubemap, or stops doing so.
+fn set_cubemap(
+    window_handle: StoredValue<Option<WindowHandle>>,
+    index: usize,
+    value: bool,
+    is_cubemap: RwSignal<bool>,
+) {
+    let Some(window_handle) = window_handle.get_value()
+    else {
+        return;
+    };
+
+    spawn_local_and_handle_error(async move {
+        window_handle.set_channel_cubemap(index, value).await?;
+        is_cubemap.set(value);
+        Ok::<(), ChannelError>(())
+    });
+}
+
+fn set_text_channel(
+    window_handle: StoredValue<Option<WindowHandle>>,
+    index: usize,
+    text: String,
+    file_name: RwSignal<Option<String>>,
+    thumbnail: RwSignal<Option<String>>,
+) {
+    spawn_local_and_handle_error(async move {
+        let image = if text.is_empty() {
+            None
+        }
+        else {
+            Some((render_text(&text)?, format!("text: {text}")))
+        };
+        set_channel(window_handle, index, image, file_name, thumbnail);
+        Ok::<(), ChannelError>(())
+    });
+}
+
+/// Binds the depth buffer of a layer to the channel. The layer only has one if
+/// it's drawn with the mesh or its shader has a `@depth` directive.
+fn set_depth_channel(
+    window_handle: StoredValue<Option<WindowHandle>>,
+    index: usize,
+    layer: usize,
+    file_name: RwSignal<Option<String>>,
+) {
+    let Some(window_handle) = window_handle.get_value()
+    else {
+        return;
+    };
+
+    spawn_local_and_handle_error(async move {
+        window_handle.set_channel_depth(index, Some(layer)).await?;
+        file_name.set(Some(format!("depth: layer {layer}")));
+        Ok::<(), ChannelError>(())
+    });
+}
+
+/// Asks for access to the webcam and binds its video to the channel.
+///
+/// If the user doesn't allow access, the channel is reset to
+/// [`ChannelKind::None`].
+fn set_webcam_channel(
+    window_handle: StoredValue<Option<WindowHandle>>,
+    index: usize,
+    video: HtmlVideoElement,
+    webcam: StoredValue<Option<MediaStream>>,
+    kind: RwSignal<ChannelKind>,
+) {
+    let Some(window_handle) = window_handle.get_value()
+    else {
+        return;
+    };
+
+    spawn_local_and_handle_error(async move {
+        let result = async {
+            let constraints = MediaStreamConstraints::new();
+            constraints.set_video(&JsValue::TRUE);
+            let stream = get_user_media(&constraints).await?;
+
+            video.set_src_object(Some(&stream));
+            webcam.set_value(Some(stream));
+            JsFuture::from(video.play()?).await?;
+
+            window_handle.set_channel_video(index, Some(video)).await?;
+            Ok::<(), ChannelError>(())
+        }
+        .await;
+
+        if result.is_err() {
+            if let Some(stream) = webcam.try_update_value(|webcam| webcam.take()).flatten() {
+                stop_media_stream(&stream);
+            }
+            kind.set(ChannelKind::None);
+        }
+        result
+    });
+}
+
+/// Asks for access to the webcam or microphone.
+async fn get_user_media(constraints: &MediaStreamConstraints) -> Result<MediaStream, ChannelError> {
+    let stream = JsFuture::from(
+        gloo_utils::window()
+            .navigator()
+            .media_devices()?
+            .get_user_media_with_constraints(constraints)?,
+    )
+    .await?;
+    Ok(stream.unchecked_into())
+}
+
+fn stop_media_stream(stream: &MediaStream) {
+    for track in stream.get_tracks().iter() {
+        track.unchecked_into::<MediaStreamTrack>().stop();
+    }
+}
+
+/// The audio context that an audio channel is analysed in. Each channel has its
+/// own, so that it can be closed with the channel.
+struct AudioInput {
+    context: AudioContext,
+
+    /// The microphone's stream, if the audio comes from the microphone.
+    stream: Option<MediaStream>,
+}
+
+impl AudioInput {
+    fn stop(self) {
+        let _ = self.context.close();
+        if let Some(stream) = &self.stream {
+            stop_media_stream(stream);
+        }
+    }
+}
+
+/// Decodes an audio file and binds its spectrum and waveform to the channel.
+/// The file is played in a loop while it's bound.
+fn set_audio_file_channel(
+    window_handle: StoredValue<Option<WindowHandle>>,
+    index: usize,
+    file: File,
+    audio: StoredValue<Option<AudioInput>>,
+    file_name: RwSignal<Option<String>>,
+) {
+    let Some(window_handle) = window_handle.get_value()
+    else {
+        return;
+    };
+
+    if let Some(input) = audio.try_update_value(|audio| audio.take()).flatten() {
+        input.stop();
+    }
+
+    spawn_local_and_handle_error(async move {
+        let context = AudioContext::new()?;
+        audio.set_value(Some(AudioInput {
+            context: context.clone(),
+            stream: None,
+        }));
+
+        let data = JsFuture::from(file.array_buffer()).await?;
+        let buffer: AudioBuffer = JsFuture::from(context.decode_audio_data(data.unchecked_ref())?)
+            .await?
+            .unchecked_into();
+        let source = context.create_buffer_source()?;
+        source.set_buffer(Some(&buffer));
+        source.set_loop(true);
+        source.connect_with_audio_node(&context.destination())?;
+
+        set_audio_channel(window_handle, index, &context, &source).await?;
+        source.start()?;
+        file_name.set(Some(file.name()));
+        Ok::<(), ChannelError>(())
+    });
+}
+
+/// Asks for access to the microphone and binds its spectrum and waveform to the
+/// channel. The microphone isn't played back.
+///
+/// If the user doesn't allow access, the channel is reset to
+/// [`ChannelKind::None`].
+fn set_microphone_channel(
+    window_handle: StoredValue<Option<WindowHandle>>,
+    index: usize,
+    audio: StoredValue<Option<AudioInput>>,
+    kind: RwSignal<ChannelKind>,
+    file_name: RwSignal<Option<String>>,
+) {
+    let Some(window_handle) = window_handle.get_value()
+    else {
+        return;
+    };
+
+    spawn_local_and_handle_error(async move {
+        let result = async {
+            let constraints = MediaStreamConstraints::new();
+            constraints.set_audio(&JsValue::TRUE);
+            let stream = get_user_media(&constraints).await?;
+
+            let context = AudioContext::new()?;
+            audio.set_value(Some(AudioInput {
+                context: context.clone(),
+                stream: Some(stream.clone()),
+            }));
+            let source = context.create_media_stream_source(&stream)?;
+
+            set_audio_channel(window_handle, index, &context, &source).await?;
+            file_name.set(Some("microphone".to_owned()));
+            Ok::<(), ChannelError>(())
+        }
+        .await;
+
+        if result.is_err() {
+            if let Some(input) = audio.try_update_value(|audio| audio.take()).flatten() {
+                input.stop();
+            }
+            kind.set(ChannelKind::None);
+        }
+        result
+    });
+}
+
+/// Analyses the audio from `source` and binds it to the channel.
+async fn set_audio_channel(
+    window_handle: WindowHandle,
+    index: usize,
+    context: &AudioContext,
+    source: &AudioNode,
+) -> Result<(), ChannelError> {
+    let analyser = context.create_analyser()?;
+    analyser.set_fft_size(2 * AUDIO_SAMPLES);
+    source.connect_with_audio_node(&analyser)?;
+    window_handle
+        .set_channel_audio(index, Some(analyser))
+        .await?;
+
+    // the context might have been created without a user gesture, e.g. after
+    // asking for the microphone, and then starts suspended.
+    JsFuture::from(context.resume()?).await?;
+    Ok(())
+}
+
+fn set_mesh(
+    window_handle: StoredValue<Option<WindowHandle>>,
+    file: Option<File>,
+    file_name: RwSignal<Option<String>>,
+) {
+    let Some(window_handle) = window_handle.get_value()
+    else {
+        return;
+    };
+
+    spawn_local_and_handle_error(async move {
+        let mesh = if let Some(file) = &file {
+            Some(load_mesh(file).await?)
+        }
+        else {
+            None
+        };
+        window_handle.set_mesh(mesh);
+        file_name.set(file.map(|file| file.name()));
+        Ok::<(), ChannelError>(())
+    });
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChannelError {
+    #[error("graphics error")]
+    Graphics(#[from] graphics::Error),
+
+    #[error("failed to load mesh")]
+    Mesh(#[from] MeshError),
+
+    #[error("failed to load compressed texture")]
+    CompressedImage(#[from] CompressedImageError),
+
+    #[error("failed to load HDR image")]
+    HdrImage(#[from] HdrImageError),
+
+    #[error("javascript error: {message}")]
+    Js { message: String },
+}
+
+impl From<JsValue> for ChannelError {
+    fn from(value: JsValue) -> Self {
+        Self::Js {
+            message: format!("{value:?}"),
+        }
+    }
+}
+
+/// Scales an image down to a JPEG data URL for the channel's preview.
+fn make_thumbnail(image: &ChannelImage) -> Result<String, ChannelError> {
+    let document = gloo_utils::document();
+
+    let canvas: HtmlCanvasElement = document.create_element("canvas")?.unchecked_into();
+    canvas.set_width(image.width);
+    canvas.set_height(image.height);
+    let context: CanvasRenderingContext2d = canvas
+        .get_context("2d")?
+        .expect("no 2d context")
+        .unchecked_into();
+    let image_data =
+        ImageData::new_with_u8_clamped_array_and_sh(Clamped(&image.data), image.width, image.height)?;
+    context.put_image_data(&image_data, 0.0, 0.0)?;
+
+    let width = THUMBNAIL_WIDTH.min(image.width).max(1);
+    let height = (image.height * width / image.width.max(1)).max(1);
+    let thumbnail: HtmlCanvasElement = document.create_element("canvas")?.unchecked_into();
+    thumbnail.set_width(width);
+    thumbnail.set_height(height);
+    let thumbnail_context: CanvasRenderingContext2d = thumbnail
+        .get_context("2d")?
+        .expect("no 2d context")
+        .unchecked_into();
+    thumbnail_context.draw_image_with_html_canvas_element_and_dw_and_dh(
+        &canvas,
+        0.0,
+        0.0,
+        width as f64,
+        height as f64,
+    )?;
+
+    Ok(thumbnail.to_data_url_with_type("image/jpeg")?)
+}
+
+/// Decodes an image file into RGBA8 pixels, using the browser's image
+/// decoders.
+async fn decode_image(file: &File) -> Result<ChannelImage, ChannelError> {
+    let url = Url::create_object_url_with_blob(file)?;
+    let result = decode_image_from_url(&url).await;
+    Url::revoke_object_url(&url)?;
+    result
+}
+
+/// Loads an OBJ or binary glTF file, depending on its extension.
+async fn load_mesh(file: &File) -> Result<MeshData, ChannelError> {
+    if file.name().to_lowercase().ends_with(".obj") {
+        let source = JsFuture::from(file.text())
+            .await?
+            .as_string()
+            .unwrap_or_default();
+        Ok(MeshData::from_obj(&source)?)
+    }
+    else {
+        let buffer = JsFuture::from(file.array_buffer()).await?;
+        let data = Uint8Array::new(&buffer).to_vec();
+        Ok(MeshData::from_glb(&data)?)
+    }
+}
+
+async fn decode_image_from_url(url: &str) -> Result<ChannelImage, ChannelError> {
+    let image = HtmlImageElement::new()?;
+    image.set_src(url);
+    JsFuture::from(image.decode()).await?;
+
+    let width = image.natural_width();
+    let height = image.natural_height();
+
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .expect("no document");
+    let canvas: HtmlCanvasElement = document.create_element("canvas")?.unchecked_into();
+    canvas.set_width(width);
+    canvas.set_height(height);
+    let context: CanvasRenderingContext2d = canvas
+        .get_context("2d")?
+        .expect("no 2d context")
+        .unchecked_into();
+    context.draw_image_with_html_image_element(&image, 0.0, 0.0)?;
+
+    let image_data = context.get_image_data(0.0, 0.0, width as f64, height as f64)?;
+
+    Ok(ChannelImage {
+        width,
+        height,
+        data: image_data.data().0,
+    })
+}
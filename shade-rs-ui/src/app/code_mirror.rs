@@ -2,27 +2,79 @@ use leptos::{
     component,
     create_effect,
     create_node_ref,
-    html::Textarea,
+    ev,
+    event_target_value,
+    html::{
+        Input,
+        Textarea,
+    },
     on_cleanup,
     store_value,
     view,
+    window_event_listener,
+    Callable,
+    Callback,
     IntoView,
     ReadSignal,
     RwSignal,
+    SignalGet,
     SignalSet,
     SignalWithUntracked,
 };
-use serde::Serialize;
+use serde::{
+    Deserialize,
+    Serialize,
+};
 use wasm_bindgen::{
     prelude::Closure,
     JsCast,
     JsValue,
 };
+use web_sys::MouseEvent;
+
+use crate::app::{
+    color_literal::find_color_literal,
+    number_literal::{
+        find_number_literal,
+        NumberLiteral,
+    },
+};
+
+/// Gutter that shows swatches for color literals, see
+/// [`EditorOptions::color_swatches`].
+const COLOR_GUTTER: &str = "shade-rs-colors";
+
+/// A numeric literal that is being scrubbed by alt-dragging it.
+struct Scrub {
+    line: u32,
+    literal: NumberLiteral,
+    /// The literal's end, which moves when its text gets longer or shorter.
+    end: usize,
+    text: String,
+    start_x: f64,
+}
 
+/// Code editor.
+///
+/// Numeric literals can be alt-dragged to change their value. `on_scrub` is
+/// called every time the code changes while doing so.
 #[component]
-pub fn CodeMirror(contents: RwSignal<String>, options: ReadSignal<EditorOptions>) -> impl IntoView {
+pub fn CodeMirror(
+    contents: RwSignal<String>,
+    options: ReadSignal<EditorOptions>,
+    #[prop(optional, into)] on_scrub: Option<Callback<()>>,
+) -> impl IntoView {
     let text_area_node_ref = create_node_ref::<Textarea>();
+    let color_input_node_ref = create_node_ref::<Input>();
     let on_change_closure = store_value(None);
+    let on_gutter_click_closure = store_value(None);
+    let on_mouse_down_closure = store_value(None);
+    let stored_editor = store_value::<Option<code_mirror_sys::Editor>>(None);
+
+    // line of the color literal that the color picker is open for.
+    let color_line = store_value::<Option<u32>>(None);
+
+    let scrub = store_value::<Option<Scrub>>(None);
 
     create_effect(move |_| {
         tracing::debug!("textarea loaded");
@@ -32,19 +84,152 @@ pub fn CodeMirror(contents: RwSignal<String>, options: ReadSignal<EditorOptions>
         };
 
         tracing::debug!("attaching editor to textarea");
+        let color_swatches = options.with_untracked(|options| options.color_swatches);
         let options = options.with_untracked(|options| JsValue::from(options));
         let editor = code_mirror_sys::from_text_area(&text_area, &options);
         editor.set_value(&contents.with_untracked(|contents| JsValue::from(contents)));
+        if color_swatches {
+            update_color_swatches(&editor);
+        }
 
         let closure = Closure::wrap(Box::new(
             move |editor: code_mirror_sys::Editor, _value: JsValue| {
                 //let change = ChangeObject::try_from(value).unwrap();
                 contents.set(String::try_from(editor.get_value()).unwrap());
+                if color_swatches {
+                    update_color_swatches(&editor);
+                }
             },
         )
             as Box<dyn FnMut(code_mirror_sys::Editor, JsValue)>);
         editor.on("change", closure.as_ref().unchecked_ref());
         on_change_closure.set_value(Some(closure));
+
+        let closure = Closure::wrap(Box::new(
+            move |editor: code_mirror_sys::Editor, line: u32, gutter: String, _event: JsValue| {
+                if gutter != COLOR_GUTTER {
+                    return;
+                }
+                let Some(literal) = find_color_literal(&editor.get_line(line))
+                else {
+                    return;
+                };
+                if let Some(color_input) = color_input_node_ref.get_untracked() {
+                    color_line.set_value(Some(line));
+                    color_input.set_value(&literal.to_hex());
+                    color_input.click();
+                }
+            },
+        )
+            as Box<dyn FnMut(code_mirror_sys::Editor, u32, String, JsValue)>);
+        editor.on("gutterClick", closure.as_ref().unchecked_ref());
+        on_gutter_click_closure.set_value(Some(closure));
+
+        let closure = Closure::wrap(Box::new(
+            move |editor: code_mirror_sys::Editor, event: MouseEvent| {
+                if !event.alt_key() {
+                    return;
+                }
+                let coordinates = serde_wasm_bindgen::to_value(&Coordinates {
+                    left: event.client_x() as f64,
+                    top: event.client_y() as f64,
+                })
+                .unwrap();
+                let Ok(position) = serde_wasm_bindgen::from_value::<Position>(
+                    editor.coords_char(&coordinates, "window"),
+                )
+                else {
+                    return;
+                };
+                let line = editor.get_line(position.line);
+                let Some(literal) = find_number_literal(&line, position.ch)
+                else {
+                    return;
+                };
+
+                // keeps CodeMirror from moving the cursor or selecting.
+                event.prevent_default();
+                scrub.set_value(Some(Scrub {
+                    line: position.line,
+                    end: literal.end,
+                    text: String::new(),
+                    literal,
+                    start_x: event.client_x() as f64,
+                }));
+            },
+        )
+            as Box<dyn FnMut(code_mirror_sys::Editor, MouseEvent)>);
+        editor.on("mousedown", closure.as_ref().unchecked_ref());
+        on_mouse_down_closure.set_value(Some(closure));
+
+        stored_editor.set_value(Some(editor));
+    });
+
+    // rewrites the literal in place while a color is picked.
+    let on_color_input = move |event| {
+        let hex = event_target_value(&event);
+        let (Some(line), Some(editor)) = (color_line.get_value(), stored_editor.get_value())
+        else {
+            return;
+        };
+        let Some(literal) = find_color_literal(&editor.get_line(line))
+        else {
+            return;
+        };
+        if let Some(replacement) = literal.with_hex(&hex) {
+            editor.replace_range(
+                &replacement,
+                &Position::new(line, literal.start),
+                &Position::new(line, literal.end),
+            );
+        }
+    };
+
+    let mouse_move_handle = window_event_listener(ev::mousemove, move |event| {
+        let Some(editor) = stored_editor.get_value()
+        else {
+            return;
+        };
+        let changed = scrub.try_update_value(|scrub| {
+            let Some(scrub) = scrub
+            else {
+                return false;
+            };
+            let text = scrub
+                .literal
+                .scrubbed(event.client_x() as f64 - scrub.start_x);
+            if text == scrub.text {
+                return false;
+            }
+            editor.replace_range(
+                &text,
+                &Position::new(scrub.line, scrub.literal.start),
+                &Position::new(scrub.line, scrub.end),
+            );
+            scrub.end = scrub.literal.start + text.encode_utf16().count();
+            scrub.text = text;
+            true
+        });
+        if changed == Some(true) {
+            if let Some(on_scrub) = on_scrub {
+                on_scrub.call(());
+            }
+        }
+    });
+    let mouse_up_handle = window_event_listener(ev::mouseup, move |_| {
+        scrub.set_value(None);
+    });
+
+    // follow changes that don't come from the editor, e.g. opening a file.
+    create_effect(move |_| {
+        let contents = contents.get();
+        stored_editor.with_value(|editor| {
+            if let Some(editor) = editor {
+                if editor.get_value().as_string().as_deref() != Some(&contents) {
+                    editor.set_value(&JsValue::from(&contents));
+                }
+            }
+        });
     });
 
     on_cleanup(move || {
@@ -53,6 +238,18 @@ pub fn CodeMirror(contents: RwSignal<String>, options: ReadSignal<EditorOptions>
                 closure.forget();
             }
         });
+        on_gutter_click_closure.update_value(|opt| {
+            if let Some(closure) = opt.take() {
+                closure.forget();
+            }
+        });
+        on_mouse_down_closure.update_value(|opt| {
+            if let Some(closure) = opt.take() {
+                closure.forget();
+            }
+        });
+        mouse_move_handle.remove();
+        mouse_up_handle.remove();
     });
 
     view! {
@@ -62,8 +259,24 @@ pub fn CodeMirror(contents: RwSignal<String>, options: ReadSignal<EditorOptions>
                     width: 100%;
                     height: 100%;
                 }
+                .shade-rs-colors {
+                    width: 1em;
+                }
+                .shade-rs-color-swatch {
+                    display: inline-block;
+                    width: 0.75em;
+                    height: 0.75em;
+                    border: 1px solid gray;
+                    cursor: pointer;
+                }
             "#</style>
             <textarea node_ref=text_area_node_ref></textarea>
+            <input
+                node_ref=color_input_node_ref
+                type="color"
+                style="position: absolute; width: 0; height: 0; opacity: 0;"
+                on:input=on_color_input
+            />
         </div>
     }
 }
@@ -72,6 +285,12 @@ pub fn CodeMirror(contents: RwSignal<String>, options: ReadSignal<EditorOptions>
 #[serde(rename_all = "camelCase")]
 pub struct EditorOptions {
     pub line_numbers: bool,
+    pub gutters: Vec<&'static str>,
+
+    /// Show a swatch next to lines with a color literal. Clicking it opens a
+    /// color picker that rewrites the literal.
+    #[serde(skip)]
+    pub color_swatches: bool,
 }
 
 impl EditorOptions {
@@ -79,6 +298,54 @@ impl EditorOptions {
         self.line_numbers = v;
         self
     }
+
+    pub fn color_swatches(mut self, v: bool) -> Self {
+        self.color_swatches = v;
+        self.gutters.retain(|gutter| *gutter != COLOR_GUTTER);
+        if v {
+            self.gutters.push(COLOR_GUTTER);
+        }
+        self
+    }
+}
+
+/// A position in the document, as CodeMirror expects it.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+struct Position {
+    line: u32,
+    ch: usize,
+}
+
+impl Position {
+    fn new(line: u32, ch: usize) -> JsValue {
+        serde_wasm_bindgen::to_value(&Self { line, ch }).unwrap()
+    }
+}
+
+/// Window coordinates, as CodeMirror expects them.
+#[derive(Clone, Copy, Debug, Serialize)]
+struct Coordinates {
+    left: f64,
+    top: f64,
+}
+
+fn update_color_swatches(editor: &code_mirror_sys::Editor) {
+    let document = gloo_utils::document();
+    editor.clear_gutter(COLOR_GUTTER);
+
+    for line in 0..editor.line_count() {
+        let Some(literal) = find_color_literal(&editor.get_line(line))
+        else {
+            continue;
+        };
+        let Ok(swatch) = document.create_element("span")
+        else {
+            continue;
+        };
+        swatch.set_class_name("shade-rs-color-swatch");
+        let _ = swatch.set_attribute("style", &format!("background-color: {}", literal.to_hex()));
+        editor.set_gutter_marker(line, COLOR_GUTTER, &swatch);
+    }
 }
 
 impl From<&EditorOptions> for JsValue {
@@ -92,7 +359,10 @@ mod code_mirror_sys {
         prelude::wasm_bindgen,
         JsValue,
     };
-    use web_sys::HtmlTextAreaElement;
+    use web_sys::{
+        Element,
+        HtmlTextAreaElement,
+    };
 
     #[wasm_bindgen]
     extern "C" {
@@ -112,7 +382,16 @@ mod code_mirror_sys {
         #[wasm_bindgen(method, js_name = setValue)]
         pub fn set_value(this: &Doc, text: &JsValue);
 
-        #[derive(Debug)]
+        #[wasm_bindgen(method, js_name = getLine)]
+        pub fn get_line(this: &Doc, line: u32) -> String;
+
+        #[wasm_bindgen(method, js_name = lineCount)]
+        pub fn line_count(this: &Doc) -> u32;
+
+        #[wasm_bindgen(method, js_name = replaceRange)]
+        pub fn replace_range(this: &Doc, replacement: &str, from: &JsValue, to: &JsValue);
+
+        #[derive(Clone, Debug)]
         #[wasm_bindgen(extends = Doc)]
         pub type Editor;
 
@@ -131,5 +410,14 @@ mod code_mirror_sys {
         #[wasm_bindgen(method, js_name = setSize)]
         pub fn set_size(this: &Editor, width: &JsValue, height: &JsValue);
 
+        #[wasm_bindgen(method, js_name = setGutterMarker)]
+        pub fn set_gutter_marker(this: &Editor, line: u32, gutter_id: &str, element: &Element);
+
+        #[wasm_bindgen(method, js_name = clearGutter)]
+        pub fn clear_gutter(this: &Editor, gutter_id: &str);
+
+        #[wasm_bindgen(method, js_name = coordsChar)]
+        pub fn coords_char(this: &Editor, coordinates: &JsValue, mode: &str) -> JsValue;
+
     }
 }
@@ -0,0 +1,165 @@
+//! Finds color literals like `vec3f(1.0, 0.5, 0.0)` in shader code, so that
+//! the editor can show a swatch and a color picker for them.
+//!
+//! Shaders output linear colors, which are encoded as sRGB when they're drawn
+//! to the surface. The swatches show the encoded color, i.e. what the shader
+//! would draw, and picked colors are decoded back to linear.
+
+/// A `vec3` or `vec4` constructor whose components are all float literals in
+/// `0.0..=1.0`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorLiteral {
+    /// Start of the literal in the line, in UTF-16 code units like CodeMirror
+    /// counts them.
+    pub start: usize,
+    /// End of the literal (exclusive), in UTF-16 code units.
+    pub end: usize,
+
+    /// Constructor name, e.g. `vec3f` or `vec4<f32>`.
+    constructor: String,
+    rgb: [f32; 3],
+    /// Source text of the alpha component, kept as is when the color changes.
+    alpha: Option<String>,
+}
+
+impl ColorLiteral {
+    /// The color as CSS hex color, e.g. `#ff8000`.
+    pub fn to_hex(&self) -> String {
+        let [r, g, b] = self.rgb.map(|c| (linear_to_srgb(c) * 255.0).round() as u8);
+        format!("#{r:02x}{g:02x}{b:02x}")
+    }
+
+    /// Source text of the literal with its color replaced by a CSS hex color.
+    /// Returns `None` if `hex` isn't a hex color.
+    pub fn with_hex(&self, hex: &str) -> Option<String> {
+        let hex = hex.strip_prefix('#')?;
+        if hex.len() != 6 {
+            return None;
+        }
+        let mut components = vec![];
+        for i in 0..3 {
+            let value = u8::from_str_radix(hex.get(2 * i..2 * i + 2)?, 16).ok()?;
+            components.push(format_component(srgb_to_linear(value as f32 / 255.0)));
+        }
+        components.extend(self.alpha.clone());
+        Some(format!("{}({})", self.constructor, components.join(", ")))
+    }
+}
+
+/// Finds the first color literal in a line of code.
+pub fn find_color_literal(line: &str) -> Option<ColorLiteral> {
+    let mut search_start = 0;
+    while let Some(offset) = line[search_start..].find("vec") {
+        let start = search_start + offset;
+        search_start = start + 3;
+
+        let is_word_start = line[..start]
+            .chars()
+            .next_back()
+            .map_or(true, |c| !c.is_alphanumeric() && c != '_');
+        if !is_word_start {
+            continue;
+        }
+        if let Some(literal) = parse_color_literal(line, start) {
+            return Some(literal);
+        }
+    }
+    None
+}
+
+fn parse_color_literal(line: &str, start: usize) -> Option<ColorLiteral> {
+    let rest = &line[start..];
+    let size = match rest.get(3..4)? {
+        "3" => 3,
+        "4" => 4,
+        _ => return None,
+    };
+
+    // optional type suffix, e.g. `vec3f` or `vec3<f32>`.
+    let mut constructor_len = 4;
+    for suffix in ["f", "<f32>"] {
+        if rest[constructor_len..].starts_with(suffix) {
+            constructor_len += suffix.len();
+            break;
+        }
+    }
+    let constructor = &rest[..constructor_len];
+
+    let arguments = rest[constructor_len..].trim_start();
+    let arguments_start = rest.len() - arguments.len();
+    let arguments = arguments.strip_prefix('(')?;
+    let arguments_len = arguments.find(')')?;
+    let end = start + arguments_start + 1 + arguments_len + 1;
+
+    let components = arguments[..arguments_len]
+        .split(',')
+        .map(str::trim)
+        .collect::<Vec<_>>();
+    if components.len() != size {
+        return None;
+    }
+
+    let mut rgb = [0.0; 3];
+    for (value, component) in rgb.iter_mut().zip(&components) {
+        *value = parse_float(component).filter(|value| (0.0..=1.0).contains(value))?;
+    }
+    let alpha = if size == 4 {
+        parse_float(components[3])?;
+        Some(components[3].to_owned())
+    }
+    else {
+        None
+    };
+
+    Some(ColorLiteral {
+        start: utf16_len(&line[..start]),
+        end: utf16_len(&line[..end]),
+        constructor: constructor.to_owned(),
+        rgb,
+        alpha,
+    })
+}
+
+/// Parses a WGSL float literal like `0.5`, `.5`, `1.0f` or `1e-2`. Integer
+/// literals aren't colors.
+fn parse_float(literal: &str) -> Option<f32> {
+    let literal = literal.strip_suffix('f').unwrap_or(literal);
+    if !literal.contains(['.', 'e', 'E']) {
+        return None;
+    }
+    literal.parse().ok()
+}
+
+fn format_component(value: f32) -> String {
+    let formatted = format!("{value:.3}");
+    // keep at least one digit after the point, so it stays a float.
+    let trimmed = formatted.trim_end_matches('0');
+    if trimmed.ends_with('.') {
+        format!("{trimmed}0")
+    }
+    else {
+        trimmed.to_owned()
+    }
+}
+
+fn utf16_len(s: &str) -> usize {
+    s.chars().map(char::len_utf16).sum()
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    }
+    else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    }
+    else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
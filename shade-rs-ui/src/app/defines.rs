@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+
+use kardashev_style::style;
+use leptos::{
+    component,
+    event_target,
+    event_target_value,
+    view,
+    CollectView,
+    IntoView,
+    RwSignal,
+    Signal,
+    SignalUpdate,
+    SignalWith,
+};
+use shade_rs_compile::defines::{
+    Define,
+    DefineValue,
+};
+use web_sys::HtmlInputElement;
+
+#[style(path = "src/app/defines.scss")]
+struct Style;
+
+/// Values of the defines, by name. Like the parameters, these are kept when
+/// the shader is recompiled.
+pub type DefineValues = BTreeMap<String, DefineValue>;
+
+/// Controls for the defines of the shader: a checkbox for boolean ones and a
+/// number input for integers. Changing one calls `on_change`, which should
+/// recompile the shader with the new [`values`](DefineValues).
+///
+/// See [`shade_rs_compile::defines::DefineValue`].
+#[component]
+pub fn Defines<OnChange>(
+    #[prop(into)] defines: Signal<Vec<Define>>,
+    values: RwSignal<DefineValues>,
+    on_change: OnChange,
+) -> impl IntoView
+where
+    OnChange: Fn() + Copy + 'static,
+{
+    move || {
+        defines.with(|defines| {
+            defines
+                .iter()
+                .map(|define| view! { <DefineControl define=define.clone() values on_change /> })
+                .collect_view()
+        })
+    }
+}
+
+#[component]
+fn DefineControl<OnChange>(
+    define: Define,
+    values: RwSignal<DefineValues>,
+    on_change: OnChange,
+) -> impl IntoView
+where
+    OnChange: Fn() + Copy + 'static,
+{
+    let name = define.name.clone();
+    let default = define.default;
+    // values of the wrong kind are ignored by the compiler too.
+    let value = {
+        let name = name.clone();
+        move || {
+            values.with(|values| {
+                values
+                    .get(&name)
+                    .filter(|value| value.is_same_kind(&default))
+                    .copied()
+                    .unwrap_or(default)
+            })
+        }
+    };
+    let set_value = {
+        let name = name.clone();
+        move |value: DefineValue| {
+            values.update(|values| {
+                values.insert(name.clone(), value);
+            });
+            on_change();
+        }
+    };
+
+    let input = match default {
+        DefineValue::Bool(_) => {
+            view! {
+                <input
+                    type="checkbox"
+                    prop:checked=move || value() == DefineValue::Bool(true)
+                    on:change=move |event| {
+                        let checked = event_target::<HtmlInputElement>(&event).checked();
+                        set_value(DefineValue::Bool(checked));
+                    }
+                />
+            }
+        }
+        DefineValue::Int(_) => {
+            view! {
+                <input
+                    type="number"
+                    step="1"
+                    prop:value=move || value().to_string()
+                    on:change=move |event| {
+                        let Ok(number) = event_target_value(&event).parse::<i64>()
+                        else {
+                            return;
+                        };
+                        set_value(DefineValue::Int(number));
+                    }
+                />
+            }
+        }
+    };
+
+    view! {
+        <div class=Style::define>
+            <span class=Style::name>{name}</span>
+            {input}
+            <span class=Style::default>{format!("default: {default}")}</span>
+        </div>
+    }
+}
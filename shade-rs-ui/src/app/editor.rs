@@ -0,0 +1,710 @@
+use std::collections::{
+    BTreeMap,
+    VecDeque,
+};
+
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_node_ref,
+    create_rw_signal,
+    create_signal,
+    event_target_value,
+    html::Div,
+    request_animation_frame,
+    spawn_local,
+    store_value,
+    view,
+    IntoView,
+    RwSignal,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+    SignalUpdate,
+    SignalWith,
+};
+use leptos_use::use_throttle_fn;
+use shade_rs_compile::{
+    defines::Define,
+    reflect::EntryPointError,
+    CompileOptions,
+};
+
+#[cfg(feature = "export")]
+use crate::app::capture::CaptureButton;
+use crate::{
+    app::{
+        adapter::Adapter,
+        channels::Channels,
+        code_mirror::{
+            CodeMirror,
+            EditorOptions,
+        },
+        defines::Defines,
+        file::{
+            OpenButton,
+            SaveButton,
+        },
+        gizmo::TransformGizmo,
+        history::History,
+        icon::BootstrapIcon,
+        layers::Layers,
+        params::Params,
+        probe::{
+            PixelTooltip,
+            ProbeTooltip,
+        },
+        sequence::SequenceExport,
+        stats::Stats,
+        time::TimeControl,
+        tour::{
+            self,
+            Help,
+            Tour,
+        },
+        window::{
+            use_graphics,
+            Window,
+        },
+    },
+    graphics::{
+        self,
+        backend::{
+            is_webgpu_available,
+            BackendType,
+        },
+        compositor::LayerTransform,
+        inspect::ShaderInspection,
+        pixel::PixelValue,
+        probe::ProbeValue,
+        resolution::{
+            MAX_USER_SCALE,
+            MIN_USER_SCALE,
+        },
+        CompileTimings,
+        FrameInfo,
+        Graphics,
+        OutputEncoding,
+        PlaybackState,
+        SelectBackendType,
+        TargetFps,
+        WindowHandle,
+    },
+};
+
+#[style(path = "src/app/app.scss")]
+struct Style;
+
+#[component]
+pub fn App() -> impl IntoView {
+    let window_handle = store_value::<Option<WindowHandle>>(None);
+
+    let code = create_rw_signal(INITIAL_CODE.to_owned());
+    let file_name = create_rw_signal::<Option<String>>(None);
+    let (options, _set_options) = create_signal(
+        EditorOptions::default()
+            .line_numbers(true)
+            .color_swatches(true),
+    );
+    //let code_debounced = signal_debounced(code, 1000.0);
+    let frame_info = create_rw_signal(FrameInfo::default());
+    let paused = create_rw_signal(false);
+    let render_scale = create_rw_signal(1.0f32);
+    let target_fps = create_rw_signal(TargetFps::default());
+    let render_on_demand = create_rw_signal(false);
+    let output_encoding = create_rw_signal(OutputEncoding::default());
+    let compiler_output = create_rw_signal::<Option<String>>(None);
+    let suggest_webgpu = create_rw_signal(false);
+    let graphics = create_rw_signal(use_graphics());
+    let show_inspector = create_rw_signal(false);
+    let show_diff = create_rw_signal(false);
+    let show_stats = create_rw_signal(false);
+    let show_adapter = create_rw_signal(false);
+    let show_channels = create_rw_signal(false);
+    let show_params = create_rw_signal(false);
+    let show_layers = create_rw_signal(false);
+    let show_history = create_rw_signal(false);
+    let show_sequence = create_rw_signal(false);
+    let show_tour = create_rw_signal(false);
+    if tour::is_first_run() {
+        // the tour needs the elements it highlights to be in the document.
+        request_animation_frame(move || show_tour.set(true));
+    }
+    // transform of the layer that is being edited with the gizmo.
+    let transform_gizmo = create_rw_signal::<Option<RwSignal<LayerTransform>>>(None);
+    let params_layout = create_rw_signal(None);
+    let inject_inputs = create_rw_signal(CompileOptions::default().inject_inputs);
+    // the defines the shader declared, and the values the user picked for them.
+    let defines = create_rw_signal(Vec::<Define>::new());
+    let define_values = create_rw_signal(BTreeMap::new());
+    let compile_options = move || {
+        CompileOptions {
+            inject_inputs: inject_inputs.get_untracked(),
+            defines: define_values.get_untracked(),
+            ..Default::default()
+        }
+    };
+    let compile_history = create_rw_signal(VecDeque::<CompileTimings>::new());
+    let inspection = create_rw_signal::<Option<Result<ShaderInspection, String>>>(None);
+    let preview_node_ref = create_node_ref::<Div>();
+    // mouse position relative to the preview, for the probe tooltip.
+    let preview_mouse_position = create_rw_signal::<Option<[f32; 2]>>(None);
+    let probe_values = create_rw_signal(Vec::<ProbeValue>::new());
+    // while set, clicking the preview reads back the pixel under the mouse.
+    let inspect_pixels = create_rw_signal(false);
+    let inspected_pixel = create_rw_signal::<Option<([f32; 2], PixelValue)>>(None);
+    // the fragment entry points of the shader, to pick the one that is run.
+    let fragment_entry_points = create_rw_signal(Vec::<String>::new());
+    let fragment_entry_point = create_rw_signal::<Option<String>>(None);
+
+    let inspect = move || {
+        if !show_inspector.get_untracked() {
+            return;
+        }
+        let Some(window_handle) = window_handle.get_value()
+        else {
+            return;
+        };
+        let code = code.get_untracked();
+        spawn_local(async move {
+            let result = window_handle
+                .inspect(code, compile_options())
+                .await
+                .map_err(|error| error.to_string());
+            inspection.set(Some(result));
+        });
+    };
+
+    let run = move || {
+        let Some(window_handle) = window_handle.get_value()
+        else {
+            return;
+        };
+        let code = code.get_untracked();
+        inspect();
+        spawn_local(async move {
+            match window_handle
+                .run(code, compile_options(), fragment_entry_point.get_untracked())
+                .await
+            {
+                Ok(run_info) => {
+                    compile_history.update(|compile_history| {
+                        compile_history.push_front(run_info.timings);
+                        compile_history.truncate(COMPILE_HISTORY_LENGTH);
+                    });
+                    paused.set(run_info.paused);
+                    params_layout.set(run_info.params);
+                    probe_values.set(vec![]);
+                    fragment_entry_points.set(run_info.fragment_entry_points);
+                    fragment_entry_point.set(Some(run_info.fragment_entry_point));
+                    defines.set(run_info.defines);
+                    suggest_webgpu.set(false);
+                    if run_info.warnings.is_empty() {
+                        compiler_output.set(None);
+                    }
+                    else {
+                        let warnings = run_info
+                            .warnings
+                            .iter()
+                            .map(|warning| warning.to_string())
+                            .collect::<Vec<_>>();
+                        compiler_output.set(Some(warnings.join("\n")));
+                    }
+                }
+                Err(error) => {
+                    if let graphics::CompileError::Compile(
+                        shade_rs_compile::CompileError::EntryPoint(error),
+                    ) = &error
+                    {
+                        fragment_entry_points.set(error.candidates().to_vec());
+                        if matches!(error, EntryPointError::Unknown { .. }) {
+                            // the selected entry point was removed from the code.
+                            fragment_entry_point.set(None);
+                        }
+                    }
+                    suggest_webgpu.set(error.suggests_webgpu() && is_webgpu_available());
+                    compiler_output.set(Some(error.to_string()));
+                }
+            }
+        });
+    };
+
+    // scrubbing a number changes the code a lot faster than it can be compiled.
+    let run_throttled = use_throttle_fn(run, SCRUB_RUN_INTERVAL);
+
+    #[cfg(feature = "export")]
+    let capture_button = view! { <CaptureButton window_handle /> };
+    #[cfg(not(feature = "export"))]
+    let capture_button = ();
+
+    view! {
+        <div class=Style::app>
+            <div
+                node_ref=preview_node_ref
+                class=Style::preview
+                on:mousemove=move |event| {
+                    let Some(preview) = preview_node_ref.get_untracked()
+                    else {
+                        return;
+                    };
+                    let rect = preview.get_bounding_client_rect();
+                    preview_mouse_position.set(Some([
+                        event.client_x() as f32 - rect.left() as f32,
+                        event.client_y() as f32 - rect.top() as f32,
+                    ]));
+                }
+                on:mouseleave=move |_| preview_mouse_position.set(None)
+                on:click=move |_| {
+                    if !inspect_pixels.get_untracked() {
+                        return;
+                    }
+                    let (Some(window_handle), Some(position)) = (
+                        window_handle.get_value(),
+                        preview_mouse_position.get_untracked(),
+                    )
+                    else {
+                        return;
+                    };
+                    spawn_local(async move {
+                        match window_handle.read_pixel(position).await {
+                            Ok(value) => inspected_pixel.set(Some((position, value))),
+                            Err(error) => {
+                                tracing::warn!(%error, "failed to inspect pixel");
+                                inspected_pixel.set(None);
+                            }
+                        }
+                    });
+                }
+            >
+                {move || {
+                    view! {
+                        <Window
+                            graphics=graphics.get()
+                            playback_state=PlaybackState {
+                                paused: paused.get_untracked(),
+                                ..PlaybackState::from(frame_info.get_untracked())
+                            }
+                            on_load=move |handle| {
+                                handle.set_render_scale(render_scale.get_untracked());
+                                handle.set_target_fps(target_fps.get_untracked());
+                                handle.set_render_on_demand(render_on_demand.get_untracked());
+                                handle.set_output_encoding(output_encoding.get_untracked());
+                                handle.set_diff(show_diff.get_untracked());
+                                handle.set_on_probe(Some(Box::new(move |values| {
+                                    probe_values.set(values);
+                                })));
+                                let mut surface_error = None;
+                                handle.set_on_surface_error(Some(Box::new(move |error: Option<graphics::Error>| {
+                                    // only clear the output if it still shows the surface error.
+                                    match error.map(|error| error.to_string()) {
+                                        Some(message) => {
+                                            compiler_output.set(Some(message.clone()));
+                                            surface_error = Some(message);
+                                        }
+                                        None => {
+                                            if surface_error.take().is_some_and(|message| {
+                                                compiler_output.with_untracked(|output| output.as_ref() == Some(&message))
+                                            }) {
+                                                compiler_output.set(None);
+                                            }
+                                        }
+                                    }
+                                })));
+                                handle.set_on_device_error(Some(Box::new(move |error: graphics::Error| {
+                                    compiler_output.set(Some(error.to_string()));
+                                })));
+                                window_handle.set_value(Some(handle));
+                                if PLAY_ON_LOAD {
+                                    run();
+                                }
+                            }
+                            on_frame=move |info| {
+                                frame_info.set(info);
+                            }
+                        />
+                    }
+                }}
+                {move || {
+                    transform_gizmo.get().map(|transform| view! { <TransformGizmo transform /> })
+                }}
+                <ProbeTooltip values=probe_values mouse_position=preview_mouse_position />
+                <PixelTooltip pixel=inspected_pixel />
+            </div>
+            <div class=Style::toolbar>
+                <button
+                    on:click=move |_| run()
+                    data-tour="run"
+                >
+                    <BootstrapIcon icon="play-fill" />
+                </button>
+                <select
+                    class=Style::entry_point
+                    title="Fragment entry point"
+                    data-hidden=move || fragment_entry_points.with(|names| names.len() < 2)
+                    on:change=move |event| {
+                        fragment_entry_point.set(Some(event_target_value(&event)));
+                        run();
+                    }
+                >
+                    {move || {
+                        fragment_entry_points
+                            .get()
+                            .into_iter()
+                            .map(|name| {
+                                let selected = {
+                                    let name = name.clone();
+                                    move || {
+                                        fragment_entry_point
+                                            .with(|selected| selected.as_ref() == Some(&name))
+                                    }
+                                };
+                                view! {
+                                    <option value=name.clone() selected=selected>
+                                        {name}
+                                    </option>
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                    }}
+                </select>
+                <button
+                    on:click=move |_| {
+                        if let Some(window_handle) = window_handle.get_value() {
+                            let new_value = !paused.get();
+                            paused.set(new_value);
+                            spawn_local(async move {
+                                window_handle.set_paused(new_value);
+                            });
+                        }
+                    }
+                    data-toggled=move || paused.get()
+                >
+                    <BootstrapIcon icon="pause-fill" />
+                </button>
+                <button
+                    on:click=move |_| {
+                        if let Some(window_handle) = window_handle.get_value() {
+                            spawn_local(async move {
+                                window_handle.reset();
+                            });
+                        }
+                    }
+                >
+                    <BootstrapIcon icon="skip-start-fill" />
+                </button>
+                <OpenButton
+                    file_name
+                    on_open=move |contents| {
+                        code.set(contents);
+                        run();
+                    }
+                />
+                <SaveButton code file_name />
+                {capture_button}
+                <button
+                    on:click=move |_| {
+                        show_inspector.set(!show_inspector.get());
+                        inspect();
+                    }
+                    data-toggled=move || show_inspector.get()
+                    title="Inspect compiler output"
+                >
+                    <BootstrapIcon icon="bug" />
+                </button>
+                <button
+                    on:click=move |_| {
+                        let new_value = !show_diff.get();
+                        show_diff.set(new_value);
+                        if let Some(window_handle) = window_handle.get_value() {
+                            window_handle.set_diff(new_value);
+                        }
+                    }
+                    data-toggled=move || show_diff.get()
+                    title="Show the difference to the previous run"
+                >
+                    <BootstrapIcon icon="subtract" />
+                </button>
+                <button
+                    on:click=move |_| {
+                        inspect_pixels.set(!inspect_pixels.get());
+                        inspected_pixel.set(None);
+                    }
+                    data-toggled=move || inspect_pixels.get()
+                    title="Inspect pixels by clicking the preview"
+                >
+                    <BootstrapIcon icon="eyedropper" />
+                </button>
+                <button
+                    on:click=move |_| {
+                        inject_inputs.set(!inject_inputs.get());
+                        run();
+                    }
+                    data-toggled=move || inject_inputs.get()
+                    title="Declare inputs automatically"
+                >
+                    <BootstrapIcon icon="braces" />
+                </button>
+                <button
+                    on:click=move |_| show_channels.set(!show_channels.get())
+                    data-toggled=move || show_channels.get()
+                    title="Inputs"
+                >
+                    <BootstrapIcon icon="images" />
+                </button>
+                <button
+                    on:click=move |_| show_params.set(!show_params.get())
+                    data-toggled=move || show_params.get()
+                    data-tour="params"
+                    title="Parameters"
+                >
+                    <BootstrapIcon icon="sliders" />
+                </button>
+                <button
+                    on:click=move |_| show_layers.set(!show_layers.get())
+                    data-toggled=move || show_layers.get()
+                    title="Layers"
+                >
+                    <BootstrapIcon icon="layers" />
+                </button>
+                <button
+                    on:click=move |_| show_history.set(!show_history.get())
+                    data-toggled=move || show_history.get()
+                    title="History"
+                >
+                    <BootstrapIcon icon="clock-history" />
+                </button>
+                <button
+                    on:click=move |_| show_sequence.set(!show_sequence.get())
+                    data-toggled=move || show_sequence.get()
+                    title="Image sequence"
+                >
+                    <BootstrapIcon icon="film" />
+                </button>
+                <button
+                    on:click=move |_| show_stats.set(!show_stats.get())
+                    data-toggled=move || show_stats.get()
+                    title="Statistics"
+                >
+                    <BootstrapIcon icon="speedometer2" />
+                </button>
+                <button
+                    on:click=move |_| show_adapter.set(!show_adapter.get())
+                    data-toggled=move || show_adapter.get()
+                    title="Adapter"
+                >
+                    <BootstrapIcon icon="gpu-card" />
+                </button>
+                <button
+                    on:click=move |_| show_tour.set(true)
+                    data-tour="help"
+                    title="Show tour"
+                >
+                    <BootstrapIcon icon="question-circle" />
+                </button>
+                <label class=Style::render_scale title="Render resolution">
+                    <input
+                        type="range"
+                        min=MIN_USER_SCALE
+                        max=MAX_USER_SCALE
+                        step="0.25"
+                        value=move || render_scale.get()
+                        on:input=move |event| {
+                            let Ok(scale) = event_target_value(&event).parse()
+                            else {
+                                return;
+                            };
+                            render_scale.set(scale);
+                            if let Some(window_handle) = window_handle.get_value() {
+                                window_handle.set_render_scale(scale);
+                            }
+                        }
+                    />
+                    {move || format!("{}x", render_scale.get())}
+                </label>
+                <button
+                    on:click=move |_| {
+                        let new_value = !render_on_demand.get();
+                        render_on_demand.set(new_value);
+                        if let Some(window_handle) = window_handle.get_value() {
+                            window_handle.set_render_on_demand(new_value);
+                        }
+                    }
+                    data-toggled=move || render_on_demand.get()
+                    title="Only render when something changes, if the shader doesn't use the time"
+                >
+                    <BootstrapIcon icon="battery-half" />
+                </button>
+                <button
+                    on:click=move |_| {
+                        let new_value = match output_encoding.get() {
+                            OutputEncoding::Srgb => OutputEncoding::Linear,
+                            OutputEncoding::Linear => OutputEncoding::Srgb,
+                        };
+                        output_encoding.set(new_value);
+                        if let Some(window_handle) = window_handle.get_value() {
+                            window_handle.set_output_encoding(new_value);
+                        }
+                    }
+                    data-toggled=move || output_encoding.get() == OutputEncoding::Linear
+                    title="Show the shader's output without sRGB encoding, like Shadertoy does"
+                >
+                    <BootstrapIcon icon="circle-half" />
+                </button>
+                <select
+                    title="Frame rate cap"
+                    on:change=move |event| {
+                        let Some(value) = TargetFps::from_name(&event_target_value(&event))
+                        else {
+                            return;
+                        };
+                        target_fps.set(value);
+                        if let Some(window_handle) = window_handle.get_value() {
+                            window_handle.set_target_fps(value);
+                        }
+                    }
+                >
+                    {TargetFps::ALL
+                        .into_iter()
+                        .map(|value| {
+                            view! {
+                                <option
+                                    value=value.name()
+                                    selected=move || target_fps.get() == value
+                                >
+                                    {match value {
+                                        TargetFps::Unlimited => "unlimited".to_owned(),
+                                        _ => format!("{} FPS", value.name()),
+                                    }}
+                                </option>
+                            }
+                        })
+                        .collect::<Vec<_>>()}
+                </select>
+                <TimeControl frame_info window_handle />
+                <span class=Style::fps>
+                {move || {
+                    frame_info.with(|frame_info| format!("{:.1} FPS", frame_info.fps))
+                }}
+                </span>
+            </div>
+            <div
+                class=Style::compiler_output
+                data-hidden=move || compiler_output.with(|output| output.is_none())
+            >
+                {move || compiler_output.get().unwrap_or_default()}
+                <button
+                    data-hidden=move || !suggest_webgpu.get()
+                    on:click=move |_| {
+                        // a canvas can't switch its context type, so this
+                        // recreates the window with a new graphics instance.
+                        suggest_webgpu.set(false);
+                        graphics.set(Graphics::new(graphics::Config {
+                            backend_type: SelectBackendType::Select(BackendType::WebGpu),
+                            ..Default::default()
+                        }));
+                    }
+                >
+                    "Switch to WebGPU"
+                </button>
+            </div>
+            <div
+                class=Style::channels_panel
+                data-hidden=move || !show_channels.get()
+            >
+                <Help text="Images, text and the webcam can be bound to the texture channels. Channel N is at @group(1) @binding(2N) with its sampler at @binding(2N+1). The keyboard and the previous frame have their own bind groups, and meshes are drawn by shaders with vertex inputs." />
+                <Channels window_handle />
+            </div>
+            <div
+                class=Style::params_panel
+                data-hidden=move || !show_params.get()
+            >
+                <Help text="Each field of the uniform struct at @group(0) @binding(1) gets a control. Changing it doesn't recompile the shader. Defines declared with // @define NAME VALUE are listed above them, and changing one does." />
+                <Defines defines values=define_values on_change=run />
+                <Params layout=params_layout window_handle />
+            </div>
+            <div
+                class=Style::layers_panel
+                data-hidden=move || !show_layers.get()
+            >
+                <Help text="Layers are other shaders drawn on top of this one, blended and optionally masked. The arrows button lets you move, scale and rotate a layer in the preview." />
+                <Layers window_handle code compile_options transform_gizmo />
+            </div>
+            <div
+                class=Style::history_panel
+                data-hidden=move || !show_history.get()
+            >
+                <Help text="Snapshots of the preview together with the code that rendered it. Drag the slider to go back in time and restore an earlier version." />
+                <History
+                    window_handle
+                    code
+                    on_restore=move |restored| {
+                        code.set(restored);
+                        run();
+                    }
+                />
+            </div>
+            <div
+                class=Style::sequence_panel
+                data-hidden=move || !show_sequence.get()
+            >
+                <Help text="Renders the frames from start to end at their own resolution and downloads them as a zip of PNGs. Mouse and sound are not part of the export." />
+                <SequenceExport window_handle />
+            </div>
+            <div
+                class=Style::stats_panel
+                data-hidden=move || !show_stats.get()
+            >
+                <Help text="Frame rate and how long the last compilations took, per stage." />
+                <Stats frame_info compile_history window_handle />
+            </div>
+            <div
+                class=Style::adapter_panel
+                data-hidden=move || !show_adapter.get()
+            >
+                <Help text="The backend and GPU adapter that render the preview. Shaders that use features or exceed limits the adapter doesn't have fail to run. WebGL lacks the capabilities listed as missing, e.g. compute shaders." />
+                <Adapter show=show_adapter window_handle />
+            </div>
+            <div
+                class=Style::inspector
+                data-hidden=move || !show_inspector.get()
+            >
+                {move || {
+                    inspection
+                        .get()
+                        .map(|result| {
+                            match result {
+                                Ok(inspection) => {
+                                    view! {
+                                        <h4>"Bind groups"</h4>
+                                        <pre>{inspection.bind_groups}</pre>
+                                        <h4>{inspection.backend_language}</h4>
+                                        <pre>{inspection.backend_shader}</pre>
+                                        <h4>"Naga IR"</h4>
+                                        <pre>{inspection.ir}</pre>
+                                    }
+                                        .into_view()
+                                }
+                                Err(error) => view! { <pre>{error}</pre> }.into_view(),
+                            }
+                        })
+                }}
+            </div>
+            <div class=Style::editor data-tour="editor">
+                <CodeMirror
+                    contents=code
+                    options
+                    on_scrub=move |_| {
+                        run_throttled();
+                    }
+                />
+            </div>
+            <Tour active=show_tour />
+        </div>
+    }
+}
+
+const INITIAL_CODE: &'static str = include_str!("shader.wgsl");
+const PLAY_ON_LOAD: bool = true;
+const COMPILE_HISTORY_LENGTH: usize = 10;
+/// Milliseconds between runs while scrubbing a number in the code.
+const SCRUB_RUN_INTERVAL: f64 = 100.0;
@@ -0,0 +1,124 @@
+use leptos::{
+    component,
+    event_target,
+    view,
+    IntoView,
+    RwSignal,
+    SignalGetUntracked,
+    SignalSet,
+};
+use wasm_bindgen::{
+    JsCast,
+    JsValue,
+};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    Blob,
+    BlobPropertyBag,
+    HtmlAnchorElement,
+    HtmlInputElement,
+    Url,
+};
+
+use crate::{
+    app::icon::BootstrapIcon,
+    utils::futures::spawn_local_and_handle_error,
+};
+
+/// File name used when saving a shader that wasn't opened from a file.
+const DEFAULT_FILE_NAME: &str = "shader.wgsl";
+
+/// Toolbar button that opens a shader from a local file.
+///
+/// `on_open` is called with the contents of the file. The file name is stored
+/// in `file_name`, so that [`SaveButton`] can save to the same name.
+#[component]
+pub fn OpenButton<OnOpen>(file_name: RwSignal<Option<String>>, on_open: OnOpen) -> impl IntoView
+where
+    OnOpen: Fn(String) + Copy + 'static,
+{
+    view! {
+        <label title="Open shader">
+            <BootstrapIcon icon="folder2-open" />
+            <input
+                type="file"
+                accept=".wgsl,text/plain"
+                on:change=move |event| {
+                    let input = event_target::<HtmlInputElement>(&event);
+                    let Some(file) = input.files().and_then(|files| files.get(0))
+                    else {
+                        return;
+                    };
+                    input.set_value("");
+                    spawn_local_and_handle_error(async move {
+                        let contents = JsFuture::from(file.text()).await?;
+                        let contents = contents.as_string().ok_or(FileError::NotText)?;
+                        file_name.set(Some(file.name()));
+                        on_open(contents);
+                        Ok::<(), FileError>(())
+                    });
+                }
+            />
+        </label>
+    }
+}
+
+/// Toolbar button that saves the shader to a local file.
+///
+/// In a browser this is a regular download. The desktop app asks where to save
+/// it.
+#[component]
+pub fn SaveButton(code: RwSignal<String>, file_name: RwSignal<Option<String>>) -> impl IntoView {
+    view! {
+        <button
+            on:click=move |_| {
+                let file_name = file_name
+                    .get_untracked()
+                    .unwrap_or_else(|| DEFAULT_FILE_NAME.to_owned());
+                if let Err(error) = download(&file_name, &code.get_untracked()) {
+                    tracing::error!(%error);
+                }
+            }
+            title="Save shader"
+        >
+            <BootstrapIcon icon="floppy" />
+        </button>
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FileError {
+    #[error("file is not a text file")]
+    NotText,
+
+    #[error("javascript error: {message}")]
+    Js { message: String },
+}
+
+impl From<JsValue> for FileError {
+    fn from(value: JsValue) -> Self {
+        Self::Js {
+            message: format!("{value:?}"),
+        }
+    }
+}
+
+/// Offers `contents` as a download with the given file name.
+fn download(file_name: &str, contents: &str) -> Result<(), FileError> {
+    let options = BlobPropertyBag::new();
+    options.set_type("text/plain");
+    let blob = Blob::new_with_str_sequence_and_options(
+        &js_sys::Array::of1(&JsValue::from_str(contents)),
+        &options,
+    )?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+
+    let anchor: HtmlAnchorElement = gloo_utils::document().create_element("a")?.unchecked_into();
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+
+    Url::revoke_object_url(&url)?;
+
+    Ok(())
+}
@@ -0,0 +1,176 @@
+use std::f32::consts::FRAC_PI_2;
+
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_node_ref,
+    html::Div,
+    store_value,
+    view,
+    HtmlElement,
+    IntoView,
+    RwSignal,
+    SignalGet,
+    SignalGetUntracked,
+    SignalUpdate,
+};
+use wasm_bindgen::JsCast;
+use web_sys::{
+    Element,
+    PointerEvent,
+};
+
+use crate::graphics::compositor::LayerTransform;
+
+#[style(path = "src/app/gizmo.scss")]
+struct Style;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Handle {
+    Move,
+    Scale,
+    Rotate,
+}
+
+/// Overlay that shows a layer's bounds and lets the user move, scale and rotate
+/// it by dragging.
+///
+/// This must be placed in a container that is positioned over the window.
+#[component]
+pub fn TransformGizmo(transform: RwSignal<LayerTransform>) -> impl IntoView {
+    let overlay_node_ref = create_node_ref::<Div>();
+    // the handle being dragged, and the last pointer position relative to the
+    // overlay.
+    let drag_state = store_value::<Option<(Handle, [f32; 2])>>(None);
+
+    // the handles only start a drag. the pointer is then captured, and moves
+    // bubble up to the bounds.
+    let start_drag = move |handle: Handle| {
+        move |event: PointerEvent| {
+            event.stop_propagation();
+            let Some(position) = overlay_position(overlay_node_ref.get_untracked(), &event)
+            else {
+                return;
+            };
+            if let Some(target) = event
+                .target()
+                .and_then(|target| target.dyn_into::<Element>().ok())
+            {
+                let _ = target.set_pointer_capture(event.pointer_id());
+            }
+            drag_state.set_value(Some((handle, position)));
+        }
+    };
+
+    let on_pointer_move = move |event: PointerEvent| {
+        let Some((handle, previous)) = drag_state.get_value()
+        else {
+            return;
+        };
+        let Some(overlay) = overlay_node_ref.get_untracked()
+        else {
+            return;
+        };
+        let rect = overlay.get_bounding_client_rect();
+        let size = [rect.width() as f32, rect.height() as f32];
+        let Some(position) = overlay_position(Some(overlay), &event)
+        else {
+            return;
+        };
+        drag_state.set_value(Some((handle, position)));
+        transform.update(|transform| drag(handle, transform, size, previous, position));
+    };
+
+    let on_pointer_up = move |_event: PointerEvent| {
+        drag_state.set_value(None);
+    };
+
+    let bounds_style = move || {
+        let LayerTransform {
+            scale,
+            rotation,
+            offset,
+        } = transform.get();
+        format!(
+            "left: {}%; top: {}%; width: {}%; height: {}%; transform: translate(-50%, -50%) rotate({}rad);",
+            50.0 + 50.0 * offset[0],
+            50.0 - 50.0 * offset[1],
+            100.0 * scale,
+            100.0 * scale,
+            -rotation,
+        )
+    };
+
+    view! {
+        <div class=Style::overlay node_ref=overlay_node_ref>
+            <div
+                class=Style::bounds
+                style=bounds_style
+                on:pointerdown=start_drag(Handle::Move)
+                on:pointermove=on_pointer_move
+                on:pointerup=on_pointer_up
+                title="Drag to move"
+            >
+                <div
+                    class=Style::scale_handle
+                    on:pointerdown=start_drag(Handle::Scale)
+                    title="Drag to scale"
+                ></div>
+                <div
+                    class=Style::rotate_handle
+                    on:pointerdown=start_drag(Handle::Rotate)
+                    title="Drag to rotate"
+                ></div>
+            </div>
+        </div>
+    }
+}
+
+/// Position of the pointer relative to the overlay, in pixels.
+fn overlay_position(overlay: Option<HtmlElement<Div>>, event: &PointerEvent) -> Option<[f32; 2]> {
+    let rect = overlay?.get_bounding_client_rect();
+    Some([
+        event.client_x() as f32 - rect.left() as f32,
+        event.client_y() as f32 - rect.top() as f32,
+    ])
+}
+
+/// Updates the transform for a drag of `handle` from `previous` to `position`.
+///
+/// Positions are in pixels, relative to the overlay of the given `size`.
+fn drag(
+    handle: Handle,
+    transform: &mut LayerTransform,
+    size: [f32; 2],
+    previous: [f32; 2],
+    position: [f32; 2],
+) {
+    let [width, height] = size;
+    if width <= 0.0 || height <= 0.0 {
+        return;
+    }
+
+    // center of the layer, in pixels.
+    let center = [
+        0.5 * width * (1.0 + transform.offset[0]),
+        0.5 * height * (1.0 - transform.offset[1]),
+    ];
+    let [x, y] = [position[0] - center[0], position[1] - center[1]];
+
+    match handle {
+        Handle::Move => {
+            transform.offset[0] += 2.0 * (position[0] - previous[0]) / width;
+            transform.offset[1] -= 2.0 * (position[1] - previous[1]) / height;
+        }
+        Handle::Scale => {
+            // the handle sits on a corner, which is this far from the center at scale
+            // 1.
+            let corner_distance = 0.5 * width.hypot(height);
+            transform.scale = (x.hypot(y) / corner_distance).max(0.01);
+        }
+        Handle::Rotate => {
+            // the handle sits above the center. screen y points down.
+            transform.rotation = (-y).atan2(x) - FRAC_PI_2;
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_effect,
+    create_rw_signal,
+    event_target_value,
+    view,
+    IntoView,
+    RwSignal,
+    Signal,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+    SignalWith,
+    StoredValue,
+};
+use leptos_use::{
+    use_interval_fn,
+    utils::Pausable,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use wasm_bindgen::{
+    JsCast,
+    JsValue,
+};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    CanvasRenderingContext2d,
+    HtmlCanvasElement,
+    HtmlImageElement,
+    IdbDatabase,
+    IdbObjectStore,
+    IdbObjectStoreParameters,
+    IdbTransactionMode,
+};
+
+use crate::{
+    app::icon::BootstrapIcon,
+    graphics::{
+        self,
+        WindowHandle,
+    },
+    utils::{
+        futures::spawn_local_and_handle_error,
+        indexed_db,
+    },
+};
+
+#[style(path = "src/app/history.scss")]
+struct Style;
+
+const DATABASE_NAME: &str = "shade-rs";
+const DATABASE_VERSION: u32 = 1;
+const SNAPSHOTS_STORE: &str = "snapshots";
+
+/// Oldest snapshots are deleted when there are more than this.
+const MAX_SNAPSHOTS: usize = 100;
+
+const THUMBNAIL_WIDTH: u32 = 256;
+const THUMBNAIL_QUALITY: f64 = 0.8;
+
+/// A thumbnail of the preview together with the code that rendered it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Snapshot {
+    /// Milliseconds since the unix epoch.
+    created_at: f64,
+    code: String,
+    /// JPEG data URL.
+    thumbnail: String,
+}
+
+/// Takes a snapshot of the preview every few minutes, so that the user can
+/// scrub back through their iterations and restore the code of any of them.
+///
+/// Snapshots are kept in IndexedDB, so they survive reloads.
+#[component]
+pub fn History<OnRestore>(
+    window_handle: StoredValue<Option<WindowHandle>>,
+    code: RwSignal<String>,
+    on_restore: OnRestore,
+) -> impl IntoView
+where
+    OnRestore: Fn(String) + Copy + 'static,
+{
+    // snapshots with their keys, oldest first.
+    let snapshots = create_rw_signal(Vec::<(f64, Snapshot)>::new());
+    let selected = create_rw_signal::<Option<usize>>(None);
+    let interval_minutes = create_rw_signal(0u64);
+
+    let reload = move || {
+        spawn_local_and_handle_error(async move {
+            let loaded = load_snapshots().await?;
+            selected.set(loaded.len().checked_sub(1));
+            snapshots.set(loaded);
+            Ok::<(), HistoryError>(())
+        });
+    };
+    reload();
+
+    let take_snapshot = move || {
+        let Some(window_handle) = window_handle.get_value()
+        else {
+            return;
+        };
+        let code = code.get_untracked();
+        spawn_local_and_handle_error(async move {
+            let frame = window_handle.capture_frame().await?;
+            let snapshot = Snapshot {
+                created_at: js_sys::Date::now(),
+                code,
+                thumbnail: make_thumbnail(&frame).await?,
+            };
+            add_snapshot(&snapshot).await?;
+            reload();
+            Ok::<(), HistoryError>(())
+        });
+    };
+
+    let Pausable { pause, resume, .. } = use_interval_fn(
+        take_snapshot,
+        Signal::derive(move || interval_minutes.get().max(1) * 60_000),
+    );
+    create_effect(move |_| {
+        if interval_minutes.get() == 0 {
+            pause();
+        }
+        else {
+            resume();
+        }
+    });
+
+    let selected_snapshot = move || {
+        selected
+            .get()
+            .and_then(|index| snapshots.with(|snapshots| snapshots.get(index).cloned()))
+    };
+
+    view! {
+        <div class=Style::history>
+            <div class=Style::controls>
+                <label title="Minutes between snapshots, or 0 to only take them manually">
+                    "Every "
+                    <input
+                        type="number"
+                        min="0"
+                        value=move || interval_minutes.get()
+                        on:change=move |event| {
+                            interval_minutes.set(event_target_value(&event).parse().unwrap_or_default());
+                        }
+                    />
+                    " min"
+                </label>
+                <button
+                    on:click=move |_| take_snapshot()
+                    title="Take snapshot"
+                >
+                    <BootstrapIcon icon="camera" />
+                </button>
+                <button
+                    on:click=move |_| {
+                        spawn_local_and_handle_error(async move {
+                            clear_snapshots().await?;
+                            reload();
+                            Ok::<(), HistoryError>(())
+                        });
+                    }
+                    title="Delete all snapshots"
+                >
+                    <BootstrapIcon icon="trash" />
+                </button>
+            </div>
+            <input
+                class=Style::scrubber
+                type="range"
+                min="0"
+                max=move || snapshots.with(|snapshots| snapshots.len().saturating_sub(1))
+                value=move || selected.get().unwrap_or_default()
+                data-hidden=move || snapshots.with(|snapshots| snapshots.is_empty())
+                on:input=move |event| {
+                    selected.set(event_target_value(&event).parse().ok());
+                }
+            />
+            {move || {
+                match selected_snapshot() {
+                    Some((_, snapshot)) => {
+                        let created_at = js_sys::Date::new(&JsValue::from_f64(snapshot.created_at))
+                            .to_locale_string("default", &JsValue::UNDEFINED);
+                        let code = snapshot.code;
+                        view! {
+                            <img class=Style::thumbnail src=snapshot.thumbnail />
+                            <div class=Style::controls>
+                                <span class=Style::date>{String::from(created_at)}</span>
+                                <button
+                                    on:click=move |_| on_restore(code.clone())
+                                    title="Restore the code of this snapshot"
+                                >
+                                    <BootstrapIcon icon="arrow-counterclockwise" />
+                                </button>
+                            </div>
+                        }
+                            .into_view()
+                    }
+                    None => {
+                        view! {
+                            <div class=Style::empty>"No snapshots yet."</div>
+                        }
+                            .into_view()
+                    }
+                }
+            }}
+        </div>
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum HistoryError {
+    #[error("graphics error")]
+    Graphics(#[from] graphics::Error),
+
+    #[error("invalid snapshot")]
+    InvalidSnapshot(#[from] serde_wasm_bindgen::Error),
+
+    #[error("javascript error: {message}")]
+    Js { message: String },
+}
+
+impl From<JsValue> for HistoryError {
+    fn from(value: JsValue) -> Self {
+        Self::Js {
+            message: format!("{value:?}"),
+        }
+    }
+}
+
+async fn open_store(mode: IdbTransactionMode) -> Result<IdbObjectStore, HistoryError> {
+    fn upgrade(database: &IdbDatabase) -> Result<(), JsValue> {
+        let parameters = IdbObjectStoreParameters::new();
+        parameters.set_auto_increment(true);
+        database.create_object_store_with_optional_parameters(SNAPSHOTS_STORE, &parameters)?;
+        Ok(())
+    }
+
+    let database = indexed_db::open_database(DATABASE_NAME, DATABASE_VERSION, upgrade).await?;
+    let transaction = database.transaction_with_str_and_mode(SNAPSHOTS_STORE, mode)?;
+    Ok(transaction.object_store(SNAPSHOTS_STORE)?)
+}
+
+async fn load_snapshots() -> Result<Vec<(f64, Snapshot)>, HistoryError> {
+    let store = open_store(IdbTransactionMode::Readonly).await?;
+    // both requests are in the same transaction, so they match up.
+    let keys = store.get_all_keys()?;
+    let values = store.get_all()?;
+    let keys: js_sys::Array = indexed_db::wait(&keys).await?.unchecked_into();
+    let values: js_sys::Array = indexed_db::wait(&values).await?.unchecked_into();
+
+    keys.iter()
+        .zip(values.iter())
+        .map(|(key, value)| {
+            let key = key.as_f64().unwrap_or_default();
+            Ok((key, serde_wasm_bindgen::from_value(value)?))
+        })
+        .collect()
+}
+
+/// Adds a snapshot and deletes the oldest ones if there are too many.
+async fn add_snapshot(snapshot: &Snapshot) -> Result<(), HistoryError> {
+    let value = snapshot.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?;
+    let store = open_store(IdbTransactionMode::Readwrite).await?;
+    indexed_db::wait(&store.add(&value)?).await?;
+
+    // keys are ascending, so the oldest come first.
+    let keys: js_sys::Array = indexed_db::wait(&store.get_all_keys()?)
+        .await?
+        .unchecked_into();
+    let excess = (keys.length() as usize).saturating_sub(MAX_SNAPSHOTS);
+    for key in keys.iter().take(excess) {
+        store.delete(&key)?;
+    }
+
+    Ok(())
+}
+
+async fn clear_snapshots() -> Result<(), HistoryError> {
+    let store = open_store(IdbTransactionMode::Readwrite).await?;
+    indexed_db::wait(&store.clear()?).await?;
+    Ok(())
+}
+
+/// Scales a captured frame down to [`THUMBNAIL_WIDTH`] and encodes it as JPEG,
+/// which is a lot smaller than the PNG it's captured as.
+async fn make_thumbnail(frame: &str) -> Result<String, HistoryError> {
+    let image = HtmlImageElement::new()?;
+    image.set_src(frame);
+    JsFuture::from(image.decode()).await?;
+
+    let width = THUMBNAIL_WIDTH.min(image.natural_width()).max(1);
+    let height = (image.natural_height() * width / image.natural_width().max(1)).max(1);
+
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .expect("no document");
+    let canvas: HtmlCanvasElement = document.create_element("canvas")?.unchecked_into();
+    canvas.set_width(width);
+    canvas.set_height(height);
+    let context: CanvasRenderingContext2d = canvas
+        .get_context("2d")?
+        .expect("no 2d context")
+        .unchecked_into();
+    context.draw_image_with_html_image_element_and_dw_and_dh(
+        &image,
+        0.0,
+        0.0,
+        width as f64,
+        height as f64,
+    )?;
+
+    Ok(canvas.to_data_url_with_type_and_encoder_options(
+        "image/jpeg",
+        &JsValue::from_f64(THUMBNAIL_QUALITY),
+    )?)
+}
@@ -0,0 +1,415 @@
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_effect,
+    create_rw_signal,
+    event_target_value,
+    spawn_local,
+    store_value,
+    view,
+    For,
+    IntoView,
+    RwSignal,
+    Signal,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+    SignalUpdate,
+    SignalWith,
+    SignalWithUntracked,
+    StoredValue,
+};
+use shade_rs_compile::CompileOptions;
+
+use crate::{
+    app::icon::BootstrapIcon,
+    graphics::{
+        compositor::{
+            BlendMode,
+            LayerBlend,
+            LayerMask,
+            LayerTransform,
+        },
+        WindowHandle,
+    },
+};
+
+#[style(path = "src/app/layers.scss")]
+struct Style;
+
+/// A layer on top of the editor's shader.
+#[derive(Clone, Debug)]
+struct LayerEntry {
+    /// Stable key for rendering. The layer index is the position in the list.
+    id: usize,
+    name: String,
+    enabled: RwSignal<bool>,
+    blend: RwSignal<LayerBlend>,
+
+    /// Id of the layer that is used as mask.
+    mask: RwSignal<Option<usize>>,
+    invert_mask: RwSignal<bool>,
+
+    transform: RwSignal<LayerTransform>,
+
+    error: RwSignal<Option<String>>,
+}
+
+/// Id of the editor's layer, which is always at the bottom.
+const EDITOR_LAYER_ID: usize = 0;
+
+/// Lets the user stack shaders on top of the one in the editor, each with its
+/// own blend mode and opacity. Layers can be masked by any other layer, and
+/// moved, scaled and rotated with a gizmo over the preview. They can be
+/// disabled temporarily, and reordered by dragging them by their handle.
+///
+/// The editor's shader is always the bottom layer (index 0). New layers are
+/// snapshots of the code in the editor.
+#[component]
+pub fn Layers<CompileOptionsFn>(
+    window_handle: StoredValue<Option<WindowHandle>>,
+    code: RwSignal<String>,
+    compile_options: CompileOptionsFn,
+    transform_gizmo: RwSignal<Option<RwSignal<LayerTransform>>>,
+) -> impl IntoView
+where
+    CompileOptionsFn: Fn() -> CompileOptions + Copy + 'static,
+{
+    let layers = create_rw_signal(Vec::<LayerEntry>::new());
+    let next_id = create_rw_signal(1);
+    let editor_enabled = create_rw_signal(true);
+    // the layer being dragged to another position, and the one it's over.
+    let dragged = store_value::<Option<usize>>(None);
+    let drop_target = create_rw_signal::<Option<usize>>(None);
+
+    // layers above the editor's shader start at index 1.
+    let layer_index = move |id: usize| {
+        if id == EDITOR_LAYER_ID {
+            return Some(0);
+        }
+        layers.with_untracked(|layers| {
+            layers
+                .iter()
+                .position(|layer| layer.id == id)
+                .map(|position| position + 1)
+        })
+    };
+
+    let add_layer = move |_| {
+        let Some(window_handle) = window_handle.get_value()
+        else {
+            return;
+        };
+
+        let id = next_id.get_untracked();
+        next_id.set(id + 1);
+        let entry = LayerEntry {
+            id,
+            name: format!("layer {id}"),
+            enabled: create_rw_signal(true),
+            blend: create_rw_signal(LayerBlend::default()),
+            mask: create_rw_signal(None),
+            invert_mask: create_rw_signal(false),
+            transform: create_rw_signal(LayerTransform::default()),
+            error: create_rw_signal(None),
+        };
+        let error = entry.error;
+        layers.update(|layers| layers.push(entry));
+        let index = layer_index(id).unwrap();
+
+        let code = code.get_untracked();
+        spawn_local(async move {
+            let result = window_handle
+                .run_layer(index, code, compile_options(), None)
+                .await;
+            error.set(result.err().map(|error| error.to_string()));
+        });
+    };
+
+    let set_blend = move |id: usize, blend: LayerBlend| {
+        if let (Some(window_handle), Some(index)) = (window_handle.get_value(), layer_index(id)) {
+            window_handle.set_layer_blend(index, blend);
+        }
+    };
+
+    let set_enabled = move |id: usize, enabled: bool| {
+        if let (Some(window_handle), Some(index)) = (window_handle.get_value(), layer_index(id)) {
+            window_handle.set_layer_enabled(index, enabled);
+        }
+    };
+
+    // the dragged layer takes the position of the one it's dropped on.
+    let move_layer = move |id: usize, target_id: usize| {
+        let (Some(from), Some(to)) = (layer_index(id), layer_index(target_id))
+        else {
+            return;
+        };
+        if from == to || from == 0 || to == 0 {
+            return;
+        }
+        layers.update(|layers| {
+            let entry = layers.remove(from - 1);
+            layers.insert(to - 1, entry);
+        });
+        if let Some(window_handle) = window_handle.get_value() {
+            window_handle.move_layer(from, to);
+        }
+    };
+
+    let set_mask = move |id: usize, mask: Option<usize>, invert: bool| {
+        let (Some(window_handle), Some(index)) = (window_handle.get_value(), layer_index(id))
+        else {
+            return;
+        };
+        let mask = mask
+            .and_then(layer_index)
+            .map(|source| LayerMask { source, invert });
+        window_handle.set_layer_mask(index, mask);
+    };
+
+    let set_transform = move |id: usize, transform: LayerTransform| {
+        if let (Some(window_handle), Some(index)) = (window_handle.get_value(), layer_index(id)) {
+            window_handle.set_layer_transform(index, transform);
+        }
+    };
+
+    let remove_layer = move |id: usize| {
+        if let (Some(window_handle), Some(index)) = (window_handle.get_value(), layer_index(id)) {
+            window_handle.remove_layer(index);
+        }
+        let transform = layers.with_untracked(|layers| {
+            layers
+                .iter()
+                .find(|layer| layer.id == id)
+                .map(|layer| layer.transform)
+        });
+        if transform.is_some() && transform_gizmo.get_untracked() == transform {
+            transform_gizmo.set(None);
+        }
+        layers.update(|layers| layers.retain(|layer| layer.id != id));
+        // the graphics remove masks using this layer themselves.
+        layers.with_untracked(|layers| {
+            for layer in layers {
+                if layer.mask.get_untracked() == Some(id) {
+                    layer.mask.set(None);
+                }
+            }
+        });
+    };
+
+    view! {
+        <div class=Style::layer>
+            <span class=Style::handle />
+            <span class=Style::name>"editor"</span>
+            <EnableButton
+                enabled=editor_enabled
+                on_toggle=move |enabled| set_enabled(EDITOR_LAYER_ID, enabled)
+            />
+            <span class=Style::grow />
+            <button on:click=add_layer title="Add the current code as a layer">
+                <BootstrapIcon icon="layers" />
+            </button>
+        </div>
+        <For
+            each=move || layers.get()
+            key=|layer| layer.id
+            children=move |layer| {
+                let LayerEntry {
+                    id,
+                    name,
+                    enabled,
+                    blend,
+                    mask,
+                    invert_mask,
+                    transform,
+                    error,
+                } = layer;
+                create_effect(move |_| {
+                    set_transform(id, transform.get());
+                });
+                let editing_transform = move || transform_gizmo.get() == Some(transform);
+                // any layer but this one can be a mask.
+                let mask_options = move || {
+                    let others = layers.with(|layers| {
+                        layers
+                            .iter()
+                            .filter(|layer| layer.id != id)
+                            .map(|layer| (layer.id, layer.name.clone()))
+                            .collect::<Vec<_>>()
+                    });
+                    std::iter::once((EDITOR_LAYER_ID, "editor".to_owned()))
+                        .chain(others)
+                        .map(|(source_id, source_name)| {
+                            view! {
+                                <option
+                                    value=source_id
+                                    selected=move || mask.get() == Some(source_id)
+                                >
+                                    {source_name}
+                                </option>
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                };
+                view! {
+                    <div
+                        class=Style::layer
+                        data-disabled=move || !enabled.get()
+                        data-drop-target=move || drop_target.get() == Some(id)
+                        on:dragover=move |event| {
+                            if dragged.get_value().is_some() {
+                                // allows dropping.
+                                event.prevent_default();
+                                drop_target.set(Some(id));
+                            }
+                        }
+                        on:dragleave=move |_| {
+                            if drop_target.get_untracked() == Some(id) {
+                                drop_target.set(None);
+                            }
+                        }
+                        on:drop=move |event| {
+                            event.prevent_default();
+                            drop_target.set(None);
+                            if let Some(dragged_id) = dragged.get_value() {
+                                move_layer(dragged_id, id);
+                            }
+                        }
+                    >
+                        // only the handle is draggable, so that the inputs still work.
+                        <span
+                            class=Style::handle
+                            draggable="true"
+                            title="Drag to reorder"
+                            on:dragstart=move |event| {
+                                // firefox only starts a drag with some data.
+                                if let Some(data_transfer) = event.data_transfer() {
+                                    let _ = data_transfer.set_data("text/plain", &id.to_string());
+                                    data_transfer.set_effect_allowed("move");
+                                }
+                                dragged.set_value(Some(id));
+                            }
+                            on:dragend=move |_| {
+                                dragged.set_value(None);
+                                drop_target.set(None);
+                            }
+                        >
+                            <BootstrapIcon icon="grip-vertical" />
+                        </span>
+                        <span class=Style::name>{name}</span>
+                        <EnableButton
+                            enabled
+                            on_toggle=move |enabled| set_enabled(id, enabled)
+                        />
+                        <select
+                            on:change=move |event| {
+                                let Some(mode) = BlendMode::from_name(&event_target_value(&event))
+                                else {
+                                    return;
+                                };
+                                blend.update(|blend| blend.mode = mode);
+                                set_blend(id, blend.get_untracked());
+                            }
+                        >
+                            {BlendMode::ALL
+                                .into_iter()
+                                .map(|mode| {
+                                    view! {
+                                        <option
+                                            value=mode.name()
+                                            selected=move || blend.with(|blend| blend.mode == mode)
+                                        >
+                                            {mode.name()}
+                                        </option>
+                                    }
+                                })
+                                .collect::<Vec<_>>()}
+                        </select>
+                        <input
+                            type="range"
+                            min="0"
+                            max="1"
+                            step="0.01"
+                            title="Opacity"
+                            prop:value=move || blend.with(|blend| blend.opacity)
+                            on:input=move |event| {
+                                let Ok(opacity) = event_target_value(&event).parse::<f32>()
+                                else {
+                                    return;
+                                };
+                                blend.update(|blend| blend.opacity = opacity);
+                                set_blend(id, blend.get_untracked());
+                            }
+                        />
+                        <select
+                            title="Mask"
+                            on:change=move |event| {
+                                let source = event_target_value(&event).parse::<usize>().ok();
+                                mask.set(source);
+                                set_mask(id, source, invert_mask.get_untracked());
+                            }
+                        >
+                            <option value="" selected=move || mask.get().is_none()>
+                                "no mask"
+                            </option>
+                            {mask_options}
+                        </select>
+                        <label title="Invert mask">
+                            <input
+                                type="checkbox"
+                                prop:checked=move || invert_mask.get()
+                                on:change=move |_| {
+                                    invert_mask.set(!invert_mask.get_untracked());
+                                    set_mask(id, mask.get_untracked(), invert_mask.get_untracked());
+                                }
+                            />
+                            "invert"
+                        </label>
+                        <button
+                            on:click=move |_| {
+                                transform_gizmo.set((!editing_transform()).then_some(transform));
+                            }
+                            data-toggled=editing_transform
+                            title="Transform layer"
+                        >
+                            <BootstrapIcon icon="arrows-move" />
+                        </button>
+                        <button
+                            on:click=move |_| transform.set(LayerTransform::default())
+                            title="Reset transform"
+                        >
+                            <BootstrapIcon icon="arrow-counterclockwise" />
+                        </button>
+                        <span class=Style::error>{move || error.get()}</span>
+                        <button on:click=move |_| remove_layer(id) title="Remove layer">
+                            <BootstrapIcon icon="x" />
+                        </button>
+                    </div>
+                }
+            }
+        />
+    }
+}
+
+/// Toggles whether a layer is drawn.
+#[component]
+fn EnableButton<OnToggle>(enabled: RwSignal<bool>, on_toggle: OnToggle) -> impl IntoView
+where
+    OnToggle: Fn(bool) + 'static,
+{
+    view! {
+        <button
+            on:click=move |_| {
+                let new_value = !enabled.get_untracked();
+                enabled.set(new_value);
+                on_toggle(new_value);
+            }
+            data-toggled=move || !enabled.get()
+            title=move || if enabled.get() { "Disable layer" } else { "Enable layer" }
+        >
+            <BootstrapIcon icon=Signal::derive(move || {
+                if enabled.get() { "eye" } else { "eye-slash" }.to_owned()
+            }) />
+        </button>
+    }
+}
@@ -0,0 +1,127 @@
+//! Finds numeric literals in shader code, so that the editor can scrub their
+//! value by alt-dragging them.
+
+/// Pixels the mouse has to be dragged to change a literal by its smallest
+/// digit.
+const PIXELS_PER_STEP: f64 = 2.0;
+
+/// A decimal integer or float literal, e.g. `42`, `-0.5` or `1.0f`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NumberLiteral {
+    /// Start of the literal in the line, in UTF-16 code units like CodeMirror
+    /// counts them.
+    pub start: usize,
+    /// End of the literal (exclusive), in UTF-16 code units.
+    pub end: usize,
+
+    value: f64,
+    /// Digits after the decimal point, or `None` for integers.
+    decimals: Option<usize>,
+    /// Type suffix like `f` or `u`.
+    suffix: Option<char>,
+}
+
+impl NumberLiteral {
+    /// Source text of the literal after dragging it by `delta` pixels to the
+    /// right. The precision of the literal determines how fast it changes.
+    pub fn scrubbed(&self, delta: f64) -> String {
+        let steps = (delta / PIXELS_PER_STEP).round();
+        let suffix = self.suffix.map(String::from).unwrap_or_default();
+
+        match self.decimals {
+            Some(decimals) => {
+                let value = self.value + steps * 10f64.powi(-(decimals as i32));
+                // `1.` has to keep its point to stay a float.
+                let point = if decimals == 0 { "." } else { "" };
+                format!("{value:.decimals$}{point}{suffix}")
+            }
+            None => {
+                let mut value = self.value + steps;
+                if self.suffix == Some('u') {
+                    value = value.max(0.0);
+                }
+                format!("{value}{suffix}")
+            }
+        }
+    }
+}
+
+/// Finds the numeric literal at `ch` (in UTF-16 code units) in a line of
+/// code.
+pub fn find_number_literal(line: &str, ch: usize) -> Option<NumberLiteral> {
+    let chars = line.chars().collect::<Vec<_>>();
+
+    // the position is in front of the character it's on.
+    let mut utf16_offset = 0;
+    let mut index = chars.len();
+    for (i, c) in chars.iter().enumerate() {
+        if utf16_offset >= ch {
+            index = i;
+            break;
+        }
+        utf16_offset += c.len_utf16();
+    }
+
+    let is_number_char = |c: char| c.is_ascii_digit() || c == '.';
+    // the mouse might be right behind the literal, or behind its suffix.
+    let index = [Some(index), index.checked_sub(1), index.checked_sub(2)]
+        .into_iter()
+        .flatten()
+        .find(|&i| chars.get(i).copied().is_some_and(is_number_char))?;
+
+    let mut start = index;
+    while start > 0 && is_number_char(chars[start - 1]) {
+        start -= 1;
+    }
+    let mut end = index;
+    while end < chars.len() && is_number_char(chars[end]) {
+        end += 1;
+    }
+
+    // part of an identifier, e.g. `vec3` or `channel0`.
+    if start > 0 && (chars[start - 1].is_alphabetic() || chars[start - 1] == '_') {
+        return None;
+    }
+
+    let digits = chars[start..end].iter().collect::<String>();
+    let value = digits.parse::<f64>().ok()?;
+    let decimals = digits.find('.').map(|point| digits.len() - point - 1);
+
+    let suffix = chars
+        .get(end)
+        .copied()
+        .filter(|c| matches!(c, 'f' | 'h' | 'i' | 'u'));
+    if suffix.is_some() {
+        end += 1;
+    }
+    // e.g. exponents or hex literals, which aren't supported.
+    if chars
+        .get(end)
+        .is_some_and(|c| c.is_alphanumeric() || *c == '_')
+    {
+        return None;
+    }
+
+    // a unary minus belongs to the literal.
+    let mut value = value;
+    let is_negative = start > 0
+        && chars[start - 1] == '-'
+        && chars[..start - 1]
+            .iter()
+            .rev()
+            .find(|c| !c.is_whitespace())
+            .map_or(true, |c| matches!(c, '(' | ',' | '=' | '[' | ':'));
+    if is_negative {
+        start -= 1;
+        value = -value;
+    }
+
+    let utf16_len = |chars: &[char]| chars.iter().map(|c| c.len_utf16()).sum::<usize>();
+    Some(NumberLiteral {
+        start: utf16_len(&chars[..start]),
+        end: utf16_len(&chars[..end]),
+        value,
+        decimals,
+        suffix,
+    })
+}
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_effect,
+    create_rw_signal,
+    event_target_value,
+    view,
+    CollectView,
+    IntoView,
+    RwSignal,
+    Signal,
+    SignalUpdate,
+    SignalWith,
+    StoredValue,
+};
+use shade_rs_compile::reflect::{
+    ParamField,
+    ParamHint,
+    ParamScalar,
+    ParamsLayout,
+};
+
+use crate::graphics::WindowHandle;
+
+#[style(path = "src/app/params.scss")]
+struct Style;
+
+/// Values of the parameters, by field name. These are kept when the shader is
+/// recompiled, so that tweaks aren't lost.
+type ParamValues = HashMap<String, [f64; 4]>;
+
+/// Controls for the user-defined parameters of the shader.
+///
+/// See [`shade_rs_compile::reflect::PARAMS_BINDING`].
+#[component]
+pub fn Params(
+    #[prop(into)] layout: Signal<Option<ParamsLayout>>,
+    window_handle: StoredValue<Option<WindowHandle>>,
+) -> impl IntoView {
+    let values = create_rw_signal(ParamValues::new());
+
+    create_effect(move |_| {
+        let data = layout.with(|layout| {
+            layout
+                .as_ref()
+                .map(|layout| values.with(|values| encode_params(layout, values)))
+        });
+        if let (Some(data), Some(window_handle)) = (data, window_handle.get_value()) {
+            window_handle.set_params(data);
+        }
+    });
+
+    move || {
+        layout.with(|layout| {
+            let Some(layout) = layout
+            else {
+                return view! {
+                    <div class=Style::empty>
+                        "Declare a uniform at @group(0) @binding(1) to get controls for its fields."
+                    </div>
+                }
+                .into_view();
+            };
+
+            layout
+                .fields
+                .iter()
+                .map(|field| view! { <ParamControl field=field.clone() values /> })
+                .collect_view()
+        })
+    }
+}
+
+#[component]
+fn ParamControl(field: ParamField, values: RwSignal<ParamValues>) -> impl IntoView {
+    let name = field.name.clone();
+    let scalar = field.ty.scalar;
+
+    // a color picker edits the RGB components, alpha gets a regular control.
+    let color_picker = (field.hint == Some(ParamHint::Color)).then(|| {
+        let value = {
+            let name = name.clone();
+            move || {
+                values.with(|values| {
+                    let color = values.get(&name).copied().unwrap_or_default();
+                    color_to_hex(&color[..3])
+                })
+            }
+        };
+        let name = name.clone();
+        let set_value = move |event| {
+            let Some(color) = color_from_hex(&event_target_value(&event))
+            else {
+                return;
+            };
+            values.update(|values| {
+                values.entry(name.clone()).or_default()[..3].copy_from_slice(&color);
+            });
+        };
+
+        view! {
+            <div class=Style::component>
+                <input
+                    type="color"
+                    prop:value=value
+                    on:input=set_value
+                />
+            </div>
+        }
+    });
+    let first_component = if color_picker.is_some() { 3 } else { 0 };
+
+    let inputs = (first_component..usize::from(field.ty.components))
+        .map(|component| {
+            let value = {
+                let name = name.clone();
+                move || {
+                    values.with(|values| values.get(&name).map_or(0.0, |value| value[component]))
+                }
+            };
+            let set_value = {
+                let name = name.clone();
+                move |event| {
+                    let Ok(value) = event_target_value(&event).parse::<f64>()
+                    else {
+                        return;
+                    };
+                    values.update(|values| {
+                        values.entry(name.clone()).or_default()[component] = value;
+                    });
+                }
+            };
+
+            let slider = (scalar == ParamScalar::F32).then(|| {
+                view! {
+                    <input
+                        type="range"
+                        min="0"
+                        max="1"
+                        step="0.001"
+                        prop:value=value.clone()
+                        on:input=set_value.clone()
+                    />
+                }
+            });
+            let step = if scalar == ParamScalar::F32 {
+                "0.001"
+            }
+            else {
+                "1"
+            };
+            let min = if scalar == ParamScalar::U32 {
+                Some("0")
+            }
+            else {
+                None
+            };
+
+            view! {
+                <div class=Style::component>
+                    {slider}
+                    <input
+                        type="number"
+                        step=step
+                        min=min
+                        prop:value=value
+                        on:input=set_value
+                    />
+                </div>
+            }
+        })
+        .collect_view();
+
+    view! {
+        <div class=Style::param>
+            <span class=Style::name>{name.clone()}</span>
+            {color_picker}
+            {inputs}
+        </div>
+    }
+}
+
+/// Encodes the values into the layout of the params struct.
+fn encode_params(layout: &ParamsLayout, values: &ParamValues) -> Vec<u8> {
+    let mut data = vec![0; layout.size as usize];
+
+    for field in &layout.fields {
+        let Some(value) = values.get(&field.name)
+        else {
+            continue;
+        };
+
+        for (component, value) in value
+            .iter()
+            .take(usize::from(field.ty.components))
+            .enumerate()
+        {
+            let bytes = match field.ty.scalar {
+                ParamScalar::F32 => (*value as f32).to_le_bytes(),
+                ParamScalar::I32 => (*value as i32).to_le_bytes(),
+                ParamScalar::U32 => (*value as u32).to_le_bytes(),
+            };
+            let offset = field.offset as usize + 4 * component;
+            data[offset..offset + 4].copy_from_slice(&bytes);
+        }
+    }
+
+    data
+}
+
+/// Formats RGB components in `0..=1` as a CSS hex color, e.g. `#ff8000`.
+fn color_to_hex(color: &[f64]) -> String {
+    color.iter().fold(String::from("#"), |mut hex, component| {
+        let byte = (component.clamp(0.0, 1.0) * 255.0).round() as u8;
+        hex.push_str(&format!("{byte:02x}"));
+        hex
+    })
+}
+
+/// Parses a CSS hex color as returned by a color input.
+fn color_from_hex(hex: &str) -> Option<[f64; 3]> {
+    let hex = hex.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let mut color = [0.0; 3];
+    for (i, component) in color.iter_mut().enumerate() {
+        let byte = u8::from_str_radix(hex.get(2 * i..2 * i + 2)?, 16).ok()?;
+        *component = f64::from(byte) / 255.0;
+    }
+    Some(color)
+}
@@ -0,0 +1,165 @@
+use futures::StreamExt;
+use gloo_net::websocket::{
+    futures::WebSocket,
+    Message,
+};
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_rw_signal,
+    spawn_local,
+    view,
+    IntoView,
+    RwSignal,
+    SignalGet,
+    SignalSet,
+    SignalWith,
+};
+use shade_rs_compile::{
+    dev::DevMessage,
+    project::PassBlendMode,
+};
+
+use crate::{
+    app::window::Window,
+    graphics::{
+        compositor::{
+            BlendMode,
+            LayerBlend,
+        },
+        FrameInfo,
+        WindowHandle,
+    },
+};
+
+#[style(path = "src/app/player.scss")]
+struct Style;
+
+/// A minimal player that only shows the output of a shader, without editor or
+/// toolbar.
+#[component]
+pub fn Player(code: String) -> impl IntoView {
+    view! {
+        <div class=Style::player>
+            <Window
+                on_load=move |window_handle: WindowHandle| {
+                    spawn_local(async move {
+                        if let Err(error) = window_handle.run(code, Default::default(), None).await {
+                            tracing::error!("{error}");
+                        }
+                    });
+                }
+                on_frame=|_: FrameInfo| {}
+            />
+        </div>
+    }
+}
+
+/// A player that previews a project from the CLI's `dev` server. It runs the
+/// passes as layers, and updates them whenever the project changes on disk.
+///
+/// `url` is the server's [WebSocket](shade_rs_compile::dev).
+#[component]
+pub fn DevPlayer(url: String) -> impl IntoView {
+    let error_message = create_rw_signal::<Option<String>>(None);
+
+    view! {
+        <div class=Style::player>
+            <Window
+                on_load=move |window_handle: WindowHandle| {
+                    spawn_local(receive_projects(url, window_handle, error_message));
+                }
+                on_frame=|_: FrameInfo| {}
+            />
+            <div
+                class=Style::error
+                data-hidden=move || error_message.with(|message| message.is_none())
+            >
+                {move || error_message.get().unwrap_or_default()}
+            </div>
+        </div>
+    }
+}
+
+async fn receive_projects(
+    url: String,
+    window_handle: WindowHandle,
+    error_message: RwSignal<Option<String>>,
+) {
+    let mut socket = match WebSocket::open(&url) {
+        Ok(socket) => socket,
+        Err(error) => {
+            error_message.set(Some(format!("could not connect to the dev server: {error}")));
+            return;
+        }
+    };
+
+    let mut num_layers = 0;
+    while let Some(message) = socket.next().await {
+        let message = match message {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Bytes(_)) => continue,
+            Err(error) => {
+                error_message.set(Some(format!("lost the connection to the dev server: {error}")));
+                return;
+            }
+        };
+        let message = match serde_json::from_str::<DevMessage>(&message) {
+            Ok(message) => message,
+            Err(error) => {
+                tracing::error!(%error, "invalid message from the dev server");
+                continue;
+            }
+        };
+
+        match message {
+            DevMessage::Project {
+                title,
+                passes,
+                options,
+            } => {
+                tracing::info!(%title, "project changed");
+                leptos::document().set_title(&title);
+
+                let mut errors = vec![];
+                for (layer, dev_pass) in passes.iter().enumerate() {
+                    window_handle.set_layer_blend(
+                        layer,
+                        LayerBlend {
+                            mode: blend_mode(dev_pass.pass.blend),
+                            opacity: dev_pass.pass.opacity,
+                        },
+                    );
+                    if let Err(error) = window_handle
+                        .run_layer(layer, dev_pass.code.clone(), options.clone(), None)
+                        .await
+                    {
+                        errors.push(format!("{}:\n{error}", dev_pass.pass.name));
+                    }
+                }
+
+                // passes that were removed.
+                for layer in (passes.len()..num_layers).rev() {
+                    window_handle.remove_layer(layer);
+                }
+                num_layers = passes.len();
+
+                error_message.set((!errors.is_empty()).then(|| errors.join("\n\n")));
+            }
+            DevMessage::Error { message } => {
+                error_message.set(Some(message));
+            }
+        }
+    }
+
+    error_message.set(Some("the dev server closed the connection".to_owned()));
+}
+
+fn blend_mode(blend: PassBlendMode) -> BlendMode {
+    match blend {
+        PassBlendMode::Normal => BlendMode::Normal,
+        PassBlendMode::Add => BlendMode::Add,
+        PassBlendMode::Multiply => BlendMode::Multiply,
+        PassBlendMode::Screen => BlendMode::Screen,
+    }
+}
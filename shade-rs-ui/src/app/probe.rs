@@ -0,0 +1,101 @@
+use kardashev_style::style;
+use leptos::{
+    component,
+    view,
+    CollectView,
+    IntoView,
+    Signal,
+    SignalGet,
+    SignalWith,
+};
+
+use crate::graphics::{
+    pixel::PixelValue,
+    probe::ProbeValue,
+};
+
+#[style(path = "src/app/probe.scss")]
+struct Style;
+
+/// Tooltip next to the mouse with the values of the shader's `@probe`
+/// directives at the pixel under it.
+///
+/// This must be placed in a container that is positioned over the window, and
+/// `mouse_position` must be relative to it.
+#[component]
+pub fn ProbeTooltip(
+    #[prop(into)] values: Signal<Vec<ProbeValue>>,
+    #[prop(into)] mouse_position: Signal<Option<[f32; 2]>>,
+) -> impl IntoView {
+    move || {
+        let [x, y] = mouse_position.get()?;
+        if values.with(|values| values.is_empty()) {
+            return None;
+        }
+
+        let tooltip_style = format!(
+            "left: {}px; top: {}px;",
+            x + TOOLTIP_OFFSET,
+            y + TOOLTIP_OFFSET,
+        );
+        let rows = move || {
+            values.with(|values| {
+                values
+                    .iter()
+                    .map(|value| {
+                        view! {
+                            <tr>
+                                <td class=Style::line>{value.line}</td>
+                                <td class=Style::expression>{value.expression.clone()}</td>
+                                <td>
+                                    {value
+                                        .value
+                                        .clone()
+                                        .unwrap_or_else(|| "not reached".to_owned())}
+                                </td>
+                            </tr>
+                        }
+                    })
+                    .collect_view()
+            })
+        };
+
+        Some(view! {
+            <table class=Style::tooltip style=tooltip_style>
+                {rows}
+            </table>
+        })
+    }
+}
+
+/// Tooltip at the pixel that was clicked while inspecting pixels, with the
+/// frame's color there.
+///
+/// Like [`ProbeTooltip`], this must be placed in a container that is
+/// positioned over the window. The position is the one that was clicked.
+#[component]
+pub fn PixelTooltip(#[prop(into)] pixel: Signal<Option<([f32; 2], PixelValue)>>) -> impl IntoView {
+    move || {
+        let ([x, y], value) = pixel.get()?;
+
+        let tooltip_style = format!(
+            "left: {}px; top: {}px;",
+            x + TOOLTIP_OFFSET,
+            y + TOOLTIP_OFFSET,
+        );
+        let [column, row] = value.position;
+        let [r, g, b, a] = value.color;
+
+        Some(view! {
+            <table class=Style::tooltip style=tooltip_style>
+                <tr>
+                    <td class=Style::line>{format!("{column}, {row}")}</td>
+                    <td>{format!("{r:.4} {g:.4} {b:.4} {a:.4}")}</td>
+                </tr>
+            </table>
+        })
+    }
+}
+
+/// Distance of the tooltip from the mouse, in pixels.
+const TOOLTIP_OFFSET: f32 = 12.0;
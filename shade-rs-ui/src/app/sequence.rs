@@ -0,0 +1,364 @@
+use std::io::{
+    Cursor,
+    Write,
+};
+
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_rw_signal,
+    event_target_value,
+    store_value,
+    view,
+    IntoView,
+    RwSignal,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+    StoredValue,
+};
+use wasm_bindgen::{
+    JsCast,
+    JsValue,
+};
+use web_sys::{
+    Blob,
+    BlobPropertyBag,
+    CanvasRenderingContext2d,
+    HtmlAnchorElement,
+    HtmlCanvasElement,
+    Url,
+};
+use zip::{
+    write::SimpleFileOptions,
+    CompressionMethod,
+    ZipWriter,
+};
+
+use crate::{
+    app::icon::BootstrapIcon,
+    graphics::{
+        self,
+        sequence::{
+            SequenceFrame,
+            SequenceOptions,
+        },
+        WindowHandle,
+    },
+    utils::futures::spawn_local_and_handle_error,
+};
+
+#[style(path = "src/app/sequence.scss")]
+struct Style;
+
+const FILE_NAME: &str = "sequence.zip";
+
+/// Renders a range of time to PNG images, e.g. for compositing in other tools,
+/// and downloads them as a zip file.
+///
+/// The frames are rendered offscreen at their own resolution, independent of
+/// the preview. Optionally an attribution line, e.g. the shader's title and
+/// author, is burned into the bottom right corner of every frame.
+#[component]
+pub fn SequenceExport(window_handle: StoredValue<Option<WindowHandle>>) -> impl IntoView {
+    let start = create_rw_signal(0.0f32);
+    let end = create_rw_signal(5.0f32);
+    let fps = create_rw_signal(30.0f32);
+    let width = create_rw_signal(1920u32);
+    let height = create_rw_signal(1080u32);
+    let attribution = create_rw_signal(String::new());
+    // (frames done, total frames) while exporting.
+    let progress = create_rw_signal::<Option<(usize, usize)>>(None);
+    // dropping the receiver cancels the export.
+    let cancel = store_value(false);
+
+    let export = move || {
+        let Some(window_handle) = window_handle.get_value()
+        else {
+            return;
+        };
+        let options = SequenceOptions {
+            start: start.get_untracked(),
+            end: end.get_untracked(),
+            fps: fps.get_untracked(),
+            width: width.get_untracked(),
+            height: height.get_untracked(),
+        };
+        let attribution = attribution.get_untracked();
+        cancel.set_value(false);
+        progress.set(Some((0, options.num_frames())));
+
+        spawn_local_and_handle_error(async move {
+            let result =
+                export_sequence(&window_handle, options, &attribution, progress, cancel).await;
+            progress.set(None);
+            result
+        });
+    };
+
+    let number_input = move |label: &'static str, value: RwSignal<f32>| {
+        view! {
+            <label>
+                {label}
+                <input
+                    type="number"
+                    min="0"
+                    step="any"
+                    value=move || value.get()
+                    on:change=move |event| {
+                        if let Ok(parsed) = event_target_value(&event).parse() {
+                            value.set(parsed);
+                        }
+                    }
+                />
+            </label>
+        }
+    };
+    let size_input = move |label: &'static str, value: RwSignal<u32>| {
+        view! {
+            <label>
+                {label}
+                <input
+                    type="number"
+                    min="1"
+                    value=move || value.get()
+                    on:change=move |event| {
+                        if let Ok(parsed) = event_target_value(&event).parse() {
+                            value.set(parsed);
+                        }
+                    }
+                />
+            </label>
+        }
+    };
+
+    view! {
+        <div class=Style::sequence>
+            <div class=Style::row>
+                {number_input("Start (s)", start)}
+                {number_input("End (s)", end)}
+                {number_input("FPS", fps)}
+            </div>
+            <div class=Style::row>
+                {size_input("Width", width)}
+                {size_input("Height", height)}
+            </div>
+            <div class=Style::row>
+                <label class=Style::attribution>
+                    "Attribution"
+                    <input
+                        type="text"
+                        placeholder="Title by author"
+                        value=move || attribution.get()
+                        on:change=move |event| attribution.set(event_target_value(&event))
+                    />
+                </label>
+            </div>
+            <div class=Style::row>
+                {move || {
+                    match progress.get() {
+                        Some((done, total)) => {
+                            view! {
+                                <progress max=total value=done />
+                                <span>{format!("{done} / {total}")}</span>
+                                <button on:click=move |_| cancel.set_value(true) title="Cancel">
+                                    <BootstrapIcon icon="x" />
+                                </button>
+                            }
+                                .into_view()
+                        }
+                        None => {
+                            view! {
+                                <button on:click=move |_| export() title="Export image sequence">
+                                    <BootstrapIcon icon="film" />
+                                    " Export"
+                                </button>
+                            }
+                                .into_view()
+                        }
+                    }
+                }}
+            </div>
+        </div>
+    }
+}
+
+async fn export_sequence(
+    window_handle: &WindowHandle,
+    options: SequenceOptions,
+    attribution: &str,
+    progress: RwSignal<Option<(usize, usize)>>,
+    cancel: StoredValue<bool>,
+) -> Result<(), SequenceError> {
+    let attribution = (!attribution.trim().is_empty())
+        .then(|| Attribution::render(attribution.trim(), &options))
+        .transpose()?;
+    let mut rx_frame = window_handle.export_sequence(options).await?;
+    let mut zip = ZipWriter::new(Cursor::new(vec![]));
+    let mut done = 0;
+
+    while let Some(mut frame) = rx_frame.recv().await {
+        if cancel.get_value() {
+            return Ok(());
+        }
+
+        if let Some(attribution) = &attribution {
+            attribution.draw(&mut frame);
+        }
+
+        // PNGs are already compressed.
+        zip.start_file(
+            format!("frame_{:05}.png", frame.index),
+            SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+        )?;
+        zip.write_all(&encode_png(&frame)?)?;
+
+        done += 1;
+        progress.set(Some((done, frame.num_frames)));
+    }
+
+    let data = zip.finish()?.into_inner();
+    download(FILE_NAME, &data)?;
+
+    Ok(())
+}
+
+/// A line of text that is drawn over the frames after they were read back, so
+/// the shader never sees it.
+struct Attribution {
+    /// Position of the top left corner in the frames.
+    x: u32,
+    y: u32,
+
+    width: u32,
+    height: u32,
+
+    /// RGBA8 pixels, row by row. The color is not premultiplied.
+    data: Vec<u8>,
+}
+
+impl Attribution {
+    fn render(text: &str, options: &SequenceOptions) -> Result<Self, SequenceError> {
+        let font_size = (options.height / 40).max(12);
+        // room for the shadow that keeps the text readable on bright frames.
+        let padding = font_size / 4;
+        let margin = font_size / 2;
+
+        let canvas: HtmlCanvasElement = gloo_utils::document()
+            .create_element("canvas")?
+            .unchecked_into();
+        let context: CanvasRenderingContext2d = canvas
+            .get_context("2d")?
+            .expect("no 2d context")
+            .unchecked_into();
+
+        let font = format!("{font_size}px sans-serif");
+        context.set_font(&font);
+        let text_width = context.measure_text(text)?.width().ceil() as u32;
+
+        // lines that don't fit are cut off.
+        let width = (text_width + 2 * padding).min(options.width);
+        let height = (font_size * 3 / 2 + 2 * padding).min(options.height);
+
+        // resizing the canvas resets the context's state.
+        canvas.set_width(width);
+        canvas.set_height(height);
+        context.set_font(&font);
+        context.set_text_baseline("middle");
+        context.set_fill_style_str("white");
+        context.set_shadow_color("rgba(0, 0, 0, 0.75)");
+        context.set_shadow_blur(padding as f64);
+        context.fill_text(text, padding as f64, height as f64 / 2.0)?;
+
+        let data = context
+            .get_image_data(0.0, 0.0, width as f64, height as f64)?
+            .data()
+            .0;
+
+        Ok(Self {
+            x: options.width.saturating_sub(width + margin),
+            y: options.height.saturating_sub(height + margin),
+            width,
+            height,
+            data,
+        })
+    }
+
+    /// Blends the attribution over the frame.
+    fn draw(&self, frame: &mut SequenceFrame) {
+        let row_bytes = 4 * self.width as usize;
+
+        for (row, source) in self.data.chunks_exact(row_bytes).enumerate() {
+            let offset = 4 * ((self.y as usize + row) * frame.width as usize + self.x as usize);
+            let target = &mut frame.data[offset..offset + row_bytes];
+
+            for (target, source) in target.chunks_exact_mut(4).zip(source.chunks_exact(4)) {
+                let alpha = u32::from(source[3]);
+                let blend = |source: u8, target: u8| {
+                    ((u32::from(source) * alpha + u32::from(target) * (255 - alpha) + 127) / 255)
+                        as u8
+                };
+
+                target[0] = blend(source[0], target[0]);
+                target[1] = blend(source[1], target[1]);
+                target[2] = blend(source[2], target[2]);
+                target[3] = blend(255, target[3]);
+            }
+        }
+    }
+}
+
+fn encode_png(frame: &SequenceFrame) -> Result<Vec<u8>, SequenceError> {
+    let mut png = vec![];
+    let mut encoder = png::Encoder::new(&mut png, frame.width, frame.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&frame.data)?;
+    Ok(png)
+}
+
+/// Offers `data` as a download with the given file name.
+fn download(file_name: &str, data: &[u8]) -> Result<(), SequenceError> {
+    let options = BlobPropertyBag::new();
+    options.set_type("application/zip");
+    let blob = Blob::new_with_u8_array_sequence_and_options(
+        &js_sys::Array::of1(&js_sys::Uint8Array::from(data)),
+        &options,
+    )?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+
+    let anchor: HtmlAnchorElement = gloo_utils::document().create_element("a")?.unchecked_into();
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+
+    Url::revoke_object_url(&url)?;
+
+    Ok(())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SequenceError {
+    #[error("graphics error")]
+    Graphics(#[from] graphics::Error),
+
+    #[error("failed to write zip file")]
+    Zip(#[from] zip::result::ZipError),
+
+    #[error("failed to encode png")]
+    Png(#[from] png::EncodingError),
+
+    #[error("io error")]
+    Io(#[from] std::io::Error),
+
+    #[error("javascript error: {message}")]
+    Js { message: String },
+}
+
+impl From<JsValue> for SequenceError {
+    fn from(value: JsValue) -> Self {
+        Self::Js {
+            message: format!("{value:?}"),
+        }
+    }
+}
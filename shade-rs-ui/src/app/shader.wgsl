@@ -1,11 +1,5 @@
-struct ShadeRs {
-    time: f32,
-    aspect: f32,
-    mouse: vec2f,
-}
-
-@group(0) @binding(0)
-var<uniform> input: ShadeRs;
+// declares the `ShadeRs` input struct as `input`
+// @inputs v2
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4f,
@@ -0,0 +1,181 @@
+use std::{
+    collections::VecDeque,
+    time::Duration,
+};
+
+use kardashev_style::style;
+use leptos::{
+    component,
+    event_target_value,
+    view,
+    CollectView,
+    IntoView,
+    Signal,
+    SignalWith,
+    StoredValue,
+};
+
+use crate::graphics::{
+    memory::{
+        format_bytes,
+        DEFAULT_MEMORY_BUDGET,
+    },
+    CompileTimings,
+    FrameInfo,
+    WindowHandle,
+};
+
+const MIB: u64 = 1024 * 1024;
+
+#[style(path = "src/app/stats.scss")]
+struct Style;
+
+/// Shows frame statistics and how long compiling the last shaders took.
+///
+/// If the GPU times are available, the time of each layer is listed too, and
+/// the slowest one is highlighted.
+///
+/// The GPU memory the window uses is shown with its budget, which can be
+/// changed here.
+#[component]
+pub fn Stats(
+    #[prop(into)] frame_info: Signal<FrameInfo>,
+    #[prop(into)] compile_history: Signal<VecDeque<CompileTimings>>,
+    window_handle: StoredValue<Option<WindowHandle>>,
+) -> impl IntoView {
+    let set_budget = move |event| {
+        let Ok(budget) = event_target_value(&event).parse::<u64>()
+        else {
+            return;
+        };
+        if let Some(window_handle) = window_handle.get_value() {
+            window_handle.set_memory_budget(budget * MIB);
+        }
+    };
+
+    view! {
+        <div class=Style::stats>
+            <div>
+                {move || {
+                    frame_info.with(|frame_info| {
+                        let mut text = format!(
+                            "{:.1} FPS, time: {:.3} s, resolution: {:.0}%",
+                            frame_info.fps,
+                            frame_info.time,
+                            frame_info.render_scale * 100.0,
+                        );
+                        if let Some(gpu_times) = &frame_info.gpu_times {
+                            text.push_str(&format!(", GPU: {:.2} ms", gpu_times.frame));
+                        }
+                        text
+                    })
+                }}
+            </div>
+            <div
+                class=Style::memory
+                data-over-budget=move || {
+                    frame_info.with(|frame_info| frame_info.memory.is_over_budget())
+                }
+            >
+                {move || {
+                    frame_info.with(|frame_info| {
+                        let memory = &frame_info.memory;
+                        format!(
+                            "Memory: {} (frame: {}, channels: {}, buffers: {})",
+                            format_bytes(memory.total()),
+                            format_bytes(memory.frame),
+                            format_bytes(memory.channels),
+                            format_bytes(memory.buffers),
+                        )
+                    })
+                }}
+                <label title="Channel images and sequence exports that would exceed the budget are refused">
+                    " Budget (MiB): "
+                    <input
+                        type="number"
+                        min="1"
+                        value=DEFAULT_MEMORY_BUDGET / MIB
+                        on:change=set_budget
+                    />
+                </label>
+            </div>
+            <table data-hidden=move || {
+                frame_info.with(|frame_info| {
+                    frame_info
+                        .gpu_times
+                        .as_ref()
+                        .map_or(true, |gpu_times| gpu_times.layers.is_empty())
+                })
+            }>
+                <tr>
+                    <th>"Layer"</th>
+                    <th>"GPU"</th>
+                    <th>"Share"</th>
+                </tr>
+                {move || {
+                    frame_info.with(|frame_info| {
+                        let Some(gpu_times) = &frame_info.gpu_times
+                        else {
+                            return vec![];
+                        };
+                        let slowest = gpu_times.slowest_layer();
+                        gpu_times
+                            .layers
+                            .iter()
+                            .enumerate()
+                            .map(|(index, time)| {
+                                let share = if gpu_times.frame > 0.0 {
+                                    time / gpu_times.frame
+                                }
+                                else {
+                                    0.0
+                                };
+                                view! {
+                                    <tr data-slowest=slowest == Some(index)>
+                                        <td>{if index == 0 { "editor".to_owned() } else { format!("layer {index}") }}</td>
+                                        <td>{format!("{time:.2} ms")}</td>
+                                        <td>{format!("{:.0}%", share * 100.0)}</td>
+                                    </tr>
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                }}
+            </table>
+            <table>
+                <tr>
+                    <th>"Run"</th>
+                    <th>"Parse"</th>
+                    <th>"Validate"</th>
+                    <th>"Shader module"</th>
+                    <th>"Pipeline"</th>
+                    <th>"Total"</th>
+                </tr>
+                {move || {
+                    compile_history.with(|compile_history| {
+                        compile_history
+                            .iter()
+                            .enumerate()
+                            .map(|(i, timings)| {
+                                view! {
+                                    <tr>
+                                        <td>{if i == 0 { "last".to_owned() } else { format!("-{i}") }}</td>
+                                        <td>{format_duration(timings.parse)}</td>
+                                        <td>{format_duration(timings.validate)}</td>
+                                        <td>{format_duration(timings.create_shader_module)}</td>
+                                        <td>{format_duration(timings.create_pipeline)}</td>
+                                        <td>{format_duration(timings.total())}</td>
+                                    </tr>
+                                }
+                            })
+                            .collect_view()
+                    })
+                }}
+            </table>
+        </div>
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
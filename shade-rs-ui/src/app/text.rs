@@ -0,0 +1,194 @@
+use wasm_bindgen::JsCast;
+use web_sys::{
+    CanvasRenderingContext2d,
+    HtmlCanvasElement,
+};
+
+use crate::{
+    app::channels::ChannelError,
+    graphics::channel::ChannelImage,
+};
+
+const FONT: &str = "bold 96px sans-serif";
+
+/// Height of a line of text in the image, in pixels.
+const LINE_HEIGHT: u32 = 128;
+
+/// Distance from the outline, in pixels, at which the signed distance field
+/// saturates. The text is padded by this much on all sides.
+const SDF_SPREAD: u32 = 16;
+
+/// Renders text into an image that can be bound to a channel. Lines are
+/// separated by `\n`.
+///
+/// The RGB channels contain the antialiased text in white. The alpha channel
+/// contains a signed distance field, which is 0.5 on the outline of the
+/// glyphs, and increases towards their inside. This can be used for crisp
+/// edges at any scale, outlines or glows:
+///
+/// ```wgsl
+/// let distance = textureSample(channel0, channel0_sampler, uv).a;
+/// let fill = smoothstep(0.45, 0.55, distance);
+/// ```
+pub fn render_text(text: &str) -> Result<ChannelImage, ChannelError> {
+    let lines = text.lines().collect::<Vec<_>>();
+
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .expect("no document");
+    let canvas: HtmlCanvasElement = document.create_element("canvas")?.unchecked_into();
+    let context: CanvasRenderingContext2d = canvas
+        .get_context("2d")?
+        .expect("no 2d context")
+        .unchecked_into();
+
+    context.set_font(FONT);
+    let mut text_width = 1.0f64;
+    for line in &lines {
+        text_width = text_width.max(context.measure_text(line)?.width());
+    }
+
+    let width = text_width.ceil() as u32 + 2 * SDF_SPREAD;
+    let height = LINE_HEIGHT * (lines.len().max(1) as u32) + 2 * SDF_SPREAD;
+    // resizing the canvas resets the context's state.
+    canvas.set_width(width);
+    canvas.set_height(height);
+    context.set_font(FONT);
+    context.set_text_baseline("middle");
+    context.set_fill_style_str("white");
+    for (i, line) in lines.iter().enumerate() {
+        let y = SDF_SPREAD as f64 + (i as f64 + 0.5) * LINE_HEIGHT as f64;
+        context.fill_text(line, SDF_SPREAD as f64, y)?;
+    }
+
+    let mut data = context
+        .get_image_data(0.0, 0.0, width as f64, height as f64)?
+        .data()
+        .0;
+
+    let inside = data
+        .chunks_exact(4)
+        .map(|pixel| pixel[3] >= 128)
+        .collect::<Vec<_>>();
+    let outside = inside.iter().map(|inside| !inside).collect::<Vec<_>>();
+    let to_inside = squared_distances(&inside, width as usize, height as usize);
+    let to_outside = squared_distances(&outside, width as usize, height as usize);
+
+    for (i, pixel) in data.chunks_exact_mut(4).enumerate() {
+        // the text is white, so the coverage is in the alpha channel.
+        let coverage = pixel[3];
+        let distance = to_inside[i].sqrt() - to_outside[i].sqrt();
+        let sdf = 0.5 - 0.5 * distance / f64::from(SDF_SPREAD);
+        pixel.copy_from_slice(&[
+            coverage,
+            coverage,
+            coverage,
+            (sdf.clamp(0.0, 1.0) * 255.0).round() as u8,
+        ]);
+    }
+
+    Ok(ChannelImage {
+        width,
+        height,
+        data,
+    })
+}
+
+/// Distance that stands in for infinity, i.e. there's no feature in range.
+const INFINITY: f64 = 1e20;
+
+/// Computes the squared euclidean distance of every pixel to the nearest
+/// pixel in `features`.
+///
+/// This is the linear-time algorithm from Felzenszwalb and Huttenlocher,
+/// "Distance Transforms of Sampled Functions", applied to columns and then
+/// rows.
+fn squared_distances(features: &[bool], width: usize, height: usize) -> Vec<f64> {
+    let mut grid = features
+        .iter()
+        .map(|feature| {
+            if *feature {
+                0.0
+            }
+            else {
+                INFINITY
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let n = width.max(height);
+    let mut input = vec![0.0; n];
+    let mut output = vec![0.0; n];
+    let mut parabolas = vec![0; n];
+    let mut boundaries = vec![0.0; n + 1];
+
+    for x in 0..width {
+        for y in 0..height {
+            input[y] = grid[y * width + x];
+        }
+        distance_transform_1d(
+            &input[..height],
+            &mut output[..height],
+            &mut parabolas,
+            &mut boundaries,
+        );
+        for y in 0..height {
+            grid[y * width + x] = output[y];
+        }
+    }
+
+    for row in grid.chunks_exact_mut(width) {
+        input[..width].copy_from_slice(row);
+        distance_transform_1d(
+            &input[..width],
+            &mut output[..width],
+            &mut parabolas,
+            &mut boundaries,
+        );
+        row.copy_from_slice(&output[..width]);
+    }
+
+    grid
+}
+
+/// Computes the lower envelope of the parabolas rooted at `(q, f[q])`.
+///
+/// `parabolas` and `boundaries` are scratch space and must hold at least `n`
+/// and `n + 1` elements.
+fn distance_transform_1d(
+    f: &[f64],
+    output: &mut [f64],
+    parabolas: &mut [usize],
+    boundaries: &mut [f64],
+) {
+    let intersection = |q: usize, p: usize| {
+        let (qf, pf) = (q as f64, p as f64);
+        ((f[q] + qf * qf) - (f[p] + pf * pf)) / (2.0 * (qf - pf))
+    };
+
+    let mut k = 0;
+    parabolas[0] = 0;
+    boundaries[0] = -INFINITY;
+    boundaries[1] = INFINITY;
+
+    for q in 1..f.len() {
+        let mut s = intersection(q, parabolas[k]);
+        while s <= boundaries[k] {
+            k -= 1;
+            s = intersection(q, parabolas[k]);
+        }
+        k += 1;
+        parabolas[k] = q;
+        boundaries[k] = s;
+        boundaries[k + 1] = INFINITY;
+    }
+
+    k = 0;
+    for (q, output) in output.iter_mut().enumerate() {
+        while boundaries[k + 1] < q as f64 {
+            k += 1;
+        }
+        let distance = q as f64 - parabolas[k] as f64;
+        *output = distance * distance + f[parabolas[k]];
+    }
+}
@@ -0,0 +1,160 @@
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_node_ref,
+    create_rw_signal,
+    event_target_value,
+    html::Input,
+    store_value,
+    view,
+    IntoView,
+    Signal,
+    SignalGet,
+    SignalSet,
+    SignalWith,
+    SignalWithUntracked,
+    StoredValue,
+};
+use web_sys::{
+    KeyboardEvent,
+    PointerEvent,
+};
+
+use crate::graphics::{
+    FrameInfo,
+    WindowHandle,
+};
+
+#[style(path = "src/app/time.scss")]
+struct Style;
+
+/// Seconds that dragging over the time by one pixel seeks.
+const SECONDS_PER_PIXEL: f32 = 0.01;
+
+/// Same as [`SECONDS_PER_PIXEL`], but while shift is held.
+const SECONDS_PER_PIXEL_FINE: f32 = 0.001;
+
+/// Pointer movement after which a press on the time is a drag instead of a
+/// click.
+const DRAG_THRESHOLD: i32 = 3;
+
+#[derive(Clone, Copy, Debug)]
+struct Drag {
+    start_x: i32,
+    start_time: f32,
+    dragging: bool,
+}
+
+/// Shows the time of the window. Dragging it horizontally scrubs through time,
+/// clicking it lets the user type a time to jump to.
+#[component]
+pub fn TimeControl(
+    #[prop(into)] frame_info: Signal<FrameInfo>,
+    window_handle: StoredValue<Option<WindowHandle>>,
+) -> impl IntoView {
+    let input_node_ref = create_node_ref::<Input>();
+    // while the user types, the value isn't updated every frame.
+    let editing = create_rw_signal(false);
+    let drag = store_value::<Option<Drag>>(None);
+
+    let seek = move |time: f32| {
+        if let Some(window_handle) = window_handle.get_value() {
+            window_handle.seek_time(time);
+        }
+    };
+
+    let format_time = |frame_info: &FrameInfo| format!("{:.3} s", frame_info.time);
+
+    let on_pointer_down = move |event: PointerEvent| {
+        if editing.get() || event.button() != 0 {
+            return;
+        }
+        // otherwise the input is focused right away.
+        event.prevent_default();
+        if let Some(input) = input_node_ref.get_untracked() {
+            let _ = input.set_pointer_capture(event.pointer_id());
+        }
+        drag.set_value(Some(Drag {
+            start_x: event.client_x(),
+            start_time: frame_info.with_untracked(|frame_info| frame_info.time),
+            dragging: false,
+        }));
+    };
+
+    let on_pointer_move = move |event: PointerEvent| {
+        let Some(mut state) = drag.get_value()
+        else {
+            return;
+        };
+        let dx = event.client_x() - state.start_x;
+        state.dragging |= dx.abs() >= DRAG_THRESHOLD;
+        drag.set_value(Some(state));
+
+        if state.dragging {
+            let seconds_per_pixel = if event.shift_key() {
+                SECONDS_PER_PIXEL_FINE
+            }
+            else {
+                SECONDS_PER_PIXEL
+            };
+            seek(state.start_time + dx as f32 * seconds_per_pixel);
+        }
+    };
+
+    let on_pointer_up = move |_event: PointerEvent| {
+        let Some(state) = drag.get_value()
+        else {
+            return;
+        };
+        drag.set_value(None);
+
+        // a click without moving edits the time.
+        if !state.dragging {
+            if let Some(input) = input_node_ref.get_untracked() {
+                let _ = input.focus();
+                input.select();
+            }
+        }
+    };
+
+    let on_change = move |event| {
+        let value = event_target_value(&event);
+        let value = value.trim().trim_end_matches('s').trim_end();
+        if let Ok(time) = value.parse::<f32>() {
+            seek(time);
+        }
+    };
+
+    let on_key_down = move |event: KeyboardEvent| {
+        if event.key() == "Enter" || event.key() == "Escape" {
+            if let Some(input) = input_node_ref.get_untracked() {
+                let _ = input.blur();
+            }
+        }
+    };
+
+    view! {
+        <input
+            class=Style::time
+            type="text"
+            title="Drag to scrub through time, or click to type a time in seconds"
+            node_ref=input_node_ref
+            data-editing=move || editing.get()
+            prop:value=move || {
+                if editing.get() {
+                    frame_info.with_untracked(format_time)
+                }
+                else {
+                    frame_info.with(format_time)
+                }
+            }
+            on:pointerdown=on_pointer_down
+            on:pointermove=on_pointer_move
+            on:pointerup=on_pointer_up
+            on:focus=move |_| editing.set(true)
+            on:blur=move |_| editing.set(false)
+            on:change=on_change
+            on:keydown=on_key_down
+        />
+    }
+}
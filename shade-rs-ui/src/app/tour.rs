@@ -0,0 +1,205 @@
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_rw_signal,
+    ev,
+    view,
+    window_event_listener,
+    IntoView,
+    RwSignal,
+    SignalGet,
+    SignalSet,
+    SignalUpdate,
+};
+use web_sys::DomRect;
+
+use crate::app::icon::BootstrapIcon;
+
+#[style(path = "src/app/tour.scss")]
+struct Style;
+
+/// Key in local storage that is set once the tour was finished or skipped.
+const TOUR_DONE_KEY: &str = "shade-rs-tour-done";
+
+/// Space between the highlighted element and the popover.
+const POPOVER_MARGIN: f64 = 8.0;
+
+/// A step of the tour, explaining one element of the editor.
+struct TourStep {
+    /// Value of the `data-tour` attribute of the highlighted element.
+    target: &'static str,
+    title: &'static str,
+    text: &'static str,
+}
+
+const STEPS: &[TourStep] = &[
+    TourStep {
+        target: "editor",
+        title: "Shader code",
+        text: "This is your fragment shader, written in WGSL. The inputs like the time and \
+               resolution are declared for you, so you can start with a `main` function right \
+               away.",
+    },
+    TourStep {
+        target: "run",
+        title: "Run",
+        text: "Compiles the shader and shows it in the preview. Errors and warnings appear \
+               below the toolbar.",
+    },
+    TourStep {
+        target: "params",
+        title: "Uniforms",
+        text: "Declare a uniform struct at @group(0) @binding(1) and each of its fields gets a \
+               control here, so you can tweak values without recompiling.",
+    },
+    TourStep {
+        target: "help",
+        title: "Help",
+        text: "That's it! Click this to see the tour again. Panels have a ? button that \
+               explains them.",
+    },
+];
+
+/// Whether the tour should start by itself, i.e. the user hasn't seen it yet.
+pub fn is_first_run() -> bool {
+    local_storage()
+        .and_then(|storage| storage.get_item(TOUR_DONE_KEY).ok().flatten())
+        .is_none()
+}
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+/// Guides the user through the editor by highlighting one element after the
+/// other, with an explanation next to it.
+///
+/// Elements are found by their `data-tour` attribute, see [`STEPS`]. The tour
+/// is shown while `active` is set.
+#[component]
+pub fn Tour(active: RwSignal<bool>) -> impl IntoView {
+    let step = create_rw_signal(0usize);
+
+    // the highlight follows the element when the layout changes.
+    let layout_changed = create_rw_signal(());
+    let resize_handle = window_event_listener(ev::resize, move |_| layout_changed.set(()));
+    leptos::on_cleanup(move || resize_handle.remove());
+
+    let finish = move || {
+        active.set(false);
+        step.set(0);
+        if let Some(storage) = local_storage() {
+            let _ = storage.set_item(TOUR_DONE_KEY, "1");
+        }
+    };
+
+    move || {
+        if !active.get() {
+            return ().into_view();
+        }
+        layout_changed.get();
+
+        let index = step.get();
+        let step_info = &STEPS[index];
+        let is_last = index + 1 == STEPS.len();
+
+        let Some(rect) = target_rect(step_info.target)
+        else {
+            // the element isn't there, e.g. because of the enabled features.
+            if is_last {
+                finish();
+            }
+            else {
+                step.update(|step| *step += 1);
+            }
+            return ().into_view();
+        };
+
+        let highlight_style = format!(
+            "left: {}px; top: {}px; width: {}px; height: {}px;",
+            rect.left(),
+            rect.top(),
+            rect.width(),
+            rect.height(),
+        );
+
+        // below the element if there's more space there, above otherwise.
+        let viewport_height = web_sys::window()
+            .and_then(|window| window.inner_height().ok())
+            .and_then(|height| height.as_f64())
+            .unwrap_or_default();
+        let left = rect.left().max(POPOVER_MARGIN);
+        let popover_style = if rect.bottom() < viewport_height - rect.top() {
+            format!("left: {left}px; top: {}px;", rect.bottom() + POPOVER_MARGIN)
+        }
+        else {
+            format!(
+                "left: {left}px; bottom: {}px;",
+                viewport_height - rect.top() + POPOVER_MARGIN
+            )
+        };
+
+        view! {
+            <div class=Style::highlight style=highlight_style></div>
+            <div class=Style::popover style=popover_style>
+                <h4>{step_info.title}</h4>
+                <p>{step_info.text}</p>
+                <div class=Style::buttons>
+                    <span class=Style::progress>{format!("{} / {}", index + 1, STEPS.len())}</span>
+                    <button on:click=move |_| finish()>"Skip"</button>
+                    <button
+                        disabled={index == 0}
+                        on:click=move |_| step.update(|step| *step -= 1)
+                    >
+                        "Back"
+                    </button>
+                    <button
+                        on:click=move |_| {
+                            if is_last {
+                                finish();
+                            }
+                            else {
+                                step.update(|step| *step += 1);
+                            }
+                        }
+                    >
+                        {if is_last { "Done" } else { "Next" }}
+                    </button>
+                </div>
+            </div>
+        }
+        .into_view()
+    }
+}
+
+fn target_rect(target: &str) -> Option<DomRect> {
+    let element = web_sys::window()?
+        .document()?
+        .query_selector(&format!("[data-tour=\"{target}\"]"))
+        .ok()??;
+    Some(element.get_bounding_client_rect())
+}
+
+/// A "?" button that explains the panel it's placed in.
+#[component]
+pub fn Help(text: &'static str) -> impl IntoView {
+    let open = create_rw_signal(false);
+
+    view! {
+        <div class=Style::help>
+            <button
+                on:click=move |_| open.set(!open.get())
+                data-toggled=move || open.get()
+                title="Help"
+            >
+                <BootstrapIcon icon="question" />
+            </button>
+            <div
+                class=Style::help_text
+                data-hidden=move || !open.get()
+            >
+                {text}
+            </div>
+        </div>
+    }
+}
@@ -3,28 +3,37 @@ use leptos::{
     component,
     create_effect,
     create_node_ref,
+    ev,
     html::{
         Canvas,
         Div,
     },
     on_cleanup,
     provide_context,
+    spawn_local,
     store_value,
     use_context,
     view,
+    window_event_listener,
     IntoView,
     Signal,
     SignalGet,
     SignalGetUntracked,
+    StoredValue,
 };
 use leptos_use::{
-    signal_debounced,
     use_document_visibility,
     use_element_size_with_options,
     use_element_visibility,
     UseElementSizeOptions,
 };
+use wasm_bindgen::{
+    JsCast,
+    JsValue,
+};
+use wasm_bindgen_futures::JsFuture;
 use web_sys::{
+    DeviceOrientationEvent,
     ResizeObserverBoxOptions,
     VisibilityState,
 };
@@ -33,6 +42,7 @@ use crate::graphics::{
     self,
     FrameInfo,
     Graphics,
+    PlaybackState,
     SurfaceSize,
     WindowHandle,
     WindowId,
@@ -56,11 +66,19 @@ pub fn use_graphics() -> Graphics {
 /// This creates a container (div) that can be sized using CSS. The canvas will
 /// atomatically be resized to fill this container.
 ///
+/// If no `graphics` is given, the one from the context is used.
+/// `playback_state` can be used to continue where a previous window left off.
+///
 /// # TODO
 ///
 /// - Add event handler property
 #[component]
-pub fn Window<OnLoad, OnFrame>(on_load: OnLoad, on_frame: OnFrame) -> impl IntoView
+pub fn Window<OnLoad, OnFrame>(
+    on_load: OnLoad,
+    on_frame: OnFrame,
+    #[prop(optional)] graphics: Option<Graphics>,
+    #[prop(optional)] playback_state: PlaybackState,
+) -> impl IntoView
 where
     OnLoad: FnOnce(WindowHandle) + 'static,
     OnFrame: FnMut(FrameInfo) + 'static,
@@ -68,28 +86,34 @@ where
     let container_node_ref = create_node_ref::<Div>();
     let canvas_node_ref = create_node_ref::<Canvas>();
     let stored_window_handle = store_value(None);
+    // total scroll of the mouse wheel, see `WindowHandle::set_scroll`.
+    let scroll = store_value([0.0f32; 2]);
+    // whether we already asked for the device orientation, see
+    // `request_orientation_permission`.
+    let orientation_requested = store_value(false);
 
     let container_size = use_element_size_with_options(
         container_node_ref,
         UseElementSizeOptions::default().box_(ResizeObserverBoxOptions::ContentBox),
     );
-    let container_size = signal_debounced(
-        Signal::derive(move || {
-            SurfaceSize {
-                width: (container_size.width.get() as u32).max(1),
-                height: (container_size.height.get() as u32).max(1),
-            }
-        }),
-        500.,
-    );
+    // reconfiguring the surface is cheap, so we follow the container size
+    // immediately.
+    let container_size = Signal::derive(move || {
+        SurfaceSize {
+            width: (container_size.width.get() as u32).max(1),
+            height: (container_size.height.get() as u32).max(1),
+        }
+    });
 
     let window_id = WindowId::new();
 
     canvas_node_ref.on_load(move |_canvas| {
         tracing::debug!("window loaded");
-        let window_handle = use_graphics().register_window(
+        let graphics = graphics.unwrap_or_else(use_graphics);
+        let window_handle = graphics.register_window(
             window_id,
             container_size.get_untracked(),
+            playback_state,
             Box::new(on_frame),
         );
         stored_window_handle.set_value(Some(window_handle.clone()));
@@ -121,7 +145,21 @@ where
         });
     });
 
+    let orientation_handle = window_event_listener(
+        ev::Custom::<DeviceOrientationEvent>::new("deviceorientation"),
+        move |event| {
+            stored_window_handle.with_value(|window_handle_opt| {
+                if let Some(window_handle) = window_handle_opt {
+                    if window_handle.uses_orientation() {
+                        window_handle.set_orientation(orientation_from_websys(&event));
+                    }
+                }
+            });
+        },
+    );
+
     on_cleanup(move || {
+        orientation_handle.remove();
         stored_window_handle.with_value(|window_handle_opt| {
             if let Some(window_handle) = window_handle_opt {
                 window_handle.destroy_window();
@@ -139,6 +177,21 @@ where
                 width=move || container_size.get().width
                 height=move || container_size.get().height
                 data-raw-handle=window_id
+                // make the canvas focusable, so it receives key events
+                tabindex="0"
+                on:keydown=move |event| {
+                    handle_key_event(stored_window_handle, &event, true);
+                }
+                on:keyup=move |event| {
+                    handle_key_event(stored_window_handle, &event, false);
+                }
+                on:blur=move |_event| {
+                    stored_window_handle.with_value(|window_handle_opt| {
+                        if let Some(window_handle) = window_handle_opt {
+                            window_handle.release_all_keys();
+                        }
+                    });
+                }
                 on:mousemove=move |event| {
                     stored_window_handle.with_value(|window_handle_opt| {
                         if let Some(window_handle) = window_handle_opt {
@@ -146,9 +199,62 @@ where
                         }
                     });
                 }
-                on:mouseleave=move |_event| {
+                on:click=move |_event| {
                     stored_window_handle.with_value(|window_handle_opt| {
                         if let Some(window_handle) = window_handle_opt {
+                            if window_handle.uses_orientation() && !orientation_requested.get_value() {
+                                orientation_requested.set_value(true);
+                                request_orientation_permission();
+                            }
+                        }
+                    });
+                }
+                on:mousedown=move |event| {
+                    if event.button() == 0 {
+                        stored_window_handle.with_value(|window_handle_opt| {
+                            if let Some(window_handle) = window_handle_opt {
+                                window_handle.set_mouse_button(true, mouse_position_from_websys(&event));
+                            }
+                        });
+                    }
+                }
+                on:mouseup=move |event| {
+                    if event.button() == 0 {
+                        stored_window_handle.with_value(|window_handle_opt| {
+                            if let Some(window_handle) = window_handle_opt {
+                                window_handle.set_mouse_button(false, mouse_position_from_websys(&event));
+                            }
+                        });
+                    }
+                }
+                on:wheel:undelegated=move |event| {
+                    stored_window_handle.with_value(|window_handle_opt| {
+                        if let Some(window_handle) = window_handle_opt {
+                            // only take over scrolling if the shader has a camera to zoom, or
+                            // reads the scroll itself.
+                            let [delta_x, delta_y] = wheel_delta_pixels(&event);
+                            if window_handle.uses_camera() {
+                                event.prevent_default();
+                                window_handle.zoom_camera(delta_y);
+                            }
+                            if window_handle.uses_scroll() {
+                                event.prevent_default();
+                                scroll.update_value(|[x, y]| {
+                                    *x += delta_x;
+                                    *y += delta_y;
+                                });
+                                window_handle.set_scroll(scroll.get_value());
+                            }
+                        }
+                    });
+                }
+                on:mouseleave=move |event| {
+                    stored_window_handle.with_value(|window_handle_opt| {
+                        if let Some(window_handle) = window_handle_opt {
+                            // we won't see the button being released outside of the canvas
+                            if event.buttons() & 1 != 0 {
+                                window_handle.set_mouse_button(false, mouse_position_from_websys(&event));
+                            }
                             window_handle.set_mouse_position(None);
                         }
                     });
@@ -158,6 +264,88 @@ where
     }
 }
 
+fn handle_key_event(
+    stored_window_handle: StoredValue<Option<WindowHandle>>,
+    event: &web_sys::KeyboardEvent,
+    pressed: bool,
+) {
+    if event.repeat() {
+        return;
+    }
+    let Ok(key_code) = u8::try_from(event.key_code())
+    else {
+        return;
+    };
+    // don't scroll the page with the arrow keys, etc.
+    event.prevent_default();
+    stored_window_handle.with_value(|window_handle_opt| {
+        if let Some(window_handle) = window_handle_opt {
+            window_handle.set_key_state(key_code, pressed);
+        }
+    });
+}
+
+/// Horizontal and vertical scroll distance of a wheel event, in pixels.
+fn wheel_delta_pixels(event: &web_sys::WheelEvent) -> [f32; 2] {
+    let scale = match event.delta_mode() {
+        web_sys::WheelEvent::DOM_DELTA_LINE => 16.0,
+        web_sys::WheelEvent::DOM_DELTA_PAGE => 400.0,
+        _ => 1.0,
+    };
+    [event.delta_x() as f32 * scale, event.delta_y() as f32 * scale]
+}
+
+/// The angles of a device orientation event, or `None` if the device doesn't
+/// know its orientation.
+fn orientation_from_websys(event: &DeviceOrientationEvent) -> Option<[f32; 3]> {
+    Some([
+        event.alpha()? as f32,
+        event.beta()? as f32,
+        event.gamma()? as f32,
+    ])
+}
+
+/// Asks for permission to receive device orientation events.
+///
+/// Only Safari on iOS needs this, and only allows it in response to a click, so
+/// the permission is requested synchronously when the canvas is clicked.
+fn request_orientation_permission() {
+    let request = || {
+        let constructor = js_sys::Reflect::get(
+            &gloo_utils::window(),
+            &JsValue::from_str("DeviceOrientationEvent"),
+        )?;
+        if constructor.is_undefined() {
+            return Ok(None);
+        }
+        let request_permission =
+            js_sys::Reflect::get(&constructor, &JsValue::from_str("requestPermission"))?;
+        let Some(request_permission) = request_permission.dyn_ref::<js_sys::Function>()
+        else {
+            return Ok(None);
+        };
+        Ok::<_, JsValue>(Some(js_sys::Promise::from(
+            request_permission.call0(&constructor)?,
+        )))
+    };
+
+    match request() {
+        Ok(Some(promise)) => {
+            spawn_local(async move {
+                match JsFuture::from(promise).await {
+                    Ok(state) if state.as_string().as_deref() == Some("granted") => {}
+                    Ok(state) => tracing::warn!(?state, "device orientation wasn't allowed"),
+                    Err(error) => {
+                        tracing::error!(?error, "failed to request device orientation")
+                    }
+                }
+            });
+        }
+        Ok(None) => {}
+        Err(error) => tracing::error!(?error, "failed to request device orientation"),
+    }
+}
+
 fn mouse_position_from_websys(event: &web_sys::MouseEvent) -> [f32; 2] {
     [event.offset_x() as f32, event.offset_y() as f32]
 }
@@ -0,0 +1,141 @@
+//! Audio output for sound shaders.
+//!
+//! Samples are generated on the GPU (see [`crate::graphics::sound`]) and played
+//! by an `AudioWorklet`, which queues the blocks it receives.
+
+use std::rc::Rc;
+
+use js_sys::{
+    Array,
+    Float32Array,
+};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    AudioContext,
+    AudioContextState,
+    AudioWorkletNode,
+    AudioWorkletNodeOptions,
+    Blob,
+    BlobPropertyBag,
+    Url,
+};
+
+const WORKLET_SOURCE: &str = include_str!("worklet.js");
+
+/// Name the processor is registered with in [`WORKLET_SOURCE`].
+const PROCESSOR_NAME: &str = "shade-rs-sound";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("javascript error: {message}")]
+    Js { message: String },
+}
+
+impl From<JsValue> for Error {
+    fn from(value: JsValue) -> Self {
+        Self::Js {
+            message: format!("{value:?}"),
+        }
+    }
+}
+
+/// An audio context with the worklet that plays the generated samples.
+///
+/// This is cheap to clone. All clones refer to the same context, which is
+/// closed when the last one is dropped.
+#[derive(Clone, Debug)]
+pub struct AudioOutput {
+    inner: Rc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    context: AudioContext,
+    node: AudioWorkletNode,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        let _ = self.context.close();
+    }
+}
+
+impl AudioOutput {
+    pub async fn new() -> Result<Self, Error> {
+        let context = AudioContext::new()?;
+
+        // the worklet has to be loaded from a URL.
+        let options = BlobPropertyBag::new();
+        options.set_type("text/javascript");
+        let blob = Blob::new_with_str_sequence_and_options(
+            &Array::of1(&JsValue::from_str(WORKLET_SOURCE)),
+            &options,
+        )?;
+        let url = Url::create_object_url_with_blob(&blob)?;
+        let result = JsFuture::from(context.audio_worklet()?.add_module(&url)?).await;
+        Url::revoke_object_url(&url)?;
+        result?;
+
+        let options = AudioWorkletNodeOptions::new();
+        options.set_number_of_inputs(0);
+        options.set_output_channel_count(&Array::of1(&JsValue::from(2)));
+        let node = AudioWorkletNode::new_with_options(&context, PROCESSOR_NAME, &options)?;
+        node.connect_with_audio_node(&context.destination())?;
+
+        Ok(Self {
+            inner: Rc::new(Inner { context, node }),
+        })
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.inner.context.sample_rate() as u32
+    }
+
+    /// Queues interleaved stereo samples for playback.
+    pub fn push(&self, samples: &[f32]) {
+        let block = Float32Array::from(samples);
+        if let Err(error) = self
+            .inner
+            .node
+            .port()
+            .and_then(|port| port.post_message(&block))
+        {
+            tracing::warn!(?error, "failed to queue samples");
+        }
+    }
+
+    /// Drops all queued samples, e.g. when the playback time jumps.
+    pub fn flush(&self) {
+        if let Err(error) = self
+            .inner
+            .node
+            .port()
+            .and_then(|port| port.post_message(&JsValue::NULL))
+        {
+            tracing::warn!(?error, "failed to flush samples");
+        }
+    }
+
+    /// Resumes or suspends the audio context.
+    ///
+    /// Browsers only start audio after the user interacted with the page, so
+    /// the context might stay suspended until then.
+    pub fn set_playing(&self, playing: bool) {
+        let result = if playing {
+            self.inner.context.resume()
+        }
+        else {
+            self.inner.context.suspend()
+        };
+        if let Err(error) = result {
+            tracing::warn!(?error, playing, "failed to change audio context state");
+        }
+    }
+
+    /// Whether the audio context is playing. It doesn't consume samples
+    /// otherwise.
+    pub fn is_running(&self) -> bool {
+        self.inner.context.state() == AudioContextState::Running
+    }
+}
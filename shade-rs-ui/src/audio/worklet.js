@@ -0,0 +1,41 @@
+// Plays blocks of interleaved stereo samples that are posted to the node's
+// port. Posting `null` drops all queued blocks.
+class ShadeRsSoundProcessor extends AudioWorkletProcessor {
+    constructor() {
+        super();
+        this.blocks = [];
+        this.offset = 0;
+        this.port.onmessage = (event) => {
+            if (event.data === null) {
+                this.blocks = [];
+                this.offset = 0;
+            }
+            else {
+                this.blocks.push(event.data);
+            }
+        };
+    }
+
+    process(inputs, outputs) {
+        const [left, right] = outputs[0];
+        for (let i = 0; i < left.length; i++) {
+            const block = this.blocks[0];
+            if (block === undefined) {
+                // underrun: play silence until the next block arrives.
+                left[i] = 0;
+                right[i] = 0;
+                continue;
+            }
+            left[i] = block[this.offset];
+            right[i] = block[this.offset + 1];
+            this.offset += 2;
+            if (this.offset >= block.length) {
+                this.blocks.shift();
+                this.offset = 0;
+            }
+        }
+        return true;
+    }
+}
+
+registerProcessor("shade-rs-sound", ShadeRsSoundProcessor);
@@ -0,0 +1,124 @@
+use crate::graphics::backend::{
+    Backend,
+    BackendType,
+};
+
+/// What the adapter that renders a window can do.
+///
+/// Shaders that work with one backend might not with another, e.g. because
+/// WebGL lacks compute shaders or has lower limits. This is shown to the user,
+/// so that they can tell why.
+#[derive(Clone, Debug)]
+pub struct AdapterInfo {
+    pub backend_type: BackendType,
+
+    /// Name of the adapter. Browsers might hide the actual GPU.
+    pub name: String,
+
+    pub device_type: wgpu::DeviceType,
+
+    /// Driver name and version, if the browser reports them.
+    pub driver: String,
+
+    /// The features that were enabled on the device.
+    pub features: Vec<&'static str>,
+
+    /// The capabilities of WebGPU that the backend lacks. This is usually
+    /// empty with WebGPU.
+    pub missing_capabilities: Vec<&'static str>,
+
+    /// The device limits that shaders are most likely to hit.
+    pub limits: Vec<(&'static str, u64)>,
+}
+
+impl AdapterInfo {
+    pub fn new(backend: &Backend, backend_type: BackendType) -> Self {
+        let info = backend.adapter.get_info();
+        let downlevel = backend.adapter.get_downlevel_capabilities();
+
+        let driver = [info.driver, info.driver_info]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let features = backend
+            .device
+            .features()
+            .iter_names()
+            .map(|(name, _)| name)
+            .collect();
+
+        let missing_capabilities = wgpu::DownlevelFlags::compliant()
+            .difference(downlevel.flags)
+            .iter_names()
+            .map(|(name, _)| name)
+            .collect();
+
+        Self {
+            backend_type,
+            name: info.name,
+            device_type: info.device_type,
+            driver,
+            features,
+            missing_capabilities,
+            limits: limits(&backend.device.limits()),
+        }
+    }
+}
+
+fn limits(limits: &wgpu::Limits) -> Vec<(&'static str, u64)> {
+    vec![
+        (
+            "max_texture_dimension_2d",
+            limits.max_texture_dimension_2d.into(),
+        ),
+        (
+            "max_texture_dimension_3d",
+            limits.max_texture_dimension_3d.into(),
+        ),
+        ("max_bind_groups", limits.max_bind_groups.into()),
+        (
+            "max_sampled_textures_per_shader_stage",
+            limits.max_sampled_textures_per_shader_stage.into(),
+        ),
+        (
+            "max_samplers_per_shader_stage",
+            limits.max_samplers_per_shader_stage.into(),
+        ),
+        (
+            "max_storage_textures_per_shader_stage",
+            limits.max_storage_textures_per_shader_stage.into(),
+        ),
+        (
+            "max_storage_buffers_per_shader_stage",
+            limits.max_storage_buffers_per_shader_stage.into(),
+        ),
+        (
+            "max_uniform_buffers_per_shader_stage",
+            limits.max_uniform_buffers_per_shader_stage.into(),
+        ),
+        (
+            "max_uniform_buffer_binding_size",
+            limits.max_uniform_buffer_binding_size.into(),
+        ),
+        (
+            "max_storage_buffer_binding_size",
+            limits.max_storage_buffer_binding_size.into(),
+        ),
+        ("max_vertex_attributes", limits.max_vertex_attributes.into()),
+        (
+            "max_inter_stage_shader_components",
+            limits.max_inter_stage_shader_components.into(),
+        ),
+        ("max_color_attachments", limits.max_color_attachments.into()),
+        (
+            "max_compute_invocations_per_workgroup",
+            limits.max_compute_invocations_per_workgroup.into(),
+        ),
+        (
+            "max_compute_workgroup_storage_size",
+            limits.max_compute_workgroup_storage_size.into(),
+        ),
+    ]
+}
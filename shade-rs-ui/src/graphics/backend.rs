@@ -1,4 +1,5 @@
 use std::{
+    fmt::Display,
     num::NonZeroUsize,
     sync::{
         atomic::{
@@ -13,6 +14,7 @@ use serde::{
     Deserialize,
     Serialize,
 };
+use tokio::sync::mpsc;
 
 use crate::graphics::{
     Config,
@@ -40,9 +42,40 @@ impl BackendType {
     }
 }
 
+impl Display for BackendType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackendType::WebGpu => write!(f, "WebGPU"),
+            BackendType::WebGl => write!(f, "WebGL"),
+        }
+    }
+}
+
+/// Checks whether the browser exposes the WebGPU API.
+pub fn is_webgpu_available() -> bool {
+    web_sys::window().map_or(false, |window| {
+        js_sys::Reflect::has(&window.navigator(), &"gpu".into()).unwrap_or_default()
+    })
+}
+
+/// Optional features that are requested if the adapter supports them.
+const OPTIONAL_FEATURES: wgpu::Features = wgpu::Features::FLOAT32_FILTERABLE
+    .union(wgpu::Features::TIMESTAMP_QUERY)
+    .union(wgpu::Features::TEXTURE_COMPRESSION_BC)
+    .union(wgpu::Features::TEXTURE_COMPRESSION_ETC2)
+    .union(wgpu::Features::TEXTURE_COMPRESSION_ASTC);
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct BackendId(NonZeroUsize);
 
+/// An error that the device reported outside of an error scope, e.g. a
+/// validation error in a shader that naga accepted.
+#[derive(Clone, Debug)]
+pub struct UncapturedError {
+    pub backend_id: BackendId,
+    pub message: String,
+}
+
 #[derive(Clone, Debug)]
 pub struct Backend {
     pub id: BackendId,
@@ -53,10 +86,12 @@ pub struct Backend {
 }
 
 impl Backend {
+    /// Creates a backend, whose uncaptured errors are sent to `tx_error`.
     pub(super) async fn new(
         instance: Arc<wgpu::Instance>,
         config: &Config,
         compatible_surface: Option<&wgpu::Surface<'static>>,
+        tx_error: mpsc::UnboundedSender<UncapturedError>,
     ) -> Result<Self, Error> {
         tracing::debug!("creating render adapter");
         let adapter = instance
@@ -72,24 +107,29 @@ impl Backend {
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
-                    required_features: Default::default(),
-                    required_limits: wgpu::Limits::downlevel_webgl2_defaults(),
+                    required_features: adapter.features() & OPTIONAL_FEATURES,
+                    required_limits: adapter.limits(),
                     memory_hints: wgpu::MemoryHints::Performance,
                 },
                 None,
             )
             .await?;
 
-        device.on_uncaptured_error(Box::new(|error| {
+        static IDS: AtomicUsize = AtomicUsize::new(1);
+        let id = BackendId(NonZeroUsize::new(IDS.fetch_add(1, Ordering::Relaxed)).unwrap());
+
+        device.on_uncaptured_error(Box::new(move |error| {
             tracing::error!(%error, "uncaptured wgpu error");
-            panic!("uncaptured wgpu error: {error}");
+            // the reactor is gone if the receiver was dropped, so nobody is interested
+            // anymore.
+            let _ = tx_error.send(UncapturedError {
+                backend_id: id,
+                message: error.to_string(),
+            });
         }));
 
         tracing::debug!("device features: {:#?}", device.features());
 
-        static IDS: AtomicUsize = AtomicUsize::new(1);
-        let id = BackendId(NonZeroUsize::new(IDS.fetch_add(1, Ordering::Relaxed)).unwrap());
-
         Ok(Self {
             id,
             instance,
@@ -0,0 +1,161 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bytemuck::{
+    Pod,
+    Zeroable,
+};
+
+/// Radians the camera turns per pixel that the mouse is dragged.
+const ORBIT_SPEED: f32 = 0.01;
+
+/// Factor the distance changes by per pixel that is scrolled.
+const ZOOM_SPEED: f32 = 0.001;
+
+const MIN_DISTANCE: f32 = 0.1;
+const MAX_DISTANCE: f32 = 100.0;
+
+/// Keeps the camera from flipping over the poles.
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+const FIELD_OF_VIEW: f32 = FRAC_PI_2 / 2.0;
+const NEAR: f32 = 0.01;
+const FAR: f32 = 1000.0;
+
+/// A camera that orbits around the origin. Dragging the mouse orbits, and
+/// scrolling zooms.
+///
+/// See [`shade_rs_compile::reflect::CAMERA_BINDING`] for how shaders access
+/// it.
+#[derive(Clone, Copy, Debug)]
+pub struct OrbitCamera {
+    /// Rotation around the y axis.
+    yaw: f32,
+
+    /// Rotation above the xz plane.
+    pitch: f32,
+
+    distance: f32,
+}
+
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        Self {
+            yaw: 0.0,
+            pitch: 0.0,
+            distance: 3.0,
+        }
+    }
+}
+
+impl OrbitCamera {
+    /// Orbits by a mouse drag of `delta` pixels.
+    pub fn orbit(&mut self, delta: [f32; 2]) {
+        self.yaw -= delta[0] * ORBIT_SPEED;
+        self.pitch = (self.pitch + delta[1] * ORBIT_SPEED).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    /// Zooms by a scroll of `delta` pixels. Positive values zoom out.
+    pub fn zoom(&mut self, delta: f32) {
+        self.distance =
+            (self.distance * (delta * ZOOM_SPEED).exp()).clamp(MIN_DISTANCE, MAX_DISTANCE);
+    }
+
+    pub fn position(&self) -> [f32; 3] {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        [
+            self.distance * cos_pitch * sin_yaw,
+            self.distance * sin_pitch,
+            self.distance * cos_pitch * cos_yaw,
+        ]
+    }
+
+    pub fn as_uniform(&self, aspect: f32) -> CameraUniform {
+        let position = self.position();
+
+        // orthonormal basis of the camera, looking at the origin.
+        let forward = normalize(position.map(|x| -x));
+        let right = normalize(cross(forward, [0.0, 1.0, 0.0]));
+        let up = cross(right, forward);
+
+        let view = [
+            [right[0], up[0], -forward[0], 0.0],
+            [right[1], up[1], -forward[1], 0.0],
+            [right[2], up[2], -forward[2], 0.0],
+            [
+                -dot(right, position),
+                -dot(up, position),
+                dot(forward, position),
+                1.0,
+            ],
+        ];
+        // the inverse of a rigid transform is the transposed rotation and the
+        // position.
+        let inverse_view = [
+            [right[0], right[1], right[2], 0.0],
+            [up[0], up[1], up[2], 0.0],
+            [-forward[0], -forward[1], -forward[2], 0.0],
+            [position[0], position[1], position[2], 1.0],
+        ];
+
+        // right-handed, with depth in 0..1 like WebGPU.
+        let f = 1.0 / (0.5 * FIELD_OF_VIEW).tan();
+        let a = FAR / (NEAR - FAR);
+        let b = NEAR * FAR / (NEAR - FAR);
+        let projection = [
+            [f / aspect, 0.0, 0.0, 0.0],
+            [0.0, f, 0.0, 0.0],
+            [0.0, 0.0, a, -1.0],
+            [0.0, 0.0, b, 0.0],
+        ];
+        let inverse_projection = [
+            [aspect / f, 0.0, 0.0, 0.0],
+            [0.0, 1.0 / f, 0.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0 / b],
+            [0.0, 0.0, -1.0, a / b],
+        ];
+
+        CameraUniform {
+            view_projection: mul(&projection, &view),
+            inverse_view_projection: mul(&inverse_view, &inverse_projection),
+            position,
+            _padding: 0.0,
+        }
+    }
+}
+
+/// Layout of the camera uniform.
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+pub struct CameraUniform {
+    pub view_projection: [[f32; 4]; 4],
+    pub inverse_view_projection: [[f32; 4]; 4],
+    pub position: [f32; 3],
+    _padding: f32,
+}
+
+type Matrix = [[f32; 4]; 4];
+
+/// Multiplies column-major matrices.
+fn mul(a: &Matrix, b: &Matrix) -> Matrix {
+    std::array::from_fn(|column| {
+        std::array::from_fn(|row| (0..4).map(|k| a[k][row] * b[column][k]).sum())
+    })
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = dot(v, v).sqrt();
+    v.map(|x| x / length)
+}
@@ -0,0 +1,1058 @@
+use std::{
+    collections::HashMap,
+    ops::Range,
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use shade_rs_compile::reflect::CHANNEL_CUBE_BINDING;
+#[cfg(feature = "editor")]
+use web_sys::AnalyserNode;
+use web_sys::HtmlVideoElement;
+
+use crate::graphics::{
+    backend::Backend,
+    blit::Blitter,
+    cubemap::{
+        face_size,
+        EquirectConverter,
+        CUBEMAP_FORMAT,
+        CUBE_FACES,
+    },
+    depth,
+    memory::texture_bytes,
+};
+#[cfg(feature = "editor")]
+use crate::graphics::{
+    compressed::CompressedImage,
+    hdr::{
+        HdrImage,
+        HDR_FORMAT,
+    },
+};
+
+/// Number of texture channels a shader can use.
+pub const NUM_CHANNELS: usize = 4;
+
+/// Bind group that contains the channels.
+///
+/// Each channel `i` is bound as a `texture_2d<f32>` at binding `2 * i` and a
+/// `sampler` at binding `2 * i + 1`:
+///
+/// ```wgsl
+/// @group(1) @binding(0) var channel0: texture_2d<f32>;
+/// @group(1) @binding(1) var channel0_sampler: sampler;
+/// ```
+///
+/// Channels that are [converted to cubemaps](Channels::set_cubemap) are also
+/// bound as a `texture_cube<f32>` at binding `8 + i`, and use the same sampler:
+///
+/// ```wgsl
+/// @group(1) @binding(8) var channel0_cube: texture_cube<f32>;
+/// ```
+pub const CHANNEL_BIND_GROUP: u32 = 1;
+
+/// How many bytes of channel images are uploaded per frame, so that large
+/// images don't stall the frame they're bound in.
+pub const UPLOAD_BYTES_PER_FRAME: u64 = 4 * 1024 * 1024;
+
+/// Format of the channel textures, except for compressed and HDR images.
+pub const CHANNEL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Width of the texture of a channel that shows audio, which is the number of
+/// frequency bins and of waveform samples. The analyser's FFT size must be
+/// twice this.
+#[cfg(feature = "editor")]
+pub const AUDIO_SAMPLES: u32 = 512;
+
+/// Format of the texture of a channel that shows audio.
+#[cfg(feature = "editor")]
+const AUDIO_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+
+/// Whether the mip levels of channel textures with this format are generated
+/// by drawing them, which compressed formats don't support.
+pub fn generates_mipmaps(format: wgpu::TextureFormat) -> bool {
+    !format.is_compressed()
+}
+
+/// Decoded RGBA8 (sRGB) image data that can be bound to a channel.
+#[derive(Clone)]
+pub struct ChannelImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl ChannelImage {
+    fn size(&self) -> wgpu::Extent3d {
+        wgpu::Extent3d {
+            width: self.width,
+            height: self.height,
+            depth_or_array_layers: 1,
+        }
+    }
+
+    /// Size of the texture that holds the image with all its mip levels, in
+    /// bytes.
+    pub fn texture_bytes(&self) -> u64 {
+        let size = self.size();
+        (0..size.max_mips(wgpu::TextureDimension::D2))
+            .map(|level| {
+                let size = size.mip_level_size(level, wgpu::TextureDimension::D2);
+                4 * size.width as u64 * size.height as u64
+            })
+            .sum()
+    }
+}
+
+impl std::fmt::Debug for ChannelImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChannelImage")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish_non_exhaustive()
+    }
+}
+
+/// How a channel's texture is sampled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ChannelSampler {
+    pub filter: ChannelFilter,
+    pub wrap: ChannelWrap,
+}
+
+impl ChannelSampler {
+    fn create(&self, backend: &Backend) -> wgpu::Sampler {
+        let filter = match self.filter {
+            ChannelFilter::Linear => wgpu::FilterMode::Linear,
+            ChannelFilter::Nearest => wgpu::FilterMode::Nearest,
+        };
+        let address_mode = match self.wrap {
+            ChannelWrap::Repeat => wgpu::AddressMode::Repeat,
+            ChannelWrap::Clamp => wgpu::AddressMode::ClampToEdge,
+            ChannelWrap::Mirror => wgpu::AddressMode::MirrorRepeat,
+        };
+
+        backend.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("channel sampler"),
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            address_mode_w: address_mode,
+            mag_filter: filter,
+            min_filter: filter,
+            mipmap_filter: filter,
+            // nearest filtering shows the texels as they are, even when minified.
+            lod_max_clamp: match self.filter {
+                ChannelFilter::Linear => 32.0,
+                ChannelFilter::Nearest => 0.0,
+            },
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChannelFilter {
+    #[default]
+    Linear,
+    Nearest,
+}
+
+impl ChannelFilter {
+    pub const ALL: [Self; 2] = [Self::Linear, Self::Nearest];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Linear => "linear",
+            Self::Nearest => "nearest",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|filter| filter.name() == name)
+    }
+}
+
+/// What happens to texture coordinates outside of `[0, 1]`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChannelWrap {
+    #[default]
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+impl ChannelWrap {
+    pub const ALL: [Self; 3] = [Self::Repeat, Self::Clamp, Self::Mirror];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Repeat => "repeat",
+            Self::Clamp => "clamp",
+            Self::Mirror => "mirror",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|wrap| wrap.name() == name)
+    }
+}
+
+#[derive(Debug)]
+struct Channel {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+
+    /// Copied into the texture every frame, if the channel shows a video.
+    video: Option<HtmlVideoElement>,
+
+    /// Written to the texture every frame, if the channel shows audio.
+    #[cfg(feature = "editor")]
+    audio: Option<AnalyserNode>,
+}
+
+impl Channel {
+    fn create_texture(
+        backend: &Backend,
+        size: wgpu::Extent3d,
+        format: wgpu::TextureFormat,
+        mip_level_count: u32,
+    ) -> wgpu::Texture {
+        let mut usage = wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST;
+        // copying external images and generating mipmaps needs `RENDER_ATTACHMENT`.
+        if generates_mipmaps(format) {
+            usage |= wgpu::TextureUsages::RENDER_ATTACHMENT;
+        }
+
+        backend.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("channel texture"),
+            size,
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        })
+    }
+
+    /// Creates a channel for an image. Its data still has to be
+    /// [uploaded](Self::write_rows).
+    fn new(
+        backend: &Backend,
+        size: wgpu::Extent3d,
+        format: wgpu::TextureFormat,
+        mip_level_count: u32,
+    ) -> Self {
+        let texture = Self::create_texture(backend, size, format, mip_level_count);
+        let view = texture.create_view(&Default::default());
+
+        Self {
+            texture,
+            view,
+            video: None,
+            #[cfg(feature = "editor")]
+            audio: None,
+        }
+    }
+
+    /// Writes the rows `rows` of a mip level, whose data is `data`. For
+    /// compressed formats these are rows of blocks.
+    fn write_rows(&self, backend: &Backend, level: u32, data: &[u8], rows: Range<u32>) {
+        let format = self.texture.format();
+        let (block_width, block_height) = format.block_dimensions();
+        let size = self
+            .texture
+            .size()
+            .mip_level_size(level, wgpu::TextureDimension::D2);
+        let bytes_per_row = data.len() / size.height.div_ceil(block_height) as usize;
+        let start = rows.start as usize * bytes_per_row;
+        let end = rows.end as usize * bytes_per_row;
+
+        backend.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: level,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: rows.start * block_height,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &data[start..end],
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row as u32),
+                rows_per_image: Some(rows.len() as u32),
+            },
+            // copies of compressed textures cover whole blocks, even if the mip level is
+            // smaller.
+            wgpu::Extent3d {
+                width: size.width.next_multiple_of(block_width),
+                height: rows.len() as u32 * block_height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Creates a channel for a cubemap with `face_size` sized faces, that is
+    /// [converted](EquirectConverter::convert) from an equirectangular image.
+    fn cubemap(backend: &Backend, face_size: u32) -> Self {
+        let size = wgpu::Extent3d {
+            width: face_size,
+            height: face_size,
+            depth_or_array_layers: CUBE_FACES,
+        };
+        let texture = backend.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("channel cubemap"),
+            size,
+            mip_level_count: size.max_mips(wgpu::TextureDimension::D2),
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: CUBEMAP_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            video: None,
+            #[cfg(feature = "editor")]
+            audio: None,
+        }
+    }
+
+    /// Records drawing each mip level, starting at `first_level`, from the one
+    /// above it. Each layer, e.g. the faces of a cubemap, is drawn separately.
+    fn generate_mipmaps(
+        &self,
+        backend: &Backend,
+        blitter: &Blitter,
+        encoder: &mut wgpu::CommandEncoder,
+        first_level: u32,
+    ) {
+        let level_view = |level, layer| {
+            self.texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("channel mip level"),
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                base_mip_level: level,
+                mip_level_count: Some(1),
+                base_array_layer: layer,
+                array_layer_count: Some(1),
+                ..Default::default()
+            })
+        };
+
+        for layer in 0..self.texture.depth_or_array_layers() {
+            for level in first_level.max(1)..self.texture.mip_level_count() {
+                let source = blitter.bind_source(backend, &level_view(level - 1, layer));
+                blitter.blit(encoder, &source, &level_view(level, layer));
+            }
+        }
+    }
+
+    /// Creates a channel that shows a video, e.g. from the webcam. The texture
+    /// is created with the video's size once it has loaded.
+    ///
+    /// Videos change every frame, so they don't have mipmaps.
+    fn from_video(backend: &Backend, video: HtmlVideoElement) -> Self {
+        let texture = Self::create_texture(
+            backend,
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            CHANNEL_FORMAT,
+            1,
+        );
+        let view = texture.create_view(&Default::default());
+
+        Self {
+            texture,
+            view,
+            video: Some(video),
+            #[cfg(feature = "editor")]
+            audio: None,
+        }
+    }
+
+    /// Creates a channel that shows audio, e.g. from the microphone. Its
+    /// texture is [`AUDIO_SAMPLES`] wide and 2 high, with the values in the
+    /// red component. Like on Shadertoy, the first row is the spectrum and
+    /// the second the waveform.
+    #[cfg(feature = "editor")]
+    fn from_audio(backend: &Backend, analyser: AnalyserNode) -> Self {
+        let texture = Self::create_texture(
+            backend,
+            wgpu::Extent3d {
+                width: AUDIO_SAMPLES,
+                height: 2,
+                depth_or_array_layers: 1,
+            },
+            AUDIO_FORMAT,
+            1,
+        );
+        let view = texture.create_view(&Default::default());
+
+        Self {
+            texture,
+            view,
+            video: None,
+            audio: Some(analyser),
+        }
+    }
+
+    /// Writes the current spectrum and waveform of the audio into the texture.
+    #[cfg(feature = "editor")]
+    fn update_audio(&self, backend: &Backend) {
+        let Some(analyser) = &self.audio
+        else {
+            return;
+        };
+
+        let mut data = vec![0; 2 * AUDIO_SAMPLES as usize];
+        let (spectrum, waveform) = data.split_at_mut(AUDIO_SAMPLES as usize);
+        analyser.get_byte_frequency_data(spectrum);
+        analyser.get_byte_time_domain_data(waveform);
+        self.write_rows(backend, 0, &data, 0..2);
+    }
+
+    /// Whether the texture changes every frame, because the channel shows a
+    /// video or audio.
+    fn is_stream(&self) -> bool {
+        #[cfg(feature = "editor")]
+        if self.audio.is_some() {
+            return true;
+        }
+        self.video.is_some()
+    }
+
+    /// Copies the current frame of the video into the texture.
+    ///
+    /// Returns `true` if the texture was recreated, because the video's size
+    /// changed.
+    fn update_video(&mut self, backend: &Backend) -> bool {
+        let Some(video) = &self.video
+        else {
+            return false;
+        };
+
+        // the video has no frame yet.
+        if video.ready_state() < HAVE_CURRENT_DATA
+            || video.video_width() == 0
+            || video.video_height() == 0
+        {
+            return false;
+        }
+
+        let size = wgpu::Extent3d {
+            width: video.video_width(),
+            height: video.video_height(),
+            depth_or_array_layers: 1,
+        };
+        let resized = self.texture.size() != size;
+        if resized {
+            self.texture = Self::create_texture(backend, size, CHANNEL_FORMAT, 1);
+            self.view = self.texture.create_view(&Default::default());
+        }
+
+        backend.queue.copy_external_image_to_texture(
+            &wgpu::ImageCopyExternalImage {
+                source: wgpu::ExternalImageSource::HTMLVideoElement(video.clone()),
+                origin: wgpu::Origin2d::ZERO,
+                flip_y: false,
+            },
+            wgpu::ImageCopyTextureTagged {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+                color_space: wgpu::PredefinedColorSpace::Srgb,
+                premultiplied_alpha: false,
+            },
+            size,
+        );
+
+        resized
+    }
+}
+
+/// `HTMLMediaElement.HAVE_CURRENT_DATA`
+const HAVE_CURRENT_DATA: u16 = 2;
+
+/// An image that is uploaded to a channel over several frames.
+#[derive(Debug)]
+struct PendingUpload {
+    channel: Channel,
+
+    /// The data of the mip levels that are uploaded. The texture's other
+    /// levels are generated afterwards.
+    levels: Vec<Vec<u8>>,
+
+    /// The mip level that is being uploaded.
+    level: usize,
+
+    /// The rows of the level before this one were uploaded already.
+    next_row: u32,
+}
+
+impl PendingUpload {
+    fn new(channel: Channel, levels: Vec<Vec<u8>>) -> Self {
+        Self {
+            channel,
+            levels,
+            level: 0,
+            next_row: 0,
+        }
+    }
+
+    /// Number of rows (of blocks) in the current level, and their size in
+    /// bytes.
+    fn level_rows(&self) -> (u32, u64) {
+        let (_, block_height) = self.channel.texture.format().block_dimensions();
+        let rows = self
+            .channel
+            .texture
+            .size()
+            .mip_level_size(self.level as u32, wgpu::TextureDimension::D2)
+            .height
+            .div_ceil(block_height);
+        let bytes_per_row = self.levels[self.level].len() as u64 / rows as u64;
+        (rows, bytes_per_row)
+    }
+
+    fn is_complete(&self) -> bool {
+        self.level == self.levels.len()
+    }
+}
+
+/// The textures bound to a window's channels.
+#[derive(Debug)]
+pub struct Channels {
+    channels: [Option<Channel>; NUM_CHANNELS],
+
+    /// Images that are being uploaded. The channel keeps its previous texture
+    /// until the upload is done.
+    pending: [Option<PendingUpload>; NUM_CHANNELS],
+
+    /// Bound to channels that have no image.
+    placeholder: Channel,
+
+    /// The channels' images converted to cubemaps, for the channels that
+    /// [are shown as cubemaps](Self::set_cubemap). These are converted once
+    /// the image is uploaded.
+    cubemaps: [Option<Channel>; NUM_CHANNELS],
+
+    /// Which channels are converted to cubemaps.
+    is_cubemap: [bool; NUM_CHANNELS],
+
+    /// Bound to channels that have no cubemap.
+    cube_placeholder: wgpu::TextureView,
+
+    /// Created when the first channel is converted to a cubemap, since this
+    /// needs compute shaders.
+    equirect_converter: Option<EquirectConverter>,
+
+    /// Generate the mipmaps of uploaded images, by format.
+    mipmap_blitters: HashMap<wgpu::TextureFormat, Blitter>,
+
+    samplers: [wgpu::Sampler; NUM_CHANNELS],
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Channels {
+    pub fn new(backend: &Backend) -> Self {
+        let placeholder_image = ChannelImage {
+            width: 1,
+            height: 1,
+            data: vec![0, 0, 0, 255],
+        };
+        let placeholder = Channel::new(backend, placeholder_image.size(), CHANNEL_FORMAT, 1);
+        placeholder.write_rows(backend, 0, &placeholder_image.data, 0..1);
+        let cube_placeholder = create_cube_placeholder(backend, &placeholder_image.data);
+
+        let samplers = std::array::from_fn(|_| ChannelSampler::default().create(backend));
+
+        let bind_group_layout =
+            backend
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("channel bind group layout"),
+                    entries: &Self::bind_group_layout_entries(),
+                });
+
+        let channels = std::array::from_fn(|_| None);
+        let cubemaps = std::array::from_fn(|_| None);
+        let bind_group = create_bind_group(
+            backend,
+            &bind_group_layout,
+            &channels,
+            &placeholder,
+            &cubemaps,
+            &cube_placeholder,
+            &samplers,
+        );
+
+        Self {
+            channels,
+            pending: std::array::from_fn(|_| None),
+            placeholder,
+            cubemaps,
+            is_cubemap: [false; NUM_CHANNELS],
+            cube_placeholder,
+            equirect_converter: None,
+            mipmap_blitters: HashMap::new(),
+            samplers,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    /// Entries of the channels' bind group layout. See [`CHANNEL_BIND_GROUP`].
+    pub fn bind_group_layout_entries() -> Vec<wgpu::BindGroupLayoutEntry> {
+        (0..NUM_CHANNELS as u32)
+            .flat_map(|i| {
+                [
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2 * i,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2 * i + 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: CHANNEL_CUBE_BINDING + i,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::Cube,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                ]
+            })
+            .collect()
+    }
+
+    /// Binds an image to a channel, or clears the channel if `image` is
+    /// `None`.
+    ///
+    /// The image is uploaded over the next frames by [`Self::upload`], and
+    /// bound once it's complete.
+    pub fn set(&mut self, backend: &Backend, index: usize, image: Option<ChannelImage>) {
+        if let Some(image) = image {
+            let size = image.size();
+            let channel = Channel::new(
+                backend,
+                size,
+                CHANNEL_FORMAT,
+                size.max_mips(wgpu::TextureDimension::D2),
+            );
+            self.pending[index] = Some(PendingUpload::new(channel, vec![image.data]));
+        }
+        else {
+            self.pending[index] = None;
+            self.channels[index] = None;
+            self.cubemaps[index] = None;
+            self.recreate_bind_group(backend);
+        }
+    }
+
+    /// Binds a compressed image to a channel. Like [`Self::set`], it's bound
+    /// once it's uploaded.
+    ///
+    /// Its mip levels are generated if it's not actually compressed.
+    #[cfg(feature = "editor")]
+    pub fn set_compressed(&mut self, backend: &Backend, index: usize, image: CompressedImage) {
+        let size = wgpu::Extent3d {
+            width: image.width,
+            height: image.height,
+            depth_or_array_layers: 1,
+        };
+        let mip_level_count = if generates_mipmaps(image.format) {
+            size.max_mips(wgpu::TextureDimension::D2)
+        }
+        else {
+            image.levels.len() as u32
+        };
+        let channel = Channel::new(backend, size, image.format, mip_level_count);
+        self.pending[index] = Some(PendingUpload::new(channel, image.levels));
+    }
+
+    /// Binds an HDR image to a channel, with its values scaled by
+    /// `2^exposure`. Like [`Self::set`], it's bound once it's uploaded.
+    #[cfg(feature = "editor")]
+    pub fn set_hdr(&mut self, backend: &Backend, index: usize, image: &HdrImage, exposure: f32) {
+        let size = wgpu::Extent3d {
+            width: image.width,
+            height: image.height,
+            depth_or_array_layers: 1,
+        };
+        let channel = Channel::new(
+            backend,
+            size,
+            HDR_FORMAT,
+            size.max_mips(wgpu::TextureDimension::D2),
+        );
+        self.pending[index] = Some(PendingUpload::new(
+            channel,
+            vec![image.to_texture_data(exposure)],
+        ));
+    }
+
+    /// Binds a video to a channel, or clears the channel if `video` is `None`.
+    /// The current frame of the video is copied into the channel before every
+    /// frame is rendered.
+    pub fn set_video(&mut self, backend: &Backend, index: usize, video: Option<HtmlVideoElement>) {
+        self.pending[index] = None;
+        self.channels[index] = video.map(|video| Channel::from_video(backend, video));
+        self.cubemaps[index] = None;
+        self.recreate_bind_group(backend);
+    }
+
+    /// Binds audio to a channel, or clears the channel if `analyser` is `None`.
+    /// The analyser's current spectrum and waveform are written to the channel
+    /// before every frame is rendered.
+    #[cfg(feature = "editor")]
+    pub fn set_audio(&mut self, backend: &Backend, index: usize, analyser: Option<AnalyserNode>) {
+        self.pending[index] = None;
+        self.channels[index] = analyser.map(|analyser| Channel::from_audio(backend, analyser));
+        self.cubemaps[index] = None;
+        self.recreate_bind_group(backend);
+    }
+
+    /// Creates a channel of the given size for a layer's depth buffer, or
+    /// clears the channel if `size` is `None`. The depth is drawn into the
+    /// channel's [view](Self::view) after the layer was drawn.
+    pub fn set_depth(&mut self, backend: &Backend, index: usize, size: Option<wgpu::Extent3d>) {
+        self.pending[index] = None;
+        self.channels[index] =
+            size.map(|size| Channel::new(backend, size, depth::channel_format(backend), 1));
+        self.cubemaps[index] = None;
+        self.recreate_bind_group(backend);
+    }
+
+    /// The texture view that is bound to a channel, if it has one.
+    pub fn view(&self, index: usize) -> Option<&wgpu::TextureView> {
+        self.channels[index].as_ref().map(|channel| &channel.view)
+    }
+
+    /// Sets whether a channel's image is treated as an equirectangular
+    /// panorama and also bound as a cubemap (see [`CHANNEL_BIND_GROUP`]).
+    ///
+    /// The cubemap is converted by [`Self::upload`], and again whenever
+    /// another image is bound to the channel. Videos and audio aren't
+    /// converted.
+    pub fn set_cubemap(&mut self, backend: &Backend, index: usize, is_cubemap: bool) {
+        self.is_cubemap[index] = is_cubemap;
+        if !is_cubemap && self.cubemaps[index].take().is_some() {
+            self.recreate_bind_group(backend);
+        }
+    }
+
+    /// Size of the cubemap that the channel's current image would be converted
+    /// to, in bytes.
+    pub fn cubemap_bytes(&self, backend: &Backend, index: usize) -> u64 {
+        let Some(channel) = &self.channels[index]
+        else {
+            return 0;
+        };
+        let face_size = face_size(
+            channel.texture.width(),
+            backend.device.limits().max_texture_dimension_2d,
+        );
+        let size = wgpu::Extent3d {
+            width: face_size,
+            height: face_size,
+            depth_or_array_layers: 1,
+        };
+        let block_size = CUBEMAP_FORMAT.block_copy_size(None).unwrap_or(8) as u64;
+        (0..size.max_mips(wgpu::TextureDimension::D2))
+            .map(|level| {
+                let size = size.mip_level_size(level, wgpu::TextureDimension::D2);
+                block_size * size.width as u64 * size.height as u64
+            })
+            .sum::<u64>()
+            * CUBE_FACES as u64
+    }
+
+    /// Whether a channel should be converted to a cubemap, but wasn't yet.
+    fn needs_conversion(&self, index: usize) -> bool {
+        self.is_cubemap[index]
+            && self.cubemaps[index].is_none()
+            && self.channels[index]
+                .as_ref()
+                .is_some_and(|channel| !channel.is_stream())
+    }
+
+    pub fn set_sampler(&mut self, backend: &Backend, index: usize, sampler: ChannelSampler) {
+        self.samplers[index] = sampler.create(backend);
+        self.recreate_bind_group(backend);
+    }
+
+    /// Size of the channels' textures, including the ones being uploaded, in
+    /// bytes.
+    pub fn memory_bytes(&self) -> u64 {
+        self.channels
+            .iter()
+            .flatten()
+            .chain(self.cubemaps.iter().flatten())
+            .chain(self.pending.iter().flatten().map(|pending| &pending.channel))
+            .map(|channel| texture_bytes(&channel.texture))
+            .sum()
+    }
+
+    /// Size of the textures that are bound to a channel, including its
+    /// cubemap, in bytes.
+    pub fn channel_memory_bytes(&self, index: usize) -> u64 {
+        self.channels[index]
+            .iter()
+            .chain(&self.cubemaps[index])
+            .map(|channel| texture_bytes(&channel.texture))
+            .sum()
+    }
+
+    /// Whether any channel shows a video or audio, which change every frame.
+    pub fn has_stream(&self) -> bool {
+        self.channels.iter().flatten().any(Channel::is_stream)
+    }
+
+    /// Whether images are still being uploaded or converted to cubemaps.
+    pub fn is_uploading(&self) -> bool {
+        self.pending.iter().any(Option::is_some)
+            || (0..NUM_CHANNELS).any(|index| self.needs_conversion(index))
+    }
+
+    /// Uploads up to `max_bytes` of the pending images, but at least one row
+    /// of each, level by level. Images that are complete get their mipmaps generated in
+    /// `encoder` and are bound. Channels that are shown as cubemaps are then
+    /// converted, also in `encoder`.
+    pub fn upload(
+        &mut self,
+        backend: &Backend,
+        encoder: &mut wgpu::CommandEncoder,
+        max_bytes: u64,
+    ) {
+        let mut budget = max_bytes;
+        let mut completed = false;
+
+        for index in 0..NUM_CHANNELS {
+            let Some(pending) = &mut self.pending[index]
+            else {
+                continue;
+            };
+
+            loop {
+                let (rows, bytes_per_row) = pending.level_rows();
+                let count = (budget / bytes_per_row.max(1))
+                    .clamp(1, (rows - pending.next_row) as u64) as u32;
+                let end = pending.next_row + count;
+                pending.channel.write_rows(
+                    backend,
+                    pending.level as u32,
+                    &pending.levels[pending.level],
+                    pending.next_row..end,
+                );
+                budget = budget.saturating_sub(count as u64 * bytes_per_row);
+
+                if end == rows {
+                    pending.level += 1;
+                    pending.next_row = 0;
+                }
+                else {
+                    pending.next_row = end;
+                }
+
+                if pending.is_complete() || budget == 0 {
+                    break;
+                }
+            }
+
+            if pending.is_complete() {
+                let pending = self.pending[index].take().unwrap();
+                let format = pending.channel.texture.format();
+                if generates_mipmaps(format) {
+                    let blitter = self
+                        .mipmap_blitters
+                        .entry(format)
+                        .or_insert_with(|| Blitter::new(backend, format));
+                    pending.channel.generate_mipmaps(
+                        backend,
+                        blitter,
+                        encoder,
+                        pending.levels.len() as u32,
+                    );
+                }
+                self.channels[index] = Some(pending.channel);
+                self.cubemaps[index] = None;
+                completed = true;
+            }
+        }
+
+        for index in 0..NUM_CHANNELS {
+            if !self.needs_conversion(index) {
+                continue;
+            }
+            let source = self.channels[index].as_ref().unwrap();
+
+            let cubemap = Channel::cubemap(
+                backend,
+                face_size(
+                    source.texture.width(),
+                    backend.device.limits().max_texture_dimension_2d,
+                ),
+            );
+            self.equirect_converter
+                .get_or_insert_with(|| EquirectConverter::new(backend))
+                .convert(backend, encoder, &source.view, &cubemap.texture);
+            let blitter = self
+                .mipmap_blitters
+                .entry(CUBEMAP_FORMAT)
+                .or_insert_with(|| Blitter::new(backend, CUBEMAP_FORMAT));
+            cubemap.generate_mipmaps(backend, blitter, encoder, 1);
+
+            self.cubemaps[index] = Some(cubemap);
+            completed = true;
+        }
+
+        if completed {
+            self.recreate_bind_group(backend);
+        }
+    }
+
+    /// Copies the current frames of videos and the current spectrum and
+    /// waveform of audio into their channels.
+    pub fn update_streams(&mut self, backend: &Backend) {
+        let mut resized = false;
+        for channel in self.channels.iter_mut().flatten() {
+            resized |= channel.update_video(backend);
+            #[cfg(feature = "editor")]
+            channel.update_audio(backend);
+        }
+        if resized {
+            self.recreate_bind_group(backend);
+        }
+    }
+
+    fn recreate_bind_group(&mut self, backend: &Backend) {
+        self.bind_group = create_bind_group(
+            backend,
+            &self.bind_group_layout,
+            &self.channels,
+            &self.placeholder,
+            &self.cubemaps,
+            &self.cube_placeholder,
+            &self.samplers,
+        );
+    }
+}
+
+/// Creates a cubemap whose faces are a single texel with `data` in
+/// [`CHANNEL_FORMAT`].
+fn create_cube_placeholder(backend: &Backend, data: &[u8]) -> wgpu::TextureView {
+    let size = wgpu::Extent3d {
+        width: 1,
+        height: 1,
+        depth_or_array_layers: CUBE_FACES,
+    };
+    let texture = backend.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("channel cubemap placeholder"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: CHANNEL_FORMAT,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+
+    backend.queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        &data.repeat(CUBE_FACES as usize),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(data.len() as u32),
+            rows_per_image: Some(1),
+        },
+        size,
+    );
+
+    texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::Cube),
+        ..Default::default()
+    })
+}
+
+fn create_bind_group(
+    backend: &Backend,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    channels: &[Option<Channel>; NUM_CHANNELS],
+    placeholder: &Channel,
+    cubemaps: &[Option<Channel>; NUM_CHANNELS],
+    cube_placeholder: &wgpu::TextureView,
+    samplers: &[wgpu::Sampler; NUM_CHANNELS],
+) -> wgpu::BindGroup {
+    let entries = channels
+        .iter()
+        .zip(cubemaps)
+        .zip(samplers)
+        .zip(0..)
+        .flat_map(|(((channel, cubemap), sampler), i)| {
+            let channel = channel.as_ref().unwrap_or(placeholder);
+            let cubemap = cubemap
+                .as_ref()
+                .map_or(cube_placeholder, |cubemap| &cubemap.view);
+            [
+                wgpu::BindGroupEntry {
+                    binding: 2 * i,
+                    resource: wgpu::BindingResource::TextureView(&channel.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2 * i + 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: CHANNEL_CUBE_BINDING + i,
+                    resource: wgpu::BindingResource::TextureView(cubemap),
+                },
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    backend
+        .device
+        .create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("channel bind group"),
+            layout: bind_group_layout,
+            entries: &entries,
+        })
+}
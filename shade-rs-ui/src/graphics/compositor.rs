@@ -0,0 +1,451 @@
+use std::fmt::Display;
+
+use bytemuck::{
+    Pod,
+    Zeroable,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::graphics::{
+    backend::Backend,
+    memory::texture_bytes,
+};
+
+/// How a layer is combined with the layers below it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Add,
+    Multiply,
+    Screen,
+}
+
+impl BlendMode {
+    pub const ALL: [Self; 4] = [Self::Normal, Self::Add, Self::Multiply, Self::Screen];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Normal => "normal",
+            Self::Add => "add",
+            Self::Multiply => "multiply",
+            Self::Screen => "screen",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    fn entry_point(&self) -> &'static str {
+        match self {
+            Self::Normal => "fs_normal",
+            Self::Add => "fs_add",
+            Self::Multiply => "fs_multiply",
+            Self::Screen => "fs_screen",
+        }
+    }
+
+    /// The fixed-function part of the blend mode. See `compositor.wgsl` for
+    /// what the fragment shaders output.
+    fn blend_state(&self) -> wgpu::BlendState {
+        // all modes but normal keep the alpha of the layers below.
+        let keep_alpha = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Zero,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let color = |src_factor, dst_factor| {
+            wgpu::BlendComponent {
+                src_factor,
+                dst_factor,
+                operation: wgpu::BlendOperation::Add,
+            }
+        };
+
+        match self {
+            Self::Normal => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            Self::Add => {
+                wgpu::BlendState {
+                    color: color(wgpu::BlendFactor::One, wgpu::BlendFactor::One),
+                    alpha: keep_alpha,
+                }
+            }
+            Self::Multiply => {
+                wgpu::BlendState {
+                    color: color(wgpu::BlendFactor::Zero, wgpu::BlendFactor::Src),
+                    alpha: keep_alpha,
+                }
+            }
+            Self::Screen => {
+                wgpu::BlendState {
+                    color: color(wgpu::BlendFactor::One, wgpu::BlendFactor::OneMinusSrc),
+                    alpha: keep_alpha,
+                }
+            }
+        }
+    }
+}
+
+impl Display for BlendMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Blend mode and opacity of a layer.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LayerBlend {
+    pub mode: BlendMode,
+    pub opacity: f32,
+}
+
+impl LayerBlend {
+    /// Whether the layer simply replaces what's below it, if it's opaque.
+    pub fn is_opaque_normal(&self) -> bool {
+        self.mode == BlendMode::Normal && self.opacity >= 1.0
+    }
+}
+
+impl Default for LayerBlend {
+    fn default() -> Self {
+        Self {
+            mode: BlendMode::Normal,
+            opacity: 1.0,
+        }
+    }
+}
+
+/// 2D transform of a layer, applied when it's composited.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LayerTransform {
+    pub scale: f32,
+
+    /// Counter-clockwise rotation around the layer's center, in radians.
+    pub rotation: f32,
+
+    /// Offset of the layer's center, in clip space (i.e. `[-1, 1]` spans the
+    /// whole window).
+    pub offset: [f32; 2],
+}
+
+impl LayerTransform {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Default for LayerTransform {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            rotation: 0.0,
+            offset: [0.0; 2],
+        }
+    }
+}
+
+/// Masks a layer by the alpha of another layer.
+///
+/// Layers that are used as a mask are not drawn themselves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LayerMask {
+    /// Index of the layer whose alpha is used as mask.
+    pub source: usize,
+
+    /// Show the layer where the mask is transparent, instead of where it's
+    /// opaque.
+    pub invert: bool,
+}
+
+impl LayerMask {
+    fn mode(mask: Option<Self>) -> u32 {
+        match mask {
+            None => 0,
+            Some(Self { invert: false, .. }) => 1,
+            Some(Self { invert: true, .. }) => 2,
+        }
+    }
+}
+
+/// `Composite` in `compositor.wgsl`.
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+struct CompositeUniform {
+    opacity: f32,
+    mask_mode: u32,
+    scale: f32,
+    rotation: f32,
+    offset: [f32; 2],
+    aspect: f32,
+    _padding: u32,
+}
+
+/// Composites layers onto a render target.
+#[derive(Debug)]
+pub struct Compositor {
+    pipelines: [wgpu::RenderPipeline; BlendMode::ALL.len()],
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+
+    /// Bound as mask for layers that have none. It's ignored by the shader.
+    placeholder_mask: wgpu::TextureView,
+}
+
+impl Compositor {
+    pub fn new(backend: &Backend, target_format: wgpu::TextureFormat) -> Self {
+        let shader = backend
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("compositor shader"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("compositor.wgsl").into()),
+            });
+
+        let bind_group_layout =
+            backend
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("compositor bind group layout"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 3,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+
+        let pipeline_layout =
+            backend
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("compositor pipeline layout"),
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let pipelines = BlendMode::ALL.map(|mode| {
+            backend
+                .device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("compositor pipeline"),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: "vs_main",
+                        buffers: &[],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: mode.entry_point(),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: target_format,
+                            blend: Some(mode.blend_state()),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                        compilation_options: Default::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    multisample: Default::default(),
+                    multiview: None,
+                    cache: None,
+                })
+        });
+
+        let sampler = backend.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("compositor sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let placeholder_mask = backend.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("placeholder mask"),
+            size: wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let placeholder_mask = placeholder_mask.create_view(&Default::default());
+
+        Self {
+            pipelines,
+            bind_group_layout,
+            sampler,
+            placeholder_mask,
+        }
+    }
+
+    /// Records compositing `layer` onto `target`, masked by the alpha of
+    /// `mask_target`, if given.
+    #[allow(clippy::too_many_arguments)]
+    pub fn composite(
+        &self,
+        backend: &Backend,
+        encoder: &mut wgpu::CommandEncoder,
+        layer: &LayerTarget,
+        blend: LayerBlend,
+        transform: LayerTransform,
+        mask: Option<(LayerMask, &LayerTarget)>,
+        target: &wgpu::TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) {
+        backend.queue.write_buffer(
+            &layer.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&CompositeUniform {
+                opacity: blend.opacity,
+                mask_mode: LayerMask::mode(mask.map(|(mask, _)| mask)),
+                scale: transform.scale,
+                rotation: transform.rotation,
+                offset: transform.offset,
+                aspect: layer.aspect,
+                _padding: 0,
+            }),
+        );
+
+        let mask_view = mask.map_or(&self.placeholder_mask, |(_, mask_target)| &mask_target.view);
+        let bind_group = backend
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("layer bind group"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&layer.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: layer.uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::TextureView(mask_view),
+                    },
+                ],
+            });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("compositor render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        let index = BlendMode::ALL
+            .iter()
+            .position(|mode| *mode == blend.mode)
+            .unwrap();
+        render_pass.set_pipeline(&self.pipelines[index]);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..6, 0..1);
+    }
+}
+
+/// Texture a layer is rendered into, before it's composited.
+#[derive(Debug)]
+pub struct LayerTarget {
+    texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    uniform_buffer: wgpu::Buffer,
+    aspect: f32,
+}
+
+impl LayerTarget {
+    /// Size of the texture and the uniform buffer, in bytes.
+    pub fn memory_bytes(&self) -> u64 {
+        texture_bytes(&self.texture) + self.uniform_buffer.size()
+    }
+
+    pub fn new(backend: &Backend, surface_configuration: &wgpu::SurfaceConfiguration) -> Self {
+        let texture = backend.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("layer target"),
+            size: wgpu::Extent3d {
+                width: surface_configuration.width,
+                height: surface_configuration.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: surface_configuration.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&Default::default());
+
+        let uniform_buffer = backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("layer uniform buffer"),
+            size: std::mem::size_of::<CompositeUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            texture,
+            view,
+            uniform_buffer,
+            aspect: surface_configuration.width as f32 / surface_configuration.height as f32,
+        }
+    }
+}
@@ -0,0 +1,104 @@
+struct Composite {
+    opacity: f32,
+    // 0: no mask, 1: mask by alpha, 2: mask by inverted alpha
+    mask_mode: u32,
+    scale: f32,
+    // counter-clockwise, in radians
+    rotation: f32,
+    // in clip space
+    offset: vec2f,
+    aspect: f32,
+}
+
+@group(0) @binding(0)
+var layer: texture_2d<f32>;
+
+@group(0) @binding(1)
+var layer_sampler: sampler;
+
+@group(0) @binding(2)
+var<uniform> composite: Composite;
+
+@group(0) @binding(3)
+var mask: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) uv: vec2f,
+}
+
+// a quad with the layer's transform applied. without a transform it covers the
+// whole target.
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var corners = array<vec2f, 6>(
+        vec2f(0.0, 0.0),
+        vec2f(1.0, 0.0),
+        vec2f(0.0, 1.0),
+        vec2f(0.0, 1.0),
+        vec2f(1.0, 0.0),
+        vec2f(1.0, 1.0),
+    );
+    let uv = corners[vertex_index];
+
+    // scale and rotate with x in the same units as y, so rotations don't skew
+    // the layer.
+    let aspect = vec2f(composite.aspect, 1.0);
+    let position = (uv * vec2f(2.0, -2.0) + vec2f(-1.0, 1.0)) * aspect * composite.scale;
+    let c = cos(composite.rotation);
+    let s = sin(composite.rotation);
+    let rotated = vec2f(c * position.x - s * position.y, s * position.x + c * position.y);
+
+    var output: VertexOutput;
+    output.position = vec4f(rotated / aspect + composite.offset, 0.0, 1.0);
+    output.uv = uv;
+    return output;
+}
+
+// alpha of the layer, after opacity and mask were applied.
+fn layer_alpha(color: vec4f, uv: vec2f) -> f32 {
+    // sample outside of the switch, to stay in uniform control flow.
+    let mask_alpha = textureSample(mask, layer_sampler, uv).a;
+    var alpha = color.a * composite.opacity;
+    switch composite.mask_mode {
+        case 1u: {
+            alpha *= mask_alpha;
+        }
+        case 2u: {
+            alpha *= 1.0 - mask_alpha;
+        }
+        default: {}
+    }
+    return alpha;
+}
+
+// each blend mode has its own entry point. the outputs are combined with the
+// target by the fixed-function blend state of the mode's pipeline.
+
+@fragment
+fn fs_normal(input: VertexOutput) -> @location(0) vec4f {
+    let color = textureSample(layer, layer_sampler, input.uv);
+    let alpha = layer_alpha(color, input.uv);
+    return vec4f(color.rgb * alpha, alpha);
+}
+
+@fragment
+fn fs_add(input: VertexOutput) -> @location(0) vec4f {
+    let color = textureSample(layer, layer_sampler, input.uv);
+    let alpha = layer_alpha(color, input.uv);
+    return vec4f(color.rgb * alpha, 0.0);
+}
+
+@fragment
+fn fs_multiply(input: VertexOutput) -> @location(0) vec4f {
+    let color = textureSample(layer, layer_sampler, input.uv);
+    let alpha = layer_alpha(color, input.uv);
+    return vec4f(mix(vec3f(1.0), color.rgb, alpha), 1.0);
+}
+
+@fragment
+fn fs_screen(input: VertexOutput) -> @location(0) vec4f {
+    let color = textureSample(layer, layer_sampler, input.uv);
+    let alpha = layer_alpha(color, input.uv);
+    return vec4f(color.rgb * alpha, 0.0);
+}
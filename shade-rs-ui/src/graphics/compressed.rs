@@ -0,0 +1,268 @@
+use std::io::Read;
+
+use wgpu::{
+    AstcBlock,
+    AstcChannel,
+    TextureFormat,
+};
+
+use crate::graphics::channel::{
+    generates_mipmaps,
+    CHANNEL_FORMAT,
+};
+
+/// Block-compressed texture data with its mip levels, e.g. from a KTX2 file.
+///
+/// Unlike [`ChannelImage`](super::channel::ChannelImage)s, these are uploaded
+/// as they are, so they take a fraction of the GPU memory. The device must
+/// support the format, see [`TextureFormat::required_features`].
+#[derive(Clone)]
+pub struct CompressedImage {
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+
+    /// The data of each mip level, starting with the full size one. Missing
+    /// levels are generated for uncompressed images, and left out otherwise.
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl std::fmt::Debug for CompressedImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompressedImage")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("format", &self.format)
+            .field("levels", &self.levels.len())
+            .finish()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CompressedImageError {
+    #[error("invalid KTX2 file")]
+    Ktx2(#[from] ktx2::ParseError),
+
+    #[error("failed to decompress mip level {level}")]
+    Zstd {
+        level: usize,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error(
+        "Basis Universal textures can't be transcoded yet. Encode the texture with a BC, ETC2 or ASTC format instead"
+    )]
+    Basis,
+
+    #[error("unsupported texture format: {format:?}")]
+    UnsupportedFormat { format: ktx2::Format },
+
+    #[error("unsupported supercompression scheme: {scheme:?}")]
+    UnsupportedSupercompression {
+        scheme: ktx2::SupercompressionScheme,
+    },
+
+    #[error("only 2D textures without array layers or cube faces are supported")]
+    Not2d,
+
+    #[error("size {width}x{height} is not a multiple of the {format:?} block size")]
+    Unaligned {
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+    },
+
+    #[error("mip level {level} has {actual} bytes, but should have {expected}")]
+    LevelSize {
+        level: usize,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl CompressedImage {
+    /// Reads a KTX2 file. Levels can be supercompressed with Zstandard.
+    pub fn from_ktx2(data: &[u8]) -> Result<Self, CompressedImageError> {
+        let reader = ktx2::Reader::new(data)?;
+        let header = reader.header();
+
+        if header.supercompression_scheme == Some(ktx2::SupercompressionScheme::BasisLZ) {
+            return Err(CompressedImageError::Basis);
+        }
+        let format = match header.format {
+            Some(format) => {
+                texture_format(format).ok_or(CompressedImageError::UnsupportedFormat { format })?
+            }
+            // UASTC is stored without a Vulkan format.
+            None => return Err(CompressedImageError::Basis),
+        };
+
+        if header.pixel_depth > 1
+            || header.layer_count > 1
+            || header.face_count != 1
+            || header.pixel_height == 0
+        {
+            return Err(CompressedImageError::Not2d);
+        }
+
+        let (block_width, block_height) = format.block_dimensions();
+        if header.pixel_width % block_width != 0 || header.pixel_height % block_height != 0 {
+            return Err(CompressedImageError::Unaligned {
+                width: header.pixel_width,
+                height: header.pixel_height,
+                format,
+            });
+        }
+
+        let size = wgpu::Extent3d {
+            width: header.pixel_width,
+            height: header.pixel_height,
+            depth_or_array_layers: 1,
+        };
+        let max_levels = size.max_mips(wgpu::TextureDimension::D2) as usize;
+
+        let levels = reader
+            .levels()
+            .take(max_levels)
+            .enumerate()
+            .map(|(level, data)| {
+                let data = match header.supercompression_scheme {
+                    None => data.to_vec(),
+                    Some(ktx2::SupercompressionScheme::Zstandard) => {
+                        let mut decompressed = vec![];
+                        let mut data = data;
+                        ruzstd::StreamingDecoder::new(&mut data)
+                            .map_err(|error| std::io::Error::other(error.to_string()))
+                            .and_then(|mut decoder| decoder.read_to_end(&mut decompressed))
+                            .map_err(|source| CompressedImageError::Zstd { level, source })?;
+                        decompressed
+                    }
+                    Some(scheme) => {
+                        return Err(CompressedImageError::UnsupportedSupercompression { scheme })
+                    }
+                };
+
+                let expected = level_bytes(format, size, level as u32);
+                if data.len() != expected {
+                    return Err(CompressedImageError::LevelSize {
+                        level,
+                        expected,
+                        actual: data.len(),
+                    });
+                }
+
+                Ok(data)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            width: header.pixel_width,
+            height: header.pixel_height,
+            format,
+            levels,
+        })
+    }
+
+    /// Size of the texture that holds the image with all its mip levels, in
+    /// bytes.
+    pub fn texture_bytes(&self) -> u64 {
+        let size = wgpu::Extent3d {
+            width: self.width,
+            height: self.height,
+            depth_or_array_layers: 1,
+        };
+        let levels = if generates_mipmaps(self.format) {
+            size.max_mips(wgpu::TextureDimension::D2)
+        }
+        else {
+            self.levels.len() as u32
+        };
+        (0..levels)
+            .map(|level| level_bytes(self.format, size, level) as u64)
+            .sum()
+    }
+}
+
+/// Size of a mip level of a 2D texture, in bytes.
+pub(super) fn level_bytes(format: TextureFormat, size: wgpu::Extent3d, level: u32) -> usize {
+    let (block_width, block_height) = format.block_dimensions();
+    let size = size.mip_level_size(level, wgpu::TextureDimension::D2);
+    let blocks_x = size.width.div_ceil(block_width) as usize;
+    let blocks_y = size.height.div_ceil(block_height) as usize;
+    blocks_x * blocks_y * format.block_copy_size(None).unwrap_or(4) as usize
+}
+
+/// The texture format for a Vulkan format, as KTX2 files store it.
+fn texture_format(format: ktx2::Format) -> Option<TextureFormat> {
+    use ktx2::Format as F;
+
+    let format = match format {
+        F::R8G8B8A8_SRGB => CHANNEL_FORMAT,
+        F::BC1_RGBA_UNORM_BLOCK => TextureFormat::Bc1RgbaUnorm,
+        F::BC1_RGBA_SRGB_BLOCK => TextureFormat::Bc1RgbaUnormSrgb,
+        F::BC2_UNORM_BLOCK => TextureFormat::Bc2RgbaUnorm,
+        F::BC2_SRGB_BLOCK => TextureFormat::Bc2RgbaUnormSrgb,
+        F::BC3_UNORM_BLOCK => TextureFormat::Bc3RgbaUnorm,
+        F::BC3_SRGB_BLOCK => TextureFormat::Bc3RgbaUnormSrgb,
+        F::BC4_UNORM_BLOCK => TextureFormat::Bc4RUnorm,
+        F::BC4_SNORM_BLOCK => TextureFormat::Bc4RSnorm,
+        F::BC5_UNORM_BLOCK => TextureFormat::Bc5RgUnorm,
+        F::BC5_SNORM_BLOCK => TextureFormat::Bc5RgSnorm,
+        F::BC6H_UFLOAT_BLOCK => TextureFormat::Bc6hRgbUfloat,
+        F::BC6H_SFLOAT_BLOCK => TextureFormat::Bc6hRgbFloat,
+        F::BC7_UNORM_BLOCK => TextureFormat::Bc7RgbaUnorm,
+        F::BC7_SRGB_BLOCK => TextureFormat::Bc7RgbaUnormSrgb,
+        F::ETC2_R8G8B8_UNORM_BLOCK => TextureFormat::Etc2Rgb8Unorm,
+        F::ETC2_R8G8B8_SRGB_BLOCK => TextureFormat::Etc2Rgb8UnormSrgb,
+        F::ETC2_R8G8B8A1_UNORM_BLOCK => TextureFormat::Etc2Rgb8A1Unorm,
+        F::ETC2_R8G8B8A1_SRGB_BLOCK => TextureFormat::Etc2Rgb8A1UnormSrgb,
+        F::ETC2_R8G8B8A8_UNORM_BLOCK => TextureFormat::Etc2Rgba8Unorm,
+        F::ETC2_R8G8B8A8_SRGB_BLOCK => TextureFormat::Etc2Rgba8UnormSrgb,
+        F::EAC_R11_UNORM_BLOCK => TextureFormat::EacR11Unorm,
+        F::EAC_R11_SNORM_BLOCK => TextureFormat::EacR11Snorm,
+        F::EAC_R11G11_UNORM_BLOCK => TextureFormat::EacRg11Unorm,
+        F::EAC_R11G11_SNORM_BLOCK => TextureFormat::EacRg11Snorm,
+        format => return astc_format(format),
+    };
+
+    Some(format)
+}
+
+fn astc_format(format: ktx2::Format) -> Option<TextureFormat> {
+    use ktx2::Format as F;
+
+    let (block, channel) = match format {
+        F::ASTC_4x4_UNORM_BLOCK => (AstcBlock::B4x4, AstcChannel::Unorm),
+        F::ASTC_4x4_SRGB_BLOCK => (AstcBlock::B4x4, AstcChannel::UnormSrgb),
+        F::ASTC_5x4_UNORM_BLOCK => (AstcBlock::B5x4, AstcChannel::Unorm),
+        F::ASTC_5x4_SRGB_BLOCK => (AstcBlock::B5x4, AstcChannel::UnormSrgb),
+        F::ASTC_5x5_UNORM_BLOCK => (AstcBlock::B5x5, AstcChannel::Unorm),
+        F::ASTC_5x5_SRGB_BLOCK => (AstcBlock::B5x5, AstcChannel::UnormSrgb),
+        F::ASTC_6x5_UNORM_BLOCK => (AstcBlock::B6x5, AstcChannel::Unorm),
+        F::ASTC_6x5_SRGB_BLOCK => (AstcBlock::B6x5, AstcChannel::UnormSrgb),
+        F::ASTC_6x6_UNORM_BLOCK => (AstcBlock::B6x6, AstcChannel::Unorm),
+        F::ASTC_6x6_SRGB_BLOCK => (AstcBlock::B6x6, AstcChannel::UnormSrgb),
+        F::ASTC_8x5_UNORM_BLOCK => (AstcBlock::B8x5, AstcChannel::Unorm),
+        F::ASTC_8x5_SRGB_BLOCK => (AstcBlock::B8x5, AstcChannel::UnormSrgb),
+        F::ASTC_8x6_UNORM_BLOCK => (AstcBlock::B8x6, AstcChannel::Unorm),
+        F::ASTC_8x6_SRGB_BLOCK => (AstcBlock::B8x6, AstcChannel::UnormSrgb),
+        F::ASTC_8x8_UNORM_BLOCK => (AstcBlock::B8x8, AstcChannel::Unorm),
+        F::ASTC_8x8_SRGB_BLOCK => (AstcBlock::B8x8, AstcChannel::UnormSrgb),
+        F::ASTC_10x5_UNORM_BLOCK => (AstcBlock::B10x5, AstcChannel::Unorm),
+        F::ASTC_10x5_SRGB_BLOCK => (AstcBlock::B10x5, AstcChannel::UnormSrgb),
+        F::ASTC_10x6_UNORM_BLOCK => (AstcBlock::B10x6, AstcChannel::Unorm),
+        F::ASTC_10x6_SRGB_BLOCK => (AstcBlock::B10x6, AstcChannel::UnormSrgb),
+        F::ASTC_10x8_UNORM_BLOCK => (AstcBlock::B10x8, AstcChannel::Unorm),
+        F::ASTC_10x8_SRGB_BLOCK => (AstcBlock::B10x8, AstcChannel::UnormSrgb),
+        F::ASTC_10x10_UNORM_BLOCK => (AstcBlock::B10x10, AstcChannel::Unorm),
+        F::ASTC_10x10_SRGB_BLOCK => (AstcBlock::B10x10, AstcChannel::UnormSrgb),
+        F::ASTC_12x10_UNORM_BLOCK => (AstcBlock::B12x10, AstcChannel::Unorm),
+        F::ASTC_12x10_SRGB_BLOCK => (AstcBlock::B12x10, AstcChannel::UnormSrgb),
+        F::ASTC_12x12_UNORM_BLOCK => (AstcBlock::B12x12, AstcChannel::Unorm),
+        F::ASTC_12x12_SRGB_BLOCK => (AstcBlock::B12x12, AstcChannel::UnormSrgb),
+        _ => return None,
+    };
+
+    Some(TextureFormat::Astc { block, channel })
+}
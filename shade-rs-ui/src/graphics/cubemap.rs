@@ -0,0 +1,161 @@
+use crate::graphics::backend::Backend;
+
+/// Format of the cubemaps that channels are converted to. Unlike the channels'
+/// sRGB format, it can be written from a compute shader.
+pub const CUBEMAP_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Number of faces, i.e. array layers, of a cubemap.
+pub const CUBE_FACES: u32 = 6;
+
+const WORKGROUP_SIZE: u32 = 8;
+
+/// Size of the faces of the cubemap that an equirectangular image of this
+/// width is converted to. Each face covers a quarter of the panorama's width.
+pub fn face_size(width: u32, max: u32) -> u32 {
+    (width / 4).clamp(1, max)
+}
+
+/// Converts equirectangular panoramas into cubemaps, with a compute shader.
+///
+/// The faces are written to the cubemap's first mip level, in the order +X,
+/// -X, +Y, -Y, +Z, -Z. The center of the panorama ends up on the -Z face.
+#[derive(Debug)]
+pub struct EquirectConverter {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+}
+
+impl EquirectConverter {
+    pub fn new(backend: &Backend) -> Self {
+        let shader = backend
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("cubemap shader"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("cubemap.wgsl").into()),
+            });
+
+        let bind_group_layout =
+            backend
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("cubemap bind group layout"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::StorageTexture {
+                                access: wgpu::StorageTextureAccess::WriteOnly,
+                                format: CUBEMAP_FORMAT,
+                                view_dimension: wgpu::TextureViewDimension::D2Array,
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+
+        let pipeline_layout =
+            backend
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("cubemap pipeline layout"),
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let pipeline = backend
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("cubemap pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: "main",
+                compilation_options: Default::default(),
+                cache: None,
+            });
+
+        // the panorama wraps around horizontally, but not at the poles.
+        let sampler = backend.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("cubemap sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            sampler,
+        }
+    }
+
+    /// Records converting `source` into the first mip level of `target`, which
+    /// must have [`CUBE_FACES`] layers and [`CUBEMAP_FORMAT`].
+    pub fn convert(
+        &self,
+        backend: &Backend,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::TextureView,
+        target: &wgpu::Texture,
+    ) {
+        let target_view = target.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("cubemap faces"),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            base_mip_level: 0,
+            mip_level_count: Some(1),
+            ..Default::default()
+        });
+
+        let bind_group = backend
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("cubemap bind group"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(source),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(&target_view),
+                    },
+                ],
+            });
+
+        let size = target.size();
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("cubemap compute pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &bind_group, &[]);
+        compute_pass.dispatch_workgroups(
+            size.width.div_ceil(WORKGROUP_SIZE),
+            size.height.div_ceil(WORKGROUP_SIZE),
+            CUBE_FACES,
+        );
+    }
+}
@@ -0,0 +1,42 @@
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+@group(0) @binding(2)
+var target: texture_storage_2d_array<rgba16float, write>;
+
+const PI: f32 = 3.14159265359;
+
+// direction through a point on a cube face, with the faces in the order +X, -X,
+// +Y, -Y, +Z, -Z, like the layers of a cube texture.
+fn face_direction(face: u32, uv: vec2f) -> vec3f {
+    let st = uv * 2.0 - 1.0;
+    switch face {
+        case 0u: { return vec3f(1.0, -st.y, -st.x); }
+        case 1u: { return vec3f(-1.0, -st.y, st.x); }
+        case 2u: { return vec3f(st.x, 1.0, st.y); }
+        case 3u: { return vec3f(st.x, -1.0, -st.y); }
+        case 4u: { return vec3f(st.x, -st.y, 1.0); }
+        default: { return vec3f(-st.x, -st.y, -1.0); }
+    }
+}
+
+// one invocation per texel of each face. the center of the panorama is at -Z.
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) id: vec3u) {
+    let size = textureDimensions(target);
+    if id.x >= size.x || id.y >= size.y {
+        return;
+    }
+
+    let uv = (vec2f(id.xy) + 0.5) / vec2f(size);
+    let direction = normalize(face_direction(id.z, uv));
+    let source_uv = vec2f(
+        atan2(direction.x, -direction.z) / (2.0 * PI) + 0.5,
+        acos(clamp(direction.y, -1.0, 1.0)) / PI,
+    );
+    let color = textureSampleLevel(source, source_sampler, source_uv, 0.0);
+    textureStore(target, id.xy, id.z, color);
+}
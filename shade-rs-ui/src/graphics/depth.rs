@@ -0,0 +1,197 @@
+use shade_rs_compile::depth::DepthFormat;
+
+use crate::graphics::{
+    backend::Backend,
+    memory::texture_bytes,
+};
+
+pub fn texture_format(format: DepthFormat) -> wgpu::TextureFormat {
+    match format {
+        DepthFormat::Depth16Unorm => wgpu::TextureFormat::Depth16Unorm,
+        DepthFormat::Depth24Plus => wgpu::TextureFormat::Depth24Plus,
+        DepthFormat::Depth32Float => wgpu::TextureFormat::Depth32Float,
+    }
+}
+
+/// Format of channels that show a layer's depth buffer.
+///
+/// Channels must be filterable, so this is only `R32Float` if the device can
+/// filter it. Otherwise the depth has half precision.
+pub fn channel_format(backend: &Backend) -> wgpu::TextureFormat {
+    if backend
+        .device
+        .features()
+        .contains(wgpu::Features::FLOAT32_FILTERABLE)
+    {
+        wgpu::TextureFormat::R32Float
+    }
+    else {
+        wgpu::TextureFormat::R16Float
+    }
+}
+
+/// Depth buffer of a layer. It has the size of the rendered frame, and is
+/// cleared every time the layer is drawn.
+#[derive(Debug)]
+pub struct DepthTarget {
+    texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+}
+
+impl DepthTarget {
+    pub fn new(
+        backend: &Backend,
+        surface_configuration: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let texture = backend.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("depth texture"),
+            size: wgpu::Extent3d {
+                width: surface_configuration.width,
+                height: surface_configuration.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            // bound when it's copied into a channel.
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&Default::default());
+        Self { texture, view }
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.texture.format()
+    }
+
+    pub fn memory_bytes(&self) -> u64 {
+        texture_bytes(&self.texture)
+    }
+}
+
+/// Copies a layer's depth buffer into a channel texture, so that later layers
+/// can sample it like any other channel.
+///
+/// Depth textures can't be sampled with a filtering sampler, and can't be
+/// copied into color textures, so they are drawn.
+#[derive(Debug)]
+pub struct DepthResolver {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+}
+
+impl DepthResolver {
+    pub fn new(backend: &Backend) -> Self {
+        let shader = backend
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("depth resolve shader"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("depth.wgsl").into()),
+            });
+
+        let bind_group_layout =
+            backend
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("depth resolve bind group layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Depth,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    }],
+                });
+
+        let pipeline_layout =
+            backend
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("depth resolve pipeline layout"),
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let pipeline = backend
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("depth resolve pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: channel_format(backend),
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: Default::default(),
+                multiview: None,
+                cache: None,
+            });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+        }
+    }
+
+    /// Records drawing the depth buffer onto `target`, which must have the
+    /// same size and the [`channel_format`].
+    pub fn resolve(
+        &self,
+        backend: &Backend,
+        encoder: &mut wgpu::CommandEncoder,
+        depth: &DepthTarget,
+        target: &wgpu::TextureView,
+    ) {
+        let bind_group = backend
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("depth resolve bind group"),
+                layout: &self.bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&depth.view),
+                }],
+            });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("depth resolve render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,17 @@
+@group(0) @binding(0)
+var depth: texture_depth_2d;
+
+// a single triangle that covers the whole target.
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4f {
+    let uv = vec2f(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4f(uv * vec2f(2.0, -2.0) + vec2f(-1.0, 1.0), 0.0, 1.0);
+}
+
+// the channel has the size of the depth buffer, so every pixel reads its own
+// depth.
+@fragment
+fn fs_main(@builtin(position) position: vec4f) -> @location(0) vec4f {
+    let value = textureLoad(depth, vec2i(position.xy), 0);
+    return vec4f(value, value, value, 1.0);
+}
@@ -0,0 +1,223 @@
+use crate::graphics::backend::Backend;
+
+/// Shows how the output of the bottom layer's shader differs from the version
+/// that was compiled before it, e.g. to check that a refactor didn't change
+/// anything.
+///
+/// Both versions are drawn into their own target, and the amplified absolute
+/// difference is drawn into the frame instead of the layers. Identical pixels
+/// are black.
+#[derive(Debug)]
+pub struct DiffPass {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    targets: Option<DiffTargets>,
+}
+
+impl DiffPass {
+    pub fn new(backend: &Backend, target_format: wgpu::TextureFormat) -> Self {
+        let shader = backend
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("diff shader"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("diff.wgsl").into()),
+            });
+
+        let texture_entry = |binding| {
+            wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }
+        };
+        let bind_group_layout =
+            backend
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("diff bind group layout"),
+                    entries: &[texture_entry(0), texture_entry(1)],
+                });
+
+        let pipeline_layout =
+            backend
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("diff pipeline layout"),
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let pipeline = backend
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("diff pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: target_format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: Default::default(),
+                multiview: None,
+                cache: None,
+            });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            targets: None,
+        }
+    }
+
+    /// Creates the targets for [`Self::views`], or recreates them if the frame
+    /// size changed.
+    pub fn prepare(
+        &mut self,
+        backend: &Backend,
+        render_configuration: &wgpu::SurfaceConfiguration,
+    ) {
+        let size_matches = self.targets.as_ref().is_some_and(|targets| {
+            targets.width == render_configuration.width
+                && targets.height == render_configuration.height
+        });
+        if !size_matches {
+            self.targets = Some(DiffTargets::new(
+                backend,
+                &self.bind_group_layout,
+                render_configuration,
+            ));
+        }
+    }
+
+    /// The views that the current and the previous version are drawn into.
+    pub fn views(&self) -> Option<(&wgpu::TextureView, &wgpu::TextureView)> {
+        self.targets
+            .as_ref()
+            .map(|targets| (&targets.current.view, &targets.previous.view))
+    }
+
+    /// Records drawing the difference onto `target`, after both versions were
+    /// drawn into the [views](Self::views).
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        let Some(targets) = &self.targets
+        else {
+            return;
+        };
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("diff render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &targets.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+#[derive(Debug)]
+struct DiffTargets {
+    current: DiffTarget,
+    previous: DiffTarget,
+    bind_group: wgpu::BindGroup,
+    width: u32,
+    height: u32,
+}
+
+impl DiffTargets {
+    fn new(
+        backend: &Backend,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        render_configuration: &wgpu::SurfaceConfiguration,
+    ) -> Self {
+        let current = DiffTarget::new(backend, render_configuration);
+        let previous = DiffTarget::new(backend, render_configuration);
+
+        let bind_group = backend
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("diff bind group"),
+                layout: bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&current.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&previous.view),
+                    },
+                ],
+            });
+
+        Self {
+            current,
+            previous,
+            bind_group,
+            width: render_configuration.width,
+            height: render_configuration.height,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct DiffTarget {
+    _texture: wgpu::Texture,
+    view: wgpu::TextureView,
+}
+
+impl DiffTarget {
+    fn new(backend: &Backend, render_configuration: &wgpu::SurfaceConfiguration) -> Self {
+        let texture = backend.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("diff target"),
+            size: wgpu::Extent3d {
+                width: render_configuration.width,
+                height: render_configuration.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: render_configuration.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&Default::default());
+
+        Self {
+            _texture: texture,
+            view,
+        }
+    }
+}
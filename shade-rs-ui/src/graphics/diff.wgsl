@@ -0,0 +1,25 @@
+@group(0) @binding(0)
+var current: texture_2d<f32>;
+
+@group(0) @binding(1)
+var previous: texture_2d<f32>;
+
+// small differences, e.g. from reordering float operations, are still visible.
+const AMPLIFICATION: f32 = 16.0;
+
+// a single triangle that covers the whole target.
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4f {
+    let uv = vec2f(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4f(uv * vec2f(2.0, -2.0) + vec2f(-1.0, 1.0), 0.0, 1.0);
+}
+
+// the targets have the same size as the frame, so fragments map to texels.
+@fragment
+fn fs_main(@builtin(position) position: vec4f) -> @location(0) vec4f {
+    let texel = vec2i(position.xy);
+    let difference = abs(textureLoad(current, texel, 0) - textureLoad(previous, texel, 0));
+    // alpha differences show up as white.
+    let color = max(difference.rgb, vec3f(difference.a));
+    return vec4f(min(color * AMPLIFICATION, vec3f(1.0)), 1.0);
+}
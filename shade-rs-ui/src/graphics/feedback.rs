@@ -0,0 +1,214 @@
+use crate::graphics::{
+    backend::Backend,
+    blit::Blitter,
+    memory::texture_bytes,
+};
+
+/// Bind group that contains the previous frame.
+///
+/// This is the final output of the window (i.e. after all layers were
+/// composited), so shaders can use it for feedback effects and stateful
+/// simulations:
+///
+/// ```wgsl
+/// @group(3) @binding(0) var previous_frame: texture_2d<f32>;
+/// @group(3) @binding(1) var previous_frame_sampler: sampler;
+/// ```
+///
+/// Before the first frame, and after a reset or resize, it's transparent black.
+pub const FEEDBACK_BIND_GROUP: u32 = 3;
+
+/// Whether the shader declares the previous frame in [`FEEDBACK_BIND_GROUP`].
+pub fn reads_previous_frame(module: &naga::Module) -> bool {
+    module.global_variables.iter().any(|(_, global)| {
+        global
+            .binding
+            .as_ref()
+            .is_some_and(|binding| binding.group == FEEDBACK_BIND_GROUP)
+    })
+}
+
+/// Two frame textures that are swapped every frame: One is rendered into, while
+/// the other one holds the previous frame.
+#[derive(Debug)]
+pub struct Feedback {
+    targets: [FrameTarget; 2],
+
+    /// Index of the target that is rendered into.
+    current: usize,
+
+    sampler: wgpu::Sampler,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+}
+
+impl Feedback {
+    pub fn new(
+        backend: &Backend,
+        blitter: &Blitter,
+        surface_configuration: &wgpu::SurfaceConfiguration,
+    ) -> Self {
+        let sampler = backend.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("previous frame sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group_layout =
+            backend
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("feedback bind group layout"),
+                    entries: &Self::bind_group_layout_entries(),
+                });
+
+        let targets = std::array::from_fn(|_| {
+            FrameTarget::new(
+                backend,
+                blitter,
+                &bind_group_layout,
+                &sampler,
+                surface_configuration,
+            )
+        });
+
+        Self {
+            targets,
+            current: 0,
+            sampler,
+            bind_group_layout,
+        }
+    }
+
+    /// Entries of the previous frame's bind group layout. See
+    /// [`FEEDBACK_BIND_GROUP`].
+    pub fn bind_group_layout_entries() -> Vec<wgpu::BindGroupLayoutEntry> {
+        vec![
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ]
+    }
+
+    /// Recreates the frame textures, e.g. after a resize. This clears the
+    /// previous frame.
+    pub fn recreate(
+        &mut self,
+        backend: &Backend,
+        blitter: &Blitter,
+        surface_configuration: &wgpu::SurfaceConfiguration,
+    ) {
+        self.targets = std::array::from_fn(|_| {
+            FrameTarget::new(
+                backend,
+                blitter,
+                &self.bind_group_layout,
+                &self.sampler,
+                surface_configuration,
+            )
+        });
+    }
+
+    /// Makes the current frame the previous one. Called before rendering a new
+    /// frame.
+    pub fn swap(&mut self) {
+        self.current = 1 - self.current;
+    }
+
+    /// The target that is rendered into.
+    pub fn current(&self) -> &FrameTarget {
+        &self.targets[self.current]
+    }
+
+    /// Size of both frame textures, in bytes.
+    pub fn memory_bytes(&self) -> u64 {
+        self.targets
+            .iter()
+            .map(|target| texture_bytes(&target.texture))
+            .sum()
+    }
+
+    /// The bind group containing the previous frame.
+    pub fn previous_frame_bind_group(&self) -> &wgpu::BindGroup {
+        &self.targets[1 - self.current].previous_frame_bind_group
+    }
+}
+
+#[derive(Debug)]
+pub struct FrameTarget {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+
+    /// For drawing the frame onto the surface.
+    pub blit_source: wgpu::BindGroup,
+
+    /// For sampling the frame in the next frame.
+    previous_frame_bind_group: wgpu::BindGroup,
+}
+
+impl FrameTarget {
+    fn new(
+        backend: &Backend,
+        blitter: &Blitter,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        surface_configuration: &wgpu::SurfaceConfiguration,
+    ) -> Self {
+        let texture = backend.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("frame target"),
+            size: wgpu::Extent3d {
+                width: surface_configuration.width,
+                height: surface_configuration.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: surface_configuration.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&Default::default());
+        let blit_source = blitter.bind_source(backend, &view);
+
+        let previous_frame_bind_group =
+            backend
+                .device
+                .create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("previous frame bind group"),
+                    layout: bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::Sampler(sampler),
+                        },
+                    ],
+                });
+
+        Self {
+            texture,
+            view,
+            blit_source,
+            previous_frame_bind_group,
+        }
+    }
+}
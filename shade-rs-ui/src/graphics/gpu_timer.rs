@@ -0,0 +1,272 @@
+use std::{
+    cell::{
+        Cell,
+        RefCell,
+    },
+    rc::Rc,
+    sync::Arc,
+};
+
+use crate::graphics::backend::Backend;
+
+/// Measures how long the GPU takes to render a frame, and each layer in it,
+/// with timestamp queries.
+///
+/// This needs [`wgpu::Features::TIMESTAMP_QUERY`]. WebGPU only writes
+/// timestamps at the beginning and end of passes, so timestamps are written by
+/// empty compute passes: One at the beginning of the frame, one after each
+/// step that is [attributed to a layer](Self::mark), and one at the end.
+///
+/// Like the [probe pass](super::probe::ProbePass), only one readback is pending
+/// at a time, and frames in between aren't measured.
+#[derive(Debug)]
+pub struct GpuTimer {
+    queries: Queries,
+
+    /// Nanoseconds per timestamp tick.
+    period: f32,
+
+    /// For each span between two timestamps of the measured frame, the layer
+    /// it's attributed to.
+    spans: RefCell<Vec<Option<usize>>>,
+
+    /// Set between [`Self::begin`] and [`Self::end`].
+    recording: Cell<bool>,
+
+    /// Set after the timestamps were resolved, until they're read back.
+    encoded: bool,
+
+    /// Set while the timestamps are being read back.
+    in_flight: Rc<Cell<bool>>,
+
+    /// The last measured times.
+    times: Rc<RefCell<Option<GpuTimes>>>,
+}
+
+impl GpuTimer {
+    /// Returns `None` if the device doesn't support timestamp queries.
+    pub fn new(backend: &Backend) -> Option<Self> {
+        if !backend
+            .device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+        {
+            return None;
+        }
+
+        Some(Self {
+            queries: Queries::new(backend, 2),
+            period: backend.queue.get_timestamp_period(),
+            spans: RefCell::new(vec![]),
+            recording: Cell::new(false),
+            encoded: false,
+            in_flight: Rc::new(Cell::new(false)),
+            times: Rc::new(RefCell::new(None)),
+        })
+    }
+
+    /// Whether this frame is measured. If so, [`Self::begin`] and
+    /// [`Self::end`] must be called around it.
+    pub fn is_ready(&self) -> bool {
+        !self.encoded && !self.in_flight.get()
+    }
+
+    /// Starts measuring a frame with `num_layers` layers. Each of them can be
+    /// [marked](Self::mark) twice, once after it's drawn and once after it's
+    /// composited.
+    pub fn begin(
+        &mut self,
+        backend: &Backend,
+        encoder: &mut wgpu::CommandEncoder,
+        num_layers: usize,
+    ) {
+        let capacity = 2 * num_layers as u32 + 2;
+        if self.queries.capacity < capacity {
+            self.queries = Queries::new(backend, capacity);
+        }
+
+        self.spans.borrow_mut().clear();
+        self.recording.set(true);
+        self.write_timestamp(encoder, 0);
+    }
+
+    /// Ends the current span and attributes it to `layer`, or to no layer if
+    /// it's `None`. Does nothing if this frame isn't measured.
+    pub fn mark(&self, encoder: &mut wgpu::CommandEncoder, layer: Option<usize>) {
+        if !self.recording.get() {
+            return;
+        }
+
+        let index = self.spans.borrow().len() as u32 + 1;
+        if index >= self.queries.capacity {
+            return;
+        }
+        self.write_timestamp(encoder, index);
+        self.spans.borrow_mut().push(layer);
+    }
+
+    pub fn end(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        self.mark(encoder, None);
+        self.recording.set(false);
+
+        let count = self.spans.borrow().len() as u32 + 1;
+        encoder.resolve_query_set(
+            &self.queries.query_set,
+            0..count,
+            &self.queries.resolve_buffer,
+            0,
+        );
+        encoder.copy_buffer_to_buffer(
+            &self.queries.resolve_buffer,
+            0,
+            &self.queries.readback_buffer,
+            0,
+            count as u64 * wgpu::QUERY_SIZE as u64,
+        );
+        self.encoded = true;
+    }
+
+    fn write_timestamp(&self, encoder: &mut wgpu::CommandEncoder, index: u32) {
+        encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("frame timestamp pass"),
+            timestamp_writes: Some(wgpu::ComputePassTimestampWrites {
+                query_set: &self.queries.query_set,
+                beginning_of_pass_write_index: Some(index),
+                end_of_pass_write_index: None,
+            }),
+        });
+    }
+
+    /// Starts reading back the timestamps. Must be called after the commands
+    /// from [`Self::end`] were submitted.
+    pub fn read_back(&mut self) {
+        if !self.encoded {
+            return;
+        }
+        self.encoded = false;
+        self.in_flight.set(true);
+
+        let spans = self.spans.take();
+        let size = (spans.len() as u64 + 1) * wgpu::QUERY_SIZE as u64;
+        let readback_buffer = self.queries.readback_buffer.clone();
+        let in_flight = self.in_flight.clone();
+        let times = self.times.clone();
+        let period = self.period;
+        self.queries
+            .readback_buffer
+            .slice(..size)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                in_flight.set(false);
+                if let Err(error) = result {
+                    tracing::warn!(?error, "failed to read back frame timestamps");
+                    return;
+                }
+
+                let timestamps = readback_buffer
+                    .slice(..size)
+                    .get_mapped_range()
+                    .chunks_exact(wgpu::QUERY_SIZE as usize)
+                    .map(bytemuck::pod_read_unaligned::<u64>)
+                    .collect::<Vec<_>>();
+                readback_buffer.unmap();
+
+                if let Some(measured) = GpuTimes::from_timestamps(&timestamps, &spans, period) {
+                    *times.borrow_mut() = Some(measured);
+                }
+            });
+    }
+
+    /// The last measured times.
+    pub fn times(&self) -> Option<GpuTimes> {
+        self.times.borrow().clone()
+    }
+}
+
+/// How long the GPU took to render a frame, in milliseconds.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GpuTimes {
+    pub frame: f32,
+
+    /// Time of each layer, by layer index. This includes drawing the layer and
+    /// compositing it.
+    pub layers: Vec<f32>,
+}
+
+impl GpuTimes {
+    fn from_timestamps(timestamps: &[u64], spans: &[Option<usize>], period: f32) -> Option<Self> {
+        let to_milliseconds = |ticks: u64| ticks as f32 * period / 1_000_000.0;
+
+        // timestamps can be reset in between, e.g. when the GPU changes its power
+        // state.
+        let frame = timestamps.last()?.checked_sub(*timestamps.first()?)?;
+
+        let mut layers = vec![];
+        for (span, layer) in timestamps.windows(2).zip(spans) {
+            let ticks = span[1].checked_sub(span[0])?;
+            if let Some(layer) = *layer {
+                if layer >= layers.len() {
+                    layers.resize(layer + 1, 0.0);
+                }
+                layers[layer] += to_milliseconds(ticks);
+            }
+        }
+
+        Some(Self {
+            frame: to_milliseconds(frame),
+            layers,
+        })
+    }
+
+    /// Index of the layer that took the longest, if there's more than one.
+    pub fn slowest_layer(&self) -> Option<usize> {
+        if self.layers.len() < 2 {
+            return None;
+        }
+        self.layers
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+    }
+}
+
+/// The query set and the buffers the timestamps are copied through.
+#[derive(Debug)]
+struct Queries {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: Arc<wgpu::Buffer>,
+
+    /// Number of timestamps they hold.
+    capacity: u32,
+}
+
+impl Queries {
+    fn new(backend: &Backend, capacity: u32) -> Self {
+        let query_set = backend.device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("frame timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count: capacity,
+        });
+        let size = capacity as u64 * wgpu::QUERY_SIZE as u64;
+        let resolve_buffer = backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("frame timestamps resolve buffer"),
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+            size,
+        });
+        let readback_buffer = backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("frame timestamps readback buffer"),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+            size,
+        });
+
+        Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer: Arc::new(readback_buffer),
+            capacity,
+        }
+    }
+}
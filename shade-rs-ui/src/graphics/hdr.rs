@@ -0,0 +1,108 @@
+use half::f16;
+
+use crate::graphics::channel::ChannelImage;
+
+/// Format of the textures that HDR images are bound as.
+///
+/// Unlike 32 bit floats, these can always be filtered.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Linear RGBA32F image data, e.g. decoded from a Radiance HDR or OpenEXR file.
+///
+/// Channels bound to these read values outside of `[0, 1]`, e.g. for
+/// image-based lighting.
+#[derive(Clone)]
+pub struct HdrImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<f32>,
+}
+
+impl std::fmt::Debug for HdrImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HdrImage")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum HdrImageError {
+    #[error("failed to decode image")]
+    Decode(#[from] image::ImageError),
+}
+
+impl HdrImage {
+    /// Decodes a `.hdr` or `.exr` file. The format is detected from its
+    /// contents.
+    pub fn decode(data: &[u8]) -> Result<Self, HdrImageError> {
+        let image = image::load_from_memory(data)?.into_rgba32f();
+        Ok(Self {
+            width: image.width(),
+            height: image.height(),
+            data: image.into_raw(),
+        })
+    }
+
+    /// The pixels as [`HDR_FORMAT`] texture data, scaled by `2^exposure`.
+    pub fn to_texture_data(&self, exposure: f32) -> Vec<u8> {
+        let scale = exposure.exp2();
+        self.data
+            .chunks_exact(4)
+            .flat_map(|pixel| {
+                [pixel[0] * scale, pixel[1] * scale, pixel[2] * scale, pixel[3]]
+            })
+            .flat_map(|value| f16::from_f32(value).to_le_bytes())
+            .collect()
+    }
+
+    /// Maps the image to 8 bit sRGB with the Reinhard operator, e.g. for
+    /// previews.
+    pub fn tonemap(&self, exposure: f32) -> ChannelImage {
+        let scale = exposure.exp2();
+        let data = self
+            .data
+            .chunks_exact(4)
+            .flat_map(|pixel| {
+                let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(|value| {
+                    let value = (value * scale).max(0.0);
+                    linear_to_srgb(value / (1.0 + value))
+                });
+                [r, g, b, (pixel[3].clamp(0.0, 1.0) * 255.0).round() as u8]
+            })
+            .collect();
+
+        ChannelImage {
+            width: self.width,
+            height: self.height,
+            data,
+        }
+    }
+
+    /// Size of the texture that holds the image with all its mip levels, in
+    /// bytes.
+    pub fn texture_bytes(&self) -> u64 {
+        let size = wgpu::Extent3d {
+            width: self.width,
+            height: self.height,
+            depth_or_array_layers: 1,
+        };
+        (0..size.max_mips(wgpu::TextureDimension::D2))
+            .map(|level| {
+                let size = size.mip_level_size(level, wgpu::TextureDimension::D2);
+                8 * size.width as u64 * size.height as u64
+            })
+            .sum()
+    }
+}
+
+fn linear_to_srgb(value: f32) -> u8 {
+    let value = if value <= 0.0031308 {
+        12.92 * value
+    }
+    else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    };
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
@@ -0,0 +1,107 @@
+use std::fmt::Write;
+
+use naga::{
+    back::{
+        glsl,
+        wgsl,
+    },
+    valid::{
+        Capabilities,
+        ModuleInfo,
+        ValidationFlags,
+        Validator,
+    },
+    Module,
+};
+use shade_rs_compile::reflect::describe_bind_groups;
+
+use crate::graphics::backend::BackendType;
+
+/// What the compiler produced for a shader.
+#[derive(Clone, Debug)]
+pub struct ShaderInspection {
+    /// The validated naga IR.
+    pub ir: String,
+
+    /// The bind group layout inferred from the resource bindings.
+    pub bind_groups: String,
+
+    /// Name of the language of [`Self::backend_shader`].
+    pub backend_language: &'static str,
+
+    /// The shader as it's passed to the backend, or the error if the
+    /// translation failed.
+    pub backend_shader: String,
+}
+
+pub fn inspect_shader(module: &Module, backend_type: BackendType) -> ShaderInspection {
+    let mut validator = Validator::new(ValidationFlags::all(), Capabilities::all());
+    let module_info = validator.validate(module);
+
+    let (backend_language, backend_shader) = match backend_type {
+        BackendType::WebGpu => {
+            // the WebGPU backend passes WGSL to the browser
+            let backend_shader =
+                module_info
+                    .map_err(|error| error.to_string())
+                    .and_then(|module_info| {
+                        wgsl::write_string(module, &module_info, wgsl::WriterFlags::empty())
+                            .map_err(|error| error.to_string())
+                    });
+            ("WGSL", backend_shader)
+        }
+        BackendType::WebGl => {
+            let backend_shader = module_info
+                .map_err(|error| error.to_string())
+                .and_then(|module_info| write_glsl(module, &module_info));
+            ("GLSL ES 3.00", backend_shader)
+        }
+    };
+
+    ShaderInspection {
+        ir: format!("{module:#?}"),
+        bind_groups: describe_bind_groups(module),
+        backend_language,
+        backend_shader: backend_shader.unwrap_or_else(|error| format!("error: {error}")),
+    }
+}
+
+fn write_glsl(module: &Module, module_info: &ModuleInfo) -> Result<String, String> {
+    let options = glsl::Options {
+        version: glsl::Version::Embedded {
+            version: 300,
+            is_webgl: true,
+        },
+        ..Default::default()
+    };
+
+    let mut output = String::new();
+    for entry_point in &module.entry_points {
+        let pipeline_options = glsl::PipelineOptions {
+            shader_stage: entry_point.stage,
+            entry_point: entry_point.name.clone(),
+            multiview: None,
+        };
+
+        writeln!(
+            output,
+            "// {:?} entry point `{}`",
+            entry_point.stage, entry_point.name
+        )
+        .unwrap();
+        let mut writer = glsl::Writer::new(
+            &mut output,
+            module,
+            module_info,
+            &options,
+            &pipeline_options,
+            Default::default(),
+        )
+        .map_err(|error| error.to_string())?;
+        writer.write().map_err(|error| error.to_string())?;
+        drop(writer);
+        output.push('\n');
+    }
+
+    Ok(output)
+}
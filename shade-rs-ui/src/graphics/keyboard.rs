@@ -0,0 +1,134 @@
+use crate::graphics::backend::Backend;
+
+/// Bind group that contains the keyboard state.
+///
+/// The state is a 256x1 texture, indexed by the key's `keyCode`. The red
+/// channel is 1.0 while the key is held down:
+///
+/// ```wgsl
+/// @group(2) @binding(0) var keyboard: texture_2d<f32>;
+///
+/// fn is_key_down(key_code: u32) -> bool {
+///     return textureLoad(keyboard, vec2<u32>(key_code, 0u), 0).r > 0.5;
+/// }
+/// ```
+pub const KEYBOARD_BIND_GROUP: u32 = 2;
+
+/// Number of key codes that are tracked.
+pub const NUM_KEYS: usize = 256;
+
+#[derive(Debug)]
+pub struct Keyboard {
+    state: [u8; NUM_KEYS],
+    dirty: bool,
+    texture: wgpu::Texture,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Keyboard {
+    pub fn new(backend: &Backend) -> Self {
+        let texture = backend.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("keyboard texture"),
+            size: texture_size(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&Default::default());
+
+        let bind_group_layout =
+            backend
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("keyboard bind group layout"),
+                    entries: &Self::bind_group_layout_entries(),
+                });
+
+        let bind_group = backend
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("keyboard bind group"),
+                layout: &bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                }],
+            });
+
+        Self {
+            state: [0; NUM_KEYS],
+            // the texture's initial contents must be written once
+            dirty: true,
+            texture,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    /// Entries of the keyboard's bind group layout. See
+    /// [`KEYBOARD_BIND_GROUP`].
+    pub fn bind_group_layout_entries() -> Vec<wgpu::BindGroupLayoutEntry> {
+        vec![wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        }]
+    }
+
+    pub fn set(&mut self, key_code: u8, pressed: bool) {
+        let value = if pressed { 255 } else { 0 };
+        let slot = &mut self.state[usize::from(key_code)];
+        if *slot != value {
+            *slot = value;
+            self.dirty = true;
+        }
+    }
+
+    pub fn release_all(&mut self) {
+        if self.state.iter().any(|value| *value != 0) {
+            self.state = [0; NUM_KEYS];
+            self.dirty = true;
+        }
+    }
+
+    /// Uploads the state to the texture, if it changed.
+    pub fn flush(&mut self, backend: &Backend) {
+        if !self.dirty {
+            return;
+        }
+
+        backend.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &self.state,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(NUM_KEYS as u32),
+                rows_per_image: Some(1),
+            },
+            texture_size(),
+        );
+        self.dirty = false;
+    }
+}
+
+fn texture_size() -> wgpu::Extent3d {
+    wgpu::Extent3d {
+        width: NUM_KEYS as u32,
+        height: 1,
+        depth_or_array_layers: 1,
+    }
+}
@@ -0,0 +1,280 @@
+//! Matching the resource bindings that a shader uses with the bind groups that
+//! the renderer provides.
+//!
+//! The pipeline layout of a shader is built from the bind groups up to the
+//! highest one the shader declares, so that e.g. shaders that don't read the
+//! keyboard or the previous frame don't need them. Bindings that the renderer
+//! can't provide are reported as a [`BindingError`] when the shader is
+//! compiled, instead of failing when the pipeline is created.
+
+use shade_rs_compile::reflect::{
+    stage_name,
+    BindingKind,
+    ResourceBinding,
+};
+
+/// A bind group that the renderer provides.
+#[derive(Clone, Debug)]
+pub struct BindGroupSlot {
+    pub group: u32,
+
+    /// What the group contains, for error messages.
+    pub name: &'static str,
+
+    pub entries: Vec<wgpu::BindGroupLayoutEntry>,
+}
+
+/// Checks that every binding the shader uses is in one of the `slots`, with a
+/// compatible type and visible to the stages that use it.
+pub fn check_bindings(
+    bindings: &[ResourceBinding],
+    slots: &[BindGroupSlot],
+) -> Result<(), BindingError> {
+    for binding in bindings {
+        let Some(slot) = slots.iter().find(|slot| slot.group == binding.group)
+        else {
+            return Err(BindingError::UnknownGroup {
+                binding: binding.to_string(),
+                available: describe_slots(slots),
+            });
+        };
+
+        let Some(entry) = slot
+            .entries
+            .iter()
+            .find(|entry| entry.binding == binding.binding)
+        else {
+            return Err(BindingError::UnknownBinding {
+                binding: binding.to_string(),
+                group: slot.group,
+                group_name: slot.name,
+                available: slot
+                    .entries
+                    .iter()
+                    .map(|entry| {
+                        format!(
+                            "@binding({}): {}",
+                            entry.binding,
+                            describe_binding_type(&entry.ty)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            });
+        };
+
+        if !is_compatible(&binding.kind, &entry.ty) {
+            return Err(BindingError::TypeMismatch {
+                binding: binding.to_string(),
+                expected: describe_binding_type(&entry.ty),
+            });
+        }
+
+        for stage in &binding.stages {
+            let visibility = match stage {
+                naga::ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+                naga::ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+                naga::ShaderStage::Compute => wgpu::ShaderStages::COMPUTE,
+            };
+            if !entry.visibility.contains(visibility) {
+                return Err(BindingError::NotVisible {
+                    binding: binding.to_string(),
+                    stage: stage_name(*stage),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// How many bind groups the pipeline layout of a shader has: all up to the
+/// highest group it declares, but at least the inputs in group 0, and at most
+/// the `num_slots` that the renderer provides.
+///
+/// Declared groups are counted, rather than used ones, so that this works on
+/// shaders that were already checked, but whose entry points aren't known
+/// anymore, e.g. probe shaders.
+pub fn bind_group_count(module: &naga::Module, num_slots: usize) -> usize {
+    module
+        .global_variables
+        .iter()
+        .filter_map(|(_, global)| global.binding.as_ref())
+        .map(|binding| binding.group as usize + 1)
+        .max()
+        .unwrap_or_default()
+        .clamp(1, num_slots)
+}
+
+fn is_compatible(kind: &BindingKind, ty: &wgpu::BindingType) -> bool {
+    match (kind, ty) {
+        (
+            BindingKind::Uniform,
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                ..
+            },
+        ) => true,
+        (
+            BindingKind::Storage { read_only },
+            wgpu::BindingType::Buffer {
+                ty:
+                    wgpu::BufferBindingType::Storage {
+                        read_only: provided_read_only,
+                    },
+                ..
+            },
+        ) => *read_only || !provided_read_only,
+        (
+            BindingKind::Texture {
+                dimension,
+                arrayed,
+                class,
+            },
+            wgpu::BindingType::Texture {
+                sample_type,
+                view_dimension,
+                multisampled,
+            },
+        ) => {
+            let class_matches = match (class, sample_type) {
+                (
+                    naga::ImageClass::Sampled { kind, multi },
+                    wgpu::TextureSampleType::Float { .. },
+                ) => *kind == naga::ScalarKind::Float && multi == multisampled,
+                (naga::ImageClass::Sampled { kind, multi }, wgpu::TextureSampleType::Sint) => {
+                    *kind == naga::ScalarKind::Sint && multi == multisampled
+                }
+                (naga::ImageClass::Sampled { kind, multi }, wgpu::TextureSampleType::Uint) => {
+                    *kind == naga::ScalarKind::Uint && multi == multisampled
+                }
+                (naga::ImageClass::Depth { multi }, wgpu::TextureSampleType::Depth) => {
+                    multi == multisampled
+                }
+                _ => false,
+            };
+            class_matches && view_dimension_matches(*dimension, *arrayed, *view_dimension)
+        }
+        (
+            BindingKind::Texture {
+                dimension,
+                arrayed,
+                class: naga::ImageClass::Storage { .. },
+            },
+            wgpu::BindingType::StorageTexture { view_dimension, .. },
+        ) => view_dimension_matches(*dimension, *arrayed, *view_dimension),
+        (BindingKind::Sampler { comparison }, wgpu::BindingType::Sampler(sampler_type)) => {
+            *comparison == (*sampler_type == wgpu::SamplerBindingType::Comparison)
+        }
+        _ => false,
+    }
+}
+
+fn view_dimension_matches(
+    dimension: naga::ImageDimension,
+    arrayed: bool,
+    view_dimension: wgpu::TextureViewDimension,
+) -> bool {
+    let expected = match (dimension, arrayed) {
+        (naga::ImageDimension::D1, _) => wgpu::TextureViewDimension::D1,
+        (naga::ImageDimension::D2, false) => wgpu::TextureViewDimension::D2,
+        (naga::ImageDimension::D2, true) => wgpu::TextureViewDimension::D2Array,
+        (naga::ImageDimension::D3, _) => wgpu::TextureViewDimension::D3,
+        (naga::ImageDimension::Cube, false) => wgpu::TextureViewDimension::Cube,
+        (naga::ImageDimension::Cube, true) => wgpu::TextureViewDimension::CubeArray,
+    };
+    expected == view_dimension
+}
+
+/// Describes a binding type like it's declared in WGSL.
+fn describe_binding_type(ty: &wgpu::BindingType) -> String {
+    match ty {
+        wgpu::BindingType::Buffer { ty, .. } => {
+            match ty {
+                wgpu::BufferBindingType::Uniform => "var<uniform>".to_owned(),
+                wgpu::BufferBindingType::Storage { read_only: true } => {
+                    "var<storage, read>".to_owned()
+                }
+                wgpu::BufferBindingType::Storage { read_only: false } => {
+                    "var<storage, read_write>".to_owned()
+                }
+            }
+        }
+        wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison) => {
+            "sampler_comparison".to_owned()
+        }
+        wgpu::BindingType::Sampler(_) => "sampler".to_owned(),
+        wgpu::BindingType::Texture {
+            sample_type,
+            view_dimension,
+            multisampled,
+        } => {
+            let multisampled = if *multisampled { "multisampled_" } else { "" };
+            let dimension = describe_view_dimension(*view_dimension);
+            match sample_type {
+                wgpu::TextureSampleType::Float { .. } => {
+                    format!("texture_{multisampled}{dimension}<f32>")
+                }
+                wgpu::TextureSampleType::Sint => format!("texture_{multisampled}{dimension}<i32>"),
+                wgpu::TextureSampleType::Uint => format!("texture_{multisampled}{dimension}<u32>"),
+                wgpu::TextureSampleType::Depth => {
+                    format!("texture_depth_{multisampled}{dimension}")
+                }
+            }
+        }
+        wgpu::BindingType::StorageTexture {
+            format,
+            view_dimension,
+            ..
+        } => {
+            let format = format!("{format:?}").to_lowercase();
+            format!(
+                "texture_storage_{}<{format}>",
+                describe_view_dimension(*view_dimension)
+            )
+        }
+        wgpu::BindingType::AccelerationStructure => "acceleration_structure".to_owned(),
+    }
+}
+
+fn describe_view_dimension(view_dimension: wgpu::TextureViewDimension) -> &'static str {
+    match view_dimension {
+        wgpu::TextureViewDimension::D1 => "1d",
+        wgpu::TextureViewDimension::D2 => "2d",
+        wgpu::TextureViewDimension::D2Array => "2d_array",
+        wgpu::TextureViewDimension::Cube => "cube",
+        wgpu::TextureViewDimension::CubeArray => "cube_array",
+        wgpu::TextureViewDimension::D3 => "3d",
+    }
+}
+
+fn describe_slots(slots: &[BindGroupSlot]) -> String {
+    slots
+        .iter()
+        .map(|slot| format!("@group({}) {}", slot.group, slot.name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BindingError {
+    #[error("{binding} is in a bind group that doesn't exist. the bind groups are: {available}")]
+    UnknownGroup { binding: String, available: String },
+
+    #[error("{binding} isn't in @group({group}) ({group_name}). its bindings are: {available}")]
+    UnknownBinding {
+        binding: String,
+        group: u32,
+        group_name: &'static str,
+        available: String,
+    },
+
+    #[error("{binding} has the wrong type. it must be declared as {expected}")]
+    TypeMismatch { binding: String, expected: String },
+
+    #[error("{binding} can't be used in the {stage} stage")]
+    NotVisible {
+        binding: String,
+        stage: &'static str,
+    },
+}
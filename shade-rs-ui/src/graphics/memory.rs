@@ -0,0 +1,72 @@
+/// Default for [`WindowHandle::set_memory_budget`](super::WindowHandle::set_memory_budget).
+pub const DEFAULT_MEMORY_BUDGET: u64 = 512 * 1024 * 1024;
+
+/// GPU memory that a window's textures and buffers take, in bytes.
+///
+/// This is what the window allocated, not what the driver actually uses, which
+/// includes padding and the surface's own textures.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// The frame textures, i.e. the previous frame, layer targets and depth
+    /// buffer. These grow with the resolution.
+    pub frame: u64,
+
+    /// Textures bound to the channels.
+    pub channels: u64,
+
+    /// Uniform buffers, the mesh and readback buffers.
+    pub buffers: u64,
+
+    /// See [`WindowHandle::set_memory_budget`](super::WindowHandle::set_memory_budget).
+    pub budget: u64,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> u64 {
+        self.frame + self.channels + self.buffers
+    }
+
+    pub fn is_over_budget(&self) -> bool {
+        self.total() > self.budget
+    }
+}
+
+/// Size of a texture's data, including all mip levels and samples.
+pub fn texture_bytes(texture: &wgpu::Texture) -> u64 {
+    let format = texture.format();
+    let (block_width, block_height) = format.block_dimensions();
+    let block_size = format
+        .block_copy_size(None)
+        .or_else(|| format.block_copy_size(Some(wgpu::TextureAspect::DepthOnly)))
+        .unwrap_or(4) as u64;
+    let size = texture.size();
+
+    (0..texture.mip_level_count())
+        .map(|level| {
+            let size = size.mip_level_size(level, texture.dimension());
+            let blocks_x = size.width.div_ceil(block_width) as u64;
+            let blocks_y = size.height.div_ceil(block_height) as u64;
+            blocks_x * blocks_y * size.depth_or_array_layers as u64 * block_size
+        })
+        .sum::<u64>()
+        * texture.sample_count() as u64
+}
+
+/// Formats a size in bytes for humans, e.g. `12.3 MiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    }
+    else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
@@ -0,0 +1,437 @@
+use std::{
+    cell::Cell,
+    collections::HashMap,
+};
+
+use bytemuck::{
+    Pod,
+    Zeroable,
+};
+
+use crate::graphics::backend::Backend;
+
+/// Vertex attributes of a mesh, as they're passed to the vertex entry point.
+///
+/// If the vertex entry point takes any `@location` inputs, the shader is drawn with the
+/// window's mesh instead of a fullscreen triangle:
+///
+/// ```wgsl
+/// struct Vertex {
+///     @location(0) position: vec3f,
+///     @location(1) normal: vec3f,
+///     @location(2) uv: vec2f,
+/// }
+///
+/// @vertex
+/// fn vs_main(vertex: Vertex) -> VertexOutput {
+///     // ...
+/// }
+/// ```
+///
+/// Meshes are drawn with depth testing and back-face culling, with a
+/// [`DepthFormat::Depth32Float`](shade_rs_compile::depth::DepthFormat) depth
+/// buffer unless the shader asks for another format. Nothing is drawn while no
+/// mesh is loaded.
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+#[repr(C)]
+pub struct MeshVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+}
+
+impl MeshVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x2];
+
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MeshError {
+    #[error("line {line}: {message}")]
+    Obj { line: usize, message: String },
+
+    #[error("invalid glTF")]
+    Gltf(#[from] gltf::Error),
+
+    #[error("glTF buffers must be embedded. Try exporting as .glb")]
+    ExternalBuffer,
+
+    #[error("the mesh has no triangles")]
+    Empty,
+}
+
+/// Triangles with indexed vertices.
+#[derive(Clone, Default)]
+pub struct MeshData {
+    pub vertices: Vec<MeshVertex>,
+    pub indices: Vec<u32>,
+}
+
+impl std::fmt::Debug for MeshData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MeshData")
+            .field("vertices", &self.vertices.len())
+            .field("indices", &self.indices.len())
+            .finish()
+    }
+}
+
+impl MeshData {
+    /// Parses a Wavefront OBJ file. Only geometry is read, materials are
+    /// ignored. Polygons are triangulated as fans.
+    ///
+    /// Vertices without a normal get the average of the normals of the faces
+    /// they're part of.
+    pub fn from_obj(source: &str) -> Result<Self, MeshError> {
+        let mut positions = vec![];
+        let mut normals = vec![];
+        let mut uvs = vec![];
+        let mut mesh = MeshData::default();
+        let mut missing_normals = vec![];
+        let mut vertex_indices = HashMap::new();
+
+        for (line_index, line) in source.lines().enumerate() {
+            let error = |message: &str| {
+                MeshError::Obj {
+                    line: line_index + 1,
+                    message: message.to_owned(),
+                }
+            };
+            let line = line.split_once('#').map_or(line, |(line, _)| line);
+            let mut words = line.split_whitespace();
+            let Some(keyword) = words.next()
+            else {
+                continue;
+            };
+
+            match keyword {
+                "v" | "vn" | "vt" => {
+                    let values = words
+                        .map(|word| word.parse::<f32>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|_| error("invalid number"))?;
+                    match keyword {
+                        "v" if values.len() >= 3 => {
+                            positions.push([values[0], values[1], values[2]])
+                        }
+                        "vn" if values.len() >= 3 => {
+                            normals.push([values[0], values[1], values[2]])
+                        }
+                        "vt" if !values.is_empty() => {
+                            uvs.push([values[0], values.get(1).copied().unwrap_or_default()])
+                        }
+                        _ => return Err(error("too few components")),
+                    }
+                }
+                "f" => {
+                    let mut face = vec![];
+                    for word in words {
+                        let mut parts = word.split('/');
+                        let mut index = |count: usize| -> Result<Option<usize>, MeshError> {
+                            let Some(part) = parts.next().filter(|part| !part.is_empty())
+                            else {
+                                return Ok(None);
+                            };
+                            let index =
+                                part.parse::<isize>().map_err(|_| error("invalid index"))?;
+                            // indices are 1-based, negative ones are relative to the end.
+                            let index = if index < 0 {
+                                count.checked_add_signed(index)
+                            }
+                            else {
+                                usize::try_from(index - 1).ok()
+                            };
+                            index
+                                .filter(|index| *index < count)
+                                .map(Some)
+                                .ok_or_else(|| error("index out of range"))
+                        };
+                        let position = index(positions.len())?
+                            .ok_or_else(|| error("face vertex without position"))?;
+                        let uv = index(uvs.len())?;
+                        let normal = index(normals.len())?;
+
+                        let vertex_index = *vertex_indices
+                            .entry((position, uv, normal))
+                            .or_insert_with(|| {
+                                let vertex_index = mesh.vertices.len() as u32;
+                                mesh.vertices.push(MeshVertex {
+                                    position: positions[position],
+                                    normal: normal
+                                        .map(|normal| normals[normal])
+                                        .unwrap_or_default(),
+                                    uv: uv.map(|uv| uvs[uv]).unwrap_or_default(),
+                                });
+                                missing_normals.push(normal.is_none());
+                                vertex_index
+                            });
+                        face.push(vertex_index);
+                    }
+
+                    if face.len() < 3 {
+                        return Err(error("face with less than 3 vertices"));
+                    }
+                    for i in 1..face.len() - 1 {
+                        mesh.indices.extend([face[0], face[i], face[i + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        compute_normals(&mut mesh.vertices, &mesh.indices, &missing_normals);
+        mesh.check_not_empty()
+    }
+
+    /// Loads the meshes of a binary glTF file (`.glb`). Buffers must be
+    /// embedded in the file.
+    ///
+    /// All meshes in the default scene are combined, with their nodes'
+    /// transforms applied. Only triangle lists are supported; other primitives
+    /// are skipped.
+    pub fn from_glb(data: &[u8]) -> Result<Self, MeshError> {
+        let gltf = gltf::Gltf::from_slice(data)?;
+        let mut mesh = MeshData::default();
+
+        let scene = gltf.default_scene().or_else(|| gltf.scenes().next());
+        let mut stack = scene
+            .into_iter()
+            .flat_map(|scene| scene.nodes())
+            .map(|node| (node, IDENTITY))
+            .collect::<Vec<_>>();
+
+        while let Some((node, parent_transform)) = stack.pop() {
+            let transform = mul_matrix(&parent_transform, &node.transform().matrix());
+            stack.extend(node.children().map(|child| (child, transform)));
+
+            let Some(node_mesh) = node.mesh()
+            else {
+                continue;
+            };
+
+            for primitive in node_mesh.primitives() {
+                if primitive.mode() != gltf::mesh::Mode::Triangles {
+                    continue;
+                }
+
+                let external_buffer = Cell::new(false);
+                let reader = primitive.reader(|buffer| {
+                    match buffer.source() {
+                        gltf::buffer::Source::Bin => gltf.blob.as_deref(),
+                        gltf::buffer::Source::Uri(_) => {
+                            external_buffer.set(true);
+                            None
+                        }
+                    }
+                });
+
+                let Some(positions) = reader.read_positions()
+                else {
+                    if external_buffer.get() {
+                        return Err(MeshError::ExternalBuffer);
+                    }
+                    continue;
+                };
+                let mut vertices = positions
+                    .map(|position| {
+                        MeshVertex {
+                            position: transform_point(&transform, position),
+                            ..Default::default()
+                        }
+                    })
+                    .collect::<Vec<_>>();
+
+                let indices = if let Some(indices) = reader.read_indices() {
+                    indices.into_u32().collect::<Vec<_>>()
+                }
+                else {
+                    (0..vertices.len() as u32).collect()
+                };
+
+                if let Some(normals) = reader.read_normals() {
+                    for (vertex, normal) in vertices.iter_mut().zip(normals) {
+                        vertex.normal = normalize(transform_vector(&transform, normal));
+                    }
+                }
+                else {
+                    compute_normals(&mut vertices, &indices, &vec![true; vertices.len()]);
+                }
+
+                if let Some(uvs) = reader.read_tex_coords(0) {
+                    for (vertex, uv) in vertices.iter_mut().zip(uvs.into_f32()) {
+                        vertex.uv = uv;
+                    }
+                }
+
+                let first_vertex = mesh.vertices.len() as u32;
+                mesh.indices
+                    .extend(indices.iter().map(|index| first_vertex + index));
+                mesh.vertices.extend(vertices);
+            }
+        }
+
+        mesh.check_not_empty()
+    }
+
+    fn check_not_empty(self) -> Result<Self, MeshError> {
+        if self.indices.is_empty() {
+            Err(MeshError::Empty)
+        }
+        else {
+            Ok(self)
+        }
+    }
+}
+
+/// Sets the normals of the vertices for which `missing` is `true` to the
+/// average of the normals of their faces.
+fn compute_normals(vertices: &mut [MeshVertex], indices: &[u32], missing: &[bool]) {
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| vertices[triangle[i] as usize].position);
+        let normal = cross(sub(b, a), sub(c, a));
+        for &index in triangle {
+            if missing[index as usize] {
+                let vertex = &mut vertices[index as usize];
+                vertex.normal = add(vertex.normal, normal);
+            }
+        }
+    }
+    for (vertex, missing) in vertices.iter_mut().zip(missing) {
+        if *missing {
+            vertex.normal = normalize(vertex.normal);
+        }
+    }
+}
+
+type Matrix = [[f32; 4]; 4];
+
+const IDENTITY: Matrix = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+/// Multiplies column-major matrices.
+fn mul_matrix(a: &Matrix, b: &Matrix) -> Matrix {
+    std::array::from_fn(|column| {
+        std::array::from_fn(|row| (0..4).map(|k| a[k][row] * b[column][k]).sum())
+    })
+}
+
+fn transform_point(matrix: &Matrix, [x, y, z]: [f32; 3]) -> [f32; 3] {
+    std::array::from_fn(|row| {
+        matrix[0][row] * x + matrix[1][row] * y + matrix[2][row] * z + matrix[3][row]
+    })
+}
+
+/// Transforms a direction. This ignores non-uniform scaling, which would need
+/// the inverse transpose.
+fn transform_vector(matrix: &Matrix, [x, y, z]: [f32; 3]) -> [f32; 3] {
+    std::array::from_fn(|row| matrix[0][row] * x + matrix[1][row] * y + matrix[2][row] * z)
+}
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    std::array::from_fn(|i| a[i] + b[i])
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    std::array::from_fn(|i| a[i] - b[i])
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if length > 0.0 {
+        v.map(|x| x / length)
+    }
+    else {
+        v
+    }
+}
+
+/// A mesh uploaded to the GPU.
+#[derive(Debug)]
+pub struct Mesh {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+}
+
+impl Mesh {
+    pub fn new(backend: &Backend, data: &MeshData) -> Self {
+        let vertex_buffer = backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("mesh vertex buffer"),
+            size: std::mem::size_of_val(data.vertices.as_slice()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        backend
+            .queue
+            .write_buffer(&vertex_buffer, 0, bytemuck::cast_slice(&data.vertices));
+
+        let index_buffer = backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("mesh index buffer"),
+            size: std::mem::size_of_val(data.indices.as_slice()) as u64,
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        backend
+            .queue
+            .write_buffer(&index_buffer, 0, bytemuck::cast_slice(&data.indices));
+
+        Self {
+            vertex_buffer,
+            index_buffer,
+            num_indices: data.indices.len() as u32,
+        }
+    }
+
+    /// Size of the vertex and index buffers, in bytes.
+    pub fn memory_bytes(&self) -> u64 {
+        self.vertex_buffer.size() + self.index_buffer.size()
+    }
+
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass<'_>) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+    }
+}
+
+/// Whether the shader's vertex entry point takes vertex attributes, i.e. any
+/// `@location` inputs, either directly or as members of a struct.
+pub fn uses_vertex_inputs(module: &naga::Module, entry_point: &str) -> bool {
+    let is_location =
+        |binding: &Option<naga::Binding>| matches!(binding, Some(naga::Binding::Location { .. }));
+
+    module
+        .entry_points
+        .iter()
+        .find(|vertex| vertex.stage == naga::ShaderStage::Vertex && vertex.name == entry_point)
+        .is_some_and(|entry_point| {
+            entry_point.function.arguments.iter().any(|argument| {
+                is_location(&argument.binding)
+                    || matches!(
+                        &module.types[argument.ty].inner,
+                        naga::TypeInner::Struct { members, .. }
+                            if members.iter().any(|member| is_location(&member.binding))
+                    )
+            })
+        })
+}
@@ -1,19 +1,54 @@
+pub mod adapter;
 pub mod backend;
+pub mod blit;
+pub mod camera;
+pub mod channel;
+pub mod compositor;
+#[cfg(feature = "editor")]
+pub mod compressed;
+pub mod cubemap;
+pub mod depth;
+pub mod diff;
+pub mod feedback;
+pub mod gpu_timer;
+#[cfg(feature = "editor")]
+pub mod hdr;
+#[cfg(feature = "editor")]
+pub mod inspect;
+pub mod keyboard;
+pub mod layout;
+pub mod memory;
+#[cfg(feature = "mesh")]
+pub mod mesh;
+#[cfg(feature = "editor")]
+pub mod pixel;
+pub mod playback;
+pub mod probe;
+pub mod requirements;
+pub mod resolution;
+pub mod sequence;
+pub mod sound;
 
 use std::{
     borrow::Cow,
-    collections::HashMap,
+    cell::Cell,
+    collections::{
+        HashMap,
+        VecDeque,
+    },
     fmt::{
         Debug,
         Display,
     },
     num::NonZeroU32,
+    rc::Rc,
     sync::{
         atomic::{
             AtomicU32,
             Ordering,
         },
         Arc,
+        Mutex,
     },
     time::Duration,
 };
@@ -22,20 +57,127 @@ use bytemuck::{
     Pod,
     Zeroable,
 };
+use leptos::{
+    request_animation_frame,
+    spawn_local,
+};
 use serde::{
     Deserialize,
     Serialize,
 };
+use shade_rs_compile::{
+    compile_shader_with_fragment,
+    defines::Define,
+    depth::DepthFormat,
+    lint::{
+        lint_webgl_compat,
+        Lint,
+    },
+    probe::{
+        compile_probe_shader,
+        ProbeShader,
+        PROBE_BINDING,
+    },
+    reflect::{
+        declares_camera,
+        entry_point_names,
+        reads_orientation,
+        reads_scroll,
+        reads_time,
+        EntryPoints,
+        ParamsLayout,
+        CAMERA_BINDING,
+        PARAMS_BINDING,
+    },
+    requirements::Requirement,
+    sound::compile_sound_shader,
+    CompileOptions,
+    CompiledShader,
+};
 use tokio::sync::{
     mpsc,
     oneshot,
 };
+use wasm_bindgen::JsCast;
 use web_sys::HtmlCanvasElement;
 
 use crate::{
-    graphics::backend::{
-        Backend,
-        BackendType,
+    audio::AudioOutput,
+    graphics::{
+        adapter::AdapterInfo,
+        backend::{
+            Backend,
+            BackendId,
+            BackendType,
+            UncapturedError,
+        },
+        blit::Blitter,
+        camera::{
+            CameraUniform,
+            OrbitCamera,
+        },
+        channel::{
+            ChannelImage,
+            ChannelSampler,
+            Channels,
+            CHANNEL_BIND_GROUP,
+            NUM_CHANNELS,
+            UPLOAD_BYTES_PER_FRAME,
+        },
+        compositor::{
+            Compositor,
+            LayerBlend,
+            LayerMask,
+            LayerTarget,
+            LayerTransform,
+        },
+        depth::{
+            DepthResolver,
+            DepthTarget,
+        },
+        diff::DiffPass,
+        feedback::{
+            reads_previous_frame,
+            Feedback,
+            FEEDBACK_BIND_GROUP,
+        },
+        gpu_timer::{
+            GpuTimer,
+            GpuTimes,
+        },
+        keyboard::{
+            Keyboard,
+            KEYBOARD_BIND_GROUP,
+        },
+        layout::{
+            bind_group_count,
+            check_bindings,
+            BindGroupSlot,
+            BindingError,
+        },
+        memory::{
+            format_bytes,
+            MemoryUsage,
+            DEFAULT_MEMORY_BUDGET,
+        },
+        playback::Playback,
+        probe::{
+            ProbePass,
+            ProbeValue,
+        },
+        requirements::check_requirements,
+        resolution::ResolutionScaler,
+        sequence::{
+            SequenceExport,
+            SequenceFrame,
+            SequenceOptions,
+            FRAMES_IN_FLIGHT,
+        },
+        sound::SoundPass,
+    },
+    library::{
+        resolve_libraries,
+        FetchError,
     },
     utils::{
         futures::spawn_local_and_handle_error,
@@ -58,6 +200,49 @@ pub enum Error {
 
     #[error("failed to request device")]
     RequestDevice(#[from] wgpu::RequestDeviceError),
+
+    #[error("canvas not found: {0:?}")]
+    CanvasNotFound(WindowId),
+
+    #[error("failed to capture canvas stream: {message}")]
+    CaptureStream { message: String },
+
+    #[error("failed to capture frame: {message}")]
+    CaptureFrame { message: String },
+
+    #[error("failed to read pixel: {message}")]
+    ReadPixel { message: String },
+
+    #[error("nothing is being rendered")]
+    NotRendering,
+
+    #[error("invalid channel: {index}")]
+    InvalidChannel { index: usize },
+
+    #[error("image too large: {width}x{height}, maximum is {max}x{max}")]
+    ImageTooLarge { width: u32, height: u32, max: u32 },
+
+    #[error("the device doesn't support {format:?} textures")]
+    UnsupportedTextureFormat { format: wgpu::TextureFormat },
+
+    #[error("converting channels to cubemaps needs compute shaders with storage textures")]
+    CubemapUnsupported,
+
+    #[error("invalid sequence: {message}")]
+    InvalidSequence { message: String },
+
+    #[error(
+        "over the memory budget: this would use {}, but the budget is {}",
+        format_bytes(*required),
+        format_bytes(*budget)
+    )]
+    OverMemoryBudget { required: u64, budget: u64 },
+
+    #[error("failed to get the surface texture: {0}")]
+    Surface(#[from] wgpu::SurfaceError),
+
+    #[error("GPU error: {message}")]
+    Device { message: String },
 }
 
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
@@ -74,64 +259,215 @@ pub enum SelectBackendType {
     Select(BackendType),
 }
 
+/// Number of frames in a row that can fail to get a surface texture before
+/// it's reported with [`WindowHandle::set_on_surface_error`].
+const MAX_SURFACE_FAILURES: u32 = 3;
+
+/// Capacity of the regular command channel.
+const COMMAND_CHANNEL_CAPACITY: usize = 64;
+
 #[derive(Clone, Debug)]
 pub struct Graphics {
-    tx_command: mpsc::UnboundedSender<Command>,
+    tx_command: mpsc::Sender<Command>,
+    command_backlog: CommandBacklog,
+    tx_priority: mpsc::UnboundedSender<Command>,
+    tx_mouse: mpsc::Sender<()>,
+    mouse_positions: MousePositions,
 }
 
 impl Graphics {
     pub fn new(config: Config) -> Self {
         tracing::debug!(?config, "initializing graphics");
 
-        let (tx_command, rx_command) = mpsc::unbounded_channel();
+        let (tx_command, rx_command) = mpsc::channel(COMMAND_CHANNEL_CAPACITY);
+        let (tx_priority, rx_priority) = mpsc::unbounded_channel();
+        let (tx_mouse, rx_mouse) = mpsc::channel(1);
+        let mouse_positions = MousePositions::default();
 
+        let channels = ReactorChannels {
+            rx_command,
+            rx_priority,
+            rx_mouse,
+            mouse_positions: mouse_positions.clone(),
+        };
         spawn_local_and_handle_error(async move {
-            let reactor = Reactor::new(config, rx_command).await?;
+            let reactor = Reactor::new(config, channels).await?;
             reactor.run().await
         });
 
-        Self { tx_command }
+        Self {
+            tx_command,
+            command_backlog: CommandBacklog::default(),
+            tx_priority,
+            tx_mouse,
+            mouse_positions,
+        }
     }
 
     fn send_command(&self, command: Command) {
-        self.tx_command
-            .send(command)
-            .expect("graphics reactor died");
+        if command.is_priority() {
+            self.tx_priority
+                .send(command)
+                .expect("graphics reactor died");
+            return;
+        }
+
+        let mut backlog = self.command_backlog.commands.lock().unwrap();
+        if !backlog.is_empty() {
+            // commands must not overtake the ones that are still waiting.
+            backlog.push_back(command);
+            return;
+        }
+
+        match self.tx_command.try_send(command) {
+            Ok(()) => {}
+            Err(mpsc::error::TrySendError::Full(command)) => {
+                // the reactor is lagging behind. we can't block here, so the command
+                // waits in the backlog, which is drained in the background.
+                tracing::warn!("graphics command channel full");
+                backlog.push_back(command);
+                spawn_local(self.command_backlog.clone().drain(self.tx_command.clone()));
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => panic!("graphics reactor died"),
+        }
+    }
+
+    fn set_mouse_position(&self, window_id: WindowId, position: Option<[f32; 2]>) {
+        self.mouse_positions.set(window_id, position);
+        // if the channel is full, the reactor has already been woken up and will see
+        // the new position.
+        let _ = self.tx_mouse.try_send(());
+    }
+
+    /// Returns what the adapter that renders the window can do. With WebGPU,
+    /// all windows share one adapter.
+    ///
+    /// Returns `None` if the window doesn't exist (anymore).
+    pub async fn adapter_info(&self, window_id: WindowId) -> Option<AdapterInfo> {
+        let (tx_result, rx_result) = oneshot::channel();
+        self.send_command(Command::AdapterInfo {
+            window_id,
+            tx_result,
+        });
+        rx_result.await.ok().flatten()
     }
 
     pub fn register_window(
         &self,
         window_id: WindowId,
         surface_size: SurfaceSize,
+        playback_state: PlaybackState,
         on_frame: Box<dyn FnMut(FrameInfo) + 'static>,
     ) -> WindowHandle {
+        let uses_camera = Rc::new(Cell::new(false));
+        let uses_scroll = Rc::new(Cell::new(false));
+        let uses_orientation = Rc::new(Cell::new(false));
+
         self.send_command(Command::RegisterWindow {
             window_id,
             surface_size,
+            playback_state,
             on_frame,
+            uses_camera: uses_camera.clone(),
+            uses_scroll: uses_scroll.clone(),
+            uses_orientation: uses_orientation.clone(),
         });
 
         WindowHandle {
             graphics: self.clone(),
             window_id,
+            pending_mouse_position: Default::default(),
+            uses_camera,
+            uses_scroll,
+            uses_orientation,
+        }
+    }
+}
+
+/// Commands that didn't fit into the command channel, in the order they were
+/// sent.
+///
+/// While it's not empty, exactly one task drains it, and all new commands are
+/// appended to it.
+#[derive(Clone, Default)]
+struct CommandBacklog {
+    commands: Arc<Mutex<VecDeque<Command>>>,
+}
+
+impl Debug for CommandBacklog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CommandBacklog")
+            .field("len", &self.commands.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl CommandBacklog {
+    async fn drain(self, tx_command: mpsc::Sender<Command>) {
+        loop {
+            let permit = tx_command.reserve().await.expect("graphics reactor died");
+            let mut commands = self.commands.lock().unwrap();
+            let command = commands
+                .pop_front()
+                .expect("command backlog drained by two tasks");
+            permit.send(command);
+            if commands.is_empty() {
+                break;
+            }
         }
     }
 }
 
+/// Mouse positions that haven't been picked up by the reactor yet.
+///
+/// A newer position replaces the pending one for the same window, so a flood of
+/// mouse moves never queues up behind other commands.
+#[derive(Clone, Debug, Default)]
+struct MousePositions {
+    pending: Arc<Mutex<HashMap<WindowId, Option<[f32; 2]>>>>,
+}
+
+impl MousePositions {
+    fn set(&self, window_id: WindowId, position: Option<[f32; 2]>) {
+        self.pending.lock().unwrap().insert(window_id, position);
+    }
+
+    fn take(&self) -> HashMap<WindowId, Option<[f32; 2]>> {
+        std::mem::take(&mut *self.pending.lock().unwrap())
+    }
+}
+
+struct ReactorChannels {
+    rx_command: mpsc::Receiver<Command>,
+    rx_priority: mpsc::UnboundedReceiver<Command>,
+    rx_mouse: mpsc::Receiver<()>,
+    mouse_positions: MousePositions,
+}
+
 struct Reactor {
     config: Config,
     backend_type: BackendType,
     shared_backend: Option<Backend>,
-    rx_command: mpsc::UnboundedReceiver<Command>,
+    channels: ReactorChannels,
+
+    /// Passed to the backends, which send their uncaptured errors here.
+    tx_error: mpsc::UnboundedSender<UncapturedError>,
+    rx_error: mpsc::UnboundedReceiver<UncapturedError>,
+
     windows: HashMap<WindowId, Window>,
-    render_interval: Interval,
+    /// Only set while there is something to render, so that idle pages don't
+    /// wake up 60 times per second.
+    render_interval: Option<Interval>,
+
+    /// Period of the render interval. This is the shortest period of the
+    /// windows' [`TargetFps`].
+    render_period: Duration,
 }
 
 impl Reactor {
-    async fn new(
-        config: Config,
-        rx_command: mpsc::UnboundedReceiver<Command>,
-    ) -> Result<Self, Error> {
+    async fn new(config: Config, channels: ReactorChannels) -> Result<Self, Error> {
+        let (tx_error, rx_error) = mpsc::unbounded_channel();
+
         let (backend_type, shared_backend) = match config.backend_type {
             SelectBackendType::AutoDetect => {
                 tracing::debug!("trying WEBGPU");
@@ -140,7 +476,9 @@ impl Reactor {
                     ..Default::default()
                 });
 
-                if let Ok(shared_backend) = Backend::new(Arc::new(instance), &config, None).await {
+                if let Ok(shared_backend) =
+                    Backend::new(Arc::new(instance), &config, None, tx_error.clone()).await
+                {
                     (BackendType::WebGpu, Some(shared_backend))
                 }
                 else {
@@ -154,7 +492,8 @@ impl Reactor {
                     backends: backend_type.as_wgpu(),
                     ..Default::default()
                 });
-                let shared_backend = Backend::new(Arc::new(instance), &config, None).await?;
+                let shared_backend =
+                    Backend::new(Arc::new(instance), &config, None, tx_error.clone()).await?;
                 (backend_type, Some(shared_backend))
             }
         };
@@ -163,44 +502,170 @@ impl Reactor {
             config,
             backend_type,
             shared_backend,
-            rx_command,
+            channels,
+            tx_error,
+            rx_error,
             windows: HashMap::new(),
-            render_interval: interval(Duration::from_millis(1000 / 60)),
+            render_interval: None,
+            render_period: Duration::ZERO,
         })
     }
 
     async fn run(mut self) -> Result<(), Error> {
         loop {
+            self.update_render_interval();
+
             tokio::select! {
-                command_opt = self.rx_command.recv() => {
+                // resize, visibility, etc. are handled before anything else
+                biased;
+                command_opt = self.channels.rx_priority.recv() => {
                     let Some(command) = command_opt else { break; };
+                    let window_id = command.window_id();
                     self.handle_command(command).await?;
+                    self.request_frame(window_id);
                 }
-                _ = self.render_interval.tick() => {
-                    for window in self.windows.values_mut() {
-                        if !window.paused {
-                            window.update();
-                        }
-                        if window.visible {
-                            window.render();
+                command_opt = self.channels.rx_command.recv() => {
+                    let Some(command) = command_opt else { break; };
+                    let window_id = command.window_id();
+                    self.handle_command(command).await?;
+                    self.request_frame(window_id);
+                }
+                Some(()) = self.channels.rx_mouse.recv() => {
+                    for (window_id, position) in self.channels.mouse_positions.take() {
+                        if let Some(window) = self.windows.get_mut(&window_id) {
+                            window.set_mouse_position(position);
                         }
+                        self.request_frame(window_id);
                     }
                 }
+                Some(error) = self.rx_error.recv() => {
+                    self.handle_uncaptured_error(error);
+                }
+                _ = tick(&mut self.render_interval) => {
+                    self.render_frame();
+                }
             }
         }
 
         Ok(())
     }
 
+    /// Reports an uncaptured error to the windows that use the backend it came
+    /// from.
+    fn handle_uncaptured_error(&mut self, error: UncapturedError) {
+        for window in self.windows.values_mut() {
+            if window.backend.id == error.backend_id {
+                window.report_device_error(&error.message);
+            }
+        }
+    }
+
+    /// Makes a window that only renders on demand render the next frame,
+    /// because something it might depend on changed.
+    fn request_frame(&mut self, window_id: WindowId) {
+        if let Some(window) = self.windows.get_mut(&window_id) {
+            window.frame_requested = true;
+        }
+    }
+
+    /// Renders all visible windows.
+    ///
+    /// Windows that share a backend are recorded into a single command encoder
+    /// and submitted together.
+    fn render_frame(&mut self) {
+        let mut batches: HashMap<BackendId, BatchedFrame> = HashMap::new();
+        let mut rendered = vec![];
+        let now = Instant::now();
+
+        for (window_id, window) in &mut self.windows {
+            // exporting a sequence replaces the window's regular frames.
+            if window.sequence.is_some() {
+                window.render_sequence();
+                continue;
+            }
+            if !window.wants_frame() || !window.is_frame_due(now, self.render_period) {
+                continue;
+            }
+            if !window.playback.paused {
+                window.update();
+            }
+            if window.visible {
+                let batch = batches
+                    .entry(window.backend.id)
+                    .or_insert_with(|| BatchedFrame::new(&window.backend));
+                if let Some(target_texture) = window.encode_frame(&mut batch.encoder) {
+                    batch.target_textures.push(target_texture);
+                    rendered.push(*window_id);
+                }
+            }
+        }
+
+        for batch in batches.into_values() {
+            batch.submit();
+        }
+
+        for window_id in rendered {
+            if let Some(window) = self.windows.get_mut(&window_id) {
+                window.frame_presented();
+            }
+        }
+    }
+
+    /// Starts or stops the render interval, depending on whether any window
+    /// needs rendering, and adjusts it to the highest target frame rate.
+    fn update_render_interval(&mut self) {
+        let render_period = self
+            .windows
+            .values()
+            .filter_map(|window| {
+                if window.sequence.is_some() {
+                    // sequences are exported as fast as possible.
+                    Some(TargetFps::Unlimited.period())
+                }
+                else {
+                    (window.visible && window.has_pipeline() && window.wants_frame())
+                        .then(|| window.target_fps.period())
+                }
+            })
+            .min();
+
+        match render_period {
+            Some(render_period)
+                if self.render_interval.is_none() || render_period != self.render_period =>
+            {
+                tracing::debug!(?render_period, "starting render interval");
+                self.render_interval = Some(interval(render_period));
+                self.render_period = render_period;
+            }
+            None if self.render_interval.is_some() => {
+                tracing::debug!("no windows to render. stopping render interval");
+                self.render_interval = None;
+            }
+            _ => {}
+        }
+    }
+
     async fn handle_command(&mut self, command: Command) -> Result<(), Error> {
         match command {
             Command::RegisterWindow {
                 window_id: window_handle,
                 surface_size,
+                playback_state,
                 on_frame,
+                uses_camera,
+                uses_scroll,
+                uses_orientation,
             } => {
-                self.create_window(window_handle, surface_size, on_frame)
-                    .await?;
+                self.create_window(
+                    window_handle,
+                    surface_size,
+                    playback_state,
+                    on_frame,
+                    uses_camera,
+                    uses_scroll,
+                    uses_orientation,
+                )
+                .await?;
             }
             Command::DestroyWindow { window_id } => {
                 self.windows.remove(&window_id);
@@ -215,16 +680,94 @@ impl Reactor {
             }
             Command::Run {
                 window_id,
+                layer,
                 code,
+                options,
+                fragment_entry_point,
                 tx_result,
             } => {
-                match compile_shader(&code) {
-                    Ok(shader) => {
-                        if let Some(window) = self.windows.get_mut(&window_id) {
-                            window.create_pipeline(shader);
-                            window.paused = false;
+                let mut timings = CompileTimings::default();
+                let result = self
+                    .compile(
+                        window_id,
+                        &code,
+                        &options,
+                        fragment_entry_point.as_deref(),
+                        &mut timings,
+                    )
+                    .and_then(|shader| {
+                        // only the bottom layer's sound is played and its probes read.
+                        let (sound, probe) = if layer == 0 {
+                            (self.compile_sound(&shader)?, self.compile_probe(&shader)?)
+                        }
+                        else {
+                            (None, None)
+                        };
+                        Ok((shader, sound, probe))
+                    });
+                match result {
+                    Ok((shader, sound, probe)) => {
+                        let warnings = if self.backend_type == BackendType::WebGpu {
+                            lint_webgl_compat(&shader.module, &shader.source)
+                        }
+                        else {
+                            vec![]
+                        };
+                        let fragment_entry_points =
+                            entry_point_names(&shader.module, naga::ShaderStage::Fragment);
+                        let fragment_entry_point = shader.entry_points.fragment.clone();
+                        let defines = shader.defines.clone();
+                        let audio_output = if sound.is_some() {
+                            self.audio_output(window_id).await
+                        }
+                        else {
+                            None
+                        };
+                        let result = if let Some(window) = self.windows.get_mut(&window_id) {
+                            window
+                                .create_pipeline(
+                                    layer,
+                                    shader.module,
+                                    shader.entry_points,
+                                    shader.params.as_ref(),
+                                    shader.depth,
+                                    &mut timings,
+                                )
+                                .await
+                                .map(|()| {
+                                    window.playback.ran();
+                                    if layer == 0 {
+                                        window.create_sound_pass(sound.zip(audio_output));
+                                        window.create_probe_pass(probe);
+                                    }
+                                    window.playback.paused
+                                })
+                        }
+                        else {
+                            Ok(false)
+                        };
+                        match result {
+                            Ok(paused) => {
+                                tracing::debug!(?timings, "compiled shader");
+                                let _ = tx_result.send(Ok(RunInfo {
+                                    warnings,
+                                    params: shader.params,
+                                    fragment_entry_point,
+                                    fragment_entry_points,
+                                    defines,
+                                    paused,
+                                    timings,
+                                }));
+                            }
+                            Err(error) => {
+                                let error = CompileError::Pipeline {
+                                    message: error.to_string(),
+                                    backend_type: self.backend_type,
+                                };
+                                tracing::error!(?error);
+                                let _ = tx_result.send(Err(error));
+                            }
                         }
-                        let _ = tx_result.send(Ok(()));
                     }
                     Err(error) => {
                         tracing::error!(?error);
@@ -232,12 +775,206 @@ impl Reactor {
                     }
                 }
             }
-            Command::SetMousePosition {
+            #[cfg(feature = "editor")]
+            Command::Inspect {
+                window_id,
+                code,
+                options,
+                tx_result,
+            } => {
+                let result = self
+                    .compile(
+                        window_id,
+                        &code,
+                        &options,
+                        None,
+                        &mut CompileTimings::default(),
+                    )
+                    .map(|shader| inspect::inspect_shader(&shader.module, self.backend_type));
+                let _ = tx_result.send(result);
+            }
+            Command::SetChannel {
+                window_id,
+                index,
+                image,
+                tx_result,
+            } => {
+                let result = if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.set_channel(index, image)
+                }
+                else {
+                    Ok(())
+                };
+                let _ = tx_result.send(result);
+            }
+            #[cfg(feature = "editor")]
+            Command::SetChannelCompressed {
+                window_id,
+                index,
+                image,
+                tx_result,
+            } => {
+                let result = if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.set_channel_compressed(index, image)
+                }
+                else {
+                    Ok(())
+                };
+                let _ = tx_result.send(result);
+            }
+            #[cfg(feature = "editor")]
+            Command::SetChannelHdr {
+                window_id,
+                index,
+                image,
+                exposure,
+                tx_result,
+            } => {
+                let result = if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.set_channel_hdr(index, &image, exposure)
+                }
+                else {
+                    Ok(())
+                };
+                let _ = tx_result.send(result);
+            }
+            Command::SetChannelVideo {
+                window_id,
+                index,
+                video,
+                tx_result,
+            } => {
+                let result = if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.set_channel_video(index, video)
+                }
+                else {
+                    Ok(())
+                };
+                let _ = tx_result.send(result);
+            }
+            #[cfg(feature = "editor")]
+            Command::SetChannelAudio {
+                window_id,
+                index,
+                analyser,
+                tx_result,
+            } => {
+                let result = if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.set_channel_audio(index, analyser)
+                }
+                else {
+                    Ok(())
+                };
+                let _ = tx_result.send(result);
+            }
+            Command::SetChannelCubemap {
+                window_id,
+                index,
+                is_cubemap,
+                tx_result,
+            } => {
+                let result = if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.set_channel_cubemap(index, is_cubemap)
+                }
+                else {
+                    Ok(())
+                };
+                let _ = tx_result.send(result);
+            }
+            Command::SetMemoryBudget { window_id, budget } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.memory_budget = budget;
+                }
+            }
+            Command::SetChannelSampler {
+                window_id,
+                index,
+                sampler,
+            } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.set_channel_sampler(index, sampler);
+                }
+            }
+            Command::SetChannelDepth {
+                window_id,
+                index,
+                layer,
+                tx_result,
+            } => {
+                let result = if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.set_channel_depth(index, layer)
+                }
+                else {
+                    Ok(())
+                };
+                let _ = tx_result.send(result);
+            }
+            Command::ExportSequence {
+                window_id,
+                options,
+                tx_frame,
+                tx_result,
+            } => {
+                let result = if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.start_sequence(options, tx_frame)
+                }
+                else {
+                    Err(Error::NotRendering)
+                };
+                let _ = tx_result.send(result);
+            }
+            #[cfg(feature = "mesh")]
+            Command::SetMesh { window_id, mesh } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.mesh = mesh.map(|mesh| mesh::Mesh::new(&window.backend, &mesh));
+                }
+            }
+            Command::SetParams { window_id, data } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.set_params(&data);
+                }
+            }
+            Command::SetMouseButton {
                 window_id,
+                pressed,
                 position,
             } => {
                 if let Some(window) = self.windows.get_mut(&window_id) {
-                    window.mouse_position = position;
+                    window.set_mouse_button(pressed, position);
+                }
+            }
+            Command::ZoomCamera { window_id, delta } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.camera.zoom(delta);
+                }
+            }
+            Command::SetScroll { window_id, scroll } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.scroll = scroll;
+                    window.update_input_uniform();
+                }
+            }
+            Command::SetOrientation {
+                window_id,
+                orientation,
+            } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.rotation = orientation.map_or(IDENTITY_ROTATION, rotation);
+                    window.update_input_uniform();
+                }
+            }
+            Command::SetKeyState {
+                window_id,
+                key_code,
+                pressed,
+            } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.keyboard.set(key_code, pressed);
+                }
+            }
+            Command::ReleaseAllKeys { window_id } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.keyboard.release_all();
                 }
             }
             Command::SetVisibility { window_id, visible } => {
@@ -250,60 +987,317 @@ impl Reactor {
                     if !paused {
                         window.previous_frame_time = Instant::now();
                     }
-                    window.paused = paused;
+                    window.playback.paused = paused;
+                    if let Some(sound) = &window.sound {
+                        sound.set_playing(!paused);
+                    }
+                }
+            }
+            Command::SeekTime { window_id, time } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.seek_time(time);
                 }
             }
             Command::Reset { window_id } => {
                 if let Some(window) = self.windows.get_mut(&window_id) {
                     window.previous_frame_time = Instant::now();
-                    window.time = 0.0;
+                    window.playback.time = 0.0;
+                    window.playback.frame = 0;
+                    window.camera = OrbitCamera::default();
+                    if let Some(sound) = &mut window.sound {
+                        sound.seek(0.0);
+                    }
+                    window.feedback.recreate(
+                        &window.backend,
+                        &window.blitter,
+                        &window.render_configuration,
+                    );
                     window.update();
                 }
             }
-        }
-
-        Ok(())
-    }
-
-    async fn create_window(
-        &mut self,
-        window_id: WindowId,
-        surface_size: SurfaceSize,
-        on_frame: Box<dyn FnMut(FrameInfo) + 'static>,
-    ) -> Result<(), Error> {
-        tracing::info!(?window_id, ?surface_size, "creating surface");
-
-        let (surface, backend) = if self.backend_type.uses_shared_backend() {
-            let backend = self
-                .shared_backend
-                .as_ref()
-                .expect("expected a shared backend for WebGPU backend");
-            let surface = backend
-                .instance
-                .create_surface(window_id)
-                .expect("failed to create surface");
-            (surface, backend.clone())
-        }
-        else {
-            tracing::debug!("creating WebGL instance");
-            let instance = Arc::new(wgpu::Instance::new(wgpu::InstanceDescriptor {
-                backends: self.backend_type.as_wgpu(),
-                ..Default::default()
-            }));
-
-            let surface = instance
-                .create_surface(window_id)
-                .expect("failed to create surface");
-
-            let backend = Backend::new(instance, &self.config, Some(&surface))
-                .await
-                .expect("todo: handle error");
-
-            (surface, backend)
-        };
-
-        let surface_capabilities = surface.get_capabilities(&backend.adapter);
-
+            Command::SetLayerBlend {
+                window_id,
+                layer,
+                blend,
+            } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.layer_mut(layer).blend = blend;
+                }
+            }
+            Command::SetRenderOnDemand { window_id, enabled } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.render_on_demand = enabled;
+                }
+            }
+            Command::SetDiff { window_id, enabled } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.set_diff(enabled);
+                }
+            }
+            Command::SetTargetFps {
+                window_id,
+                target_fps,
+            } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.target_fps = target_fps;
+                }
+            }
+            Command::SetOutputEncoding {
+                window_id,
+                encoding,
+            } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.set_output_encoding(encoding);
+                }
+            }
+            Command::SetRenderScale { window_id, scale } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.set_render_scale(scale);
+                }
+            }
+            Command::SetLayerTransform {
+                window_id,
+                layer,
+                transform,
+            } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.layer_mut(layer).transform = transform;
+                }
+            }
+            Command::SetLayerMask {
+                window_id,
+                layer,
+                mask,
+            } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.layer_mut(layer).mask = mask;
+                }
+            }
+            Command::SetLayerEnabled {
+                window_id,
+                layer,
+                enabled,
+            } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.layer_mut(layer).disabled = !enabled;
+                }
+            }
+            Command::MoveLayer {
+                window_id,
+                from,
+                to,
+            } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.move_layer(from, to);
+                }
+            }
+            Command::RemoveLayer { window_id, layer } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.remove_layer(layer);
+                }
+            }
+            Command::SetOnFrameTexture {
+                window_id,
+                on_frame_texture,
+            } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.set_on_frame_texture(on_frame_texture);
+                }
+            }
+            Command::SetOnProbe {
+                window_id,
+                on_probe,
+            } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.on_probe = on_probe;
+                }
+            }
+            Command::SetOnSurfaceError {
+                window_id,
+                on_surface_error,
+            } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.on_surface_error = on_surface_error;
+                }
+            }
+            Command::SetOnDeviceError {
+                window_id,
+                on_device_error,
+            } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.on_device_error = on_device_error;
+                }
+            }
+            Command::AdapterInfo {
+                window_id,
+                tx_result,
+            } => {
+                let adapter_info = self
+                    .windows
+                    .get(&window_id)
+                    .map(|window| AdapterInfo::new(&window.backend, self.backend_type));
+                let _ = tx_result.send(adapter_info);
+            }
+            Command::CaptureFrame {
+                window_id,
+                tx_result,
+            } => {
+                match self.windows.get_mut(&window_id) {
+                    Some(window) if window.visible && window.has_pipeline() => {
+                        window.pending_captures.push(tx_result);
+                    }
+                    _ => {
+                        let _ = tx_result.send(Err(Error::NotRendering));
+                    }
+                }
+            }
+            #[cfg(feature = "editor")]
+            Command::ReadPixel {
+                window_id,
+                position,
+                tx_result,
+            } => {
+                match self.windows.get_mut(&window_id) {
+                    Some(window) if window.visible && window.has_pipeline() => {
+                        // the mouse is in canvas pixels, but the frame might be scaled.
+                        let scale = window.resolution.scale();
+                        window
+                            .pixel_reader
+                            .request(position.map(|x| x * scale), tx_result);
+                        window.frame_requested = true;
+                    }
+                    _ => {
+                        let _ = tx_result.send(Err(Error::NotRendering));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn compile(
+        &self,
+        window_id: WindowId,
+        code: &str,
+        options: &CompileOptions,
+        fragment_entry_point: Option<&str>,
+        timings: &mut CompileTimings,
+    ) -> Result<CompiledShader, CompileError> {
+        let compiled = compile_shader_with_fragment(code, options, fragment_entry_point)?;
+        timings.parse = compiled.timings.parse;
+        timings.validate = compiled.timings.validate;
+
+        if let Some(window) = self.windows.get(&window_id) {
+            check_requirements(&compiled.requirements, &window.backend, self.backend_type)?;
+            check_bindings(&compiled.bindings, &window.bind_group_slots())?;
+        }
+
+        Ok(compiled)
+    }
+
+    /// Builds the sound pass, if the shader declares
+    /// [`main_sound`](shade_rs_compile::sound::SOUND_FUNCTION).
+    fn compile_sound(&self, shader: &CompiledShader) -> Result<Option<naga::Module>, CompileError> {
+        let Some(module) = compile_sound_shader(shader)?
+        else {
+            return Ok(None);
+        };
+
+        // the sound pass is a compute shader.
+        if self.backend_type != BackendType::WebGpu {
+            return Err(CompileError::MissingRequirements {
+                missing: vec![Requirement::WebGpu],
+                backend_type: self.backend_type,
+            });
+        }
+
+        Ok(Some(module))
+    }
+
+    /// Builds the probe pass, if the shader has
+    /// [`@probe`](shade_rs_compile::probe::PROBE_BINDING) directives.
+    fn compile_probe(&self, shader: &CompiledShader) -> Result<Option<ProbeShader>, CompileError> {
+        let Some(probe) = compile_probe_shader(shader)?
+        else {
+            return Ok(None);
+        };
+
+        // the probes are written to a storage buffer from the fragment shader.
+        if self.backend_type != BackendType::WebGpu {
+            return Err(CompileError::MissingRequirements {
+                missing: vec![Requirement::WebGpu],
+                backend_type: self.backend_type,
+            });
+        }
+
+        Ok(Some(probe))
+    }
+
+    /// Returns the window's audio output, creating one if it has none yet.
+    async fn audio_output(&self, window_id: WindowId) -> Option<AudioOutput> {
+        let existing = self
+            .windows
+            .get(&window_id)
+            .and_then(|window| window.sound.as_ref())
+            .map(|sound| sound.output().clone());
+        if existing.is_some() {
+            return existing;
+        }
+
+        match AudioOutput::new().await {
+            Ok(output) => Some(output),
+            Err(error) => {
+                tracing::error!(?error, "failed to create audio output");
+                None
+            }
+        }
+    }
+
+    async fn create_window(
+        &mut self,
+        window_id: WindowId,
+        surface_size: SurfaceSize,
+        playback_state: PlaybackState,
+        on_frame: Box<dyn FnMut(FrameInfo) + 'static>,
+        uses_camera: Rc<Cell<bool>>,
+        uses_scroll: Rc<Cell<bool>>,
+        uses_orientation: Rc<Cell<bool>>,
+    ) -> Result<(), Error> {
+        tracing::info!(?window_id, ?surface_size, "creating surface");
+
+        let (surface, backend) = if self.backend_type.uses_shared_backend() {
+            let backend = self
+                .shared_backend
+                .as_ref()
+                .expect("expected a shared backend for WebGPU backend");
+            let surface = backend
+                .instance
+                .create_surface(window_id)
+                .expect("failed to create surface");
+            (surface, backend.clone())
+        }
+        else {
+            tracing::debug!("creating WebGL instance");
+            let instance = Arc::new(wgpu::Instance::new(wgpu::InstanceDescriptor {
+                backends: self.backend_type.as_wgpu(),
+                ..Default::default()
+            }));
+
+            let surface = instance
+                .create_surface(window_id)
+                .expect("failed to create surface");
+
+            let backend =
+                Backend::new(instance, &self.config, Some(&surface), self.tx_error.clone())
+                    .await
+                    .expect("todo: handle error");
+
+            (surface, backend)
+        };
+
+        let surface_capabilities = surface.get_capabilities(&backend.adapter);
+
         let surface_format = surface_capabilities
             .formats
             .iter()
@@ -311,6 +1305,22 @@ impl Reactor {
             .copied()
             .unwrap_or(surface_capabilities.formats[0]);
 
+        // allow drawing onto the surface with the other encoding, see `OutputEncoding`.
+        let mut view_formats = vec![];
+        if backend
+            .adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::SURFACE_VIEW_FORMATS)
+        {
+            for encoding in [OutputEncoding::Srgb, OutputEncoding::Linear] {
+                let view_format = encoding.format(surface_format);
+                if view_format != surface_format {
+                    view_formats.push(view_format);
+                }
+            }
+        }
+
         let surface_configuration = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
@@ -319,38 +1329,200 @@ impl Reactor {
             present_mode: surface_capabilities.present_modes[0],
             desired_maximum_frame_latency: 2,
             alpha_mode: surface_capabilities.alpha_modes[0],
-            view_formats: vec![],
+            view_formats,
         };
 
         surface.configure(&backend.device, &surface_configuration);
 
-        self.windows.insert(
+        let channels = Channels::new(&backend);
+        let keyboard = Keyboard::new(&backend);
+        let output_format = output_format(&surface_configuration, OutputEncoding::default())
+            .unwrap_or(surface_format);
+        let blitter = Blitter::new(&backend, output_format);
+        let compositor = Compositor::new(&backend, surface_format);
+        let feedback = Feedback::new(&backend, &blitter, &surface_configuration);
+        let render_configuration = surface_configuration.clone();
+        let camera_buffer = backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("camera buffer"),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+            size: wgpu_buffer_size::<CameraUniform>(),
+        });
+
+        let mut window = Window {
             window_id,
-            Window {
-                backend,
-                surface,
-                surface_configuration,
-                pipeline: None,
-                mouse_position: None,
-                visible: true,
-                on_frame,
-                paused: false,
-                previous_frame_time: Instant::now(),
-                time: 0.0,
-                fps: TicksPerSecond::new(30),
-                input_uniform: InputUniform::default(),
-            },
-        );
+            channels,
+            keyboard,
+            #[cfg(feature = "mesh")]
+            mesh: None,
+            backend,
+            surface,
+            surface_configuration,
+            render_configuration,
+            resolution: ResolutionScaler::default(),
+            blitter,
+            output_format,
+            compositor,
+            feedback,
+            depth_channels: [None; NUM_CHANNELS],
+            depth_resolver: None,
+            layers: vec![Layer::default()],
+            sound: None,
+            camera: OrbitCamera::default(),
+            camera_buffer,
+            uses_camera,
+            uses_scroll,
+            scroll: [0.0; 2],
+            uses_orientation,
+            rotation: IDENTITY_ROTATION,
+            mouse_position: None,
+            mouse_button: MouseButtonState::default(),
+            visible: true,
+            on_frame,
+            on_frame_texture: None,
+            probe: None,
+            on_probe: None,
+            on_surface_error: None,
+            surface_failures: 0,
+            on_device_error: None,
+            device_error: None,
+            diff: None,
+            gpu_timer: GpuTimer::new(&backend),
+            sequence: None,
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+            over_memory_budget: false,
+            pending_captures: vec![],
+            #[cfg(feature = "editor")]
+            pixel_reader: pixel::PixelReader::default(),
+            playback: Playback::restore(playback_state),
+            render_on_demand: false,
+            frame_requested: true,
+            target_fps: TargetFps::default(),
+            previous_frame_time: Instant::now(),
+            delta_time: 0.0,
+            fps: TicksPerSecond::new(30),
+            input_uniform: InputUniform::default(),
+        };
+        // a window that is restored paused doesn't update its inputs until it's
+        // played, but its frames must show the restored time.
+        window.update_input_uniform();
+        self.windows.insert(window_id, window);
 
         Ok(())
     }
 }
 
+/// How often a window renders frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TargetFps {
+    Fps30,
+    #[default]
+    Fps60,
+    Fps120,
+
+    /// As often as the browser's timers fire.
+    Unlimited,
+}
+
+impl TargetFps {
+    pub const ALL: [Self; 4] = [Self::Fps30, Self::Fps60, Self::Fps120, Self::Unlimited];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Fps30 => "30",
+            Self::Fps60 => "60",
+            Self::Fps120 => "120",
+            Self::Unlimited => "unlimited",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|target_fps| target_fps.name() == name)
+    }
+
+    /// Time between two frames that the [`ResolutionScaler`] tries to keep up
+    /// with. Without a cap, there's no point in lowering the resolution below
+    /// what the default frame rate needs.
+    fn resolution_period(&self) -> Duration {
+        match self {
+            Self::Unlimited => Self::default().period(),
+            _ => self.period(),
+        }
+    }
+
+    /// Time between two frames.
+    fn period(&self) -> Duration {
+        match self {
+            Self::Fps30 => Duration::from_millis(1000 / 30),
+            Self::Fps60 => Duration::from_millis(1000 / 60),
+            Self::Fps120 => Duration::from_millis(1000 / 120),
+            // browsers clamp shorter timeouts anyway.
+            Self::Unlimited => Duration::from_millis(1),
+        }
+    }
+}
+
+/// How the colors that shaders output are written to the canvas.
+///
+/// Surfaces are configured once with their preferred format, and the frame is
+/// drawn onto an sRGB or linear view of it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputEncoding {
+    /// Outputs are linear, and encoded as sRGB for display.
+    #[default]
+    Srgb,
+
+    /// Outputs are written as they are. Shaders that were ported from other
+    /// playgrounds, e.g. Shadertoy, usually expect this and apply gamma
+    /// themselves.
+    Linear,
+}
+
+impl OutputEncoding {
+    /// The variant of `format` with this encoding. Formats that have no sRGB
+    /// variant are returned as they are.
+    fn format(&self, format: wgpu::TextureFormat) -> wgpu::TextureFormat {
+        match self {
+            Self::Srgb => format.add_srgb_suffix(),
+            Self::Linear => format.remove_srgb_suffix(),
+        }
+    }
+}
+
+/// Format of the surface view that frames are drawn onto with `encoding`, or
+/// `None` if the surface doesn't allow it.
+fn output_format(
+    surface_configuration: &wgpu::SurfaceConfiguration,
+    encoding: OutputEncoding,
+) -> Option<wgpu::TextureFormat> {
+    let format = encoding.format(surface_configuration.format);
+    (format == surface_configuration.format || surface_configuration.view_formats.contains(&format))
+        .then_some(format)
+}
+
+/// Waits for the next tick, or forever if there is no interval.
+async fn tick(interval: &mut Option<Interval>) {
+    if let Some(interval) = interval {
+        interval.tick().await;
+    }
+    else {
+        std::future::pending::<()>().await;
+    }
+}
+
 enum Command {
     RegisterWindow {
         window_id: WindowId,
         surface_size: SurfaceSize,
+        playback_state: PlaybackState,
         on_frame: Box<dyn FnMut(FrameInfo) + 'static>,
+        uses_camera: Rc<Cell<bool>>,
+        uses_scroll: Rc<Cell<bool>>,
+        uses_orientation: Rc<Cell<bool>>,
     },
     DestroyWindow {
         window_id: WindowId,
@@ -360,13 +1532,114 @@ enum Command {
         surface_size: SurfaceSize,
     },
     Run {
+        window_id: WindowId,
+        layer: usize,
+        code: String,
+        options: CompileOptions,
+        fragment_entry_point: Option<String>,
+        tx_result: oneshot::Sender<Result<RunInfo, CompileError>>,
+    },
+    #[cfg(feature = "editor")]
+    Inspect {
         window_id: WindowId,
         code: String,
-        tx_result: oneshot::Sender<Result<(), CompileError>>,
+        options: CompileOptions,
+        tx_result: oneshot::Sender<Result<inspect::ShaderInspection, CompileError>>,
+    },
+    SetChannel {
+        window_id: WindowId,
+        index: usize,
+        image: Option<ChannelImage>,
+        tx_result: oneshot::Sender<Result<(), Error>>,
+    },
+    #[cfg(feature = "editor")]
+    SetChannelCompressed {
+        window_id: WindowId,
+        index: usize,
+        image: compressed::CompressedImage,
+        tx_result: oneshot::Sender<Result<(), Error>>,
+    },
+    #[cfg(feature = "editor")]
+    SetChannelHdr {
+        window_id: WindowId,
+        index: usize,
+        image: hdr::HdrImage,
+        exposure: f32,
+        tx_result: oneshot::Sender<Result<(), Error>>,
+    },
+    SetChannelVideo {
+        window_id: WindowId,
+        index: usize,
+        video: Option<web_sys::HtmlVideoElement>,
+        tx_result: oneshot::Sender<Result<(), Error>>,
+    },
+    #[cfg(feature = "editor")]
+    SetChannelAudio {
+        window_id: WindowId,
+        index: usize,
+        analyser: Option<web_sys::AnalyserNode>,
+        tx_result: oneshot::Sender<Result<(), Error>>,
+    },
+    SetChannelCubemap {
+        window_id: WindowId,
+        index: usize,
+        is_cubemap: bool,
+        tx_result: oneshot::Sender<Result<(), Error>>,
+    },
+    SetMemoryBudget {
+        window_id: WindowId,
+        budget: u64,
+    },
+    SetChannelSampler {
+        window_id: WindowId,
+        index: usize,
+        sampler: ChannelSampler,
+    },
+    SetChannelDepth {
+        window_id: WindowId,
+        index: usize,
+        layer: Option<usize>,
+        tx_result: oneshot::Sender<Result<(), Error>>,
+    },
+    ExportSequence {
+        window_id: WindowId,
+        options: SequenceOptions,
+        tx_frame: mpsc::UnboundedSender<SequenceFrame>,
+        tx_result: oneshot::Sender<Result<(), Error>>,
+    },
+    #[cfg(feature = "mesh")]
+    SetMesh {
+        window_id: WindowId,
+        mesh: Option<mesh::MeshData>,
+    },
+    SetParams {
+        window_id: WindowId,
+        data: Vec<u8>,
+    },
+    SetMouseButton {
+        window_id: WindowId,
+        pressed: bool,
+        position: [f32; 2],
+    },
+    ZoomCamera {
+        window_id: WindowId,
+        delta: f32,
+    },
+    SetScroll {
+        window_id: WindowId,
+        scroll: [f32; 2],
+    },
+    SetOrientation {
+        window_id: WindowId,
+        orientation: Option<[f32; 3]>,
+    },
+    SetKeyState {
+        window_id: WindowId,
+        key_code: u8,
+        pressed: bool,
     },
-    SetMousePosition {
+    ReleaseAllKeys {
         window_id: WindowId,
-        position: Option<[f32; 2]>,
     },
     SetVisibility {
         window_id: WindowId,
@@ -376,27 +1649,187 @@ enum Command {
         window_id: WindowId,
         paused: bool,
     },
+    SeekTime {
+        window_id: WindowId,
+        time: f32,
+    },
     Reset {
         window_id: WindowId,
     },
-}
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct WindowId {
-    id: NonZeroU32,
-}
-
-impl WindowId {
-    pub fn new() -> Self {
-        static IDS: AtomicU32 = AtomicU32::new(1);
-        Self {
-            id: NonZeroU32::new(IDS.fetch_add(1, Ordering::Relaxed)).unwrap(),
-        }
-    }
-
+    SetLayerBlend {
+        window_id: WindowId,
+        layer: usize,
+        blend: LayerBlend,
+    },
+    SetLayerTransform {
+        window_id: WindowId,
+        layer: usize,
+        transform: LayerTransform,
+    },
+    SetRenderScale {
+        window_id: WindowId,
+        scale: f32,
+    },
+    SetTargetFps {
+        window_id: WindowId,
+        target_fps: TargetFps,
+    },
+    SetOutputEncoding {
+        window_id: WindowId,
+        encoding: OutputEncoding,
+    },
+    SetDiff {
+        window_id: WindowId,
+        enabled: bool,
+    },
+    SetRenderOnDemand {
+        window_id: WindowId,
+        enabled: bool,
+    },
+    SetLayerMask {
+        window_id: WindowId,
+        layer: usize,
+        mask: Option<LayerMask>,
+    },
+    SetLayerEnabled {
+        window_id: WindowId,
+        layer: usize,
+        enabled: bool,
+    },
+    MoveLayer {
+        window_id: WindowId,
+        from: usize,
+        to: usize,
+    },
+    RemoveLayer {
+        window_id: WindowId,
+        layer: usize,
+    },
+    SetOnFrameTexture {
+        window_id: WindowId,
+        on_frame_texture: Option<Box<dyn FnMut(FrameTexture<'_>) + 'static>>,
+    },
+    SetOnProbe {
+        window_id: WindowId,
+        on_probe: Option<Box<dyn FnMut(Vec<ProbeValue>) + 'static>>,
+    },
+    SetOnSurfaceError {
+        window_id: WindowId,
+        on_surface_error: Option<Box<dyn FnMut(Option<Error>) + 'static>>,
+    },
+    SetOnDeviceError {
+        window_id: WindowId,
+        on_device_error: Option<Box<dyn FnMut(Error) + 'static>>,
+    },
+    CaptureFrame {
+        window_id: WindowId,
+        tx_result: oneshot::Sender<Result<String, Error>>,
+    },
+    #[cfg(feature = "editor")]
+    ReadPixel {
+        window_id: WindowId,
+        position: [f32; 2],
+        tx_result: oneshot::Sender<Result<pixel::PixelValue, Error>>,
+    },
+    AdapterInfo {
+        window_id: WindowId,
+        tx_result: oneshot::Sender<Option<AdapterInfo>>,
+    },
+}
+
+impl Command {
+    fn window_id(&self) -> WindowId {
+        match self {
+            Self::RegisterWindow { window_id, .. }
+            | Self::DestroyWindow { window_id }
+            | Self::Resize { window_id, .. }
+            | Self::Run { window_id, .. }
+            | Self::SetChannel { window_id, .. }
+            | Self::SetChannelVideo { window_id, .. }
+            | Self::SetChannelCubemap { window_id, .. }
+            | Self::SetMemoryBudget { window_id, .. }
+            | Self::SetChannelSampler { window_id, .. }
+            | Self::SetChannelDepth { window_id, .. }
+            | Self::ExportSequence { window_id, .. }
+            | Self::SetParams { window_id, .. }
+            | Self::SetMouseButton { window_id, .. }
+            | Self::ZoomCamera { window_id, .. }
+            | Self::SetScroll { window_id, .. }
+            | Self::SetOrientation { window_id, .. }
+            | Self::SetKeyState { window_id, .. }
+            | Self::ReleaseAllKeys { window_id }
+            | Self::SetVisibility { window_id, .. }
+            | Self::SetPaused { window_id, .. }
+            | Self::SeekTime { window_id, .. }
+            | Self::Reset { window_id }
+            | Self::SetLayerBlend { window_id, .. }
+            | Self::SetLayerTransform { window_id, .. }
+            | Self::SetRenderScale { window_id, .. }
+            | Self::SetTargetFps { window_id, .. }
+            | Self::SetOutputEncoding { window_id, .. }
+            | Self::SetDiff { window_id, .. }
+            | Self::SetRenderOnDemand { window_id, .. }
+            | Self::SetLayerMask { window_id, .. }
+            | Self::SetLayerEnabled { window_id, .. }
+            | Self::MoveLayer { window_id, .. }
+            | Self::RemoveLayer { window_id, .. }
+            | Self::SetOnFrameTexture { window_id, .. }
+            | Self::SetOnProbe { window_id, .. }
+            | Self::SetOnSurfaceError { window_id, .. }
+            | Self::SetOnDeviceError { window_id, .. }
+            | Self::CaptureFrame { window_id, .. }
+            | Self::AdapterInfo { window_id, .. } => *window_id,
+            #[cfg(feature = "editor")]
+            Self::Inspect { window_id, .. }
+            | Self::SetChannelCompressed { window_id, .. }
+            | Self::SetChannelHdr { window_id, .. }
+            | Self::SetChannelAudio { window_id, .. }
+            | Self::ReadPixel { window_id, .. } => *window_id,
+            #[cfg(feature = "mesh")]
+            Self::SetMesh { window_id, .. } => *window_id,
+        }
+    }
+
+    /// Whether this command goes through the priority lane.
+    ///
+    /// These are cheap, and must not be delayed by a backlog of other commands.
+    fn is_priority(&self) -> bool {
+        matches!(
+            self,
+            Self::RegisterWindow { .. }
+                | Self::DestroyWindow { .. }
+                | Self::Resize { .. }
+                | Self::SetVisibility { .. }
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WindowId {
+    id: NonZeroU32,
+}
+
+impl WindowId {
+    pub fn new() -> Self {
+        static IDS: AtomicU32 = AtomicU32::new(1);
+        Self {
+            id: NonZeroU32::new(IDS.fetch_add(1, Ordering::Relaxed)).unwrap(),
+        }
+    }
+
     pub fn id(&self) -> NonZeroU32 {
         self.id
     }
+
+    /// Looks up the canvas element for this window in the document.
+    pub fn canvas(&self) -> Option<HtmlCanvasElement> {
+        web_sys::window()?
+            .document()?
+            .query_selector(&format!("canvas[data-raw-handle=\"{}\"]", self.id))
+            .ok()??
+            .dyn_into()
+            .ok()
+    }
 }
 
 impl raw_window_handle::HasWindowHandle for WindowId {
@@ -433,14 +1866,84 @@ impl leptos::IntoAttribute for WindowId {
 pub struct WindowHandle {
     graphics: Graphics,
     window_id: WindowId,
+
+    /// Mouse position that will be sent to the reactor on the next animation
+    /// frame. `Some` if a flush is scheduled.
+    pending_mouse_position: Rc<Cell<Option<Option<[f32; 2]>>>>,
+
+    /// Set by the reactor while any layer's shader uses the camera.
+    uses_camera: Rc<Cell<bool>>,
+
+    /// Set by the reactor while any layer's shader reads the scroll or zoom.
+    uses_scroll: Rc<Cell<bool>>,
+
+    /// Set by the reactor while any layer's shader reads the rotation.
+    uses_orientation: Rc<Cell<bool>>,
 }
 
 impl WindowHandle {
-    pub async fn run(&self, code: String) -> Result<(), CompileError> {
+    /// Compiles the code and runs it as the bottom layer.
+    ///
+    /// If the shader declares
+    /// [`main_sound`](shade_rs_compile::sound::SOUND_FUNCTION), its sound is
+    /// played too. This needs the WebGPU backend.
+    ///
+    /// If the shader has several fragment entry points, `fragment_entry_point`
+    /// picks the one it's drawn with. See [`RunInfo::fragment_entry_points`].
+    ///
+    /// The libraries that the shader includes are fetched from the server, or
+    /// taken from the cache if they were fetched before, unless they're already
+    /// in `options`.
+    ///
+    /// Running a shader plays it, unless the window was restored paused from a
+    /// [`PlaybackState`] and this is the first run. See [`RunInfo::paused`].
+    pub async fn run(
+        &self,
+        code: String,
+        options: CompileOptions,
+        fragment_entry_point: Option<String>,
+    ) -> Result<RunInfo, CompileError> {
+        self.run_layer(0, code, options, fragment_entry_point).await
+    }
+
+    /// Compiles the code and runs it on the given layer. Layers are drawn in
+    /// order, each one composited onto the ones below it.
+    ///
+    /// If there is no such layer yet, empty layers are added up to it.
+    pub async fn run_layer(
+        &self,
+        layer: usize,
+        code: String,
+        mut options: CompileOptions,
+        fragment_entry_point: Option<String>,
+    ) -> Result<RunInfo, CompileError> {
+        resolve_libraries(&code, &options.defines, &mut options.libraries).await?;
         let (tx_result, rx_result) = oneshot::channel();
         self.graphics.send_command(Command::Run {
+            window_id: self.window_id,
+            layer,
+            code,
+            options,
+            fragment_entry_point,
+            tx_result,
+        });
+        rx_result.await.unwrap()
+    }
+
+    /// Compiles the code and returns what the compiler produced, without
+    /// running it.
+    #[cfg(feature = "editor")]
+    pub async fn inspect(
+        &self,
+        code: String,
+        mut options: CompileOptions,
+    ) -> Result<inspect::ShaderInspection, CompileError> {
+        resolve_libraries(&code, &options.defines, &mut options.libraries).await?;
+        let (tx_result, rx_result) = oneshot::channel();
+        self.graphics.send_command(Command::Inspect {
             window_id: self.window_id,
             code,
+            options,
             tx_result,
         });
         rx_result.await.unwrap()
@@ -459,262 +1962,2510 @@ impl WindowHandle {
         });
     }
 
-    pub fn set_mouse_position(&self, position: Option<[f32; 2]>) {
-        self.graphics.send_command(Command::SetMousePosition {
-            window_id: self.window_id,
-            position,
-        });
+    /// Sets the mouse position.
+    ///
+    /// Positions are buffered and sent at most once per animation frame, since
+    /// mice can easily fire more events than we render frames.
+    pub fn set_mouse_position(&self, position: Option<[f32; 2]>) {
+        let flush_scheduled = self
+            .pending_mouse_position
+            .replace(Some(position))
+            .is_some();
+        if !flush_scheduled {
+            let graphics = self.graphics.clone();
+            let window_id = self.window_id;
+            let pending_mouse_position = self.pending_mouse_position.clone();
+            request_animation_frame(move || {
+                if let Some(position) = pending_mouse_position.take() {
+                    graphics.set_mouse_position(window_id, position);
+                }
+            });
+        }
+    }
+
+    /// Binds an image to a texture channel, or clears it if `image` is `None`.
+    ///
+    /// See [`channel::CHANNEL_BIND_GROUP`] for how shaders access channels.
+    pub async fn set_channel(
+        &self,
+        index: usize,
+        image: Option<ChannelImage>,
+    ) -> Result<(), Error> {
+        let (tx_result, rx_result) = oneshot::channel();
+        self.graphics.send_command(Command::SetChannel {
+            window_id: self.window_id,
+            index,
+            image,
+            tx_result,
+        });
+        rx_result.await.unwrap()
+    }
+
+    /// Binds a [compressed image](compressed::CompressedImage) to a texture
+    /// channel. Use [`Self::set_channel`] to clear it.
+    ///
+    /// Fails if the device doesn't support the image's format.
+    #[cfg(feature = "editor")]
+    pub async fn set_channel_compressed(
+        &self,
+        index: usize,
+        image: compressed::CompressedImage,
+    ) -> Result<(), Error> {
+        let (tx_result, rx_result) = oneshot::channel();
+        self.graphics.send_command(Command::SetChannelCompressed {
+            window_id: self.window_id,
+            index,
+            image,
+            tx_result,
+        });
+        rx_result.await.unwrap()
+    }
+
+    /// Binds an [HDR image](hdr::HdrImage) to a texture channel, with its
+    /// colors scaled by `2^exposure`. Use [`Self::set_channel`] to clear it.
+    ///
+    /// To change the exposure, the image is bound again.
+    #[cfg(feature = "editor")]
+    pub async fn set_channel_hdr(
+        &self,
+        index: usize,
+        image: hdr::HdrImage,
+        exposure: f32,
+    ) -> Result<(), Error> {
+        let (tx_result, rx_result) = oneshot::channel();
+        self.graphics.send_command(Command::SetChannelHdr {
+            window_id: self.window_id,
+            index,
+            image,
+            exposure,
+            tx_result,
+        });
+        rx_result.await.unwrap()
+    }
+
+    /// Binds a video to a texture channel, or clears it if `video` is `None`.
+    ///
+    /// The current frame of the video is copied into the channel whenever a
+    /// frame is rendered, so windows with videos render continuously.
+    pub async fn set_channel_video(
+        &self,
+        index: usize,
+        video: Option<web_sys::HtmlVideoElement>,
+    ) -> Result<(), Error> {
+        let (tx_result, rx_result) = oneshot::channel();
+        self.graphics.send_command(Command::SetChannelVideo {
+            window_id: self.window_id,
+            index,
+            video,
+            tx_result,
+        });
+        rx_result.await.unwrap()
+    }
+
+    /// Binds audio to a texture channel, or clears it if `analyser` is `None`.
+    /// The analyser's FFT size must be twice [`channel::AUDIO_SAMPLES`].
+    ///
+    /// The current spectrum and waveform are written to the channel whenever a
+    /// frame is rendered, so windows with audio render continuously.
+    #[cfg(feature = "editor")]
+    pub async fn set_channel_audio(
+        &self,
+        index: usize,
+        analyser: Option<web_sys::AnalyserNode>,
+    ) -> Result<(), Error> {
+        let (tx_result, rx_result) = oneshot::channel();
+        self.graphics.send_command(Command::SetChannelAudio {
+            window_id: self.window_id,
+            index,
+            analyser,
+            tx_result,
+        });
+        rx_result.await.unwrap()
+    }
+
+    /// Sets whether a texture channel's image is an equirectangular panorama
+    /// that is converted to a cubemap, e.g. for environment lighting.
+    ///
+    /// The cubemap is bound in addition to the image, see
+    /// [`channel::CHANNEL_BIND_GROUP`]. It's converted again whenever another
+    /// image is bound to the channel.
+    pub async fn set_channel_cubemap(&self, index: usize, is_cubemap: bool) -> Result<(), Error> {
+        let (tx_result, rx_result) = oneshot::channel();
+        self.graphics.send_command(Command::SetChannelCubemap {
+            window_id: self.window_id,
+            index,
+            is_cubemap,
+            tx_result,
+        });
+        rx_result.await.unwrap()
+    }
+
+    /// Sets how a texture channel is filtered and wrapped.
+    pub fn set_channel_sampler(&self, index: usize, sampler: ChannelSampler) {
+        self.graphics.send_command(Command::SetChannelSampler {
+            window_id: self.window_id,
+            index,
+            sampler,
+        });
+    }
+
+    /// Binds the depth buffer of a layer to a texture channel, or clears the
+    /// channel if `layer` is `None`.
+    ///
+    /// The depth is copied into the channel after the layer was drawn, so
+    /// layers above it sample the depth of the current frame, and the layer
+    /// itself and the ones below sample the previous frame's. Values are in
+    /// the red component, and are 1 where nothing was drawn.
+    ///
+    /// The layer only has a depth buffer if it's drawn with the mesh or its
+    /// shader has a [`@depth`](shade_rs_compile::depth::DepthFormat) directive.
+    /// Until then the channel stays empty.
+    pub async fn set_channel_depth(&self, index: usize, layer: Option<usize>) -> Result<(), Error> {
+        let (tx_result, rx_result) = oneshot::channel();
+        self.graphics.send_command(Command::SetChannelDepth {
+            window_id: self.window_id,
+            index,
+            layer,
+            tx_result,
+        });
+        rx_result.await.unwrap()
+    }
+
+    /// Sets how much GPU memory the window's textures and buffers may use, in
+    /// bytes. Channel images and sequence exports that would exceed it are
+    /// refused, and the [frame info](FrameInfo::memory) tells when the frame
+    /// textures alone exceed it, e.g. at a high resolution.
+    ///
+    /// Defaults to [`DEFAULT_MEMORY_BUDGET`].
+    pub fn set_memory_budget(&self, budget: u64) {
+        self.graphics.send_command(Command::SetMemoryBudget {
+            window_id: self.window_id,
+            budget,
+        });
+    }
+
+    /// Sets the mesh that shaders with vertex inputs are drawn with, or
+    /// removes it if `mesh` is `None`.
+    ///
+    /// See [`mesh::MeshVertex`] for how shaders access the mesh.
+    #[cfg(feature = "mesh")]
+    pub fn set_mesh(&self, mesh: Option<mesh::MeshData>) {
+        self.graphics.send_command(Command::SetMesh {
+            window_id: self.window_id,
+            mesh,
+        });
+    }
+
+    /// Writes the values of the user-defined parameters, laid out as described
+    /// by [`RunInfo::params`].
+    pub fn set_params(&self, data: Vec<u8>) {
+        self.graphics.send_command(Command::SetParams {
+            window_id: self.window_id,
+            data,
+        });
+    }
+
+    /// Sets whether the (primary) mouse button is held down.
+    ///
+    /// Unlike mouse positions, these are never coalesced, so that short clicks
+    /// are not lost.
+    pub fn set_mouse_button(&self, pressed: bool, position: [f32; 2]) {
+        self.graphics.send_command(Command::SetMouseButton {
+            window_id: self.window_id,
+            pressed,
+            position,
+        });
+    }
+
+    /// Whether any layer's shader declares the camera uniform. While it does,
+    /// dragging the mouse orbits the camera, and scrolling should be passed to
+    /// [`Self::zoom_camera`] instead of scrolling the page.
+    ///
+    /// See [`shade_rs_compile::reflect::CAMERA_BINDING`].
+    pub fn uses_camera(&self) -> bool {
+        self.uses_camera.get()
+    }
+
+    /// Zooms the camera by a scroll of `delta` pixels. Positive values zoom
+    /// out.
+    pub fn zoom_camera(&self, delta: f32) {
+        self.graphics.send_command(Command::ZoomCamera {
+            window_id: self.window_id,
+            delta,
+        });
+    }
+
+    /// Whether any layer's shader reads the scroll or zoom from its inputs.
+    /// The UI then passes mouse wheel events to [`Self::set_scroll`] instead
+    /// of scrolling the page.
+    pub fn uses_scroll(&self) -> bool {
+        self.uses_scroll.get()
+    }
+
+    /// Sets how far the mouse wheel was scrolled in total, in pixels. Positive
+    /// values are scrolled down or right, which zooms out.
+    ///
+    /// Shaders read this as `input.scroll` and `input.zoom`.
+    pub fn set_scroll(&self, scroll: [f32; 2]) {
+        self.graphics.send_command(Command::SetScroll {
+            window_id: self.window_id,
+            scroll,
+        });
+    }
+
+    /// Whether any layer's shader reads the device's rotation from its inputs.
+    /// The UI then listens to the device orientation and passes it to
+    /// [`Self::set_orientation`].
+    pub fn uses_orientation(&self) -> bool {
+        self.uses_orientation.get()
+    }
+
+    /// Sets the orientation of the device, as the `alpha`, `beta` and `gamma`
+    /// angles of a `DeviceOrientationEvent`, in degrees. `None` if the device
+    /// doesn't know its orientation.
+    ///
+    /// Shaders read this as the quaternion `input.rotation`.
+    pub fn set_orientation(&self, orientation: Option<[f32; 3]>) {
+        self.graphics.send_command(Command::SetOrientation {
+            window_id: self.window_id,
+            orientation,
+        });
+    }
+
+    /// Sets whether the key with the given `keyCode` is held down.
+    ///
+    /// See [`keyboard::KEYBOARD_BIND_GROUP`] for how shaders access the
+    /// keyboard state.
+    pub fn set_key_state(&self, key_code: u8, pressed: bool) {
+        self.graphics.send_command(Command::SetKeyState {
+            window_id: self.window_id,
+            key_code,
+            pressed,
+        });
+    }
+
+    /// Releases all keys, e.g. when the window loses focus and won't receive
+    /// the key-up events.
+    pub fn release_all_keys(&self) {
+        self.graphics.send_command(Command::ReleaseAllKeys {
+            window_id: self.window_id,
+        });
+    }
+
+    pub fn set_visibility(&self, visible: bool) {
+        self.graphics.send_command(Command::SetVisibility {
+            window_id: self.window_id,
+            visible,
+        });
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.graphics.send_command(Command::SetPaused {
+            window_id: self.window_id,
+            paused,
+        });
+    }
+
+    /// Jumps to `time` (in seconds), e.g. to look at a moment of an animation
+    /// while paused. The frame counter and the previous frame are kept.
+    pub fn seek_time(&self, time: f32) {
+        self.graphics.send_command(Command::SeekTime {
+            window_id: self.window_id,
+            time,
+        });
+    }
+
+    pub fn reset(&self) {
+        self.graphics.send_command(Command::Reset {
+            window_id: self.window_id,
+        });
+    }
+
+    /// Sets whether the window shows how the bottom layer's output differs from
+    /// the version of its shader that was run before, instead of the layers.
+    /// See [`DiffPass`].
+    ///
+    /// The difference is shown once the shader was run again after enabling
+    /// this.
+    pub fn set_diff(&self, enabled: bool) {
+        self.graphics.send_command(Command::SetDiff {
+            window_id: self.window_id,
+            enabled,
+        });
+    }
+
+    /// Sets whether the window only renders frames when something changes, e.g.
+    /// the size, mouse or parameters, as long as none of its shaders read the
+    /// time or the previous frame. This saves a lot of power for static
+    /// shaders.
+    pub fn set_render_on_demand(&self, enabled: bool) {
+        self.graphics.send_command(Command::SetRenderOnDemand {
+            window_id: self.window_id,
+            enabled,
+        });
+    }
+
+    /// Sets how often the window renders frames.
+    pub fn set_target_fps(&self, target_fps: TargetFps) {
+        self.graphics.send_command(Command::SetTargetFps {
+            window_id: self.window_id,
+            target_fps,
+        });
+    }
+
+    /// Sets whether the output of shaders is encoded as sRGB for display.
+    ///
+    /// This only affects the canvas. Captured frames and exported sequences
+    /// are read from the rendered frame, and are always encoded like
+    /// [`OutputEncoding::Srgb`] on surfaces with an sRGB format.
+    pub fn set_output_encoding(&self, encoding: OutputEncoding) {
+        self.graphics.send_command(Command::SetOutputEncoding {
+            window_id: self.window_id,
+            encoding,
+        });
+    }
+
+    /// Sets how a layer is composited onto the layers below it.
+    pub fn set_layer_blend(&self, layer: usize, blend: LayerBlend) {
+        self.graphics.send_command(Command::SetLayerBlend {
+            window_id: self.window_id,
+            layer,
+            blend,
+        });
+    }
+
+    /// Sets the resolution frames are rendered at, relative to the canvas. It's
+    /// clamped to [`MIN_USER_SCALE`]..=[`MAX_USER_SCALE`], and lowered further
+    /// if the shader is too slow.
+    ///
+    /// [`MIN_USER_SCALE`]: resolution::MIN_USER_SCALE
+    /// [`MAX_USER_SCALE`]: resolution::MAX_USER_SCALE
+    pub fn set_render_scale(&self, scale: f32) {
+        self.graphics.send_command(Command::SetRenderScale {
+            window_id: self.window_id,
+            scale,
+        });
+    }
+
+    /// Sets the 2D transform that is applied to a layer when it's composited.
+    pub fn set_layer_transform(&self, layer: usize, transform: LayerTransform) {
+        self.graphics.send_command(Command::SetLayerTransform {
+            window_id: self.window_id,
+            layer,
+            transform,
+        });
+    }
+
+    /// Masks a layer by the alpha of another layer, or removes the mask if
+    /// `mask` is `None`. Layers that are used as a mask are not drawn
+    /// themselves.
+    pub fn set_layer_mask(&self, layer: usize, mask: Option<LayerMask>) {
+        self.graphics.send_command(Command::SetLayerMask {
+            window_id: self.window_id,
+            layer,
+            mask,
+        });
+    }
+
+    /// Disables or enables a layer. Disabled layers aren't drawn, but keep
+    /// showing their last output if they're composited. Otherwise they're
+    /// transparent.
+    pub fn set_layer_enabled(&self, layer: usize, enabled: bool) {
+        self.graphics.send_command(Command::SetLayerEnabled {
+            window_id: self.window_id,
+            layer,
+            enabled,
+        });
+    }
+
+    /// Moves a layer to another position in the stack. The layers in between
+    /// move by one, and masks keep referring to the same layers.
+    ///
+    /// The bottom layer can't be moved, and nothing can be moved below it,
+    /// because the sound and probe passes belong to it.
+    pub fn move_layer(&self, from: usize, to: usize) {
+        self.graphics.send_command(Command::MoveLayer {
+            window_id: self.window_id,
+            from,
+            to,
+        });
+    }
+
+    /// Removes a layer. The layers above it move down by one, and masks using
+    /// it are removed.
+    pub fn remove_layer(&self, layer: usize) {
+        self.graphics.send_command(Command::RemoveLayer {
+            window_id: self.window_id,
+            layer,
+        });
+    }
+
+    /// Sets a callback that receives every rendered frame as a texture, e.g. to
+    /// composite it into a host application's scene. Pass `None` to remove it.
+    pub fn set_on_frame_texture(
+        &self,
+        on_frame_texture: Option<Box<dyn FnMut(FrameTexture<'_>) + 'static>>,
+    ) {
+        self.graphics.send_command(Command::SetOnFrameTexture {
+            window_id: self.window_id,
+            on_frame_texture,
+        });
+    }
+
+    /// Sets a callback that receives the values of the bottom layer's
+    /// [probes](shade_rs_compile::probe::PROBE_BINDING) at the pixel under the
+    /// mouse, whenever they were read back. Pass `None` to remove it.
+    pub fn set_on_probe(&self, on_probe: Option<Box<dyn FnMut(Vec<ProbeValue>) + 'static>>) {
+        self.graphics.send_command(Command::SetOnProbe {
+            window_id: self.window_id,
+            on_probe,
+        });
+    }
+
+    /// Sets a callback that is called with the error when the canvas couldn't
+    /// be rendered to for several frames in a row, and with `None` once it
+    /// renders again. Pass `None` to remove it.
+    ///
+    /// Lost and outdated surfaces are reconfigured, and frames that time out
+    /// are skipped, so this is only called when that didn't help.
+    pub fn set_on_surface_error(
+        &self,
+        on_surface_error: Option<Box<dyn FnMut(Option<Error>) + 'static>>,
+    ) {
+        self.graphics.send_command(Command::SetOnSurfaceError {
+            window_id: self.window_id,
+            on_surface_error,
+        });
+    }
+
+    /// Sets a callback that is called with errors that the GPU device reported
+    /// on its own, e.g. because a shader that passed validation was rejected
+    /// by the browser. Pass `None` to remove it.
+    ///
+    /// The same error isn't reported again until the next shader is run.
+    pub fn set_on_device_error(&self, on_device_error: Option<Box<dyn FnMut(Error) + 'static>>) {
+        self.graphics.send_command(Command::SetOnDeviceError {
+            window_id: self.window_id,
+            on_device_error,
+        });
+    }
+
+    /// Returns what the adapter that renders this window can do.
+    pub async fn adapter_info(&self) -> Option<AdapterInfo> {
+        self.graphics.adapter_info(self.window_id).await
+    }
+
+    /// Captures the next frame that is rendered, as a PNG data URL.
+    pub async fn capture_frame(&self) -> Result<String, Error> {
+        let (tx_result, rx_result) = oneshot::channel();
+        self.graphics.send_command(Command::CaptureFrame {
+            window_id: self.window_id,
+            tx_result,
+        });
+        rx_result.await.unwrap_or(Err(Error::NotRendering))
+    }
+
+    /// Reads back the pixel at `position` of the next frame that is rendered,
+    /// e.g. for inspecting the colors a shader computes. The position is in
+    /// pixels of the canvas, like the mouse position.
+    #[cfg(feature = "editor")]
+    pub async fn read_pixel(&self, position: [f32; 2]) -> Result<pixel::PixelValue, Error> {
+        let (tx_result, rx_result) = oneshot::channel();
+        self.graphics.send_command(Command::ReadPixel {
+            window_id: self.window_id,
+            position,
+            tx_result,
+        });
+        rx_result.await.unwrap_or(Err(Error::NotRendering))
+    }
+
+    /// Renders the frames of a sequence offscreen, e.g. for compositing in other
+    /// tools.
+    ///
+    /// The frames are sent to the returned receiver, not necessarily in order.
+    /// The receiver is closed when all frames were sent. Dropping it cancels the
+    /// export. While exporting, the window doesn't render regular frames, and
+    /// afterwards it continues where it was.
+    pub async fn export_sequence(
+        &self,
+        options: SequenceOptions,
+    ) -> Result<mpsc::UnboundedReceiver<SequenceFrame>, Error> {
+        let (tx_frame, rx_frame) = mpsc::unbounded_channel();
+        let (tx_result, rx_result) = oneshot::channel();
+        self.graphics.send_command(Command::ExportSequence {
+            window_id: self.window_id,
+            options,
+            tx_frame,
+            tx_result,
+        });
+        rx_result.await.unwrap()?;
+        Ok(rx_frame)
+    }
+
+    /// Captures the window's canvas as a [`MediaStream`](web_sys::MediaStream).
+    ///
+    /// If `frame_rate` is `None`, a new frame is captured every time the canvas
+    /// changes.
+    #[cfg(feature = "export")]
+    pub fn capture_stream(&self, frame_rate: Option<f64>) -> Result<web_sys::MediaStream, Error> {
+        let canvas = self
+            .window_id
+            .canvas()
+            .ok_or(Error::CanvasNotFound(self.window_id))?;
+
+        let result = if let Some(frame_rate) = frame_rate {
+            canvas.capture_stream_with_frame_request_rate(frame_rate)
+        }
+        else {
+            canvas.capture_stream()
+        };
+
+        result.map_err(|error| {
+            Error::CaptureStream {
+                message: format!("{error:?}"),
+            }
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SurfaceSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl SurfaceSize {
+    pub fn from_html_canvas(canvas: &HtmlCanvasElement) -> Self {
+        Self {
+            width: canvas.width().max(1),
+            height: canvas.height().max(1),
+        }
+    }
+
+    pub fn from_surface_configuration(surface_configuration: &wgpu::SurfaceConfiguration) -> Self {
+        Self {
+            width: surface_configuration.width,
+            height: surface_configuration.height,
+        }
+    }
+}
+
+struct Window {
+    window_id: WindowId,
+    backend: Backend,
+    surface: wgpu::Surface<'static>,
+    surface_configuration: wgpu::SurfaceConfiguration,
+
+    /// Like the surface configuration, but with the size that frames are
+    /// rendered at. This is smaller than the surface if the [`resolution`]
+    /// was lowered for a heavy shader.
+    ///
+    /// [`resolution`]: Self::resolution
+    render_configuration: wgpu::SurfaceConfiguration,
+    resolution: ResolutionScaler,
+
+    /// Blits the rendered frame onto the surface, with the
+    /// [`Self::output_format`].
+    blitter: Blitter,
+
+    /// Format of the surface view the frame is drawn onto. It's a variant of
+    /// the surface format, see [`OutputEncoding`].
+    output_format: wgpu::TextureFormat,
+
+    compositor: Compositor,
+
+    /// Frames are rendered into these, and then drawn onto the surface.
+    feedback: Feedback,
+
+    /// For each channel, the layer whose depth buffer is bound to it. See
+    /// [`WindowHandle::set_channel_depth`].
+    depth_channels: [Option<usize>; NUM_CHANNELS],
+
+    /// Created when the first depth buffer is bound to a channel.
+    depth_resolver: Option<DepthResolver>,
+
+    /// Shaders that are drawn on top of each other. There's always at least
+    /// one.
+    layers: Vec<Layer>,
+
+    /// Plays the sound of the bottom layer, if its shader generates any.
+    sound: Option<SoundPass>,
+
+    /// Reads the bottom layer's probes, if its shader has any.
+    probe: Option<ProbePass>,
+
+    /// Only exists while the difference to the previous version of the bottom
+    /// layer's shader is shown.
+    diff: Option<Diff>,
+
+    /// Measures the GPU frame time, if the device supports timestamp queries.
+    gpu_timer: Option<GpuTimer>,
+
+    /// Set while a sequence is exported.
+    sequence: Option<SequenceExport>,
+
+    /// See [`WindowHandle::set_memory_budget`].
+    memory_budget: u64,
+
+    /// Whether the window was over its budget at the last frame, so that it's
+    /// only logged once.
+    over_memory_budget: bool,
+
+    channels: Channels,
+    keyboard: Keyboard,
+    #[cfg(feature = "mesh")]
+    mesh: Option<mesh::Mesh>,
+    camera: OrbitCamera,
+    camera_buffer: wgpu::Buffer,
+
+    /// Shared with the [`WindowHandle`].
+    uses_camera: Rc<Cell<bool>>,
+
+    /// Shared with the [`WindowHandle`].
+    uses_scroll: Rc<Cell<bool>>,
+
+    /// See [`WindowHandle::set_scroll`].
+    scroll: [f32; 2],
+
+    /// Shared with the [`WindowHandle`].
+    uses_orientation: Rc<Cell<bool>>,
+
+    /// See [`WindowHandle::set_orientation`].
+    rotation: [f32; 4],
+
+    mouse_position: Option<[f32; 2]>,
+    mouse_button: MouseButtonState,
+    visible: bool,
+    playback: Playback,
+
+    /// Whether frames are only rendered when requested, unless a layer is
+    /// [animated](Pipeline::is_animated). See
+    /// [`WindowHandle::set_render_on_demand`].
+    render_on_demand: bool,
+
+    /// Set when something changed that the next frame might depend on.
+    frame_requested: bool,
+
+    target_fps: TargetFps,
+    previous_frame_time: Instant,
+    delta_time: f32,
+    fps: TicksPerSecond,
+    on_frame: Box<dyn FnMut(FrameInfo) + 'static>,
+    on_frame_texture: Option<Box<dyn FnMut(FrameTexture<'_>) + 'static>>,
+    on_probe: Option<Box<dyn FnMut(Vec<ProbeValue>) + 'static>>,
+    on_surface_error: Option<Box<dyn FnMut(Option<Error>) + 'static>>,
+
+    /// Number of frames in a row that failed to get a surface texture.
+    surface_failures: u32,
+
+    on_device_error: Option<Box<dyn FnMut(Error) + 'static>>,
+
+    /// The last error that was reported with `on_device_error`. Broken
+    /// pipelines fail the same way every frame, so it's only reported once.
+    device_error: Option<String>,
+
+    /// Requests for [`WindowHandle::capture_frame`], which are answered after
+    /// the next frame was presented.
+    pending_captures: Vec<oneshot::Sender<Result<String, Error>>>,
+
+    /// Requests for [`WindowHandle::read_pixel`], which are copied out of the
+    /// next frame.
+    #[cfg(feature = "editor")]
+    pixel_reader: pixel::PixelReader,
+
+    input_uniform: InputUniform,
+}
+
+impl Window {
+    /// Creates the pipeline for a layer's shader.
+    ///
+    /// Shaders that passed validation can still fail here, e.g. if the WebGL
+    /// backend can't translate them. The error is returned then, and the layer
+    /// keeps its previous pipeline.
+    pub async fn create_pipeline(
+        &mut self,
+        layer: usize,
+        shader: naga::Module,
+        entry_points: EntryPoints,
+        params: Option<&ParamsLayout>,
+        depth: Option<DepthFormat>,
+        timings: &mut CompileTimings,
+    ) -> Result<(), wgpu::Error> {
+        let input_buffer = self.backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("input buffer"),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+            size: wgpu_buffer_size::<InputUniform>(),
+        });
+
+        // the params buffer always exists, so that the layout doesn't depend on the
+        // shader.
+        let params_size = params.map_or(0, |params| u64::from(params.size));
+        let params_buffer = self.backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("params buffer"),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+            size: params_size.next_multiple_of(16).max(16),
+        });
+
+        let input_bind_group_layout =
+            self.backend
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("input bind group layout"),
+                    entries: &input_bind_group_layout_entries(),
+                });
+
+        let input_bind_group = self
+            .backend
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &input_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: input_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: PARAMS_BINDING,
+                        resource: params_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: CAMERA_BINDING,
+                        resource: self.camera_buffer.as_entire_binding(),
+                    },
+                ],
+                label: Some("input bind group"),
+            });
+
+        #[cfg(feature = "mesh")]
+        let uses_mesh = mesh::uses_vertex_inputs(&shader, &entry_points.vertex);
+        // without meshes, shaders are always drawn as a fullscreen triangle.
+        #[cfg(not(feature = "mesh"))]
+        let uses_mesh = false;
+        // meshes are always depth tested.
+        let depth_format = depth
+            .or_else(|| uses_mesh.then(DepthFormat::default))
+            .map(depth::texture_format);
+        let uses_camera = declares_camera(&shader);
+        let uses_scroll = reads_scroll(&shader);
+        let uses_orientation = reads_orientation(&shader);
+        let is_animated = reads_time(&shader) || reads_previous_frame(&shader);
+        let bind_group_count = bind_group_count(&shader, NUM_BIND_GROUPS);
+
+        // capture the errors, so that they don't end up in the uncaptured error handler.
+        let device = &self.backend.device;
+        device.push_error_scope(wgpu::ErrorFilter::Internal);
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let start_time = Instant::now();
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader"),
+            source: wgpu::ShaderSource::Naga(Cow::Owned(shader)),
+        });
+        timings.create_shader_module = start_time.elapsed();
+
+        let start_time = Instant::now();
+        let pipeline = self.create_render_pipeline(
+            &shader,
+            &entry_points,
+            &input_bind_group_layout,
+            bind_group_count,
+            uses_mesh,
+            depth_format,
+        );
+        timings.create_pipeline = start_time.elapsed();
+
+        let validation_error = device.pop_error_scope().await;
+        let internal_error = device.pop_error_scope().await;
+        if let Some(error) = validation_error.or(internal_error) {
+            return Err(error);
+        }
+
+        let previous = self.layer_mut(layer).pipeline.replace(Pipeline {
+            pipeline,
+            input_buffer,
+            params_buffer,
+            input_bind_group,
+            entry_points,
+            uses_mesh,
+            depth_format,
+            uses_camera,
+            uses_scroll,
+            uses_orientation,
+            is_animated,
+        });
+        if let (0, Some(diff)) = (layer, &mut self.diff) {
+            diff.previous = previous;
+        }
+        self.update_uses_camera();
+        self.update_uses_scroll();
+        self.update_uses_orientation();
+        self.device_error = None;
+
+        // give the new shader a chance at full resolution.
+        let scale = self.resolution.scale();
+        self.resolution.reset();
+        if self.resolution.scale() != scale {
+            self.recreate_render_targets();
+        }
+
+        Ok(())
+    }
+
+    /// The bind groups that shaders are drawn with, in the order of their
+    /// group index.
+    fn bind_group_slots(&self) -> [BindGroupSlot; NUM_BIND_GROUPS] {
+        [
+            BindGroupSlot {
+                group: 0,
+                name: "inputs",
+                entries: input_bind_group_layout_entries(),
+            },
+            BindGroupSlot {
+                group: CHANNEL_BIND_GROUP,
+                name: "channels",
+                entries: Channels::bind_group_layout_entries(),
+            },
+            BindGroupSlot {
+                group: KEYBOARD_BIND_GROUP,
+                name: "keyboard",
+                entries: Keyboard::bind_group_layout_entries(),
+            },
+            BindGroupSlot {
+                group: FEEDBACK_BIND_GROUP,
+                name: "previous frame",
+                entries: Feedback::bind_group_layout_entries(),
+            },
+        ]
+    }
+
+    /// Creates the render pipeline for a shader, with `input_bind_group_layout`
+    /// in `@group(0)`, and the other bind groups up to `bind_group_count`. It's
+    /// depth tested if a `depth_format` is given.
+    fn create_render_pipeline(
+        &self,
+        shader: &wgpu::ShaderModule,
+        entry_points: &EntryPoints,
+        input_bind_group_layout: &wgpu::BindGroupLayout,
+        bind_group_count: usize,
+        uses_mesh: bool,
+        depth_format: Option<wgpu::TextureFormat>,
+    ) -> wgpu::RenderPipeline {
+        let bind_group_layouts: [&wgpu::BindGroupLayout; NUM_BIND_GROUPS] = [
+            input_bind_group_layout,
+            &self.channels.bind_group_layout,
+            &self.keyboard.bind_group_layout,
+            &self.feedback.bind_group_layout,
+        ];
+        let pipeline_layout =
+            self.backend
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("Render3dMeshesWithMaterial pipeline layout"),
+                    bind_group_layouts: &bind_group_layouts[..bind_group_count],
+                    push_constant_ranges: &[],
+                });
+
+        let vertex_buffers: Vec<wgpu::VertexBufferLayout> = match uses_mesh {
+            #[cfg(feature = "mesh")]
+            true => vec![mesh::MeshVertex::layout()],
+            _ => vec![],
+        };
+
+        self.backend
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: shader,
+                    entry_point: &entry_points.vertex,
+                    buffers: &vertex_buffers,
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: shader,
+                    entry_point: &entry_points.fragment,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: self.surface_configuration.format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: depth_format.map(|format| {
+                    wgpu::DepthStencilState {
+                        format,
+                        depth_write_enabled: true,
+                        depth_compare: wgpu::CompareFunction::Less,
+                        stencil: Default::default(),
+                        bias: Default::default(),
+                    }
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+                cache: None,
+            })
+    }
+
+    /// Creates the sound pass for the bottom layer's shader, or removes it if
+    /// `sound` is `None`.
+    ///
+    /// Must be called after the bottom layer's pipeline was created.
+    fn create_sound_pass(&mut self, sound: Option<(naga::Module, AudioOutput)>) {
+        let (Some((module, output)), Some(pipeline)) = (sound, &self.layers[0].pipeline)
+        else {
+            self.sound = None;
+            return;
+        };
+
+        let mut sound = SoundPass::new(
+            &self.backend,
+            module,
+            &pipeline.input_buffer,
+            &pipeline.params_buffer,
+            output,
+        );
+        sound.seek(self.playback.time);
+        sound.set_playing(!self.playback.paused);
+        self.sound = Some(sound);
+    }
+
+    /// Creates the probe pass for the bottom layer's shader, or removes it if
+    /// `probe` is `None`.
+    ///
+    /// Must be called after the bottom layer's pipeline was created.
+    fn create_probe_pass(&mut self, probe: Option<ProbeShader>) {
+        let (Some(probe), Some(pipeline)) = (probe, &self.layers[0].pipeline)
+        else {
+            self.probe = None;
+            return;
+        };
+
+        let values_buffer = ProbePass::create_values_buffer(&self.backend, probe.probes.len());
+
+        let mut entries = input_bind_group_layout_entries();
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding: PROBE_BINDING,
+            // storage buffers can't be written from vertex shaders.
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        });
+        let input_bind_group_layout =
+            self.backend
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("probe input bind group layout"),
+                    entries: &entries,
+                });
+
+        // the inputs are shared with the bottom layer, which keeps them up to date.
+        let input_bind_group = self
+            .backend
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &input_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: pipeline.input_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: PARAMS_BINDING,
+                        resource: pipeline.params_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: CAMERA_BINDING,
+                        resource: self.camera_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: PROBE_BINDING,
+                        resource: values_buffer.as_entire_binding(),
+                    },
+                ],
+                label: Some("probe input bind group"),
+            });
+
+        let uses_mesh = pipeline.uses_mesh;
+        let depth_format = pipeline.depth_format;
+        let bind_group_count = bind_group_count(&probe.module, NUM_BIND_GROUPS);
+        let shader = self
+            .backend
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("probe shader"),
+                source: wgpu::ShaderSource::Naga(Cow::Owned(probe.module)),
+            });
+        let probe_pipeline = self.create_render_pipeline(
+            &shader,
+            &pipeline.entry_points,
+            &input_bind_group_layout,
+            bind_group_count,
+            uses_mesh,
+            depth_format,
+        );
+
+        self.probe = Some(ProbePass::new(
+            &self.backend,
+            probe.probes,
+            probe_pipeline,
+            input_bind_group,
+            uses_mesh,
+            values_buffer,
+        ));
+    }
+
+    /// Sets the parameters of the bottom layer.
+    pub fn set_params(&mut self, data: &[u8]) {
+        let Some(pipeline) = &self.layers[0].pipeline
+        else {
+            return;
+        };
+
+        let size = usize::try_from(pipeline.params_buffer.size())
+            .unwrap()
+            .min(data.len());
+        let mut data = data[..size].to_vec();
+        // writes must be a multiple of 4 bytes
+        data.resize(size.next_multiple_of(4), 0);
+
+        self.backend
+            .queue
+            .write_buffer(&pipeline.params_buffer, 0, &data);
+    }
+
+    pub fn set_channel(&mut self, index: usize, image: Option<ChannelImage>) -> Result<(), Error> {
+        if index >= NUM_CHANNELS {
+            return Err(Error::InvalidChannel { index });
+        }
+
+        if let Some(image) = &image {
+            self.check_channel_image(index, image.width, image.height, image.texture_bytes())?;
+        }
+
+        self.depth_channels[index] = None;
+        self.channels.set(&self.backend, index, image);
+        Ok(())
+    }
+
+    #[cfg(feature = "editor")]
+    pub fn set_channel_compressed(
+        &mut self,
+        index: usize,
+        image: compressed::CompressedImage,
+    ) -> Result<(), Error> {
+        if index >= NUM_CHANNELS {
+            return Err(Error::InvalidChannel { index });
+        }
+
+        if !self
+            .backend
+            .device
+            .features()
+            .contains(image.format.required_features())
+        {
+            return Err(Error::UnsupportedTextureFormat {
+                format: image.format,
+            });
+        }
+        self.check_channel_image(index, image.width, image.height, image.texture_bytes())?;
+
+        self.depth_channels[index] = None;
+        self.channels.set_compressed(&self.backend, index, image);
+        Ok(())
+    }
+
+    #[cfg(feature = "editor")]
+    pub fn set_channel_hdr(
+        &mut self,
+        index: usize,
+        image: &hdr::HdrImage,
+        exposure: f32,
+    ) -> Result<(), Error> {
+        if index >= NUM_CHANNELS {
+            return Err(Error::InvalidChannel { index });
+        }
+
+        self.check_channel_image(index, image.width, image.height, image.texture_bytes())?;
+
+        self.depth_channels[index] = None;
+        self.channels.set_hdr(&self.backend, index, image, exposure);
+        Ok(())
+    }
+
+    /// Fails if an image of this size can't be bound to a channel, because
+    /// it's larger than the device supports or would exceed the memory budget.
+    fn check_channel_image(
+        &self,
+        index: usize,
+        width: u32,
+        height: u32,
+        bytes: u64,
+    ) -> Result<(), Error> {
+        let max = self.backend.device.limits().max_texture_dimension_2d;
+        if width > max || height > max {
+            return Err(Error::ImageTooLarge { width, height, max });
+        }
+
+        self.check_memory_budget(bytes.saturating_sub(self.channels.channel_memory_bytes(index)))
+    }
+
+    pub fn set_channel_video(
+        &mut self,
+        index: usize,
+        video: Option<web_sys::HtmlVideoElement>,
+    ) -> Result<(), Error> {
+        if index >= NUM_CHANNELS {
+            return Err(Error::InvalidChannel { index });
+        }
+
+        self.depth_channels[index] = None;
+        self.channels.set_video(&self.backend, index, video);
+        Ok(())
+    }
+
+    #[cfg(feature = "editor")]
+    pub fn set_channel_audio(
+        &mut self,
+        index: usize,
+        analyser: Option<web_sys::AnalyserNode>,
+    ) -> Result<(), Error> {
+        if index >= NUM_CHANNELS {
+            return Err(Error::InvalidChannel { index });
+        }
+
+        self.depth_channels[index] = None;
+        self.channels.set_audio(&self.backend, index, analyser);
+        Ok(())
+    }
+
+    pub fn set_channel_depth(&mut self, index: usize, layer: Option<usize>) -> Result<(), Error> {
+        if index >= NUM_CHANNELS {
+            return Err(Error::InvalidChannel { index });
+        }
+
+        if layer.is_some() {
+            let (size, bytes) = self.depth_channel_size();
+            self.check_channel_image(index, size.width, size.height, bytes)?;
+            if self.depth_resolver.is_none() {
+                self.depth_resolver = Some(DepthResolver::new(&self.backend));
+            }
+        }
+
+        self.depth_channels[index] = layer;
+        let size = layer.map(|_| self.depth_channel_size().0);
+        self.channels.set_depth(&self.backend, index, size);
+        Ok(())
+    }
+
+    /// Size of channels that show a depth buffer, which is the size of the
+    /// rendered frame, and of their texture in bytes.
+    fn depth_channel_size(&self) -> (wgpu::Extent3d, u64) {
+        let size = wgpu::Extent3d {
+            width: self.render_configuration.width,
+            height: self.render_configuration.height,
+            depth_or_array_layers: 1,
+        };
+        let block_size = depth::channel_format(&self.backend)
+            .block_copy_size(None)
+            .unwrap_or(4);
+        let bytes = u64::from(block_size) * u64::from(size.width) * u64::from(size.height);
+        (size, bytes)
+    }
+
+    /// Recreates the channels that show a depth buffer with the size of the
+    /// rendered frame.
+    fn recreate_depth_channels(&mut self) {
+        let (size, _) = self.depth_channel_size();
+        for (index, layer) in self.depth_channels.iter().enumerate() {
+            if layer.is_some() {
+                self.channels.set_depth(&self.backend, index, Some(size));
+            }
+        }
+    }
+
+    pub fn set_channel_cubemap(&mut self, index: usize, is_cubemap: bool) -> Result<(), Error> {
+        if index >= NUM_CHANNELS {
+            return Err(Error::InvalidChannel { index });
+        }
+
+        if is_cubemap {
+            // the cubemap is written by a compute shader.
+            if self
+                .backend
+                .device
+                .limits()
+                .max_storage_textures_per_shader_stage
+                == 0
+            {
+                return Err(Error::CubemapUnsupported);
+            }
+            self.check_memory_budget(self.channels.cubemap_bytes(&self.backend, index))?;
+        }
+
+        self.channels.set_cubemap(&self.backend, index, is_cubemap);
+        Ok(())
+    }
+
+    /// What the window's textures and buffers take.
+    fn memory_usage(&self) -> MemoryUsage {
+        let layer_targets = self
+            .layers
+            .iter()
+            .filter_map(|layer| layer.target.as_ref())
+            .map(|target| target.memory_bytes())
+            .sum::<u64>();
+        let depth = self
+            .layers
+            .iter()
+            .filter_map(|layer| layer.depth.as_ref())
+            .map(|depth| depth.memory_bytes())
+            .sum::<u64>();
+
+        let pipelines = self
+            .layers
+            .iter()
+            .filter_map(|layer| layer.pipeline.as_ref())
+            .map(|pipeline| pipeline.input_buffer.size() + pipeline.params_buffer.size())
+            .sum::<u64>();
+        #[cfg(feature = "mesh")]
+        let mesh = self.mesh.as_ref().map_or(0, |mesh| mesh.memory_bytes());
+        #[cfg(not(feature = "mesh"))]
+        let mesh = 0;
+        let sequence = self
+            .sequence
+            .as_ref()
+            .map_or(0, |sequence| sequence.memory_bytes());
+
+        MemoryUsage {
+            frame: self.feedback.memory_bytes() + layer_targets + depth,
+            channels: self.channels.memory_bytes(),
+            buffers: pipelines + mesh + sequence,
+            budget: self.memory_budget,
+        }
+    }
+
+    /// Fails if allocating `additional` bytes would exceed the memory budget.
+    fn check_memory_budget(&self, additional: u64) -> Result<(), Error> {
+        let required = self.memory_usage().total() + additional;
+        if required > self.memory_budget {
+            Err(Error::OverMemoryBudget {
+                required,
+                budget: self.memory_budget,
+            })
+        }
+        else {
+            Ok(())
+        }
+    }
+
+    pub fn set_channel_sampler(&mut self, index: usize, sampler: ChannelSampler) {
+        if index < NUM_CHANNELS {
+            self.channels.set_sampler(&self.backend, index, sampler);
+        }
+    }
+
+    pub fn resize(&mut self, surface_size: SurfaceSize) {
+        if self.surface_configuration.width == surface_size.width
+            && self.surface_configuration.height == surface_size.height
+        {
+            return;
+        }
+
+        self.surface_configuration.width = surface_size.width;
+        self.surface_configuration.height = surface_size.height;
+        self.surface
+            .configure(&self.backend.device, &self.surface_configuration);
+        self.recreate_render_targets();
+
+        // keep the aspect ratio correct, even if paused
+        self.update_input_uniform();
+        self.render();
+    }
+
+    /// Recreates everything that has the size of the rendered frame, after the
+    /// surface was resized or the resolution scale changed. This clears the
+    /// previous frame.
+    fn recreate_render_targets(&mut self) {
+        let (width, height) = if let Some(sequence) = &self.sequence {
+            (sequence.options.width, sequence.options.height)
+        }
+        else {
+            self.resolution.render_size(
+                self.surface_configuration.width,
+                self.surface_configuration.height,
+                self.backend.device.limits().max_texture_dimension_2d,
+            )
+        };
+        self.render_configuration = wgpu::SurfaceConfiguration {
+            width,
+            height,
+            ..self.surface_configuration.clone()
+        };
+
+        for layer in &mut self.layers {
+            layer.target = None;
+            layer.depth = None;
+        }
+        self.feedback
+            .recreate(&self.backend, &self.blitter, &self.render_configuration);
+        self.recreate_depth_channels();
+    }
+
+    fn seek_time(&mut self, time: f32) {
+        self.playback.time = time.max(0.0);
+        self.previous_frame_time = Instant::now();
+        if let Some(sound) = &mut self.sound {
+            sound.seek(self.playback.time);
+        }
+        self.update_input_uniform();
+        self.render();
+    }
+
+    fn set_render_scale(&mut self, scale: f32) {
+        self.resolution.set_user_scale(scale);
+        self.recreate_render_targets();
+        self.update_input_uniform();
+        self.render();
+    }
+
+    fn set_output_encoding(&mut self, encoding: OutputEncoding) {
+        let output_format = output_format(&self.surface_configuration, encoding)
+            .unwrap_or_else(|| {
+                tracing::warn!(
+                    window_id = ?self.window_id,
+                    ?encoding,
+                    surface_format = ?self.surface_configuration.format,
+                    "the surface can't be drawn onto with this encoding"
+                );
+                self.surface_configuration.format
+            });
+
+        if output_format != self.output_format {
+            self.output_format = output_format;
+            self.blitter.set_target_format(&self.backend, output_format);
+            self.render();
+        }
+    }
+
+    pub fn update(&mut self) {
+        // update timing information
+        let now = Instant::now();
+        self.fps.push(now);
+        self.delta_time = now.duration_since(self.previous_frame_time).as_secs_f32();
+        self.playback.advance(self.delta_time);
+        self.previous_frame_time = now;
+
+        self.update_input_uniform();
+
+        if let Some(sound) = &mut self.sound {
+            sound.update(&self.backend, self.playback.time);
+        }
+    }
+
+    fn update_input_uniform(&mut self) {
+        let width = self.surface_configuration.width as f32;
+        let height = self.surface_configuration.height as f32;
+        let render_width = self.render_configuration.width as f32;
+        let render_height = self.render_configuration.height as f32;
+        // pixel coordinates are in the rendered frame, which might be scaled.
+        let scale = self.resolution.scale();
+        self.input_uniform = InputUniform {
+            time: self.playback.time,
+            aspect: width / height,
+            mouse: self
+                .mouse_position
+                .map(|pos| [pos[0] / width * 2.0 - 1.0, pos[1] / height * 2.0 - 1.0])
+                .unwrap_or_default(),
+            mouse_click: self.mouse_button.as_uniform().map(|x| x * scale),
+            resolution: [render_width, render_height],
+            frame: self.playback.frame,
+            delta_time: self.delta_time,
+            scroll: self.scroll,
+            zoom: zoom(self.scroll),
+            _padding: 0.0,
+            rotation: self.rotation,
+            pixel_aspect: (width / render_width) / (height / render_height),
+            _padding_v5: [0.0; 3],
+        };
+    }
+
+    fn has_pipeline(&self) -> bool {
+        self.layers.iter().any(|layer| layer.pipeline.is_some())
+    }
+
+    fn set_diff(&mut self, enabled: bool) {
+        if !enabled {
+            self.diff = None;
+        }
+        else if self.diff.is_none() {
+            self.diff = Some(Diff {
+                pass: DiffPass::new(&self.backend, self.surface_configuration.format),
+                previous: None,
+            });
+        }
+    }
+
+    /// Whether the window's frames change on their own, i.e. it's playing and
+    /// a layer is animated, it generates sound or a channel shows a video or
+    /// audio.
+    fn is_animated(&self) -> bool {
+        !self.playback.paused
+            && (self.sound.is_some()
+                || self.channels.has_stream()
+                || self.layers.iter().any(|layer| {
+                    !layer.disabled
+                        && layer
+                            .pipeline
+                            .as_ref()
+                            .is_some_and(|pipeline| pipeline.is_animated)
+                }))
+    }
+
+    /// Whether the window should render frames.
+    fn wants_frame(&self) -> bool {
+        !self.render_on_demand
+            || self.frame_requested
+            || self.is_animated()
+            || self.channels.is_uploading()
+    }
+
+    /// Whether the next frame should be rendered on this tick of the render
+    /// interval. Windows with a lower target frame rate than the interval skip
+    /// ticks.
+    fn is_frame_due(&self, now: Instant, render_period: Duration) -> bool {
+        // ticks are late as often as they are early, so a frame is due if this tick
+        // is closer to it than the next one.
+        now.duration_since(self.previous_frame_time) + render_period / 2
+            >= self.target_fps.period()
+    }
+
+    /// Returns the layer, adding empty layers up to it if necessary.
+    fn layer_mut(&mut self, layer: usize) -> &mut Layer {
+        if layer >= self.layers.len() {
+            self.layers.resize_with(layer + 1, Default::default);
+        }
+        &mut self.layers[layer]
+    }
+
+    fn move_layer(&mut self, from: usize, to: usize) {
+        if from == 0 || to == 0 || from >= self.layers.len() {
+            return;
+        }
+        let to = to.min(self.layers.len() - 1);
+
+        let moved = self.layers.remove(from);
+        self.layers.insert(to, moved);
+
+        // masks refer to layers by index.
+        let new_index = |index: usize| {
+            if index == from {
+                to
+            }
+            else if from < index && index <= to {
+                index - 1
+            }
+            else if to <= index && index < from {
+                index + 1
+            }
+            else {
+                index
+            }
+        };
+        for layer in &mut self.layers {
+            if let Some(mask) = &mut layer.mask {
+                mask.source = new_index(mask.source);
+            }
+        }
+        for source in self.depth_channels.iter_mut().flatten() {
+            *source = new_index(*source);
+        }
+    }
+
+    fn remove_layer(&mut self, layer: usize) {
+        if layer < self.layers.len() {
+            self.layers.remove(layer);
+        }
+        // the probe pass shares the bottom layer's inputs, and the diff compares
+        // against its previous version.
+        if layer == 0 {
+            self.probe = None;
+            if let Some(diff) = &mut self.diff {
+                diff.previous = None;
+            }
+        }
+        // masks refer to layers by index.
+        for other in &mut self.layers {
+            other.mask = other.mask.and_then(|mask| {
+                match mask.source.cmp(&layer) {
+                    std::cmp::Ordering::Less => Some(mask),
+                    std::cmp::Ordering::Equal => None,
+                    std::cmp::Ordering::Greater => {
+                        Some(LayerMask {
+                            source: mask.source - 1,
+                            ..mask
+                        })
+                    }
+                }
+            });
+        }
+        for (index, source) in self.depth_channels.iter_mut().enumerate() {
+            match source.map(|source| source.cmp(&layer)) {
+                Some(std::cmp::Ordering::Equal) => {
+                    *source = None;
+                    self.channels.set_depth(&self.backend, index, None);
+                }
+                Some(std::cmp::Ordering::Greater) => *source = source.map(|source| source - 1),
+                _ => {}
+            }
+        }
+        if self.layers.is_empty() {
+            self.layers.push(Layer::default());
+        }
+        self.update_uses_camera();
+        self.update_uses_scroll();
+        self.update_uses_orientation();
+    }
+
+    fn update_uses_camera(&mut self) {
+        let uses_camera = self.layers.iter().any(|layer| {
+            layer
+                .pipeline
+                .as_ref()
+                .is_some_and(|pipeline| pipeline.uses_camera)
+        });
+        self.uses_camera.set(uses_camera);
+    }
+
+    fn update_uses_scroll(&mut self) {
+        let uses_scroll = self.layers.iter().any(|layer| {
+            layer
+                .pipeline
+                .as_ref()
+                .is_some_and(|pipeline| pipeline.uses_scroll)
+        });
+        self.uses_scroll.set(uses_scroll);
+    }
+
+    fn update_uses_orientation(&mut self) {
+        let uses_orientation = self.layers.iter().any(|layer| {
+            layer
+                .pipeline
+                .as_ref()
+                .is_some_and(|pipeline| pipeline.uses_orientation)
+        });
+        self.uses_orientation.set(uses_orientation);
+    }
+
+    fn set_on_frame_texture(
+        &mut self,
+        on_frame_texture: Option<Box<dyn FnMut(FrameTexture<'_>) + 'static>>,
+    ) {
+        self.on_frame_texture = on_frame_texture;
+    }
+
+    fn set_mouse_position(&mut self, position: Option<[f32; 2]>) {
+        self.mouse_position = position;
+        if let (Some(position), true) = (position, self.mouse_button.pressed) {
+            if self.uses_camera.get() {
+                let [x, y] = self.mouse_button.drag_position;
+                self.camera.orbit([position[0] - x, position[1] - y]);
+            }
+            self.mouse_button.drag_position = position;
+        }
+    }
+
+    fn set_mouse_button(&mut self, pressed: bool, position: [f32; 2]) {
+        if pressed && !self.mouse_button.pressed {
+            self.mouse_button.click_position = position;
+            self.mouse_button.clicked = true;
+        }
+        self.mouse_button.pressed = pressed;
+        self.mouse_button.drag_position = position;
+    }
+
+    /// Renders a frame on its own.
+    pub fn render(&mut self) {
+        if self.sequence.is_some() {
+            return;
+        }
+
+        let mut encoder =
+            self.backend
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("render encoder"),
+                });
+
+        if let Some(target_texture) = self.encode_frame(&mut encoder) {
+            self.backend.queue.submit([encoder.finish()]);
+            target_texture.present();
+            self.frame_presented();
+        }
+    }
+
+    /// Records the commands to render a frame into `encoder`.
+    ///
+    /// Returns the surface texture, which must be presented after the encoder
+    /// was submitted. Returns `None` if there is no pipeline, or the frame is
+    /// skipped because there is no surface texture.
+    fn encode_frame(&mut self, encoder: &mut wgpu::CommandEncoder) -> Option<wgpu::SurfaceTexture> {
+        if !self.has_pipeline() {
+            return None;
+        }
+
+        let target_texture = self.acquire_surface_texture()?;
+
+        let num_layers = self.layers.len();
+        let gpu_timer = self
+            .gpu_timer
+            .as_mut()
+            .filter(|gpu_timer| gpu_timer.is_ready());
+        let timed = gpu_timer.is_some();
+        if let Some(gpu_timer) = gpu_timer {
+            gpu_timer.begin(&self.backend, encoder, num_layers);
+        }
+
+        self.prepare_frame(encoder);
+
+        let target_view = target_texture
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor {
+                format: Some(self.output_format),
+                ..Default::default()
+            });
+
+        if self.encode_diff(encoder) {
+            #[cfg(feature = "editor")]
+            self.copy_pixels(encoder);
+            self.blitter
+                .blit(encoder, &self.feedback.current().blit_source, &target_view);
+            self.end_gpu_timer(encoder, timed);
+            return Some(target_texture);
+        }
+
+        self.encode_layers(encoder);
+
+        self.encode_probe_pass(encoder);
+
+        #[cfg(feature = "editor")]
+        self.copy_pixels(encoder);
+
+        self.blitter
+            .blit(encoder, &self.feedback.current().blit_source, &target_view);
+        self.end_gpu_timer(encoder, timed);
+
+        Some(target_texture)
+    }
+
+    /// Copies the pixels requested with [`WindowHandle::read_pixel`] out of the
+    /// frame that was just drawn.
+    #[cfg(feature = "editor")]
+    fn copy_pixels(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if self.pixel_reader.is_requested() {
+            self.pixel_reader
+                .copy(&self.backend, encoder, &self.feedback.current().texture);
+        }
+    }
+
+    /// Passes an uncaptured error of the window's device to `on_device_error`,
+    /// unless it was just reported.
+    fn report_device_error(&mut self, message: &str) {
+        if self.device_error.as_deref() == Some(message) {
+            return;
+        }
+        self.device_error = Some(message.to_owned());
+
+        if let Some(on_device_error) = &mut self.on_device_error {
+            on_device_error(Error::Device {
+                message: message.to_owned(),
+            });
+        }
+    }
+
+    /// Gets the texture to render the next frame to.
+    ///
+    /// A lost or outdated surface is reconfigured, and then tried again once.
+    /// Failures are reported once they persist for [`MAX_SURFACE_FAILURES`]
+    /// frames.
+    fn acquire_surface_texture(&mut self) -> Option<wgpu::SurfaceTexture> {
+        let result = match self.surface.get_current_texture() {
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                tracing::debug!(window_id = ?self.window_id, "reconfiguring surface");
+                self.surface
+                    .configure(&self.backend.device, &self.surface_configuration);
+                self.surface.get_current_texture()
+            }
+            result => result,
+        };
+
+        match result {
+            Ok(texture) => {
+                if self.surface_failures >= MAX_SURFACE_FAILURES {
+                    tracing::info!(window_id = ?self.window_id, "surface recovered");
+                    if let Some(on_surface_error) = &mut self.on_surface_error {
+                        on_surface_error(None);
+                    }
+                }
+                self.surface_failures = 0;
+                Some(texture)
+            }
+            Err(error) => {
+                self.surface_failures += 1;
+                if error == wgpu::SurfaceError::Timeout {
+                    tracing::debug!(window_id = ?self.window_id, "surface timed out, skipping frame");
+                }
+                if self.surface_failures == MAX_SURFACE_FAILURES {
+                    tracing::error!(window_id = ?self.window_id, ?error, "failed to get surface texture");
+                    if let Some(on_surface_error) = &mut self.on_surface_error {
+                        on_surface_error(Some(error.into()));
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    /// Attributes the GPU time since the last timestamp to a layer, if this
+    /// frame is measured.
+    fn mark_gpu_timer(&self, encoder: &mut wgpu::CommandEncoder, layer: usize) {
+        if let Some(gpu_timer) = &self.gpu_timer {
+            gpu_timer.mark(encoder, Some(layer));
+        }
+    }
+
+    /// Records the end timestamp, if this frame is measured.
+    fn end_gpu_timer(&mut self, encoder: &mut wgpu::CommandEncoder, timed: bool) {
+        if let Some(gpu_timer) = self.gpu_timer.as_mut().filter(|_| timed) {
+            gpu_timer.end(encoder);
+        }
+    }
+
+    /// Updates everything the layers read, before they're drawn.
+    fn prepare_frame(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        self.prepare_layer_targets();
+        self.feedback.swap();
+        self.keyboard.flush(&self.backend);
+        self.channels.update_streams(&self.backend);
+        // exported frames must not depend on how fast images are uploaded.
+        let upload_bytes = if self.sequence.is_some() {
+            u64::MAX
+        }
+        else {
+            UPLOAD_BYTES_PER_FRAME
+        };
+        self.channels.upload(&self.backend, encoder, upload_bytes);
+        // exported sequences can have a different aspect ratio than the surface.
+        let aspect = self.input_uniform.aspect;
+        self.backend.queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&self.camera.as_uniform(aspect)),
+        );
+    }
+
+    /// Records drawing and compositing the layers into the current frame
+    /// target.
+    fn encode_layers(&self, encoder: &mut wgpu::CommandEncoder) {
+        let frame_view = &self.feedback.current().view;
+
+        // layers that are composited are drawn first, so that any layer can be used
+        // as a mask by any other.
+        for (index, layer) in self.layers.iter().enumerate() {
+            let Some(pipeline) = &layer.pipeline
+            else {
+                continue;
+            };
+            // the target keeps the layer's last output.
+            if layer.disabled {
+                continue;
+            }
+
+            self.backend.queue.write_buffer(
+                &pipeline.input_buffer,
+                0,
+                bytemuck::bytes_of(&self.input_uniform),
+            );
+
+            if let Some(layer_target) = &layer.target {
+                self.draw_pipeline(
+                    encoder,
+                    pipeline,
+                    &layer_target.view,
+                    wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    index,
+                );
+                self.resolve_depth(encoder, index);
+                self.mark_gpu_timer(encoder, index);
+            }
+        }
+
+        // the first layer that is drawn clears the frame.
+        let mut load = wgpu::LoadOp::Clear(wgpu::Color::BLACK);
+        let mask_sources = self.mask_sources();
+
+        for (index, layer) in self.layers.iter().enumerate() {
+            let Some(pipeline) = &layer.pipeline
+            else {
+                continue;
+            };
+            if mask_sources.contains(&index) {
+                continue;
+            }
+
+            if let Some(layer_target) = &layer.target {
+                let mask = layer.mask.and_then(|mask| {
+                    let mask_target = self.layers.get(mask.source)?.target.as_ref()?;
+                    Some((mask, mask_target))
+                });
+                self.compositor.composite(
+                    &self.backend,
+                    encoder,
+                    layer_target,
+                    layer.blend,
+                    layer.transform,
+                    mask,
+                    frame_view,
+                    load,
+                );
+            }
+            else if !layer.disabled {
+                self.draw_pipeline(encoder, pipeline, frame_view, load, index);
+                self.resolve_depth(encoder, index);
+            }
+            else {
+                continue;
+            }
+            self.mark_gpu_timer(encoder, index);
+
+            load = wgpu::LoadOp::Load;
+        }
+
+        if let wgpu::LoadOp::Clear(color) = load {
+            // nothing was drawn, but the frame still holds the one from before the
+            // previous frame.
+            clear_texture(encoder, frame_view, color);
+        }
+    }
+
+    fn start_sequence(
+        &mut self,
+        options: SequenceOptions,
+        tx_frame: mpsc::UnboundedSender<SequenceFrame>,
+    ) -> Result<(), Error> {
+        if !self.has_pipeline() {
+            return Err(Error::NotRendering);
+        }
+        let invalid = |message: &str| {
+            Err(Error::InvalidSequence {
+                message: message.to_owned(),
+            })
+        };
+        if self.sequence.is_some() {
+            return invalid("a sequence is already being exported");
+        }
+        if options.num_frames() == 0 {
+            return invalid("the sequence has no frames");
+        }
+        if options.width == 0 || options.height == 0 {
+            return invalid("the resolution is empty");
+        }
+        let max = self.backend.device.limits().max_texture_dimension_2d;
+        if options.width > max || options.height > max {
+            return Err(Error::ImageTooLarge {
+                width: options.width,
+                height: options.height,
+                max,
+            });
+        }
+        // frames are read back as 8 bit RGBA.
+        if self.surface_configuration.format.block_copy_size(None) != Some(4) {
+            return invalid("the surface format can't be exported");
+        }
+
+        let readback_buffer_bytes = SequenceExport::readback_buffer_bytes(&options);
+        if readback_buffer_bytes > self.backend.device.limits().max_buffer_size {
+            return invalid("the resolution is too large to be read back");
+        }
+        let readback_bytes = FRAMES_IN_FLIGHT as u64 * readback_buffer_bytes;
+        // the frame textures are recreated at the sequence's resolution.
+        let usage = self.memory_usage();
+        let render_pixels =
+            self.render_configuration.width as u64 * self.render_configuration.height as u64;
+        let sequence_pixels = options.width as u64 * options.height as u64;
+        let frame_bytes = usage.frame * sequence_pixels / render_pixels.max(1);
+        self.check_memory_budget(
+            (readback_bytes + frame_bytes).saturating_sub(usage.frame),
+        )?;
+
+        self.sequence = Some(SequenceExport::new(
+            &self.backend,
+            options,
+            self.surface_configuration.format,
+            tx_frame,
+            self.playback.time,
+            self.playback.frame,
+        ));
+        self.recreate_render_targets();
+        Ok(())
+    }
+
+    /// Renders the frames of the sequence that can be read back now, and
+    /// finishes the export when all frames were read back.
+    fn render_sequence(&mut self) {
+        while let Some((index, options)) = self
+            .sequence
+            .as_ref()
+            .and_then(|sequence| Some((sequence.next_frame()?, sequence.options)))
+        {
+            self.playback.time = options.frame_time(index);
+            self.playback.frame = index as u32;
+            self.delta_time = 1.0 / options.fps;
+            self.input_uniform = InputUniform {
+                time: self.playback.time,
+                aspect: options.width as f32 / options.height as f32,
+                mouse: [0.0; 2],
+                mouse_click: [0.0; 4],
+                resolution: [options.width as f32, options.height as f32],
+                frame: self.playback.frame,
+                delta_time: self.delta_time,
+                // the zoom is part of the framing the user picked.
+                scroll: self.scroll,
+                zoom: zoom(self.scroll),
+                _padding: 0.0,
+                rotation: self.rotation,
+                // the frames are the output, so their pixels are square.
+                pixel_aspect: 1.0,
+                _padding_v5: [0.0; 3],
+            };
+
+            // every frame is submitted on its own, since the input uniform is written
+            // with the queue.
+            let mut encoder =
+                self.backend
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                        label: Some("sequence encoder"),
+                    });
+            self.prepare_frame(&mut encoder);
+            self.encode_layers(&mut encoder);
+            if let Some(sequence) = &mut self.sequence {
+                sequence.copy_frame(&mut encoder, &self.feedback.current().texture);
+            }
+            self.backend.queue.submit([encoder.finish()]);
+            if let Some(sequence) = &self.sequence {
+                sequence.read_back();
+            }
+        }
+
+        if let Some(sequence) = self.sequence.take_if(|sequence| sequence.is_finished()) {
+            self.playback.time = sequence.saved_time;
+            self.playback.frame = sequence.saved_frame;
+            self.previous_frame_time = Instant::now();
+            self.recreate_render_targets();
+            self.update_input_uniform();
+        }
+    }
+
+    /// Indices of the layers that are used as masks.
+    fn mask_sources(&self) -> Vec<usize> {
+        self.layers
+            .iter()
+            .filter_map(|layer| layer.mask.map(|mask| mask.source))
+            .collect()
+    }
+
+    /// Creates the textures for layers that need to be composited or are used
+    /// as masks, and drops them for the others.
+    ///
+    /// Only the bottom layer can be drawn directly, and only if it's opaque,
+    /// unmasked and not transformed.
+    ///
+    /// Also creates the depth buffers of layers whose pipeline is depth tested.
+    fn prepare_layer_targets(&mut self) {
+        for layer in &mut self.layers {
+            let depth_format = layer
+                .pipeline
+                .as_ref()
+                .and_then(|pipeline| pipeline.depth_format);
+            let Some(depth_format) = depth_format
+            else {
+                layer.depth = None;
+                continue;
+            };
+            if layer
+                .depth
+                .as_ref()
+                .map_or(true, |depth| depth.format() != depth_format)
+            {
+                layer.depth = Some(DepthTarget::new(
+                    &self.backend,
+                    &self.render_configuration,
+                    depth_format,
+                ));
+            }
+        }
+
+        let mask_sources = self.mask_sources();
+        let mut is_bottom = true;
+
+        for (index, layer) in self.layers.iter_mut().enumerate() {
+            if layer.pipeline.is_none() {
+                layer.target = None;
+                continue;
+            }
+            // a disabled layer keeps its target with its last output. without one,
+            // it's skipped.
+            if layer.disabled {
+                if layer.target.is_some() && !mask_sources.contains(&index) {
+                    is_bottom = false;
+                }
+                continue;
+            }
+
+            let is_mask_source = mask_sources.contains(&index);
+            let is_direct = is_bottom
+                && !is_mask_source
+                && layer.mask.is_none()
+                && layer.transform.is_identity()
+                && layer.blend.is_opaque_normal();
+
+            if is_direct {
+                layer.target = None;
+            }
+            else if layer.target.is_none() {
+                layer.target = Some(LayerTarget::new(&self.backend, &self.render_configuration));
+            }
+
+            if !is_mask_source {
+                is_bottom = false;
+            }
+        }
+    }
+
+    /// Draws the difference between the current and the previous version of the
+    /// bottom layer's shader into the frame. Returns `false` if there's nothing
+    /// to compare, and the layers should be drawn instead.
+    fn encode_diff(&mut self, encoder: &mut wgpu::CommandEncoder) -> bool {
+        let Some(diff) = &mut self.diff
+        else {
+            return false;
+        };
+        if diff.previous.is_none() || self.layers[0].pipeline.is_none() {
+            return false;
+        }
+        diff.pass.prepare(&self.backend, &self.render_configuration);
+
+        let diff = self.diff.as_ref().unwrap();
+        let (Some(current), Some(previous), Some((current_view, previous_view))) = (
+            &self.layers[0].pipeline,
+            &diff.previous,
+            diff.pass.views(),
+        )
+        else {
+            return false;
+        };
+
+        for pipeline in [current, previous] {
+            self.backend.queue.write_buffer(
+                &pipeline.input_buffer,
+                0,
+                bytemuck::bytes_of(&self.input_uniform),
+            );
+        }
+        let clear = wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT);
+        self.draw_pipeline(encoder, current, current_view, clear, 0);
+        self.draw_pipeline(encoder, previous, previous_view, clear, 0);
+        diff.pass.draw(encoder, &self.feedback.current().view);
+
+        true
+    }
+
+    /// Draws the probe pass for the pixel under the mouse, if there is one.
+    fn encode_probe_pass(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let (Some(probe), Some([x, y])) = (&mut self.probe, self.mouse_position)
+        else {
+            return;
+        };
+        if !probe.begin(&self.backend, encoder, &self.render_configuration) {
+            return;
+        }
+
+        // the mouse position is in surface pixels.
+        let scale = self.resolution.scale();
+        let pixel_x = ((x * scale) as u32).min(self.render_configuration.width - 1);
+        let pixel_y = ((y * scale) as u32).min(self.render_configuration.height - 1);
+
+        let probe = self.probe.as_ref().unwrap();
+        let mut render_pass = self.begin_render_pass(
+            encoder,
+            probe.view().unwrap(),
+            wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+            self.layers[0].depth.as_ref(),
+            false,
+        );
+        render_pass.set_scissor_rect(pixel_x, pixel_y, 1, 1);
+        self.draw(
+            &mut render_pass,
+            &probe.pipeline,
+            &probe.input_bind_group,
+            probe.uses_mesh,
+        );
+        drop(render_pass);
+
+        self.probe.as_mut().unwrap().end(encoder);
     }
 
-    pub fn set_visibility(&self, visible: bool) {
-        self.graphics.send_command(Command::SetVisibility {
-            window_id: self.window_id,
-            visible,
-        });
-    }
+    /// Draws the pipeline of the layer at index `layer`, with the layer's
+    /// depth buffer if it's depth tested.
+    fn draw_pipeline(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &Pipeline,
+        target: &wgpu::TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+        layer: usize,
+    ) {
+        let depth = self.layers[layer]
+            .depth
+            .as_ref()
+            .filter(|depth| Some(depth.format()) == pipeline.depth_format);
+        // the previous pipeline of a diff might use another depth format than the
+        // layer's current one.
+        if pipeline.depth_format.is_some() && depth.is_none() {
+            return;
+        }
 
-    pub fn set_paused(&self, paused: bool) {
-        self.graphics.send_command(Command::SetPaused {
-            window_id: self.window_id,
-            paused,
-        });
+        // the depth only has to be kept if it's copied into a channel afterwards.
+        let store_depth = self.depth_channels.contains(&Some(layer));
+        let mut render_pass = self.begin_render_pass(encoder, target, load, depth, store_depth);
+        self.draw(
+            &mut render_pass,
+            &pipeline.pipeline,
+            &pipeline.input_bind_group,
+            pipeline.uses_mesh,
+        );
     }
 
-    pub fn reset(&self) {
-        self.graphics.send_command(Command::Reset {
-            window_id: self.window_id,
-        });
+    fn begin_render_pass<'encoder>(
+        &self,
+        encoder: &'encoder mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+        depth: Option<&DepthTarget>,
+        store_depth: bool,
+    ) -> wgpu::RenderPass<'encoder> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render3d render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: depth.map(|depth| {
+                wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: if store_depth {
+                            wgpu::StoreOp::Store
+                        }
+                        else {
+                            wgpu::StoreOp::Discard
+                        },
+                    }),
+                    stencil_ops: None,
+                }
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        })
     }
-}
 
-#[derive(Clone, Copy, Debug)]
-pub struct SurfaceSize {
-    pub width: u32,
-    pub height: u32,
-}
+    /// Copies the depth buffer of the layer at `index` into the channels it's
+    /// bound to.
+    fn resolve_depth(&self, encoder: &mut wgpu::CommandEncoder, index: usize) {
+        let (Some(depth), Some(depth_resolver)) = (&self.layers[index].depth, &self.depth_resolver)
+        else {
+            return;
+        };
 
-impl SurfaceSize {
-    pub fn from_html_canvas(canvas: &HtmlCanvasElement) -> Self {
-        Self {
-            width: canvas.width().max(1),
-            height: canvas.height().max(1),
+        for (channel, layer) in self.depth_channels.iter().enumerate() {
+            if *layer != Some(index) {
+                continue;
+            }
+            if let Some(view) = self.channels.view(channel) {
+                depth_resolver.resolve(&self.backend, encoder, depth, view);
+            }
         }
     }
 
-    pub fn from_surface_configuration(surface_configuration: &wgpu::SurfaceConfiguration) -> Self {
-        Self {
-            width: surface_configuration.width,
-            height: surface_configuration.height,
+    /// Draws the fullscreen triangle, or the mesh if `uses_mesh` is set.
+    fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        pipeline: &wgpu::RenderPipeline,
+        input_bind_group: &wgpu::BindGroup,
+        uses_mesh: bool,
+    ) {
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, input_bind_group, &[]);
+        render_pass.set_bind_group(CHANNEL_BIND_GROUP, &self.channels.bind_group, &[]);
+        render_pass.set_bind_group(KEYBOARD_BIND_GROUP, &self.keyboard.bind_group, &[]);
+        render_pass.set_bind_group(
+            FEEDBACK_BIND_GROUP,
+            self.feedback.previous_frame_bind_group(),
+            &[],
+        );
+
+        if !uses_mesh {
+            render_pass.draw(0..3, 0..1);
+        }
+        else {
+            #[cfg(feature = "mesh")]
+            if let Some(mesh) = &self.mesh {
+                mesh.draw(render_pass);
+            }
         }
     }
-}
 
-struct Window {
-    backend: Backend,
-    surface: wgpu::Surface<'static>,
-    surface_configuration: wgpu::SurfaceConfiguration,
-    pipeline: Option<Pipeline>,
-    mouse_position: Option<[f32; 2]>,
-    visible: bool,
-    paused: bool,
-    previous_frame_time: Instant,
-    time: f32,
-    fps: TicksPerSecond,
-    on_frame: Box<dyn FnMut(FrameInfo) + 'static>,
-    input_uniform: InputUniform,
-}
+    fn frame_presented(&mut self) {
+        // the click is only reported for one frame
+        self.mouse_button.clicked = false;
+        self.frame_requested = false;
 
-impl Window {
-    pub fn create_pipeline(&mut self, shader: naga::Module) {
-        let input_buffer = self.backend.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("input buffer"),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-            size: wgpu_buffer_size::<InputUniform>(),
-        });
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.read_back();
+        }
 
-        let input_bind_group_layout =
-            self.backend
-                .device
-                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: Some("input bind group layout"),
-                    entries: &[wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    }],
-                });
+        #[cfg(feature = "editor")]
+        self.pixel_reader.read_back();
 
-        let input_bind_group = self
-            .backend
-            .device
-            .create_bind_group(&wgpu::BindGroupDescriptor {
-                layout: &input_bind_group_layout,
-                entries: &[wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: input_buffer.as_entire_binding(),
-                }],
-                label: Some("input bind group"),
-            });
+        if let Some(probe) = &mut self.probe {
+            probe.read_back();
+            if let (Some(values), Some(on_probe)) = (probe.take_values(), &mut self.on_probe) {
+                on_probe(values);
+            }
+        }
 
-        let shader = self
-            .backend
-            .device
-            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: Some("shader"),
-                source: wgpu::ShaderSource::Naga(Cow::Owned(shader)),
-            });
+        // frames that are rendered on demand don't say anything about how fast the
+        // shader is.
+        let renders_continuously = !self.render_on_demand || self.is_animated();
+        if renders_continuously
+            && self
+                .resolution
+                .update(&self.fps, self.target_fps.resolution_period())
+        {
+            self.recreate_render_targets();
+            self.update_input_uniform();
+        }
 
-        let pipeline_layout =
-            self.backend
-                .device
-                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("Render3dMeshesWithMaterial pipeline layout"),
-                    bind_group_layouts: &[&input_bind_group_layout],
-                    push_constant_ranges: &[],
-                });
+        let memory = self.memory_usage();
+        if memory.is_over_budget() && !self.over_memory_budget {
+            tracing::warn!(
+                total = memory.total(),
+                budget = memory.budget,
+                "window is over its memory budget"
+            );
+        }
+        self.over_memory_budget = memory.is_over_budget();
 
-        let pipeline =
-            self.backend
-                .device
-                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                    label: Some("pipeline"),
-                    layout: Some(&pipeline_layout),
-                    vertex: wgpu::VertexState {
-                        module: &shader,
-                        entry_point: "vs_main",
-                        buffers: &[],
-                        compilation_options: Default::default(),
-                    },
-                    fragment: Some(wgpu::FragmentState {
-                        module: &shader,
-                        entry_point: "fs_main",
-                        targets: &[Some(wgpu::ColorTargetState {
-                            format: self.surface_configuration.format,
-                            blend: Some(wgpu::BlendState::REPLACE),
-                            write_mask: wgpu::ColorWrites::ALL,
-                        })],
-                        compilation_options: Default::default(),
-                    }),
-                    primitive: wgpu::PrimitiveState {
-                        topology: wgpu::PrimitiveTopology::TriangleList,
-                        strip_index_format: None,
-                        front_face: wgpu::FrontFace::Ccw,
-                        cull_mode: Some(wgpu::Face::Back),
-                        polygon_mode: wgpu::PolygonMode::Fill,
-                        unclipped_depth: false,
-                        conservative: false,
-                    },
-                    depth_stencil: None,
-                    multisample: wgpu::MultisampleState {
-                        count: 1,
-                        mask: !0,
-                        alpha_to_coverage_enabled: false,
-                    },
-                    multiview: None,
-                    cache: None,
-                });
+        let PlaybackState { time, frame, .. } = self.playback.state();
+        let info = FrameInfo {
+            time,
+            frame,
+            fps: self.fps.tps().unwrap_or_default(),
+            render_scale: self.resolution.scale(),
+            gpu_times: self
+                .gpu_timer
+                .as_ref()
+                .and_then(|gpu_timer| gpu_timer.times()),
+            memory,
+        };
+        (self.on_frame)(info.clone());
 
-        self.pipeline = Some(Pipeline {
-            pipeline,
-            input_buffer,
-            input_bind_group,
-        });
-    }
+        if let Some(on_frame_texture) = &mut self.on_frame_texture {
+            on_frame_texture(FrameTexture {
+                backend: &self.backend,
+                texture: &self.feedback.current().texture,
+                info,
+            });
+        }
 
-    pub fn resize(&mut self, surface_size: SurfaceSize) {
-        self.surface_configuration.width = surface_size.width;
-        self.surface_configuration.height = surface_size.height;
-        self.surface
-            .configure(&self.backend.device, &self.surface_configuration);
-        self.render();
+        // the canvas still holds the frame until control returns to the browser.
+        for tx_result in self.pending_captures.drain(..) {
+            let result = self
+                .window_id
+                .canvas()
+                .ok_or(Error::CanvasNotFound(self.window_id))
+                .and_then(|canvas| {
+                    canvas.to_data_url().map_err(|error| {
+                        Error::CaptureFrame {
+                            message: format!("{error:?}"),
+                        }
+                    })
+                });
+            let _ = tx_result.send(result);
+        }
     }
+}
 
-    pub fn update(&mut self) {
-        // update timing information
-        let now = Instant::now();
-        self.fps.push(now);
-        self.time += now.duration_since(self.previous_frame_time).as_secs_f32();
-        self.previous_frame_time = now;
+/// A rendered frame, as passed to [`WindowHandle::set_on_frame_texture`].
+///
+/// The texture belongs to `backend`'s device and is rendered into again after
+/// the next frame, so copy it if it's needed for longer.
+pub struct FrameTexture<'a> {
+    pub backend: &'a Backend,
+    pub texture: &'a wgpu::Texture,
+    pub info: FrameInfo,
+}
 
-        // update input uniform
-        let width = self.surface_configuration.width as f32;
-        let height = self.surface_configuration.height as f32;
-        self.input_uniform = InputUniform {
-            time: self.time,
-            aspect: width / height,
-            mouse: self
-                .mouse_position
-                .map(|pos| [pos[0] / width * 2.0 - 1.0, pos[1] / height * 2.0 - 1.0])
-                .unwrap_or_default(),
-        };
+/// Frames of all windows that share a backend, which are submitted together.
+struct BatchedFrame {
+    backend: Backend,
+    encoder: wgpu::CommandEncoder,
+    target_textures: Vec<wgpu::SurfaceTexture>,
+}
+
+impl BatchedFrame {
+    fn new(backend: &Backend) -> Self {
+        let encoder = backend
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("batched render encoder"),
+            });
+        Self {
+            backend: backend.clone(),
+            encoder,
+            target_textures: vec![],
+        }
     }
 
-    pub fn render(&mut self) {
-        if let Some(pipeline) = &mut self.pipeline {
-            self.backend.queue.write_buffer(
-                &pipeline.input_buffer,
-                0,
-                bytemuck::bytes_of(&self.input_uniform),
-            );
+    fn submit(self) {
+        self.backend.queue.submit([self.encoder.finish()]);
+        for target_texture in self.target_textures {
+            target_texture.present();
+        }
+    }
+}
 
-            let target_texture = self
-                .surface
-                .get_current_texture()
-                .expect("could not get target texture");
+fn clear_texture(encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, color: wgpu::Color) {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("clear render pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(color),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    });
+}
 
-            let target_view = target_texture
-                .texture
-                .create_view(&wgpu::TextureViewDescriptor::default());
+/// A shader in a window's layer stack.
+#[derive(Debug, Default)]
+struct Layer {
+    pipeline: Option<Pipeline>,
+    blend: LayerBlend,
+    transform: LayerTransform,
+    mask: Option<LayerMask>,
 
-            let mut encoder =
-                self.backend
-                    .device
-                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                        label: Some("render encoder"),
-                    });
+    /// Texture the layer is drawn into before it's composited. `None` if the
+    /// layer is drawn directly.
+    target: Option<LayerTarget>,
 
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render3d render pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &target_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
+    /// Only exists while the layer's pipeline is depth tested.
+    depth: Option<DepthTarget>,
 
-            render_pass.set_pipeline(&pipeline.pipeline);
-            render_pass.set_bind_group(0, &pipeline.input_bind_group, &[]);
-            render_pass.draw(0..3, 0..1);
-            drop(render_pass);
+    /// See [`WindowHandle::set_layer_enabled`].
+    disabled: bool,
+}
 
-            self.backend.queue.submit([encoder.finish()]);
-            target_texture.present();
+/// See [`WindowHandle::set_diff`].
+#[derive(Debug)]
+struct Diff {
+    pass: DiffPass,
 
-            (self.on_frame)(FrameInfo {
-                time: self.time,
-                fps: self.fps.tps().unwrap_or_default(),
-            });
-        }
-    }
+    /// The bottom layer's pipeline before the shader was last run.
+    previous: Option<Pipeline>,
 }
 
 #[derive(Debug)]
 struct Pipeline {
     pipeline: wgpu::RenderPipeline,
     input_buffer: wgpu::Buffer,
+    params_buffer: wgpu::Buffer,
     input_bind_group: wgpu::BindGroup,
+
+    /// The probe pass is drawn with the same entry points.
+    entry_points: EntryPoints,
+
+    /// Whether the shader takes vertex attributes and is drawn with the
+    /// window's mesh, instead of a fullscreen triangle.
+    uses_mesh: bool,
+
+    /// Format of the layer's depth buffer, if the pipeline is depth tested.
+    depth_format: Option<wgpu::TextureFormat>,
+
+    uses_camera: bool,
+    uses_scroll: bool,
+    uses_orientation: bool,
+
+    /// Whether the shader's output changes from frame to frame, even if its
+    /// inputs don't.
+    is_animated: bool,
+}
+
+/// Entries of the bind group layout in `@group(0)`, with the inputs, params
+/// and camera.
+/// Number of bind groups that shaders can use: the inputs, the channels, the
+/// keyboard and the previous frame.
+const NUM_BIND_GROUPS: usize = 4;
+
+fn input_bind_group_layout_entries() -> Vec<wgpu::BindGroupLayoutEntry> {
+    let uniform_entry = |binding| {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }
+    };
+    vec![
+        uniform_entry(0),
+        uniform_entry(PARAMS_BINDING),
+        uniform_entry(CAMERA_BINDING),
+    ]
 }
 
 pub fn wgpu_buffer_size<T>() -> u64 {
@@ -726,63 +4477,260 @@ pub fn wgpu_buffer_size<T>() -> u64 {
     padded_size
 }
 
+/// The input uniform, in the layout of the latest
+/// [`InputsVersion`](shade_rs_compile::inputs::InputsVersion).
+///
+/// Fields must only ever be appended, so that shaders written against older
+/// versions keep working.
 #[derive(Clone, Copy, Debug, Pod, Zeroable, Default)]
 #[repr(C)]
 pub struct InputUniform {
+    // v1
     pub time: f32,
     pub aspect: f32,
     pub mouse: [f32; 2],
+
+    // v2
+    pub mouse_click: [f32; 4],
+    pub resolution: [f32; 2],
+    pub frame: u32,
+    pub delta_time: f32,
+
+    // v3
+    pub scroll: [f32; 2],
+    pub zoom: f32,
+
+    /// The struct is aligned to 16 bytes in WGSL, so the buffer must be padded.
+    /// Since v4 this aligns `rotation`.
+    pub _padding: f32,
+
+    // v4
+    pub rotation: [f32; 4],
+
+    // v5
+    pub pixel_aspect: f32,
+
+    /// Pads the struct to 16 bytes again.
+    pub _padding_v5: [f32; 3],
 }
 
-fn compile_shader(source: &str) -> Result<naga::Module, CompileError> {
-    let module = naga::front::wgsl::parse_str(source).map_err(|parse_error| {
-        CompileError::Parse {
-            parse_error,
-            code: source.to_owned(),
-        }
-    })?;
-    let mut validator = naga::valid::Validator::new(
-        naga::valid::ValidationFlags::all(),
-        naga::valid::Capabilities::all(),
-    );
-    let _module_info = validator.validate(&module).map_err(|validation_error| {
-        CompileError::Validate {
-            validation_error,
-            code: source.to_owned(),
-        }
-    })?;
-    Ok(module)
+/// How many pixels the mouse wheel has to be scrolled up to double the
+/// [`InputUniform::zoom`].
+const ZOOM_SCROLL_PIXELS: f32 = 500.0;
+
+fn zoom(scroll: [f32; 2]) -> f32 {
+    (-scroll[1] / ZOOM_SCROLL_PIXELS).exp2()
+}
+
+/// [`InputUniform::rotation`] if the device's orientation isn't known.
+const IDENTITY_ROTATION: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+/// Converts the angles of a `DeviceOrientationEvent` to a quaternion.
+///
+/// The angles are in degrees, and are applied intrinsically in the order
+/// `alpha` around z, `beta` around x, and `gamma` around y.
+fn rotation([alpha, beta, gamma]: [f32; 3]) -> [f32; 4] {
+    let half_angle = |degrees: f32| (degrees.to_radians() * 0.5).sin_cos();
+    let (sz, cz) = half_angle(alpha);
+    let (sx, cx) = half_angle(beta);
+    let (sy, cy) = half_angle(gamma);
+    [
+        cz * sx * cy - sz * cx * sy,
+        cz * cx * sy + sz * sx * cy,
+        sz * cx * cy + cz * sx * sy,
+        cz * cx * cy - sz * sx * sy,
+    ]
+}
+
+/// Click and drag state of the primary mouse button.
+#[derive(Clone, Copy, Debug, Default)]
+struct MouseButtonState {
+    pressed: bool,
+
+    /// Set on the first frame after the button was pressed.
+    clicked: bool,
+
+    click_position: [f32; 2],
+
+    /// Position while dragging, or where the last drag ended.
+    drag_position: [f32; 2],
+}
+
+impl MouseButtonState {
+    /// Encodes the state like Shadertoy's `iMouse`, in pixels:
+    ///
+    /// - `xy`: current position while the button is held, the position where
+    ///   the button was released otherwise.
+    /// - `abs(zw)`: position of the last click.
+    /// - `sign(z)`: positive while the button is held.
+    /// - `sign(w)`: positive only on the frame the button was pressed.
+    fn as_uniform(&self) -> [f32; 4] {
+        let [x, y] = self.drag_position;
+        let [click_x, click_y] = self.click_position;
+        [
+            x,
+            y,
+            if self.pressed { click_x } else { -click_x },
+            if self.clicked { click_y } else { -click_y },
+        ]
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum CompileError {
-    Parse {
-        #[source]
-        parse_error: naga::front::wgsl::ParseError,
-        code: String,
+    Compile(#[from] shade_rs_compile::CompileError),
+    MissingRequirements {
+        missing: Vec<Requirement>,
+        backend_type: BackendType,
     },
-    Validate {
-        #[source]
-        validation_error: naga::WithSpan<naga::valid::ValidationError>,
-        code: String,
+    Library(#[from] FetchError),
+    Binding(#[from] BindingError),
+    /// The backend failed to create the pipeline for a shader that passed
+    /// validation.
+    Pipeline {
+        message: String,
+        backend_type: BackendType,
     },
 }
 
+impl CompileError {
+    /// Whether switching to the WebGPU backend would fix this error.
+    pub fn suggests_webgpu(&self) -> bool {
+        match self {
+            Self::MissingRequirements {
+                missing,
+                backend_type,
+            } => {
+                *backend_type != BackendType::WebGpu
+                    && missing.iter().any(|requirement| requirement.needs_webgpu())
+            }
+            // most likely the shader couldn't be translated to GLSL.
+            Self::Pipeline { backend_type, .. } => *backend_type != BackendType::WebGpu,
+            _ => false,
+        }
+    }
+}
+
 impl Display for CompileError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let error_string = match self {
-            CompileError::Parse { parse_error, code } => parse_error.emit_to_string(code),
-            CompileError::Validate {
-                validation_error,
-                code,
-            } => validation_error.emit_to_string(&code),
-        };
-        write!(f, "{error_string}")
+        match self {
+            CompileError::Compile(error) => write!(f, "{error}"),
+            CompileError::Library(error) => write!(f, "error: {error}"),
+            CompileError::Binding(error) => write!(f, "error: {error}"),
+            CompileError::MissingRequirements {
+                missing,
+                backend_type,
+            } => {
+                let missing = missing
+                    .iter()
+                    .map(|requirement| requirement.name())
+                    .collect::<Vec<_>>();
+                write!(
+                    f,
+                    "error: this shader needs {}, which the {backend_type} backend doesn't support",
+                    missing.join(", ")
+                )
+            }
+            CompileError::Pipeline {
+                message,
+                backend_type,
+            } => {
+                write!(
+                    f,
+                    "error: the {backend_type} backend failed to create the pipeline\n\n{message}"
+                )
+            }
+        }
     }
 }
 
+/// Information about a successful [`WindowHandle::run`].
+#[derive(Clone, Debug, Default)]
+pub struct RunInfo {
+    /// Constructs that will fail on the WebGL backend. This is only populated
+    /// when running on WebGPU.
+    pub warnings: Vec<Lint>,
+
+    /// Layout of the user-defined parameters, if the shader declares them.
+    /// Their values are set with [`WindowHandle::set_params`].
+    pub params: Option<ParamsLayout>,
+
+    /// The fragment entry point the shader is drawn with.
+    pub fragment_entry_point: String,
+
+    /// All fragment entry points of the shader, that can be picked with
+    /// [`WindowHandle::run`].
+    pub fragment_entry_points: Vec<String>,
+
+    /// Defines the shader declared. Their values are set with
+    /// [`CompileOptions::defines`].
+    pub defines: Vec<Define>,
+
+    /// Whether the window is still paused, because it was restored paused.
+    /// Otherwise running a shader plays it.
+    pub paused: bool,
+
+    pub timings: CompileTimings,
+}
+
+/// How long the individual steps of compiling a shader took.
 #[derive(Clone, Copy, Debug, Default)]
+pub struct CompileTimings {
+    pub parse: Duration,
+    pub validate: Duration,
+    pub create_shader_module: Duration,
+    pub create_pipeline: Duration,
+}
+
+impl CompileTimings {
+    pub fn total(&self) -> Duration {
+        self.parse + self.validate + self.create_shader_module + self.create_pipeline
+    }
+}
+
+/// State of the running shader that is carried over when a window is recreated,
+/// e.g. when switching backends.
+///
+/// Resizing a window reconfigures the existing surface and keeps all state.
+///
+/// A window that is restored paused stays paused when its shader is run again,
+/// see [`WindowHandle::run`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PlaybackState {
+    pub time: f32,
+    pub frame: u32,
+    pub paused: bool,
+}
+
+/// The frame info doesn't know whether the window is paused, since paused
+/// windows might not render.
+impl From<FrameInfo> for PlaybackState {
+    fn from(frame_info: FrameInfo) -> Self {
+        Self {
+            time: frame_info.time,
+            frame: frame_info.frame,
+            paused: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct FrameInfo {
     pub time: f32,
+
+    /// Number of frames rendered since the shader started, as in the inputs.
+    pub frame: u32,
+
     pub fps: f32,
+
+    /// Fraction of the surface's resolution that frames are rendered at. See
+    /// [`ResolutionScaler`].
+    pub render_scale: f32,
+
+    /// How long the GPU took to render a recent frame and its layers. Only
+    /// available if the device supports timestamp queries.
+    pub gpu_times: Option<GpuTimes>,
+
+    /// GPU memory the window's textures and buffers take.
+    pub memory: MemoryUsage,
 }
@@ -0,0 +1,203 @@
+use std::sync::Arc;
+
+use half::f16;
+use tokio::sync::oneshot;
+
+use crate::graphics::{
+    backend::Backend,
+    Error,
+};
+
+/// A pixel of the rendered frame, as read back by
+/// [`WindowHandle::read_pixel`](super::WindowHandle::read_pixel).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PixelValue {
+    /// Position of the pixel in the rendered frame, which might be scaled.
+    pub position: [u32; 2],
+
+    /// The color of the composited frame, as the shaders wrote it. With 8 bit
+    /// frames this is quantized and clamped to `[0, 1]`.
+    pub color: [f32; 4],
+}
+
+/// Reads single pixels of the frame back from the GPU, for inspecting them.
+///
+/// Requested pixels are copied into their own buffer after the next frame was
+/// drawn, and sent once the buffer is mapped.
+#[derive(Debug, Default)]
+pub struct PixelReader {
+    requested: Vec<PixelRequest>,
+    copied: Vec<CopiedPixel>,
+}
+
+impl PixelReader {
+    /// Requests reading the pixel at `position`, in pixels of the frame.
+    pub fn request(
+        &mut self,
+        position: [f32; 2],
+        tx_result: oneshot::Sender<Result<PixelValue, Error>>,
+    ) {
+        self.requested.push(PixelRequest {
+            position,
+            tx_result,
+        });
+    }
+
+    /// Records copying the requested pixels out of `texture`, which must have
+    /// `COPY_SRC` usage. The copies must be read back with [`Self::read_back`]
+    /// after the encoder was submitted.
+    pub fn copy(
+        &mut self,
+        backend: &Backend,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+    ) {
+        for request in self.requested.drain(..) {
+            let position = [
+                (request.position[0].max(0.0) as u32).min(texture.width() - 1),
+                (request.position[1].max(0.0) as u32).min(texture.height() - 1),
+            ];
+
+            // rows are copied with a padded length, even if it's only one pixel.
+            let buffer = backend.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("pixel readback buffer"),
+                size: wgpu::COPY_BYTES_PER_ROW_ALIGNMENT.into(),
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            encoder.copy_texture_to_buffer(
+                wgpu::ImageCopyTexture {
+                    texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: position[0],
+                        y: position[1],
+                        z: 0,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT),
+                        rows_per_image: Some(1),
+                    },
+                },
+                wgpu::Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+            );
+
+            self.copied.push(CopiedPixel {
+                position,
+                format: texture.format(),
+                buffer,
+                tx_result: request.tx_result,
+            });
+        }
+    }
+
+    /// Maps the buffers that pixels were copied into, and sends the pixels when
+    /// they're mapped. Must be called after the commands from [`Self::copy`]
+    /// were submitted.
+    pub fn read_back(&mut self) {
+        for copied in self.copied.drain(..) {
+            let CopiedPixel {
+                position,
+                format,
+                buffer,
+                tx_result,
+            } = copied;
+            let buffer = Arc::new(buffer);
+            let mapped_buffer = buffer.clone();
+
+            buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    let result = match result {
+                        Ok(()) => {
+                            let color = {
+                                let data = mapped_buffer.slice(..).get_mapped_range();
+                                decode_color(format, &data)
+                            };
+                            mapped_buffer.unmap();
+                            color.map(|color| PixelValue { position, color })
+                        }
+                        Err(error) => {
+                            Err(Error::ReadPixel {
+                                message: error.to_string(),
+                            })
+                        }
+                    };
+                    let _ = tx_result.send(result);
+                });
+        }
+    }
+
+    /// Whether pixels are waiting to be copied out of the next frame.
+    pub fn is_requested(&self) -> bool {
+        !self.requested.is_empty()
+    }
+}
+
+#[derive(Debug)]
+struct PixelRequest {
+    position: [f32; 2],
+    tx_result: oneshot::Sender<Result<PixelValue, Error>>,
+}
+
+#[derive(Debug)]
+struct CopiedPixel {
+    position: [u32; 2],
+    format: wgpu::TextureFormat,
+    buffer: wgpu::Buffer,
+    tx_result: oneshot::Sender<Result<PixelValue, Error>>,
+}
+
+/// Decodes the first pixel in `data` to a linear RGBA color.
+fn decode_color(format: wgpu::TextureFormat, data: &[u8]) -> Result<[f32; 4], Error> {
+    let unorm = |bytes: &[u8]| -> [f32; 4] {
+        std::array::from_fn(|i| f32::from(bytes[i]) / 255.0)
+    };
+    let color = match format {
+        wgpu::TextureFormat::Rgba8Unorm => unorm(data),
+        wgpu::TextureFormat::Bgra8Unorm => {
+            let [b, g, r, a] = unorm(data);
+            [r, g, b, a]
+        }
+        wgpu::TextureFormat::Rgba8UnormSrgb => srgb_to_linear(unorm(data)),
+        wgpu::TextureFormat::Bgra8UnormSrgb => {
+            let [b, g, r, a] = unorm(data);
+            srgb_to_linear([r, g, b, a])
+        }
+        wgpu::TextureFormat::Rgba16Float => {
+            std::array::from_fn(|i| f16::from_le_bytes([data[2 * i], data[2 * i + 1]]).to_f32())
+        }
+        wgpu::TextureFormat::Rgba32Float => {
+            std::array::from_fn(|i| f32::from_le_bytes(data[4 * i..][..4].try_into().unwrap()))
+        }
+        _ => {
+            return Err(Error::ReadPixel {
+                message: format!("can't decode {format:?} pixels"),
+            });
+        }
+    };
+    Ok(color)
+}
+
+/// sRGB textures store the encoded color, but shaders write and read linear
+/// colors. The alpha is always linear.
+fn srgb_to_linear([r, g, b, a]: [f32; 4]) -> [f32; 4] {
+    let decode = |c: f32| {
+        if c <= 0.04045 {
+            c / 12.92
+        }
+        else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    [decode(r), decode(g), decode(b), a]
+}
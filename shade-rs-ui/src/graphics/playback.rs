@@ -0,0 +1,98 @@
+use crate::graphics::PlaybackState;
+
+/// Time, frame counter and pause state of a window's shader.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Playback {
+    pub time: f32,
+    pub frame: u32,
+    pub paused: bool,
+
+    /// Set if the window was restored paused, so that the next run doesn't
+    /// start playing.
+    keep_paused: bool,
+}
+
+impl Playback {
+    /// Continues where a previous window left off.
+    pub fn restore(state: PlaybackState) -> Self {
+        Self {
+            time: state.time,
+            frame: state.frame,
+            paused: state.paused,
+            keep_paused: state.paused,
+        }
+    }
+
+    pub fn state(&self) -> PlaybackState {
+        PlaybackState {
+            time: self.time,
+            frame: self.frame,
+            paused: self.paused,
+        }
+    }
+
+    /// Called when a shader was run. Running a shader plays it, except the
+    /// first run after the window was restored paused.
+    pub fn ran(&mut self) {
+        if !std::mem::take(&mut self.keep_paused) {
+            self.paused = false;
+        }
+    }
+
+    /// Advances by a frame that came `delta_time` seconds after the previous.
+    pub fn advance(&mut self, delta_time: f32) {
+        self.time += delta_time;
+        self.frame = self.frame.wrapping_add(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebuilt_window_continues_where_it_left_off() {
+        let mut playback = Playback::default();
+        playback.ran();
+        playback.advance(0.25);
+        playback.advance(0.5);
+        playback.paused = true;
+
+        let mut rebuilt = Playback::restore(playback.state());
+        assert_eq!(
+            rebuilt.state(),
+            PlaybackState {
+                time: 0.75,
+                frame: 2,
+                paused: true,
+            }
+        );
+
+        // the rebuilt window runs the shader again, which must not unpause it.
+        rebuilt.ran();
+        assert_eq!(rebuilt.state(), playback.state());
+
+        // but running the shader again later does.
+        rebuilt.ran();
+        assert!(!rebuilt.paused);
+    }
+
+    #[test]
+    fn running_plays_a_restored_window_that_was_playing() {
+        let mut rebuilt = Playback::restore(PlaybackState {
+            time: 1.0,
+            frame: 60,
+            paused: false,
+        });
+        rebuilt.ran();
+        rebuilt.advance(0.5);
+        assert_eq!(
+            rebuilt.state(),
+            PlaybackState {
+                time: 1.5,
+                frame: 61,
+                paused: false,
+            }
+        );
+    }
+}
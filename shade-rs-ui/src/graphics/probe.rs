@@ -0,0 +1,226 @@
+use std::{
+    cell::{
+        Cell,
+        RefCell,
+    },
+    rc::Rc,
+    sync::Arc,
+};
+
+use shade_rs_compile::probe::{
+    Probe,
+    PROBE_VALUE_SIZE,
+};
+
+use crate::graphics::backend::Backend;
+
+/// The value of a probe at the pixel under the mouse.
+#[derive(Clone, Debug)]
+pub struct ProbeValue {
+    /// 1-based line of the `@probe` directive.
+    pub line: usize,
+    pub expression: String,
+
+    /// The formatted value, or `None` if the probe wasn't reached for that
+    /// pixel.
+    pub value: Option<String>,
+}
+
+/// Draws the bottom layer's shader, instrumented with its
+/// [probes](shade_rs_compile::probe::PROBE_BINDING), for the pixel under the
+/// mouse and reads back the values.
+///
+/// The pass is drawn into its own target, so it doesn't affect the frame. Only
+/// one readback is pending at a time, and frames in between aren't probed.
+#[derive(Debug)]
+pub struct ProbePass {
+    probes: Vec<Probe>,
+    pub pipeline: wgpu::RenderPipeline,
+    pub input_bind_group: wgpu::BindGroup,
+    pub uses_mesh: bool,
+    values_buffer: wgpu::Buffer,
+    readback_buffer: Arc<wgpu::Buffer>,
+    target: Option<ProbeTarget>,
+
+    /// Set after the pass was encoded, until it's read back.
+    encoded: bool,
+
+    /// Set while the values are being read back.
+    in_flight: Rc<Cell<bool>>,
+
+    /// Values that were read back, but not taken yet.
+    values: Rc<RefCell<Option<Vec<ProbeValue>>>>,
+}
+
+impl ProbePass {
+    /// Creates the storage buffer for the probes' values, which has to be bound
+    /// when creating the pipeline.
+    pub fn create_values_buffer(backend: &Backend, num_probes: usize) -> wgpu::Buffer {
+        backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("probe values buffer"),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+            size: num_probes as u64 * PROBE_VALUE_SIZE,
+        })
+    }
+
+    pub fn new(
+        backend: &Backend,
+        probes: Vec<Probe>,
+        pipeline: wgpu::RenderPipeline,
+        input_bind_group: wgpu::BindGroup,
+        uses_mesh: bool,
+        values_buffer: wgpu::Buffer,
+    ) -> Self {
+        let readback_buffer = backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("probe readback buffer"),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+            size: values_buffer.size(),
+        });
+
+        Self {
+            probes,
+            pipeline,
+            input_bind_group,
+            uses_mesh,
+            values_buffer,
+            readback_buffer: Arc::new(readback_buffer),
+            target: None,
+            encoded: false,
+            in_flight: Rc::new(Cell::new(false)),
+            values: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Prepares drawing the pass into [`Self::view`]. Returns `false` if the
+    /// previous values are still being read back.
+    ///
+    /// The target has the same size as the frame, so that the probed fragment
+    /// has the same position as in the frame.
+    pub fn begin(
+        &mut self,
+        backend: &Backend,
+        encoder: &mut wgpu::CommandEncoder,
+        render_configuration: &wgpu::SurfaceConfiguration,
+    ) -> bool {
+        if self.encoded || self.in_flight.get() {
+            return false;
+        }
+
+        let size_matches = self.target.as_ref().is_some_and(|target| {
+            target.width == render_configuration.width
+                && target.height == render_configuration.height
+        });
+        if !size_matches {
+            self.target = Some(ProbeTarget::new(backend, render_configuration));
+        }
+
+        encoder.clear_buffer(&self.values_buffer, 0, None);
+        true
+    }
+
+    pub fn view(&self) -> Option<&wgpu::TextureView> {
+        self.target.as_ref().map(|target| &target.view)
+    }
+
+    /// Copies the values for reading them back, after the pass was drawn.
+    pub fn end(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.copy_buffer_to_buffer(
+            &self.values_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            self.values_buffer.size(),
+        );
+        self.encoded = true;
+    }
+
+    /// Starts reading back the values. Must be called after the commands from
+    /// [`Self::end`] were submitted.
+    pub fn read_back(&mut self) {
+        if !self.encoded {
+            return;
+        }
+        self.encoded = false;
+        self.in_flight.set(true);
+
+        let readback_buffer = self.readback_buffer.clone();
+        let in_flight = self.in_flight.clone();
+        let values = self.values.clone();
+        let probes = self.probes.clone();
+        self.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                in_flight.set(false);
+                if let Err(error) = result {
+                    tracing::warn!(?error, "failed to read back probes");
+                    return;
+                }
+
+                let data = bytemuck::cast_slice::<u8, u32>(
+                    &readback_buffer.slice(..).get_mapped_range(),
+                )
+                .to_vec();
+                readback_buffer.unmap();
+
+                let stride = PROBE_VALUE_SIZE as usize / std::mem::size_of::<u32>();
+                let probe_values = probes
+                    .iter()
+                    .zip(data.chunks_exact(stride))
+                    .map(|(probe, data)| {
+                        let written = data[4] != 0;
+                        ProbeValue {
+                            line: probe.line,
+                            expression: probe.expression.clone(),
+                            value: written
+                                .then(|| probe.format_value([data[0], data[1], data[2], data[3]])),
+                        }
+                    })
+                    .collect();
+                *values.borrow_mut() = Some(probe_values);
+            });
+    }
+
+    /// Takes the values that were read back since the last call.
+    pub fn take_values(&self) -> Option<Vec<ProbeValue>> {
+        self.values.borrow_mut().take()
+    }
+}
+
+#[derive(Debug)]
+struct ProbeTarget {
+    _texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    width: u32,
+    height: u32,
+}
+
+impl ProbeTarget {
+    fn new(backend: &Backend, render_configuration: &wgpu::SurfaceConfiguration) -> Self {
+        let texture = backend.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("probe target"),
+            size: wgpu::Extent3d {
+                width: render_configuration.width,
+                height: render_configuration.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: render_configuration.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&Default::default());
+
+        Self {
+            _texture: texture,
+            view,
+            width: render_configuration.width,
+            height: render_configuration.height,
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use shade_rs_compile::requirements::Requirement;
+
+use crate::graphics::{
+    backend::{
+        Backend,
+        BackendType,
+    },
+    CompileError,
+};
+
+pub fn is_satisfied_by(
+    requirement: Requirement,
+    backend: &Backend,
+    backend_type: BackendType,
+) -> bool {
+    match requirement {
+        Requirement::WebGpu => backend_type == BackendType::WebGpu,
+        Requirement::Float32Filterable => {
+            backend
+                .device
+                .features()
+                .contains(wgpu::Features::FLOAT32_FILTERABLE)
+        }
+        Requirement::StorageTextures => {
+            backend
+                .device
+                .limits()
+                .max_storage_textures_per_shader_stage
+                > 0
+        }
+    }
+}
+
+/// Checks the shader's requirements against the backend it will run on.
+pub fn check_requirements(
+    requirements: &[Requirement],
+    backend: &Backend,
+    backend_type: BackendType,
+) -> Result<(), CompileError> {
+    let missing = requirements
+        .iter()
+        .copied()
+        .filter(|requirement| !is_satisfied_by(*requirement, backend, backend_type))
+        .collect::<Vec<_>>();
+
+    if missing.is_empty() {
+        Ok(())
+    }
+    else {
+        Err(CompileError::MissingRequirements {
+            missing,
+            backend_type,
+        })
+    }
+}
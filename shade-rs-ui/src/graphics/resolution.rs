@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use crate::utils::time::TicksPerSecond;
+
+/// The resolution is lowered when the frame rate drops below this fraction of
+/// the target frame rate.
+const LOWER_THRESHOLD: f32 = 0.8;
+
+/// The resolution is raised again when the frame rate is above this fraction
+/// of the target frame rate.
+const RAISE_THRESHOLD: f32 = 0.95;
+
+const MIN_SCALE: f32 = 0.25;
+const SCALE_STEP: f32 = 0.125;
+
+/// Range of the scale the user can choose, see
+/// [`ResolutionScaler::set_user_scale`].
+pub const MIN_USER_SCALE: f32 = 0.25;
+pub const MAX_USER_SCALE: f32 = 2.0;
+
+/// Frames to wait after lowering the resolution, so that the frame rate is
+/// measured at the new resolution.
+const LOWER_COOLDOWN: u32 = 30;
+
+/// Frames to wait after raising the resolution. This is longer, so that a
+/// shader right at the budget doesn't make the resolution flicker.
+const RAISE_COOLDOWN: u32 = 120;
+
+/// Scales the resolution shaders are rendered at, so that heavy shaders keep
+/// a usable frame rate. The frame is scaled to the surface's size when it's
+/// drawn.
+///
+/// The user chooses a scale, which is lowered further if the frame rate
+/// drops. This only looks at the frame rate measured by [`TicksPerSecond`],
+/// which is limited by the window's target frame rate, so it backs off when
+/// frames take longer than that.
+#[derive(Debug)]
+pub struct ResolutionScaler {
+    user_scale: f32,
+
+    /// Fraction of the user's scale that is rendered at.
+    adaptive_scale: f32,
+    cooldown: u32,
+}
+
+impl Default for ResolutionScaler {
+    fn default() -> Self {
+        Self {
+            user_scale: 1.0,
+            adaptive_scale: 1.0,
+            cooldown: LOWER_COOLDOWN,
+        }
+    }
+}
+
+impl ResolutionScaler {
+    /// Scale of the rendered frame relative to the surface.
+    pub fn scale(&self) -> f32 {
+        self.user_scale * self.adaptive_scale
+    }
+
+    /// Sets the scale the user chose. Values above 1 supersample the frame.
+    ///
+    /// This also goes back to the full chosen scale, since the frame rate
+    /// will be different.
+    pub fn set_user_scale(&mut self, scale: f32) {
+        *self = Self {
+            user_scale: scale.clamp(MIN_USER_SCALE, MAX_USER_SCALE),
+            ..Default::default()
+        };
+    }
+
+    /// Goes back to the full chosen scale, e.g. when a new shader is run.
+    pub fn reset(&mut self) {
+        self.set_user_scale(self.user_scale);
+    }
+
+    /// Called after each frame, with the time between frames at the target
+    /// frame rate. Returns whether the scale changed.
+    pub fn update(&mut self, fps: &TicksPerSecond, target_period: Duration) -> bool {
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+            return false;
+        }
+        let Some(fps) = fps.tps()
+        else {
+            return false;
+        };
+
+        let target_fps = 1.0 / target_period.as_secs_f32();
+        let scale = if fps < LOWER_THRESHOLD * target_fps {
+            self.cooldown = LOWER_COOLDOWN;
+            (self.adaptive_scale - SCALE_STEP).max(MIN_SCALE)
+        }
+        else if fps > RAISE_THRESHOLD * target_fps {
+            self.cooldown = RAISE_COOLDOWN;
+            (self.adaptive_scale + SCALE_STEP).min(1.0)
+        }
+        else {
+            self.adaptive_scale
+        };
+
+        if scale == self.adaptive_scale {
+            false
+        }
+        else {
+            tracing::debug!(fps, scale, "changing render scale");
+            self.adaptive_scale = scale;
+            true
+        }
+    }
+
+    /// Size the frame is rendered at, for a surface of the given size. It's
+    /// at most `max_size` in each dimension.
+    pub fn render_size(&self, width: u32, height: u32, max_size: u32) -> (u32, u32) {
+        let scale = |x: u32| ((x as f32 * self.scale()).round() as u32).clamp(1, max_size);
+        (scale(width), scale(height))
+    }
+}
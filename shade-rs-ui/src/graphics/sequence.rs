@@ -0,0 +1,268 @@
+use std::{
+    cell::Cell,
+    rc::Rc,
+    sync::Arc,
+};
+
+use tokio::sync::mpsc;
+
+use crate::graphics::backend::Backend;
+
+/// Number of frames that are rendered ahead while earlier ones are read back.
+pub const FRAMES_IN_FLIGHT: usize = 4;
+
+/// What to render for [`WindowHandle::export_sequence`](super::WindowHandle::export_sequence).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SequenceOptions {
+    /// Time of the first frame, in seconds.
+    pub start: f32,
+
+    /// Time at which the sequence ends, in seconds. No frame is rendered at
+    /// this time.
+    pub end: f32,
+
+    pub fps: f32,
+
+    /// Resolution of the frames. This is independent of the window's size.
+    pub width: u32,
+    pub height: u32,
+}
+
+impl SequenceOptions {
+    pub fn num_frames(&self) -> usize {
+        ((self.end - self.start) * self.fps).ceil().max(0.0) as usize
+    }
+
+    pub fn frame_time(&self, index: usize) -> f32 {
+        self.start + index as f32 / self.fps
+    }
+}
+
+/// A frame of an exported sequence.
+#[derive(Clone)]
+pub struct SequenceFrame {
+    pub index: usize,
+    pub num_frames: usize,
+    pub width: u32,
+    pub height: u32,
+
+    /// RGBA8 pixels, row by row.
+    pub data: Vec<u8>,
+}
+
+impl std::fmt::Debug for SequenceFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SequenceFrame")
+            .field("index", &self.index)
+            .field("num_frames", &self.num_frames)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A running sequence export of a window.
+///
+/// Frames are rendered offscreen, one submission each, and copied into a pool
+/// of readback buffers. A frame is only rendered if a buffer is free, so at
+/// most [`FRAMES_IN_FLIGHT`] frames are waiting to be read back.
+#[derive(Debug)]
+pub struct SequenceExport {
+    pub options: SequenceOptions,
+
+    /// Index of the next frame that is rendered.
+    next_frame: usize,
+
+    readbacks: Vec<Readback>,
+    tx_frame: mpsc::UnboundedSender<SequenceFrame>,
+
+    /// The frame textures have this format.
+    format: wgpu::TextureFormat,
+
+    /// Window state that is restored when the export is finished.
+    pub saved_time: f32,
+    pub saved_frame: u32,
+}
+
+impl SequenceExport {
+    pub fn new(
+        backend: &Backend,
+        options: SequenceOptions,
+        format: wgpu::TextureFormat,
+        tx_frame: mpsc::UnboundedSender<SequenceFrame>,
+        saved_time: f32,
+        saved_frame: u32,
+    ) -> Self {
+        let readbacks = (0..FRAMES_IN_FLIGHT)
+            .map(|_| Readback::new(backend, options.width, options.height))
+            .collect();
+
+        Self {
+            options,
+            next_frame: 0,
+            readbacks,
+            tx_frame,
+            format,
+            saved_time,
+            saved_frame,
+        }
+    }
+
+    /// Size of each of the [`FRAMES_IN_FLIGHT`] readback buffers that
+    /// [`Self::new`] creates for `options`, in bytes.
+    pub fn readback_buffer_bytes(options: &SequenceOptions) -> u64 {
+        Readback::buffer_size(options.width, options.height)
+    }
+
+    /// Size of the readback buffers, in bytes.
+    pub fn memory_bytes(&self) -> u64 {
+        self.readbacks
+            .iter()
+            .map(|readback| readback.buffer.size())
+            .sum()
+    }
+
+    /// Index of the next frame to render, if it can be rendered now.
+    pub fn next_frame(&self) -> Option<usize> {
+        (self.next_frame < self.options.num_frames()
+            && self.readbacks.iter().any(|readback| !readback.in_flight.get()))
+        .then_some(self.next_frame)
+    }
+
+    /// Whether all frames were read back, or nobody is listening for them
+    /// anymore.
+    pub fn is_finished(&self) -> bool {
+        self.tx_frame.is_closed()
+            || (self.next_frame >= self.options.num_frames()
+                && self.readbacks.iter().all(|readback| !readback.in_flight.get()))
+    }
+
+    /// Records copying the rendered frame into a free readback buffer. The
+    /// readback must be started with [`Self::read_back`] after the encoder was
+    /// submitted.
+    pub fn copy_frame(&mut self, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture) {
+        let Some(readback) = self
+            .readbacks
+            .iter()
+            .find(|readback| !readback.in_flight.get())
+        else {
+            return;
+        };
+
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &readback.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(readback.padded_bytes_per_row),
+                    rows_per_image: Some(self.options.height),
+                },
+            },
+            texture.size(),
+        );
+
+        readback.in_flight.set(true);
+        readback.index.set(Some(self.next_frame));
+        self.next_frame += 1;
+    }
+
+    /// Maps the buffer that the last frame was copied into, and sends the
+    /// frame when it's mapped.
+    pub fn read_back(&self) {
+        let Some(readback) = self.readbacks.iter().find(|readback| readback.index.get().is_some())
+        else {
+            return;
+        };
+        let Some(index) = readback.index.take()
+        else {
+            return;
+        };
+
+        let buffer = readback.buffer.clone();
+        let in_flight = readback.in_flight.clone();
+        let tx_frame = self.tx_frame.clone();
+        let padded_bytes_per_row = readback.padded_bytes_per_row as usize;
+        let width = self.options.width;
+        let height = self.options.height;
+        let num_frames = self.options.num_frames();
+        let bgra = matches!(
+            self.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+
+        readback
+            .buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                if let Err(error) = result {
+                    tracing::warn!(?error, "failed to read back sequence frame");
+                    in_flight.set(false);
+                    return;
+                }
+
+                let mut data = Vec::with_capacity(4 * (width * height) as usize);
+                {
+                    let mapped = buffer.slice(..).get_mapped_range();
+                    for row in mapped.chunks_exact(padded_bytes_per_row) {
+                        data.extend_from_slice(&row[..4 * width as usize]);
+                    }
+                }
+                buffer.unmap();
+                in_flight.set(false);
+
+                if bgra {
+                    for pixel in data.chunks_exact_mut(4) {
+                        pixel.swap(0, 2);
+                    }
+                }
+
+                let _ = tx_frame.send(SequenceFrame {
+                    index,
+                    num_frames,
+                    width,
+                    height,
+                    data,
+                });
+            });
+    }
+}
+
+#[derive(Debug)]
+struct Readback {
+    buffer: Arc<wgpu::Buffer>,
+    padded_bytes_per_row: u32,
+
+    /// Set from when a frame is copied into the buffer, until it's unmapped.
+    in_flight: Rc<Cell<bool>>,
+
+    /// Index of the frame that was copied into the buffer, until it's mapped.
+    index: Cell<Option<usize>>,
+}
+
+impl Readback {
+    fn padded_bytes_per_row(width: u32) -> u32 {
+        (4 * width).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+    }
+
+    fn buffer_size(width: u32, height: u32) -> u64 {
+        Self::padded_bytes_per_row(width) as u64 * height as u64
+    }
+
+    fn new(backend: &Backend, width: u32, height: u32) -> Self {
+        let padded_bytes_per_row = Self::padded_bytes_per_row(width);
+        let buffer = backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sequence readback buffer"),
+            size: Self::buffer_size(width, height),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            buffer: Arc::new(buffer),
+            padded_bytes_per_row,
+            in_flight: Rc::new(Cell::new(false)),
+            index: Cell::new(None),
+        }
+    }
+}
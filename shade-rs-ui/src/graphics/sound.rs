@@ -0,0 +1,296 @@
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    rc::Rc,
+    sync::Arc,
+};
+
+use bytemuck::{
+    Pod,
+    Zeroable,
+};
+use shade_rs_compile::{
+    reflect::PARAMS_BINDING,
+    sound::{
+        SOUND_BLOCK_BINDING,
+        SOUND_ENTRY_POINT,
+        SOUND_SAMPLES_BINDING,
+        SOUND_WORKGROUP_SIZE,
+    },
+};
+
+use crate::{
+    audio::AudioOutput,
+    graphics::{
+        backend::Backend,
+        wgpu_buffer_size,
+    },
+};
+
+/// Number of stereo samples that are generated at once.
+const BLOCK_SAMPLES: u32 = 4096;
+
+/// How far ahead of the window's time samples are generated, in seconds.
+const LOOKAHEAD: f32 = 0.25;
+
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+struct SoundBlockUniform {
+    first_sample: u32,
+    sample_rate: u32,
+    _padding: [u32; 2],
+}
+
+/// Runs a shader's [`main_sound`](shade_rs_compile::sound::SOUND_FUNCTION) to
+/// fill blocks of samples, reads them back and queues them on the audio output.
+///
+/// Samples are generated a bit ahead of the window's time, one block at a time.
+#[derive(Debug)]
+pub struct SoundPass {
+    output: AudioOutput,
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    block_buffer: wgpu::Buffer,
+    samples_buffer: wgpu::Buffer,
+    readback_buffer: Arc<wgpu::Buffer>,
+
+    /// Index of the next sample that will be generated.
+    next_sample: u32,
+
+    /// Set while a block is being read back.
+    in_flight: Rc<Cell<bool>>,
+
+    /// Incremented on every seek, so that blocks that were read back for the
+    /// old position are dropped.
+    generation: Rc<Cell<u32>>,
+}
+
+impl SoundPass {
+    /// Creates the sound pass for a module compiled with
+    /// [`compile_sound_shader`](shade_rs_compile::sound::compile_sound_shader).
+    ///
+    /// The input and parameter buffers are those of the layer the shader runs
+    /// on.
+    pub fn new(
+        backend: &Backend,
+        module: naga::Module,
+        input_buffer: &wgpu::Buffer,
+        params_buffer: &wgpu::Buffer,
+        output: AudioOutput,
+    ) -> Self {
+        let uniform_entry = |binding| {
+            wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }
+        };
+        let bind_group_layout =
+            backend
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("sound bind group layout"),
+                    entries: &[
+                        uniform_entry(0),
+                        uniform_entry(PARAMS_BINDING),
+                        uniform_entry(SOUND_BLOCK_BINDING),
+                        wgpu::BindGroupLayoutEntry {
+                            binding: SOUND_SAMPLES_BINDING,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+
+        let block_buffer = backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sound block buffer"),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+            size: wgpu_buffer_size::<SoundBlockUniform>(),
+        });
+        let samples_size = u64::from(BLOCK_SAMPLES) * 2 * std::mem::size_of::<f32>() as u64;
+        let samples_buffer = backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sound samples buffer"),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+            size: samples_size,
+        });
+        let readback_buffer = backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sound readback buffer"),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+            size: samples_size,
+        });
+
+        let bind_group = backend
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("sound bind group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: input_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: PARAMS_BINDING,
+                        resource: params_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: SOUND_BLOCK_BINDING,
+                        resource: block_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: SOUND_SAMPLES_BINDING,
+                        resource: samples_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+
+        let shader = backend
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("sound shader"),
+                source: wgpu::ShaderSource::Naga(Cow::Owned(module)),
+            });
+
+        let pipeline_layout =
+            backend
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("sound pipeline layout"),
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let pipeline = backend
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("sound pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: SOUND_ENTRY_POINT,
+                compilation_options: Default::default(),
+                cache: None,
+            });
+
+        Self {
+            output,
+            pipeline,
+            bind_group,
+            block_buffer,
+            samples_buffer,
+            readback_buffer: Arc::new(readback_buffer),
+            next_sample: 0,
+            in_flight: Rc::new(Cell::new(false)),
+            generation: Rc::new(Cell::new(0)),
+        }
+    }
+
+    pub fn output(&self) -> &AudioOutput {
+        &self.output
+    }
+
+    /// Drops the queued samples and continues generating at `time`.
+    pub fn seek(&mut self, time: f32) {
+        self.next_sample = (time.max(0.0) * self.output.sample_rate() as f32) as u32;
+        self.generation.set(self.generation.get().wrapping_add(1));
+        self.output.flush();
+    }
+
+    /// Generates the next block, if the samples that are queued don't reach
+    /// far enough past `time`.
+    ///
+    /// Only one block is read back at a time, so this does nothing while a
+    /// readback is still pending.
+    pub fn update(&mut self, backend: &Backend, time: f32) {
+        // a suspended context doesn't consume samples, so they would only pile up.
+        if self.in_flight.get() || !self.output.is_running() {
+            return;
+        }
+
+        let sample_rate = self.output.sample_rate();
+        let queued_until = self.next_sample as f32 / sample_rate as f32;
+        if queued_until > time + LOOKAHEAD {
+            return;
+        }
+        if queued_until < time {
+            // we fell behind, e.g. because the audio context was suspended.
+            self.seek(time);
+        }
+
+        backend.queue.write_buffer(
+            &self.block_buffer,
+            0,
+            bytemuck::bytes_of(&SoundBlockUniform {
+                first_sample: self.next_sample,
+                sample_rate,
+                _padding: [0; 2],
+            }),
+        );
+
+        let mut encoder = backend
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("sound encoder"),
+            });
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("sound pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.pipeline);
+            compute_pass.set_bind_group(0, &self.bind_group, &[]);
+            compute_pass.dispatch_workgroups(BLOCK_SAMPLES.div_ceil(SOUND_WORKGROUP_SIZE), 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(
+            &self.samples_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            self.samples_buffer.size(),
+        );
+        backend.queue.submit([encoder.finish()]);
+
+        self.next_sample = self.next_sample.wrapping_add(BLOCK_SAMPLES);
+        self.in_flight.set(true);
+
+        let readback_buffer = self.readback_buffer.clone();
+        let in_flight = self.in_flight.clone();
+        let generation = self.generation.clone();
+        let submitted_generation = generation.get();
+        let output = self.output.clone();
+        self.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                in_flight.set(false);
+                if let Err(error) = result {
+                    tracing::warn!(?error, "failed to read back samples");
+                    return;
+                }
+
+                let samples =
+                    bytemuck::cast_slice::<u8, f32>(&readback_buffer.slice(..).get_mapped_range())
+                        .to_vec();
+                readback_buffer.unmap();
+
+                if generation.get() == submitted_generation {
+                    output.push(&samples);
+                }
+            });
+    }
+
+    pub fn set_playing(&self, playing: bool) {
+        self.output.set_playing(playing);
+    }
+}
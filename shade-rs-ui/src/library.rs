@@ -0,0 +1,117 @@
+//! Fetching the [libraries](shade_rs_compile::library) that shaders include
+//! from the server.
+
+use std::{
+    cell::RefCell,
+    collections::{
+        BTreeMap,
+        HashMap,
+    },
+};
+
+use gloo_net::http::Request;
+use shade_rs_compile::{
+    conditional::evaluate_conditionals,
+    defines::{
+        parse_defines,
+        resolve_defines,
+        DefineValue,
+    },
+    library::{
+        parse_includes,
+        Include,
+        Library,
+    },
+};
+
+thread_local! {
+    /// Libraries that were already fetched, by [`Include::key`]. A requirement
+    /// resolves to the same version for the rest of the session, even if a
+    /// newer one is published in the meantime.
+    static CACHE: RefCell<HashMap<String, Library>> = RefCell::new(HashMap::new());
+}
+
+/// Adds the libraries that the code includes to `libraries`, unless they're
+/// already in there. They're fetched, or taken from the cache.
+///
+/// Conditionals are evaluated with the values of the defines first, like the
+/// compiler does, so libraries that are only included in branches that aren't
+/// taken aren't fetched.
+///
+/// Libraries that don't exist are left out, so that compiling reports them
+/// where they're included. Invalid includes are reported by the compiler too.
+pub async fn resolve_libraries(
+    code: &str,
+    defines: &BTreeMap<String, DefineValue>,
+    libraries: &mut BTreeMap<String, Library>,
+) -> Result<(), FetchError> {
+    let includes = parse_defines(code)
+        .and_then(|declared| evaluate_conditionals(code, &resolve_defines(&declared, defines)))
+        .and_then(|code| parse_includes(&code))
+        .unwrap_or_default();
+
+    for include in includes {
+        let key = include.key();
+        if libraries.contains_key(&key) {
+            continue;
+        }
+
+        let cached = CACHE.with_borrow(|cache| cache.get(&key).cloned());
+        let library = if let Some(library) = cached {
+            library
+        }
+        else {
+            let Some(library) = fetch_library(&include).await?
+            else {
+                continue;
+            };
+            tracing::debug!(%key, version = %library.version, "fetched library");
+            CACHE.with_borrow_mut(|cache| cache.insert(key.clone(), library.clone()));
+            library
+        };
+
+        libraries.insert(key, library);
+    }
+
+    Ok(())
+}
+
+async fn fetch_library(include: &Include) -> Result<Option<Library>, FetchError> {
+    let error = |message: String| {
+        FetchError {
+            include: include.key(),
+            message,
+        }
+    };
+
+    let url = format!(
+        "/api/libraries/{}/{}/{}",
+        include.library.user, include.library.name, include.requirement
+    );
+    let response = Request::get(&url)
+        .send()
+        .await
+        .map_err(|e| error(e.to_string()))?;
+
+    match response.status() {
+        200 => {
+            response
+                .json()
+                .await
+                .map(Some)
+                .map_err(|e| error(e.to_string()))
+        }
+        404 => Ok(None),
+        status => {
+            let text = response.text().await.unwrap_or_default();
+            Err(error(format!("server responded with {status}: {text}")))
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("could not fetch library `{include}`: {message}")]
+pub struct FetchError {
+    pub include: String,
+    pub message: String,
+}
@@ -1,37 +1,74 @@
 pub mod app;
+pub mod audio;
 pub mod error;
 pub mod graphics;
+pub mod library;
 pub mod utils;
 
+use leptos::view;
 use tracing::Level;
-use tracing_subscriber::{layer::SubscriberExt, Registry};
-use tracing_wasm::{WASMLayer, WASMLayerConfigBuilder};
-use wasm_bindgen::{prelude::wasm_bindgen, JsCast};
+use tracing_subscriber::{
+    layer::SubscriberExt,
+    Registry,
+};
+use tracing_wasm::{
+    WASMLayer,
+    WASMLayerConfigBuilder,
+};
+use wasm_bindgen::{
+    prelude::wasm_bindgen,
+    JsCast,
+};
 
+#[cfg(feature = "editor")]
 use crate::app::App;
+use crate::app::{
+    DevPlayer,
+    Player,
+};
 
 fn main() {
     let _ = tracing::subscriber::set_global_default(
-        Registry::default().with(WASMLayer::new(WASMLayerConfigBuilder::new()
-        .set_max_level(Level::DEBUG)
-        .build())));
+        Registry::default().with(WASMLayer::new(
+            WASMLayerConfigBuilder::new()
+                .set_max_level(Level::DEBUG)
+                .build(),
+        )),
+    );
     console_error_panic_hook::set_once();
 
     tracing::info!("shade-rs initialized");
 }
 
+#[cfg(feature = "editor")]
 #[wasm_bindgen]
 pub fn mount_to(id: &str) {
     tracing::info!("mounting shade-rs");
+    leptos::mount_to(root_element(id), App);
+}
+
+/// Mounts only the player, running the given shader code.
+#[wasm_bindgen]
+pub fn mount_player(id: &str, code: String) {
+    tracing::info!("mounting shade-rs player");
+    leptos::mount_to(root_element(id), move || view! { <Player code /> });
+}
 
-    let root = web_sys::window()
+/// Mounts a player that previews a project from the `dev` command of the CLI,
+/// which it connects to with the WebSocket at `url`.
+#[wasm_bindgen]
+pub fn mount_dev(id: &str, url: String) {
+    tracing::info!("mounting shade-rs dev player");
+    leptos::mount_to(root_element(id), move || view! { <DevPlayer url /> });
+}
+
+fn root_element(id: &str) -> web_sys::HtmlElement {
+    web_sys::window()
         .expect("no window")
         .document()
         .expect("no document")
         .get_element_by_id(id)
         .expect("root element not found")
         .dyn_into()
-        .unwrap();
-
-    leptos::mount_to(root, App);
+        .unwrap()
 }
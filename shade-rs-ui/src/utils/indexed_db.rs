@@ -0,0 +1,66 @@
+//! Small helpers to use IndexedDB with futures.
+
+use js_sys::Promise;
+use wasm_bindgen::{
+    closure::Closure,
+    JsCast,
+    JsValue,
+};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    IdbDatabase,
+    IdbRequest,
+};
+
+/// Opens a database, calling `upgrade` if it doesn't exist yet or has an older
+/// version.
+pub async fn open_database(
+    name: &str,
+    version: u32,
+    upgrade: fn(&IdbDatabase) -> Result<(), JsValue>,
+) -> Result<IdbDatabase, JsValue> {
+    let factory = web_sys::window()
+        .expect("no window")
+        .indexed_db()?
+        .ok_or_else(|| JsValue::from_str("IndexedDB is not available"))?;
+    let request = factory.open_with_u32(name, version)?;
+
+    let on_upgrade_needed = Closure::<dyn FnMut(JsValue)>::new({
+        let request = request.clone();
+        move |_event| {
+            let result = request
+                .result()
+                .and_then(|database| upgrade(database.unchecked_ref()));
+            if let Err(error) = result {
+                tracing::error!(?error, "failed to upgrade database");
+            }
+        }
+    });
+    request.set_onupgradeneeded(Some(on_upgrade_needed.as_ref().unchecked_ref()));
+
+    // the closure must live until the request is done.
+    let result = wait(&request).await;
+    request.set_onupgradeneeded(None);
+    drop(on_upgrade_needed);
+
+    Ok(result?.unchecked_into())
+}
+
+/// Waits for a request to finish, and returns its result.
+pub async fn wait(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+    let result = JsFuture::from(promise).await;
+    request.set_onsuccess(None);
+    request.set_onerror(None);
+
+    match result {
+        Ok(_) => request.result(),
+        Err(event) => {
+            // the event doesn't say much, but the request has the actual error.
+            Err(request.error()?.map_or(event, Into::into))
+        }
+    }
+}
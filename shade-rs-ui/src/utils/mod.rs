@@ -1,2 +1,4 @@
 pub mod futures;
+#[cfg(feature = "editor")]
+pub mod indexed_db;
 pub mod time;